@@ -35,15 +35,16 @@ class CommandMaker:
                 f'--store {store} --parameters {parameters}')
 
     @staticmethod
-    def run_client(address, size, rate, timeout, nodes=[]):
+    def run_client(address, size, rate, timeout, nodes=[], validator='benchmark'):
         assert isinstance(address, str)
+        assert isinstance(validator, str)
         assert isinstance(size, int) and size > 0
         assert isinstance(rate, int) and rate >= 0
         assert isinstance(nodes, list)
         assert all(isinstance(x, str) for x in nodes)
         nodes = f'--nodes {" ".join(nodes)}' if nodes else ''
         return (f'./client {address} --size {size} '
-                f'--rate {rate} --timeout {timeout} {nodes}')
+                f'--rate {rate} --timeout {timeout} --validator {validator} {nodes}')
 
     @staticmethod
     def kill():
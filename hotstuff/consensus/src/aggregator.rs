@@ -1,4 +1,4 @@
-use crate::config::{Committee, Stake};
+use crate::config::{Committee, EpochSchedule, Stake};
 use crate::consensus::Round;
 use crate::error::{ConsensusError, ConsensusResult};
 use crate::messages::{Timeout, Vote, QC, TC};
@@ -11,7 +11,7 @@ use std::collections::{HashMap, HashSet};
 pub mod aggregator_tests;
 
 pub struct Aggregator {
-    committee: Committee,
+    epochs: EpochSchedule,
     votes_aggregators: HashMap<Round, HashMap<Digest, Box<QCMaker>>>,
     timeouts_aggregators: HashMap<Round, Box<TCMaker>>,
 }
@@ -19,23 +19,29 @@ pub struct Aggregator {
 impl Aggregator {
     pub fn new(committee: Committee) -> Self {
         Self {
-            committee,
+            epochs: EpochSchedule::new(committee),
             votes_aggregators: HashMap::new(),
             timeouts_aggregators: HashMap::new(),
         }
     }
 
+    /// Count the votes and timeouts of the new committee from `activation_round` onwards.
+    pub fn schedule(&mut self, activation_round: Round, committee: Committee) {
+        self.epochs.schedule(activation_round, committee);
+    }
+
     pub fn add_vote(&mut self, vote: Vote) -> ConsensusResult<Option<QC>> {
         // TODO [issue #7]: A bad node may make us run out of memory by sending many votes
         // with different round numbers or different digests.
 
         // Add the new vote to our aggregator and see if we have a QC.
+        let committee = self.epochs.committee(vote.round);
         self.votes_aggregators
             .entry(vote.round)
             .or_insert_with(HashMap::new)
             .entry(vote.digest())
             .or_insert_with(|| Box::new(QCMaker::new()))
-            .append(vote, &self.committee)
+            .append(vote, committee)
     }
 
     pub fn add_timeout(&mut self, timeout: Timeout) -> ConsensusResult<Option<TC>> {
@@ -43,10 +49,11 @@ impl Aggregator {
         // with different round numbers.
 
         // Add the new timeout to our aggregator and see if we have a TC.
+        let committee = self.epochs.committee(timeout.round);
         self.timeouts_aggregators
             .entry(timeout.round)
             .or_insert_with(|| Box::new(TCMaker::new()))
-            .append(timeout, &self.committee)
+            .append(timeout, committee)
     }
 
     pub fn cleanup(&mut self, round: &Round) {
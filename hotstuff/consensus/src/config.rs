@@ -1,3 +1,4 @@
+use crate::consensus::Round;
 use crypto::PublicKey;
 use log::info;
 use serde::{Deserialize, Serialize};
@@ -11,6 +12,16 @@ pub type EpochNumber = u128;
 pub struct Parameters {
     pub timeout_delay: u64,
     pub sync_retry_delay: u64,
+    #[serde(default)]
+    pub leader_election: LeaderElection,
+    /// The number of rounds behind the last committed round for which blocks and their batches
+    /// are kept in the store.
+    #[serde(default = "default_gc_depth")]
+    pub gc_depth: Round,
+}
+
+fn default_gc_depth() -> Round {
+    10_000
 }
 
 impl Default for Parameters {
@@ -18,6 +29,8 @@ impl Default for Parameters {
         Self {
             timeout_delay: 5_000,
             sync_retry_delay: 10_000,
+            leader_election: LeaderElection::default(),
+            gc_depth: default_gc_depth(),
         }
     }
 }
@@ -27,9 +40,22 @@ impl Parameters {
         // NOTE: These log entries are used to compute performance.
         info!("Timeout delay set to {} rounds", self.timeout_delay);
         info!("Sync retry delay set to {} ms", self.sync_retry_delay);
+        info!("Leader election set to {:?}", self.leader_election);
+        info!("Garbage collection depth set to {} rounds", self.gc_depth);
     }
 }
 
+/// How the leader of each round is elected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum LeaderElection {
+    /// Rotate among all authorities.
+    #[default]
+    RoundRobin,
+    /// Rotate among the authorities that authored or voted for one of the last `window` blocks
+    /// committed by the chain the leader extends, except the authors of the last `exclude` ones.
+    Reputation { window: usize, exclude: usize },
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Authority {
     pub stake: Stake,
@@ -83,3 +109,52 @@ impl Committee {
             .collect()
     }
 }
+
+/// The committees of the successive epochs, each in charge from its activation round onwards.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EpochSchedule {
+    epochs: Vec<(Round, Committee)>,
+}
+
+impl EpochSchedule {
+    pub fn new(committee: Committee) -> Self {
+        Self {
+            epochs: vec![(0, committee)],
+        }
+    }
+
+    /// Returns the committee in charge of `round`.
+    pub fn committee(&self, round: Round) -> &Committee {
+        self.epochs
+            .iter()
+            .rev()
+            .find(|(activation_round, _)| *activation_round <= round)
+            .map(|(_, committee)| committee)
+            .unwrap_or(&self.epochs[0].1)
+    }
+
+    /// Returns the last scheduled committee along with its activation round.
+    pub fn latest(&self) -> (Round, &Committee) {
+        let (activation_round, committee) = self.epochs.last().expect("Empty epoch schedule");
+        (*activation_round, committee)
+    }
+
+    /// Hand over to `committee` from `activation_round` onwards. It only succeeds for the epoch
+    /// following the latest one, activated after it.
+    pub fn schedule(&mut self, activation_round: Round, committee: Committee) -> bool {
+        let (latest_activation_round, latest) = self.latest();
+        if committee.epoch != latest.epoch + 1 || activation_round <= latest_activation_round {
+            return false;
+        }
+        self.epochs.push((activation_round, committee));
+        true
+    }
+
+    /// Returns the address of `name` in the most recent committee it is part of.
+    pub fn address(&self, name: &PublicKey) -> Option<SocketAddr> {
+        self.epochs
+            .iter()
+            .rev()
+            .find_map(|(_, committee)| committee.address(name))
+    }
+}
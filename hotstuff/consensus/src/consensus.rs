@@ -1,10 +1,11 @@
-use crate::config::{Committee, Parameters};
+use crate::config::{Committee, EpochSchedule, Parameters};
 use crate::core::Core;
 use crate::error::ConsensusError;
+use crate::gc::GarbageCollector;
 use crate::helper::Helper;
-use crate::leader::LeaderElector;
+use crate::leader::make_leader_elector;
 use crate::mempool::MempoolDriver;
-use crate::messages::{Block, Timeout, Vote, TC};
+use crate::messages::{Block, Reconfiguration, Timeout, Vote, TC};
 use crate::proposer::Proposer;
 use crate::synchronizer::Synchronizer;
 use async_trait::async_trait;
@@ -18,10 +19,11 @@ use std::error::Error;
 use store::Store;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinHandle;
 use std::collections::HashMap;
 use futures::executor::block_on;
-use log::{info};
+use log::{info, warn};
 #[cfg(test)]
 #[path = "tests/consensus_tests.rs"]
 pub mod consensus_tests;
@@ -32,6 +34,10 @@ pub const CHANNEL_CAPACITY: usize = 1_000;
 /// The consensus round number.
 pub type Round = u64;
 
+/// The minimum number of rounds between a reconfiguration block and the activation of the new
+/// committee. It leaves time to commit the block before the committee it names is in charge.
+pub const RECONFIGURATION_DELAY: Round = 10;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ConsensusMessage {
     Propose(Block),
@@ -44,6 +50,9 @@ pub enum ConsensusMessage {
 pub struct Consensus;
 
 impl Consensus {
+    /// Spawn the consensus of `validator_id` and register its network handler. Returns the
+    /// handles of the spawned tasks: aborting them (once the handler is unregistered) stops the
+    /// consensus.
     #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: PublicKey,
@@ -54,9 +63,10 @@ impl Consensus {
         rx_mempool: Receiver<Digest>,
         tx_mempool: Sender<ConsensusMempoolMessage>,
         tx_commit: Sender<Block>,
+        rx_reconfiguration: Receiver<Reconfiguration>,
         validator_id: String, 
         consensus_handler_map: Arc<RwLock<HashMap<String, ConsensusReceiverHandler>>>,
-    ) {
+    ) -> Vec<JoinHandle<()>> {
         // NOTE: This log entry is used to compute performance.
         parameters.log();
 
@@ -64,6 +74,7 @@ impl Consensus {
         let (tx_loopback, rx_loopback) = channel(CHANNEL_CAPACITY);
         let (tx_proposer, rx_proposer) = channel(CHANNEL_CAPACITY);
         let (tx_helper, rx_helper) = channel(CHANNEL_CAPACITY);
+        let (tx_epochs, rx_epochs) = watch::channel(EpochSchedule::new(committee.clone()));
 
         // Spawn the network receiver.
         // let mut address = committee
@@ -90,7 +101,7 @@ impl Consensus {
         // );
 
         // Make the leader election module.
-        let leader_elector = LeaderElector::new(committee.clone());
+        let leader_elector = make_leader_elector(&parameters.leader_election, committee.clone());
 
         // Make the mempool driver.
         let mempool_driver = MempoolDriver::new(store.clone(), tx_mempool, tx_loopback.clone());
@@ -98,15 +109,30 @@ impl Consensus {
         // Make the synchronizer.
         let synchronizer = Synchronizer::new(
             name,
-            committee.clone(),
+            rx_epochs.clone(),
             store.clone(),
             tx_loopback.clone(),
             parameters.sync_retry_delay,
             validator_id.clone()
         );
 
+        // Make the garbage collector of the store. The leader elector replays the last committed
+        // blocks upon restart: they must not be collected.
+        let history_depth = leader_elector.history_depth() as Round;
+        let gc_depth = if parameters.gc_depth < history_depth {
+            warn!(
+                "Garbage collection depth raised from {} to {} rounds, the history of the leader election",
+                parameters.gc_depth, history_depth
+            );
+            history_depth
+        } else {
+            parameters.gc_depth
+        };
+        let garbage_collector = GarbageCollector::new(store.clone(), gc_depth, validator_id.clone());
+        let batch_indexer = garbage_collector.batch_indexer();
+
         // Spawn the consensus core.
-        Core::spawn(
+        let core = Core::spawn(
             name,
             committee.clone(),
             signature_service.clone(),
@@ -114,27 +140,33 @@ impl Consensus {
             leader_elector,
             mempool_driver,
             synchronizer,
+            garbage_collector,
             parameters.timeout_delay,
             /* rx_message */ rx_consensus,
             rx_loopback,
             tx_proposer,
             tx_commit,
+            tx_epochs,
             validator_id.clone()
         );
 
         // Spawn the block proposer.
-        Proposer::spawn(
+        let proposer = Proposer::spawn(
             name,
-            committee.clone(),
+            rx_epochs.clone(),
             signature_service,
             rx_mempool,
             /* rx_message */ rx_proposer,
+            rx_reconfiguration,
             tx_loopback,
+            batch_indexer,
             validator_id.clone()
         );
 
         // Spawn the helper module.
-        Helper::spawn(committee, store, /* rx_requests */ rx_helper, validator_id.clone());
+        let helper = Helper::spawn(rx_epochs, store, /* rx_requests */ rx_helper, validator_id.clone());
+
+        vec![core, proposer, helper]
     }
 }
 
@@ -1,36 +1,97 @@
 use crate::aggregator::Aggregator;
-use crate::config::Committee;
-use crate::consensus::{ConsensusMessage, Round};
+use crate::config::{Committee, EpochNumber, EpochSchedule};
+use crate::consensus::{ConsensusMessage, Round, RECONFIGURATION_DELAY};
 use crate::error::{ConsensusError, ConsensusResult};
+use crate::gc::GarbageCollector;
 use crate::leader::LeaderElector;
 use crate::mempool::MempoolDriver;
-use crate::messages::{Block, Timeout, Vote, QC, TC};
+use crate::messages::{Block, Reconfiguration, Timeout, Vote, QC, TC};
 use crate::proposer::ProposerMessage;
 use crate::synchronizer::Synchronizer;
 use crate::timer::Timer;
 use async_recursion::async_recursion;
-use bytes::Bytes;
 use crypto::Hash as _;
 use crypto::{PublicKey, SignatureService};
 use log::{debug, error, info, warn};
-use network::SimpleSender;
+use network::{Envelope, MessageKind, SimpleSender};
+use serde::{Deserialize, Serialize};
 use std::cmp::max;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use store::Store;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 #[cfg(test)]
 #[path = "tests/core_tests.rs"]
 pub mod core_tests;
 
+/// The store key of the safety state. Blocks and batches are keyed by their 32-byte digest, so
+/// this shorter key cannot collide with them.
+pub const SAFETY_STATE_KEY: &[u8] = b"consensus_safety_state";
+
+/// The store key of the committed reconfigurations, replayed upon restart.
+pub const RECONFIGURATIONS_KEY: &[u8] = b"consensus_reconfigurations";
+
+/// The store key of the digest of the last committed block.
+pub const LAST_COMMITTED_KEY: &[u8] = b"consensus_last_committed";
+
+/// Returns the epoch history persisted in `store`: the reconfigurations committed since the
+/// genesis committee of the instance, in epoch order.
+pub async fn epoch_history(store: &mut Store) -> ConsensusResult<Vec<Reconfiguration>> {
+    match store.read(RECONFIGURATIONS_KEY.to_vec()).await? {
+        Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Seed `store` with the epoch `history` of an instance whose genesis committee is `committee`,
+/// before an authority that joins it after some reconfigurations spawns its consensus. Without
+/// it, the authority would check the blocks and certificates of every round against a single
+/// committee. A store that already has a history keeps it.
+pub async fn bootstrap_epochs(
+    store: &mut Store,
+    committee: &Committee,
+    history: &[Reconfiguration],
+) -> ConsensusResult<()> {
+    let mut epochs = EpochSchedule::new(committee.clone());
+    for reconfiguration in history {
+        ensure!(
+            epochs.schedule(reconfiguration.activation_round, reconfiguration.consensus.clone()),
+            ConsensusError::InvalidEpochHistory(reconfiguration.epoch())
+        );
+    }
+    if !epoch_history(store).await?.is_empty() {
+        return Ok(());
+    }
+    let value = bincode::serialize(history).expect("Failed to serialize reconfigurations");
+    store.write(RECONFIGURATIONS_KEY.to_vec(), value).await;
+    Ok(())
+}
+
+/// The part of the state of the core that must survive a crash: without it, a restarted node
+/// could vote twice in a round or go back on its highest QC.
+#[derive(Serialize, Deserialize, Default)]
+struct SafetyState {
+    last_voted_round: Round,
+    last_committed_round: Round,
+    high_qc: QC,
+}
+
 pub struct Core {
     name: PublicKey,
-    committee: Committee,
+    epochs: EpochSchedule,
+    tx_epochs: watch::Sender<EpochSchedule>,
+    /// The committed reconfigurations, in epoch order.
+    reconfigurations: Vec<Reconfiguration>,
+    /// The latest epoch handed over to the mempool.
+    mempool_epoch: EpochNumber,
     store: Store,
     signature_service: SignatureService,
-    leader_elector: LeaderElector,
+    leader_elector: Box<dyn LeaderElector>,
     mempool_driver: MempoolDriver,
     synchronizer: Synchronizer,
+    garbage_collector: GarbageCollector,
     rx_message: Receiver<ConsensusMessage>,
     rx_loopback: Receiver<Block>,
     tx_proposer: Sender<ProposerMessage>,
@@ -51,55 +112,223 @@ impl Core {
         name: PublicKey,
         committee: Committee,
         signature_service: SignatureService,
-        store: Store,
-        leader_elector: LeaderElector,
+        mut store: Store,
+        leader_elector: Box<dyn LeaderElector>,
         mempool_driver: MempoolDriver,
         synchronizer: Synchronizer,
+        garbage_collector: GarbageCollector,
         timeout_delay: u64,
         rx_message: Receiver<ConsensusMessage>,
         rx_loopback: Receiver<Block>,
         tx_proposer: Sender<ProposerMessage>,
         tx_commit: Sender<Block>,
+        tx_epochs: watch::Sender<EpochSchedule>,
         validator_id : String
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
-            Self {
+            // Resume from the safety state persisted before a restart, if any.
+            let state = match Self::load_safety_state(&mut store).await {
+                Ok(state) => state,
+                Err(e) => {
+                    error!("Failed to load the safety state of validator {}: {}", validator_id, e);
+                    return;
+                }
+            };
+            let reconfigurations = match epoch_history(&mut store).await {
+                Ok(reconfigurations) => reconfigurations,
+                Err(e) => {
+                    error!("Failed to load the reconfigurations of validator {}: {}", validator_id, e);
+                    return;
+                }
+            };
+            let mut core = Self {
                 name,
-                committee: committee.clone(),
+                epochs: EpochSchedule::new(committee.clone()),
+                tx_epochs,
+                reconfigurations: Vec::new(),
+                mempool_epoch: committee.epoch,
                 signature_service,
                 store,
                 leader_elector,
                 mempool_driver,
                 synchronizer,
+                garbage_collector,
                 rx_message,
                 rx_loopback,
                 tx_proposer,
                 tx_commit,
-                round: 1,
-                last_voted_round: 0,
-                last_committed_round: 0,
-                high_qc: QC::genesis(),
+                round: state.high_qc.round + 1,
+                last_voted_round: state.last_voted_round,
+                last_committed_round: state.last_committed_round,
+                high_qc: state.high_qc,
                 timer: Timer::new(timeout_delay),
                 aggregator: Aggregator::new(committee),
                 network: SimpleSender::new(),
                 validator_id: validator_id.clone()
+            };
+            for reconfiguration in reconfigurations {
+                core.schedule(reconfiguration);
             }
-            .run()
-            .await
-        });
+            if let Err(e) = core.replay_committed_blocks().await {
+                error!("Failed to replay the committed blocks of validator {}: {}", validator_id, e);
+                return;
+            }
+            core.run().await
+        })
+    }
+
+    /// Let the leader elector learn again about the chain certified by our highest QC, down to
+    /// the last committed blocks it depends on.
+    async fn replay_committed_blocks(&mut self) -> ConsensusResult<()> {
+        let mut digest = self.high_qc.hash.clone();
+        if self.store.read(digest.to_vec()).await?.is_none() {
+            digest = match self.store.read(LAST_COMMITTED_KEY.to_vec()).await? {
+                Some(bytes) => bincode::deserialize(&bytes)?,
+                None => return Ok(()),
+            };
+        }
+        let mut blocks = Vec::new();
+        let mut committed = 0;
+        while committed < self.leader_elector.history_depth() {
+            let block: Block = match self.store.read(digest.to_vec()).await? {
+                Some(bytes) => bincode::deserialize(&bytes)?,
+                None => break,
+            };
+            if block.round <= self.last_committed_round {
+                committed += 1;
+            }
+            digest = block.parent().clone();
+            blocks.push(block);
+        }
+        for block in blocks.iter().rev() {
+            self.leader_elector.add_block(block);
+            if block.round <= self.last_committed_round {
+                self.leader_elector.commit(block);
+            }
+        }
+        Ok(())
+    }
+
+    async fn persist_reconfigurations(&mut self) {
+        let value = bincode::serialize(&self.reconfigurations)
+            .expect("Failed to serialize reconfigurations");
+        self.store.write(RECONFIGURATIONS_KEY.to_vec(), value).await;
+    }
+
+    /// Hand over to the committee of `reconfiguration` from its activation round onwards, unless
+    /// its epoch is already scheduled.
+    fn schedule(&mut self, reconfiguration: Reconfiguration) -> bool {
+        let activation_round = reconfiguration.activation_round;
+        let committee = reconfiguration.consensus.clone();
+        if !self.epochs.schedule(activation_round, committee.clone()) {
+            return false;
+        }
+        self.leader_elector.schedule(activation_round, committee.clone());
+        self.aggregator.schedule(activation_round, committee);
+        let _ = self.tx_epochs.send(self.epochs.clone());
+        self.reconfigurations.push(reconfiguration);
+        true
+    }
+
+    /// Schedule a committed reconfiguration. One committed once its activation round is reached
+    /// is dropped rather than applied to rounds the current committee already ran: proposers keep
+    /// it pending until its epoch is scheduled, and propose it again with a later activation
+    /// round.
+    async fn schedule_committed(&mut self, reconfiguration: Reconfiguration) {
+        if reconfiguration.activation_round <= self.round {
+            warn!(
+                "Dropped reconfiguration to epoch {}: committed at round {}, after its activation round {}",
+                reconfiguration.epoch(),
+                self.round,
+                reconfiguration.activation_round
+            );
+            return;
+        }
+        let (epoch, activation_round) = (reconfiguration.epoch(), reconfiguration.activation_round);
+        if self.schedule(reconfiguration) {
+            info!("Scheduled epoch {} from round {}", epoch, activation_round);
+            self.persist_reconfigurations().await;
+        }
+    }
+
+    /// Hand the mempool over to the committees activated by the current round.
+    async fn activate_epochs(&mut self) {
+        let activated: Vec<_> = self
+            .reconfigurations
+            .iter()
+            .filter(|r| r.epoch() > self.mempool_epoch && r.activation_round <= self.round)
+            .cloned()
+            .collect();
+        for reconfiguration in activated {
+            info!(
+                "Epoch {} starts at round {}",
+                reconfiguration.epoch(),
+                reconfiguration.activation_round
+            );
+            self.mempool_epoch = reconfiguration.epoch();
+            self.mempool_driver.reconfigure(reconfiguration.mempool).await;
+        }
+    }
+
+    /// Ensure a reconfiguration proposed in `block` can be scheduled once committed.
+    fn verify_reconfiguration(&self, block: &Block) -> ConsensusResult<()> {
+        let reconfiguration = match block.reconfiguration {
+            Some(ref reconfiguration) => reconfiguration,
+            None => return Ok(()),
+        };
+        let (latest_activation_round, latest) = self.epochs.latest();
+        let names: HashSet<_> = reconfiguration.consensus.authorities.keys().collect();
+        let mempool_names: HashSet<_> = reconfiguration.mempool.authorities.keys().collect();
+        ensure!(
+            reconfiguration.epoch() == latest.epoch + 1
+                && reconfiguration.mempool.epoch == reconfiguration.epoch()
+                && reconfiguration.activation_round >= block.round + RECONFIGURATION_DELAY
+                && reconfiguration.activation_round > latest_activation_round
+                && !names.is_empty()
+                && names == mempool_names
+                && reconfiguration.consensus.authorities.values().all(|x| x.stake > 0),
+            ConsensusError::InvalidReconfiguration {
+                digest: block.digest(),
+                epoch: reconfiguration.epoch()
+            }
+        );
+        Ok(())
+    }
+
+    async fn load_safety_state(store: &mut Store) -> ConsensusResult<SafetyState> {
+        match store.read(SAFETY_STATE_KEY.to_vec()).await? {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(SafetyState::default()),
+        }
+    }
+
+    async fn persist_safety_state(&mut self) -> ConsensusResult<()> {
+        let state = SafetyState {
+            last_voted_round: self.last_voted_round,
+            last_committed_round: self.last_committed_round,
+            high_qc: self.high_qc.clone(),
+        };
+        let value = bincode::serialize(&state).expect("Failed to serialize safety state");
+        self.store.write(SAFETY_STATE_KEY.to_vec(), value).await;
+
+        // The store processes commands in order: reading the key back ensures the state is
+        // written before we act upon it (e.g., by sending a vote).
+        self.store.read(SAFETY_STATE_KEY.to_vec()).await?;
+        Ok(())
     }
 
     async fn store_block(&mut self, block: &Block) {
         let key = block.digest().to_vec();
         let value = bincode::serialize(block).expect("Failed to serialize block");
         self.store.write(key, value).await;
+        self.garbage_collector.index(block).await;
     }
 
     fn increase_last_voted_round(&mut self, target: Round) {
         self.last_voted_round = max(self.last_voted_round, target);
     }
 
-    async fn make_vote(&mut self, block: &Block) -> Option<Vote> {
+    async fn make_vote(&mut self, block: &Block) -> ConsensusResult<Option<Vote>> {
         // Check if we can vote for this block.
         let safety_rule_1 = block.round > self.last_voted_round;
         let mut safety_rule_2 = block.qc.round + 1 == block.round;
@@ -109,13 +338,13 @@ impl Core {
             safety_rule_2 |= can_extend;
         }
         if !(safety_rule_1 && safety_rule_2) {
-            return None;
+            return Ok(None);
         }
 
-        // Ensure we won't vote for contradicting blocks.
+        // Ensure we won't vote for contradicting blocks, even after a restart.
         self.increase_last_voted_round(block.round);
-        // TODO [issue #15]: Write to storage preferred_round and last_voted_round.
-        Some(Vote::new(block, self.name, self.signature_service.clone()).await)
+        self.persist_safety_state().await?;
+        Ok(Some(Vote::new(block, self.name, self.signature_service.clone()).await))
     }
 
     async fn commit(&mut self, block: Block) -> ConsensusResult<()> {
@@ -125,6 +354,7 @@ impl Core {
 
         // Ensure we commit the entire chain. This is needed after view-change.
         let mut to_commit = VecDeque::new();
+        to_commit.push_back(block.clone());
         let mut parent = block.clone();
         while self.last_committed_round + 1 < parent.round {
             let ancestor = self
@@ -132,16 +362,22 @@ impl Core {
                 .get_parent_block(&parent)
                 .await?
                 .expect("We should have all the ancestors by now");
-            to_commit.push_front(ancestor.clone());
+            to_commit.push_back(ancestor.clone());
             parent = ancestor;
         }
-        to_commit.push_front(block.clone());
 
         // Save the last committed block.
         self.last_committed_round = block.round;
+        self.persist_safety_state().await?;
+        let digest = bincode::serialize(&block.digest()).expect("Failed to serialize digest");
+        self.store.write(LAST_COMMITTED_KEY.to_vec(), digest).await;
 
         // Send all the newly committed blocks to the node's application layer.
         while let Some(block) = to_commit.pop_back() {
+            self.leader_elector.commit(&block);
+            if let Some(ref reconfiguration) = block.reconfiguration {
+                self.schedule_committed(reconfiguration.clone()).await;
+            }
             if !block.payload.is_empty() {
                 info!("Committed {}", block);
 
@@ -156,13 +392,18 @@ impl Core {
                 warn!("Failed to send block through the commit channel: {}", e);
             }
         }
+
+        // Delete the blocks and batches that are now too old to be needed.
+        self.garbage_collector.collect(self.last_committed_round).await;
         Ok(())
     }
 
-    fn update_high_qc(&mut self, qc: &QC) {
+    async fn update_high_qc(&mut self, qc: &QC) -> ConsensusResult<()> {
         if qc.round > self.high_qc.round {
             self.high_qc = qc.clone();
+            self.persist_safety_state().await?;
         }
+        Ok(())
     }
 
     async fn local_timeout_round(&mut self) -> ConsensusResult<()> {
@@ -170,6 +411,7 @@ impl Core {
 
         // Increase the last voted round.
         self.increase_last_voted_round(self.round);
+        self.persist_safety_state().await?;
 
         // Make a timeout message.
         let timeout = Timeout::new(
@@ -187,19 +429,17 @@ impl Core {
         // Broadcast the timeout message.
         debug!("Broadcasting {:?}", timeout);
         let addresses = self
-            .committee
+            .epochs
+            .committee(self.round)
             .broadcast_addresses(&self.name)
             .into_iter()
             .map(|(_, x)| x)
             .collect();
         let message = bincode::serialize(&ConsensusMessage::Timeout(timeout.clone()))
             .expect("Failed to serialize timeout message");
-        let prefix = self.validator_id.clone().into_bytes();
-        let mut prefix_msg : Vec<u8> = Vec::new();
-        prefix_msg.extend(prefix);
-        prefix_msg.extend(message);
+        let envelope = Envelope::seal(&self.validator_id, MessageKind::Consensus, message);
         self.network
-            .broadcast(addresses, Bytes::from(prefix_msg))
+            .broadcast(addresses, envelope)
             .await;
 
         // Process our message.
@@ -214,17 +454,17 @@ impl Core {
         }
 
         // Ensure the vote is well formed.
-        vote.verify(&self.committee)?;
+        vote.verify(self.epochs.committee(vote.round))?;
 
         // Add the new vote to our aggregator and see if we have a quorum.
         if let Some(qc) = self.aggregator.add_vote(vote.clone())? {
             debug!("Assembled {:?}", qc);
 
             // Process the QC.
-            self.process_qc(&qc).await;
+            self.process_qc(&qc).await?;
 
             // Make a new block if we are the next leader.
-            if self.name == self.leader_elector.get_leader(self.round) {
+            if self.name == self.leader_elector.get_leader(self.round, &self.high_qc.hash) {
                 self.generate_proposal(None).await;
             }
         }
@@ -238,10 +478,10 @@ impl Core {
         }
 
         // Ensure the timeout is well formed.
-        timeout.verify(&self.committee)?;
+        timeout.verify(&self.epochs)?;
 
         // Process the QC embedded in the timeout.
-        self.process_qc(&timeout.high_qc).await;
+        self.process_qc(&timeout.high_qc).await?;
 
         // Add the new vote to our aggregator and see if we have a quorum.
         if let Some(tc) = self.aggregator.add_timeout(timeout.clone())? {
@@ -253,23 +493,21 @@ impl Core {
             // Broadcast the TC.
             debug!("Broadcasting {:?}", tc);
             let addresses = self
-                .committee
+                .epochs
+                .committee(self.round)
                 .broadcast_addresses(&self.name)
                 .into_iter()
                 .map(|(_, x)| x)
                 .collect();
             let message = bincode::serialize(&ConsensusMessage::TC(tc.clone()))
                 .expect("Failed to serialize timeout certificate");
-            let prefix = self.validator_id.clone().into_bytes();
-            let mut prefix_msg : Vec<u8> = Vec::new();
-            prefix_msg.extend(prefix);
-            prefix_msg.extend(message);
+            let envelope = Envelope::seal(&self.validator_id, MessageKind::Consensus, message);
             self.network
-                .broadcast(addresses, Bytes::from(prefix_msg))
+                .broadcast(addresses, envelope)
                 .await;
 
             // Make a new block if we are the next leader.
-            if self.name == self.leader_elector.get_leader(self.round) {
+            if self.name == self.leader_elector.get_leader(self.round, &self.high_qc.hash) {
                 self.generate_proposal(Some(tc)).await;
             }
         }
@@ -288,6 +526,9 @@ impl Core {
 
         // Cleanup the vote aggregator.
         self.aggregator.cleanup(&self.round);
+
+        // Hand the mempool over to the new committee if its epoch starts.
+        self.activate_epochs().await;
     }
 
     #[async_recursion]
@@ -312,9 +553,9 @@ impl Core {
             .expect("Failed to send message to proposer");
     }
 
-    async fn process_qc(&mut self, qc: &QC) {
+    async fn process_qc(&mut self, qc: &QC) -> ConsensusResult<()> {
         self.advance_round(qc.round).await;
-        self.update_high_qc(qc);
+        self.update_high_qc(qc).await
     }
 
     #[async_recursion]
@@ -334,7 +575,19 @@ impl Core {
             }
         };
 
+        // Ensure the block proposer is the right leader for the round. The leader depends on the
+        // chain the block extends, so we check it once we have all its ancestors.
+        ensure!(
+            block.author == self.leader_elector.get_leader(block.round, block.parent()),
+            ConsensusError::WrongLeader {
+                digest: block.digest(),
+                leader: block.author,
+                round: block.round
+            }
+        );
+
         // Store the block only if we have already processed all its ancestors.
+        self.leader_elector.add_block(block);
         self.store_block(block).await;
 
         self.cleanup_proposer(&b0, &b1, block).await;
@@ -354,24 +607,22 @@ impl Core {
         }
 
         // See if we can vote for this block.
-        if let Some(vote) = self.make_vote(block).await {
+        if let Some(vote) = self.make_vote(block).await? {
             debug!("Created {:?}", vote);
-            let next_leader = self.leader_elector.get_leader(self.round + 1);
+            let next_leader = self.leader_elector.get_leader(self.round + 1, &block.digest());
             if next_leader == self.name {
                 self.handle_vote(&vote).await?;
             } else {
                 debug!("Sending {:?} to {}", vote, next_leader);
                 let address = self
-                    .committee
+                    .epochs
+                    .committee(self.round + 1)
                     .address(&next_leader)
                     .expect("The next leader is not in the committee");
                 let message = bincode::serialize(&ConsensusMessage::Vote(vote))
                     .expect("Failed to serialize vote");
-                let prefix = self.validator_id.clone().into_bytes();
-                let mut prefix_msg : Vec<u8> = Vec::new();
-                prefix_msg.extend(prefix);
-                prefix_msg.extend(message);
-                self.network.send(address, Bytes::from(prefix_msg)).await;
+                let envelope = Envelope::seal(&self.validator_id, MessageKind::Consensus, message);
+                self.network.send(address, envelope).await;
             }
         }
         Ok(())
@@ -380,21 +631,12 @@ impl Core {
     async fn handle_proposal(&mut self, block: &Block) -> ConsensusResult<()> {
         let digest = block.digest();
 
-        // Ensure the block proposer is the right leader for the round.
-        ensure!(
-            block.author == self.leader_elector.get_leader(block.round),
-            ConsensusError::WrongLeader {
-                digest,
-                leader: block.author,
-                round: block.round
-            }
-        );
-
         // Check the block is correctly formed.
-        block.verify(&self.committee)?;
+        block.verify(&self.epochs)?;
+        self.verify_reconfiguration(block)?;
 
         // Process the QC. This may allow us to advance round.
-        self.process_qc(&block.qc).await;
+        self.process_qc(&block.qc).await?;
 
         // Process the TC (if any). This may also allow us to advance round.
         if let Some(ref tc) = block.tc {
@@ -414,17 +656,19 @@ impl Core {
 
     async fn handle_tc(&mut self, tc: TC) -> ConsensusResult<()> {
         self.advance_round(tc.round).await;
-        if self.name == self.leader_elector.get_leader(self.round) {
+        if self.name == self.leader_elector.get_leader(self.round, &self.high_qc.hash) {
             self.generate_proposal(Some(tc)).await;
         }
         Ok(())
     }
 
     pub async fn run(&mut self) {
-        // Upon booting, generate the very first block (if we are the leader).
-        // Also, schedule a timer in case we don't hear from the leader.
+        // Upon booting, hand the mempool over to the committee in charge after a restart.
+        // Then generate the very first block (if we are the leader), and schedule a timer
+        // in case we don't hear from the leader.
+        self.activate_epochs().await;
         self.timer.reset();
-        if self.name == self.leader_elector.get_leader(self.round) {
+        if self.name == self.leader_elector.get_leader(self.round, &self.high_qc.hash) {
             self.generate_proposal(None).await;
         }
 
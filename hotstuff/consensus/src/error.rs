@@ -1,3 +1,4 @@
+use crate::config::EpochNumber;
 use crate::consensus::Round;
 use crypto::{CryptoError, Digest, PublicKey};
 use store::StoreError;
@@ -62,4 +63,10 @@ pub enum ConsensusError {
 
     #[error("Invalid payload")]
     InvalidPayload,
+
+    #[error("Invalid reconfiguration to epoch {epoch} in block {digest}")]
+    InvalidReconfiguration { digest: Digest, epoch: EpochNumber },
+
+    #[error("Invalid epoch history at epoch {0}")]
+    InvalidEpochHistory(EpochNumber),
 }
@@ -0,0 +1,225 @@
+use crate::consensus::{Round, CHANNEL_CAPACITY};
+use crate::error::ConsensusResult;
+use crate::messages::Block;
+use crate::metrics;
+use crypto::Digest;
+use crypto::Hash as _;
+use log::{error, warn};
+use std::cmp::max;
+use store::Store;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
+
+#[cfg(test)]
+#[path = "tests/gc_tests.rs"]
+pub mod gc_tests;
+
+/// The store key prefix of the index of the keys written in each round. The round is appended
+/// in big-endian, so the index of consecutive rounds can be deleted with a single range.
+pub const GC_INDEX_PREFIX: &[u8] = b"consensus_gc_index";
+
+/// The store key of the last round collected.
+pub const GC_ROUND_KEY: &[u8] = b"consensus_gc_round";
+
+/// The store key of the index of `round`.
+pub fn gc_index_key(round: Round) -> Vec<u8> {
+    let mut key = GC_INDEX_PREFIX.to_vec();
+    key.extend_from_slice(&round.to_be_bytes());
+    key
+}
+
+#[derive(Debug)]
+enum GcMessage {
+    /// The keys of a block of the given round and of its payload.
+    Index(Round, Vec<Digest>),
+    /// Blocks are committed up to the given round.
+    Collect(Round),
+    /// The key of a batch stored by the mempool, whether a block references it or not.
+    Batch(Digest),
+}
+
+/// Deletes the blocks and batches of the rounds more than `gc_depth` rounds behind the last
+/// committed round, whether they were committed or not.
+///
+/// Batches are collected along with the first block referencing them, or `2 * gc_depth` rounds
+/// after they are stored if no block references them within `gc_depth` rounds.
+pub struct GarbageCollector {
+    tx_message: Sender<GcMessage>,
+    /// The collecting task, which lives as long as `self`.
+    handle: JoinHandle<()>,
+}
+
+impl GarbageCollector {
+    pub fn new(store: Store, gc_depth: Round, validator_id: String) -> Self {
+        let (tx_message, rx_message) = channel(CHANNEL_CAPACITY);
+        let handle = GarbageCollectorTask::spawn(store, gc_depth, rx_message, validator_id);
+        Self { tx_message, handle }
+    }
+
+    /// Index `block` and its payload for collection once its round is old enough.
+    pub async fn index(&mut self, block: &Block) {
+        let mut keys = vec![block.digest()];
+        keys.extend(block.payload.iter().cloned());
+        self.tx_message
+            .send(GcMessage::Index(block.round, keys))
+            .await
+            .expect("Failed to send message to garbage collector");
+    }
+
+    /// Returns an indexer of the batches stored by the mempool.
+    pub fn batch_indexer(&self) -> BatchIndexer {
+        BatchIndexer {
+            tx_message: self.tx_message.clone(),
+        }
+    }
+
+    /// Collect the rounds made obsolete by committing `round`.
+    pub async fn collect(&mut self, round: Round) {
+        self.tx_message
+            .send(GcMessage::Collect(round))
+            .await
+            .expect("Failed to send message to garbage collector");
+    }
+}
+
+impl Drop for GarbageCollector {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Indexes batches for collection, so that those no block references are collected too.
+#[derive(Clone)]
+pub struct BatchIndexer {
+    tx_message: Sender<GcMessage>,
+}
+
+impl BatchIndexer {
+    /// Index the batch of digest `digest`, which was just stored.
+    pub async fn index(&mut self, digest: Digest) {
+        // The collector stops along with consensus.
+        let _ = self.tx_message.send(GcMessage::Batch(digest)).await;
+    }
+}
+
+struct GarbageCollectorTask {
+    store: Store,
+    gc_depth: Round,
+    rx_message: Receiver<GcMessage>,
+    /// The last round whose keys are deleted.
+    gc_round: Round,
+    /// The last committed round we know of.
+    committed_round: Round,
+    validator_id: String,
+}
+
+impl GarbageCollectorTask {
+    fn spawn(
+        store: Store,
+        gc_depth: Round,
+        rx_message: Receiver<GcMessage>,
+        validator_id: String,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut task = Self {
+                store,
+                gc_depth,
+                rx_message,
+                gc_round: 0,
+                committed_round: 0,
+                validator_id,
+            };
+            match task.load_gc_round().await {
+                Ok(round) => task.gc_round = round,
+                Err(e) => {
+                    error!(
+                        "Failed to load the gc round of validator {}: {}",
+                        task.validator_id, e
+                    );
+                    return;
+                }
+            }
+            task.run().await;
+        })
+    }
+
+    async fn load_gc_round(&mut self) -> ConsensusResult<Round> {
+        match self.store.read(GC_ROUND_KEY.to_vec()).await? {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(0),
+        }
+    }
+
+    async fn run(&mut self) {
+        while let Some(message) = self.rx_message.recv().await {
+            let result = match message {
+                GcMessage::Index(round, keys) => self.index(round, keys).await,
+                GcMessage::Collect(round) => self.collect(round).await,
+                GcMessage::Batch(digest) => self.index(self.batch_round(), vec![digest]).await,
+            };
+            if let Err(e) = result {
+                warn!("Garbage collection of validator {} failed: {}", self.validator_id, e);
+            }
+        }
+    }
+
+    /// The round under which to index a batch stored now: `gc_depth` rounds after the next round
+    /// to commit, so that a batch referenced by a block within `gc_depth` rounds is collected
+    /// along with that block. Until we learn about the last committed round after a restart, the
+    /// rounds collected so far tell how far consensus went.
+    fn batch_round(&self) -> Round {
+        max(self.committed_round, self.gc_round + self.gc_depth) + 1 + self.gc_depth
+    }
+
+    async fn index(&mut self, round: Round, keys: Vec<Digest>) -> ConsensusResult<()> {
+        // A block may be stored after its round is collected (e.g., when syncing).
+        if round <= self.gc_round {
+            for key in keys {
+                self.store.delete(key.to_vec()).await;
+            }
+            return Ok(());
+        }
+
+        let index_key = gc_index_key(round);
+        let mut index: Vec<Digest> = match self.store.read(index_key.clone()).await? {
+            Some(bytes) => bincode::deserialize(&bytes)?,
+            None => Vec::new(),
+        };
+        index.extend(keys);
+        let value = bincode::serialize(&index).expect("Failed to serialize gc index");
+        self.store.write(index_key, value).await;
+        Ok(())
+    }
+
+    async fn collect(&mut self, committed_round: Round) -> ConsensusResult<()> {
+        self.committed_round = max(self.committed_round, committed_round);
+        let target = match committed_round.checked_sub(self.gc_depth) {
+            Some(target) if target > self.gc_round => target,
+            _ => return Ok(()),
+        };
+
+        for round in self.gc_round + 1..=target {
+            if let Some(bytes) = self.store.read(gc_index_key(round)).await? {
+                let index: Vec<Digest> = bincode::deserialize(&bytes)?;
+                for key in index {
+                    self.store.delete(key.to_vec()).await;
+                }
+            }
+        }
+        self.store
+            .delete_range(gc_index_key(self.gc_round + 1), gc_index_key(target + 1))
+            .await;
+
+        self.gc_round = target;
+        let value = bincode::serialize(&self.gc_round).expect("Failed to serialize gc round");
+        self.store.write(GC_ROUND_KEY.to_vec(), value).await;
+
+        let store_metrics = self.store.metrics().await?;
+        let labels = &[self.validator_id.as_str()];
+        metrics::set_gauge_vec(&metrics::GC_ROUND, labels, target as i64);
+        metrics::set_gauge_vec(&metrics::STORE_ESTIMATED_KEYS, labels, store_metrics.estimated_keys as i64);
+        metrics::set_gauge_vec(&metrics::STORE_LIVE_DATA_BYTES, labels, store_metrics.live_data_size as i64);
+        metrics::set_gauge_vec(&metrics::STORE_SST_FILES_BYTES, labels, store_metrics.sst_files_size as i64);
+        Ok(())
+    }
+}
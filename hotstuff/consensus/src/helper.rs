@@ -1,11 +1,12 @@
-use crate::config::Committee;
+use crate::config::EpochSchedule;
 use crate::consensus::ConsensusMessage;
-use bytes::Bytes;
 use crypto::{Digest, PublicKey};
 use log::warn;
-use network::SimpleSender;
+use network::{Envelope, MessageKind, SimpleSender};
 use store::Store;
 use tokio::sync::mpsc::Receiver;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 #[cfg(test)]
 #[path = "tests/helper_tests.rs"]
@@ -13,8 +14,8 @@ pub mod helper_tests;
 
 /// A task dedicated to help other authorities by replying to their sync requests.
 pub struct Helper {
-    /// The committee information.
-    committee: Committee,
+    /// The committees of all epochs so far.
+    rx_epochs: watch::Receiver<EpochSchedule>,
     /// The persistent storage.
     store: Store,
     /// Input channel to receive sync requests.
@@ -25,10 +26,10 @@ pub struct Helper {
 }
 
 impl Helper {
-    pub fn spawn(committee: Committee, store: Store, rx_requests: Receiver<(Digest, PublicKey)>, validator_id: String) {
+    pub fn spawn(rx_epochs: watch::Receiver<EpochSchedule>, store: Store, rx_requests: Receiver<(Digest, PublicKey)>, validator_id: String) -> JoinHandle<()> {
         tokio::spawn(async move {
             Self {
-                committee,
+                rx_epochs,
                 store,
                 rx_requests,
                 network: SimpleSender::new(),
@@ -36,7 +37,7 @@ impl Helper {
             }
             .run()
             .await;
-        });
+        })
     }
 
     async fn run(&mut self) {
@@ -44,7 +45,7 @@ impl Helper {
             // TODO [issue #58]: Do some accounting to prevent bad nodes from monopolizing our resources.
 
             // get the requestors address.
-            let address = match self.committee.address(&origin) {
+            let address = match self.rx_epochs.borrow().address(&origin) {
                 Some(x) => x,
                 None => {
                     warn!("Received sync request from unknown authority: {}", origin);
@@ -64,11 +65,8 @@ impl Helper {
                 let message = bincode::serialize(&ConsensusMessage::Propose(block))
                     .expect("Failed to serialize block");
 
-                let prefix = self.validator_id.clone().into_bytes();
-                let mut prefix_msg : Vec<u8> = Vec::new();
-                prefix_msg.extend(prefix);
-                prefix_msg.extend(message);    
-                self.network.send(address, Bytes::from(prefix_msg)).await;
+                let envelope = Envelope::seal(&self.validator_id, MessageKind::Consensus, message);
+                self.network.send(address, envelope).await;
             }
         }
     }
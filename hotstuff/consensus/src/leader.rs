@@ -1,21 +1,202 @@
-use crate::config::Committee;
+use crate::config::{Committee, EpochSchedule, LeaderElection};
 use crate::consensus::Round;
-use crypto::PublicKey;
+use crate::messages::Block;
+use crypto::{Digest, Hash as _, PublicKey};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
-pub type LeaderElector = RRLeaderElector;
+#[cfg(test)]
+#[path = "tests/leader_tests.rs"]
+pub mod leader_tests;
 
-pub struct RRLeaderElector {
+/// The number of committed blocks kept beyond the reputation window. A proposal may extend a
+/// chain whose last committed block lags a few blocks behind ours.
+pub const HISTORY_MARGIN: usize = 10;
+
+/// Elects the leader of each round. Every honest node must elect the same leader for a round,
+/// so electors may only learn from the chain certified by the QC the leader extends.
+pub trait LeaderElector: Send {
+    /// Returns the leader of `round`, whose block extends the block of digest `parent`.
+    fn get_leader(&self, round: Round, parent: &Digest) -> PublicKey;
+
+    /// Elect among `committee` from `activation_round` onwards.
+    fn schedule(&mut self, activation_round: Round, committee: Committee);
+
+    /// Learn about a block whose ancestors are all known.
+    fn add_block(&mut self, _block: &Block) {}
+
+    /// Learn about a newly committed block. Blocks are committed in increasing rounds.
+    fn commit(&mut self, _block: &Block) {}
+
+    /// The number of most recently committed blocks the elector learns from, which are replayed
+    /// upon restart.
+    fn history_depth(&self) -> usize {
+        0
+    }
+}
+
+/// Make the leader elector described by `leader_election`.
+pub fn make_leader_elector(
+    leader_election: &LeaderElection,
     committee: Committee,
+) -> Box<dyn LeaderElector> {
+    match leader_election {
+        LeaderElection::RoundRobin => Box::new(RRLeaderElector::new(committee)),
+        LeaderElection::Reputation { window, exclude } => {
+            Box::new(ReputationLeaderElector::new(committee, *window, *exclude))
+        }
+    }
+}
+
+/// Rotates among the authorities of the committee, in the order of their public keys.
+pub struct RRLeaderElector {
+    epochs: EpochSchedule,
 }
 
 impl RRLeaderElector {
     pub fn new(committee: Committee) -> Self {
-        Self { committee }
+        Self {
+            epochs: EpochSchedule::new(committee),
+        }
+    }
+}
+
+impl LeaderElector for RRLeaderElector {
+    fn get_leader(&self, round: Round, _parent: &Digest) -> PublicKey {
+        round_robin(self.epochs.committee(round), round)
+    }
+
+    fn schedule(&mut self, activation_round: Round, committee: Committee) {
+        self.epochs.schedule(activation_round, committee);
+    }
+}
+
+fn round_robin(committee: &Committee, round: Round) -> PublicKey {
+    let mut keys: Vec<_> = committee.authorities.keys().cloned().collect();
+    keys.sort();
+    keys[round as usize % committee.size()]
+}
+
+/// The round, author, voters and parent of a block known to the `ReputationLeaderElector`.
+struct BlockInfo {
+    round: Round,
+    author: PublicKey,
+    voters: Vec<PublicKey>,
+    parent: Digest,
+}
+
+/// Carousel: rotates among the authorities that recently authored or voted for committed blocks,
+/// so crashed authorities stop being elected. The authors of the very last blocks are left out
+/// to give everyone else a turn. The committed blocks are those of the chain certified by the QC
+/// the leader extends, so that every node checking a proposal elects the same leader; proposals
+/// following a timeout fall back to round robin.
+pub struct ReputationLeaderElector {
+    epochs: EpochSchedule,
+    /// The number of committed blocks whose authors and voters are candidates.
+    window: usize,
+    /// The number of most recently committed blocks whose authors are not candidates.
+    exclude: usize,
+    /// The blocks recent enough to elect leaders, by digest.
+    blocks: HashMap<Digest, BlockInfo>,
+    /// The rounds of the latest committed blocks, oldest first.
+    committed: VecDeque<Round>,
+}
+
+impl ReputationLeaderElector {
+    pub fn new(committee: Committee, window: usize, exclude: usize) -> Self {
+        Self {
+            epochs: EpochSchedule::new(committee),
+            window,
+            exclude,
+            blocks: HashMap::new(),
+            committed: VecDeque::new(),
+        }
+    }
+
+    /// Returns the last blocks committed by the chain certified by a QC on `certified`, latest
+    /// first: the 2-chain rule commits the parent of the latest certified block that directly
+    /// follows its parent.
+    fn committed_chain(&self, certified: &Digest) -> Vec<&BlockInfo> {
+        let mut child = match self.blocks.get(certified) {
+            Some(block) => block,
+            None => return Vec::new(),
+        };
+        let mut head = loop {
+            match self.blocks.get(&child.parent) {
+                Some(parent) if parent.round + 1 == child.round => break Some(parent),
+                Some(parent) => child = parent,
+                None => break None,
+            }
+        };
+        let mut chain = Vec::new();
+        while let Some(block) = head {
+            if chain.len() == self.window {
+                break;
+            }
+            chain.push(block);
+            head = self.blocks.get(&block.parent);
+        }
+        chain
+    }
+}
+
+impl LeaderElector for ReputationLeaderElector {
+    fn get_leader(&self, round: Round, parent: &Digest) -> PublicKey {
+        let committee = self.epochs.committee(round);
+
+        // After a timeout, nodes may disagree on the chain the leader extends.
+        let window = match self.blocks.get(parent) {
+            Some(block) if block.round + 1 == round => self.committed_chain(parent),
+            _ => return round_robin(committee, round),
+        };
+
+        // The candidates are sorted so that all nodes index them the same way.
+        let excluded: HashSet<_> = window
+            .iter()
+            .take(self.exclude)
+            .map(|block| &block.author)
+            .collect();
+        let candidates: Vec<_> = window
+            .iter()
+            .flat_map(|block| block.voters.iter().chain(std::iter::once(&block.author)))
+            .filter(|name| committee.stake(name) > 0 && !excluded.contains(name))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        // Without enough history (e.g., at genesis or after a reconfiguration), everyone gets a turn.
+        if candidates.is_empty() {
+            return round_robin(committee, round);
+        }
+        *candidates[round as usize % candidates.len()]
+    }
+
+    fn schedule(&mut self, activation_round: Round, committee: Committee) {
+        self.epochs.schedule(activation_round, committee);
+    }
+
+    fn add_block(&mut self, block: &Block) {
+        let info = BlockInfo {
+            round: block.round,
+            author: block.author,
+            voters: block.qc.votes.iter().map(|(name, _)| *name).collect(),
+            parent: block.parent().clone(),
+        };
+        self.blocks.insert(block.digest(), info);
+    }
+
+    fn commit(&mut self, block: &Block) {
+        self.committed.push_back(block.round);
+        while self.committed.len() > self.history_depth() {
+            self.committed.pop_front();
+        }
+
+        // Forget the blocks older than the committed ones we keep, forks included.
+        if let Some(oldest) = self.committed.front() {
+            self.blocks.retain(|_, block| block.round >= *oldest);
+        }
     }
 
-    pub fn get_leader(&self, round: Round) -> PublicKey {
-        let mut keys: Vec<_> = self.committee.authorities.keys().cloned().collect();
-        keys.sort();
-        keys[round as usize % self.committee.size()]
+    fn history_depth(&self) -> usize {
+        self.window + HISTORY_MARGIN
     }
 }
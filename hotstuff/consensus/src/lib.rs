@@ -4,10 +4,12 @@ mod aggregator;
 mod config;
 mod consensus;
 mod core;
+mod gc;
 mod helper;
 mod leader;
 mod mempool;
 mod messages;
+mod metrics;
 mod proposer;
 mod synchronizer;
 mod timer;
@@ -16,6 +18,8 @@ mod timer;
 #[path = "tests/common.rs"]
 mod common;
 
-pub use crate::config::{Committee, Parameters};
-pub use crate::consensus::{Consensus, ConsensusReceiverHandler};
-pub use crate::messages::{Block, QC, TC};
+pub use crate::config::{Committee, EpochNumber, LeaderElection, Parameters};
+pub use crate::consensus::{Consensus, ConsensusReceiverHandler, Round, RECONFIGURATION_DELAY};
+pub use crate::core::{bootstrap_epochs, epoch_history, LAST_COMMITTED_KEY};
+pub use crate::error::{ConsensusError, ConsensusResult};
+pub use crate::messages::{Block, Reconfiguration, QC, TC};
@@ -7,15 +7,18 @@ use futures::future::try_join_all;
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
 use log::error;
-use mempool::ConsensusMempoolMessage;
+use mempool::{Committee as MempoolCommittee, ConsensusMempoolMessage};
 use std::collections::HashMap;
 use store::Store;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
 
 pub struct MempoolDriver {
     store: Store,
     tx_mempool: Sender<ConsensusMempoolMessage>,
     tx_payload_waiter: Sender<PayloadWaiterMessage>,
+    /// The payload waiter, which lives as long as `self`.
+    payload_waiter: JoinHandle<()>,
 }
 
 impl MempoolDriver {
@@ -27,13 +30,14 @@ impl MempoolDriver {
         let (tx_payload_waiter, rx_payload_waiter) = channel(CHANNEL_CAPACITY);
 
         // Spawn the payload waiter.
-        PayloadWaiter::spawn(store.clone(), rx_payload_waiter, tx_loopback);
+        let payload_waiter = PayloadWaiter::spawn(store.clone(), rx_payload_waiter, tx_loopback);
 
         // Returns the mempool driver.
         Self {
             store,
             tx_mempool,
             tx_payload_waiter,
+            payload_waiter,
         }
     }
 
@@ -63,6 +67,13 @@ impl MempoolDriver {
         Ok(false)
     }
 
+    pub async fn reconfigure(&mut self, committee: MempoolCommittee) {
+        self.tx_mempool
+            .send(ConsensusMempoolMessage::Reconfigure(committee))
+            .await
+            .expect("Failed to send reconfiguration message");
+    }
+
     pub async fn cleanup(&mut self, round: Round) {
         // Cleanup the mempool.
         self.tx_mempool
@@ -78,6 +89,12 @@ impl MempoolDriver {
     }
 }
 
+impl Drop for MempoolDriver {
+    fn drop(&mut self) {
+        self.payload_waiter.abort();
+    }
+}
+
 #[derive(Debug)]
 enum PayloadWaiterMessage {
     Wait(Vec<Digest>, Block),
@@ -95,7 +112,7 @@ impl PayloadWaiter {
         store: Store,
         rx_message: Receiver<PayloadWaiterMessage>,
         tx_loopback: Sender<Block>,
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
             Self {
                 store,
@@ -104,7 +121,7 @@ impl PayloadWaiter {
             }
             .run()
             .await;
-        });
+        })
     }
 
     async fn waiter(
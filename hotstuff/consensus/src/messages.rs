@@ -1,9 +1,10 @@
-use crate::config::Committee;
+use crate::config::{Committee, EpochNumber, EpochSchedule};
 use crate::consensus::Round;
 use crate::error::{ConsensusError, ConsensusResult};
 use crypto::{Digest, Hash, PublicKey, Signature, SignatureService};
 use ed25519_dalek::Digest as _;
 use ed25519_dalek::Sha512;
+use mempool::Committee as MempoolCommittee;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::convert::TryInto;
@@ -20,6 +21,7 @@ pub struct Block {
     pub author: PublicKey,
     pub round: Round,
     pub payload: Vec<Digest>,
+    pub reconfiguration: Option<Reconfiguration>,
     pub signature: Signature,
 }
 
@@ -30,6 +32,7 @@ impl Block {
         author: PublicKey,
         round: Round,
         payload: Vec<Digest>,
+        reconfiguration: Option<Reconfiguration>,
         mut signature_service: SignatureService,
     ) -> Self {
         let block = Self {
@@ -38,6 +41,7 @@ impl Block {
             author,
             round,
             payload,
+            reconfiguration,
             signature: Signature::default(),
         };
         let signature = signature_service.request_signature(block.digest()).await;
@@ -52,9 +56,9 @@ impl Block {
         &self.qc.hash
     }
 
-    pub fn verify(&self, committee: &Committee) -> ConsensusResult<()> {
+    pub fn verify(&self, epochs: &EpochSchedule) -> ConsensusResult<()> {
         // Ensure the authority has voting rights.
-        let voting_rights = committee.stake(&self.author);
+        let voting_rights = epochs.committee(self.round).stake(&self.author);
         ensure!(
             voting_rights > 0,
             ConsensusError::UnknownAuthority(self.author)
@@ -63,14 +67,14 @@ impl Block {
         // Check the signature.
         self.signature.verify(&self.digest(), &self.author)?;
 
-        // Check the embedded QC.
+        // Check the embedded QC, certified by the committee of its own round.
         if self.qc != QC::genesis() {
-            self.qc.verify(committee)?;
+            self.qc.verify(epochs.committee(self.qc.round))?;
         }
 
         // Check the TC embedded in the block (if any).
         if let Some(ref tc) = self.tc {
-            tc.verify(committee)?;
+            tc.verify(epochs.committee(tc.round))?;
         }
         Ok(())
     }
@@ -85,6 +89,9 @@ impl Hash for Block {
             hasher.update(x);
         }
         hasher.update(&self.qc.hash);
+        if let Some(ref reconfiguration) = self.reconfiguration {
+            hasher.update(&reconfiguration.digest());
+        }
         Digest(hasher.finalize().as_slice()[..32].try_into().unwrap())
     }
 }
@@ -247,10 +254,10 @@ impl Timeout {
         }
     }
 
-    pub fn verify(&self, committee: &Committee) -> ConsensusResult<()> {
+    pub fn verify(&self, epochs: &EpochSchedule) -> ConsensusResult<()> {
         // Ensure the authority has voting rights.
         ensure!(
-            committee.stake(&self.author) > 0,
+            epochs.committee(self.round).stake(&self.author) > 0,
             ConsensusError::UnknownAuthority(self.author)
         );
 
@@ -259,7 +266,7 @@ impl Timeout {
 
         // Check the embedded QC.
         if self.high_qc != QC::genesis() {
-            self.high_qc.verify(committee)?;
+            self.high_qc.verify(epochs.committee(self.high_qc.round))?;
         }
         Ok(())
     }
@@ -324,3 +331,69 @@ impl fmt::Debug for TC {
         write!(f, "TC({}, {:?})", self.round, self.high_qc_rounds())
     }
 }
+
+/// Hands consensus and mempool over to a new set of authorities, from `activation_round` onwards.
+/// It takes effect once the block carrying it is committed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reconfiguration {
+    pub activation_round: Round,
+    pub consensus: Committee,
+    pub mempool: MempoolCommittee,
+}
+
+impl Reconfiguration {
+    pub fn new(consensus: Committee, mempool: MempoolCommittee) -> Self {
+        Self {
+            activation_round: 0,
+            consensus,
+            mempool,
+        }
+    }
+
+    pub fn epoch(&self) -> EpochNumber {
+        self.consensus.epoch
+    }
+
+    /// Returns the authorities of the new committee, in a canonical order.
+    fn names(&self) -> Vec<PublicKey> {
+        let mut names: Vec<_> = self.consensus.authorities.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl Hash for Reconfiguration {
+    fn digest(&self) -> Digest {
+        let mut hasher = Sha512::new();
+        hasher.update(self.activation_round.to_le_bytes());
+        hasher.update(self.consensus.epoch.to_le_bytes());
+        hasher.update(self.mempool.epoch.to_le_bytes());
+        for name in self.names() {
+            hasher.update(name.0);
+            if let Some(authority) = self.consensus.authorities.get(&name) {
+                hasher.update(authority.stake.to_le_bytes());
+                hasher.update(authority.address.to_string());
+            }
+            if let Some(authority) = self.mempool.authorities.get(&name) {
+                hasher.update(authority.stake.to_le_bytes());
+                hasher.update(authority.transactions_address.to_string());
+                hasher.update(authority.mempool_address.to_string());
+                hasher.update(authority.dvf_address.to_string());
+                hasher.update(authority.signature_address.to_string());
+            }
+        }
+        Digest(hasher.finalize().as_slice()[..32].try_into().unwrap())
+    }
+}
+
+impl fmt::Debug for Reconfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "R({}, {}, {:?})",
+            self.epoch(),
+            self.activation_round,
+            self.names()
+        )
+    }
+}
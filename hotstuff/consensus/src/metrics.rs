@@ -0,0 +1,24 @@
+pub use lighthouse_metrics::*;
+
+lazy_static::lazy_static! {
+    pub static ref GC_ROUND: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "hotstuff_gc_round",
+        "The last round collected from the store of each validator",
+        &["validator"]
+    );
+    pub static ref STORE_ESTIMATED_KEYS: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "hotstuff_store_estimated_keys",
+        "Estimated number of keys of the database holding the store of each validator",
+        &["validator"]
+    );
+    pub static ref STORE_LIVE_DATA_BYTES: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "hotstuff_store_live_data_bytes",
+        "Estimated size of the live data of the database holding the store of each validator",
+        &["validator"]
+    );
+    pub static ref STORE_SST_FILES_BYTES: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "hotstuff_store_sst_files_bytes",
+        "Size of the SST files of the database holding the store of each validator",
+        &["validator"]
+    );
+}
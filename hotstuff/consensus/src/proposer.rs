@@ -1,14 +1,17 @@
-use crate::config::{Committee, Stake};
-use crate::consensus::{ConsensusMessage, Round};
-use crate::messages::{Block, QC, TC};
-use bytes::Bytes;
+use crate::config::{EpochSchedule, Stake};
+use crate::consensus::{ConsensusMessage, Round, RECONFIGURATION_DELAY};
+use crate::gc::BatchIndexer;
+use crate::messages::{Block, Reconfiguration, QC, TC};
 use crypto::{Digest, PublicKey, SignatureService};
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
 use log::{debug, info};
-use network::{CancelHandler, ReliableSender};
+use network::{CancelHandler, Envelope, MessageKind, ReliableSender};
+use std::cmp::max;
 use std::collections::HashSet;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 #[derive(Debug)]
 pub enum ProposerMessage {
@@ -18,41 +21,52 @@ pub enum ProposerMessage {
 
 pub struct Proposer {
     name: PublicKey,
-    committee: Committee,
+    rx_epochs: watch::Receiver<EpochSchedule>,
     signature_service: SignatureService,
     rx_mempool: Receiver<Digest>,
     rx_message: Receiver<ProposerMessage>,
+    rx_reconfiguration: Receiver<Reconfiguration>,
     tx_loopback: Sender<Block>,
+    /// Indexes the batches we hear of, so that they are collected even if never proposed.
+    batch_indexer: BatchIndexer,
     buffer: HashSet<Digest>,
+    /// The reconfiguration to propose when we lead, until it is scheduled.
+    reconfiguration: Option<Reconfiguration>,
     network: ReliableSender,
     validator_id: String
 }
 
 impl Proposer {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: PublicKey,
-        committee: Committee,
+        rx_epochs: watch::Receiver<EpochSchedule>,
         signature_service: SignatureService,
         rx_mempool: Receiver<Digest>,
         rx_message: Receiver<ProposerMessage>,
+        rx_reconfiguration: Receiver<Reconfiguration>,
         tx_loopback: Sender<Block>,
+        batch_indexer: BatchIndexer,
         validator_id: String
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
             Self {
                 name,
-                committee,
+                rx_epochs,
                 signature_service,
                 rx_mempool,
                 rx_message,
+                rx_reconfiguration,
                 tx_loopback,
+                batch_indexer,
                 buffer: HashSet::new(),
+                reconfiguration: None,
                 network: ReliableSender::new(),
                 validator_id
             }
             .run()
             .await;
-        });
+        })
     }
 
     /// Helper function. It waits for a future to complete and then delivers a value.
@@ -61,14 +75,32 @@ impl Proposer {
         deliver
     }
 
+    /// Returns the pending reconfiguration to include in the block of `round`, if any.
+    fn reconfiguration(&mut self, round: Round) -> Option<Reconfiguration> {
+        // Drop the reconfiguration once a block carrying it is committed.
+        let latest_epoch = self.rx_epochs.borrow().latest().1.epoch;
+        if matches!(self.reconfiguration, Some(ref r) if r.epoch() <= latest_epoch) {
+            self.reconfiguration = None;
+        }
+        self.reconfiguration.clone().map(|mut reconfiguration| {
+            reconfiguration.activation_round = max(
+                reconfiguration.activation_round,
+                round + RECONFIGURATION_DELAY,
+            );
+            reconfiguration
+        })
+    }
+
     async fn make_block(&mut self, round: Round, qc: QC, tc: Option<TC>) {
         // Generate a new block.
+        let reconfiguration = self.reconfiguration(round);
         let block = Block::new(
             qc,
             tc,
             self.name,
             round,
             /* payload */ self.buffer.drain().collect(),
+            reconfiguration,
             self.signature_service.clone(),
         )
         .await;
@@ -86,21 +118,18 @@ impl Proposer {
 
         // Broadcast our new block.
         debug!("Broadcasting {:?}", block);
-        let (names, addresses): (Vec<_>, _) = self
-            .committee
+        let committee = self.rx_epochs.borrow().committee(round).clone();
+        let (names, addresses): (Vec<_>, _) = committee
             .broadcast_addresses(&self.name)
             .iter()
             .cloned()
             .unzip();
         let message = bincode::serialize(&ConsensusMessage::Propose(block.clone()))
             .expect("Failed to serialize block");
-        let mut prefix_msg : Vec<u8> = Vec::new();
-        let prefix = self.validator_id.clone().into_bytes();
-        prefix_msg.extend(prefix);
-        prefix_msg.extend(message); 
+        let envelope = Envelope::seal(&self.validator_id, MessageKind::Consensus, message);
         let handles = self
             .network
-            .broadcast(addresses, Bytes::from(prefix_msg))
+            .broadcast(addresses, envelope)
             .await;
 
         // Send our block to the core for processing.
@@ -114,15 +143,15 @@ impl Proposer {
             .into_iter()
             .zip(handles.into_iter())
             .map(|(name, handler)| {
-                let stake = self.committee.stake(&name);
+                let stake = committee.stake(&name);
                 Self::waiter(handler, stake)
             })
             .collect();
 
-        let mut total_stake = self.committee.stake(&self.name);
+        let mut total_stake = committee.stake(&self.name);
         while let Some(stake) = wait_for_quorum.next().await {
             total_stake += stake;
-            if total_stake >= self.committee.quorum_threshold() {
+            if total_stake >= committee.quorum_threshold() {
                 break;
             }
         }
@@ -132,10 +161,15 @@ impl Proposer {
         loop {
             tokio::select! {
                 Some(digest) = self.rx_mempool.recv() => {
+                    self.batch_indexer.index(digest.clone()).await;
                     //if self.buffer.len() < 155 {
                         self.buffer.insert(digest);
                     //}
                 },
+                Some(reconfiguration) = self.rx_reconfiguration.recv() => {
+                    info!("Proposing reconfiguration to epoch {}", reconfiguration.epoch());
+                    self.reconfiguration = Some(reconfiguration);
+                },
                 Some(message) = self.rx_message.recv() => match message {
                     ProposerMessage::Make(round, qc, tc) => self.make_block(round, qc, tc).await,
                     ProposerMessage::Cleanup(digests) => {
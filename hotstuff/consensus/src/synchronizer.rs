@@ -1,18 +1,19 @@
-use crate::config::Committee;
+use crate::config::EpochSchedule;
 use crate::consensus::{ConsensusMessage, CHANNEL_CAPACITY};
 use crate::error::ConsensusResult;
 use crate::messages::{Block, QC};
-use bytes::Bytes;
 use crypto::Hash as _;
 use crypto::{Digest, PublicKey};
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
 use log::{debug, error};
-use network::SimpleSender;
+use network::{Envelope, MessageKind, SimpleSender};
 use std::collections::{HashMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH};
 use store::Store;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration, Instant};
 
 #[cfg(test)]
@@ -24,12 +25,14 @@ const TIMER_ACCURACY: u64 = 5_000;
 pub struct Synchronizer {
     store: Store,
     inner_channel: Sender<Block>,
+    /// The task syncing missing blocks, which lives as long as `self`.
+    handle: JoinHandle<()>,
 }
 
 impl Synchronizer {
     pub fn new(
         name: PublicKey,
-        committee: Committee,
+        rx_epochs: watch::Receiver<EpochSchedule>,
         store: Store,
         tx_loopback: Sender<Block>,
         sync_retry_delay: u64,
@@ -39,7 +42,7 @@ impl Synchronizer {
         let (tx_inner, mut rx_inner): (_, Receiver<Block>) = channel(CHANNEL_CAPACITY);
 
         let store_copy = store.clone();
-        tokio::spawn(async move {
+        let handle = tokio::spawn(async move {
             let mut waiting = FuturesUnordered::new();
             let mut pending = HashSet::new();
             let mut requests = HashMap::new();
@@ -52,6 +55,7 @@ impl Synchronizer {
                         if pending.insert(block.digest()) {
                             let parent = block.parent().clone();
                             let author = block.author;
+                            let round = block.round;
                             let fut = Self::waiter(store_copy.clone(), parent.clone(), block);
                             waiting.push(fut);
 
@@ -62,16 +66,16 @@ impl Synchronizer {
                                     .expect("Failed to measure time")
                                     .as_millis();
                                 requests.insert(parent.clone(), now);
-                                let address = committee
+                                let address = rx_epochs
+                                    .borrow()
+                                    .committee(round)
                                     .address(&author)
                                     .expect("Author of valid block is not in the committee");
                                 let message = ConsensusMessage::SyncRequest(parent, name);
                                 let message = bincode::serialize(&message)
                                     .expect("Failed to serialize sync request");
-                                let mut prefix_msg : Vec<u8> = Vec::new();
-                                prefix_msg.extend(validator_id.clone().into_bytes());
-                                prefix_msg.extend(message);
-                                network.send(address, Bytes::from(prefix_msg)).await;
+                                let envelope = Envelope::seal(&validator_id, MessageKind::Consensus, message);
+                                network.send(address, envelope).await;
                             }
                         }
                     },
@@ -94,7 +98,10 @@ impl Synchronizer {
                                 .as_millis();
                             if timestamp + (sync_retry_delay as u128) < now {
                                 debug!("Requesting sync for block {} (retry)", digest);
-                                let addresses = committee
+                                let addresses = rx_epochs
+                                    .borrow()
+                                    .latest()
+                                    .1
                                     .broadcast_addresses(&name)
                                     .into_iter()
                                     .map(|(_, x)| x)
@@ -102,10 +109,8 @@ impl Synchronizer {
                                 let message = ConsensusMessage::SyncRequest(digest.clone(), name);
                                 let message = bincode::serialize(&message)
                                     .expect("Failed to serialize sync request");
-                                let mut prefix_msg : Vec<u8> = Vec::new();
-                                prefix_msg.extend(validator_id.clone().into_bytes());
-                                prefix_msg.extend(message);
-                                network.broadcast(addresses, Bytes::from(prefix_msg)).await;
+                                let envelope = Envelope::seal(&validator_id, MessageKind::Consensus, message);
+                                network.broadcast(addresses, envelope).await;
                             }
                         }
                         timer.as_mut().reset(Instant::now() + Duration::from_millis(TIMER_ACCURACY));
@@ -116,6 +121,7 @@ impl Synchronizer {
         Self {
             store,
             inner_channel: tx_inner,
+            handle,
         }
     }
 
@@ -155,3 +161,9 @@ impl Synchronizer {
         Ok(Some((b0, b1)))
     }
 }
+
+impl Drop for Synchronizer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
@@ -54,3 +54,26 @@ fn cleanup() {
     assert!(aggregator.votes_aggregators.is_empty());
     assert!(aggregator.timeouts_aggregators.is_empty());
 }
+
+#[test]
+fn make_qc_after_reconfiguration() {
+    // From round 5, the last authority is the only member of the committee.
+    let mut aggregator = Aggregator::new(committee());
+    let (public_key, secret_key) = keys().pop().unwrap();
+    let mut next = committee();
+    next.authorities.retain(|name, _| *name == public_key);
+    next.epoch += 1;
+    aggregator.schedule(5, next.clone());
+
+    // Its vote alone is not a quorum of the current committee...
+    let hash = qc().digest();
+    let vote = Vote::new_from_key(hash.clone(), 4, public_key, &secret_key);
+    assert!(aggregator.add_vote(vote).unwrap().is_none());
+
+    // ...but it is one of the new committee.
+    let vote = Vote::new_from_key(hash, 5, public_key, &secret_key);
+    match aggregator.add_vote(vote) {
+        Ok(Some(qc)) => assert!(qc.verify(&next).is_ok()),
+        _ => assert!(false),
+    }
+}
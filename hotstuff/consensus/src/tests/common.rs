@@ -1,10 +1,11 @@
-use crate::config::Committee;
+use crate::config::{Committee, EpochNumber};
 use crate::consensus::Round;
-use crate::messages::{Block, Timeout, Vote, QC};
+use crate::messages::{Block, Reconfiguration, Timeout, Vote, QC};
 use bytes::Bytes;
 use crypto::Hash as _;
 use crypto::{generate_keypair, Digest, PublicKey, SecretKey, Signature};
 use futures::sink::SinkExt as _;
+use mempool::Committee as MempoolCommittee;
 use futures::stream::StreamExt as _;
 use rand::rngs::StdRng;
 use rand::SeedableRng as _;
@@ -35,6 +36,21 @@ pub fn committee() -> Committee {
     )
 }
 
+// Fixture.
+pub fn mempool_committee(epoch: EpochNumber) -> MempoolCommittee {
+    MempoolCommittee::new(
+        keys()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, _))| {
+                let address: SocketAddr = format!("127.0.0.1:{}", 100 + i).parse().unwrap();
+                (name, /* stake */ 1, address, address, address, address)
+            })
+            .collect(),
+        epoch,
+    )
+}
+
 // Fixture.
 pub fn committee_with_base_port(base_port: u16) -> Committee {
     let mut committee = committee();
@@ -59,11 +75,21 @@ impl Block {
             author,
             round,
             payload,
+            reconfiguration: None,
             signature: Signature::default(),
         };
         let signature = Signature::new(&block.digest(), secret);
         Self { signature, ..block }
     }
+
+    pub fn with_reconfiguration(self, reconfiguration: Reconfiguration, secret: &SecretKey) -> Self {
+        let block = Block {
+            reconfiguration: Some(reconfiguration),
+            ..self
+        };
+        let signature = Signature::new(&block.digest(), secret);
+        Self { signature, ..block }
+    }
 }
 
 impl PartialEq for Block {
@@ -145,6 +171,14 @@ pub fn qc() -> QC {
 
 // Fixture.
 pub fn chain(keys: Vec<(PublicKey, SecretKey)>) -> Vec<Block> {
+    chain_with_reconfiguration(keys, None)
+}
+
+// Fixture. The first block of the chain carries `reconfiguration` (if any).
+pub fn chain_with_reconfiguration(
+    keys: Vec<(PublicKey, SecretKey)>,
+    mut reconfiguration: Option<Reconfiguration>,
+) -> Vec<Block> {
     let mut latest_qc = QC::genesis();
     keys.iter()
         .enumerate()
@@ -158,6 +192,10 @@ pub fn chain(keys: Vec<(PublicKey, SecretKey)>) -> Vec<Block> {
                 Vec::new(),
                 secret_key,
             );
+            let block = match reconfiguration.take() {
+                Some(reconfiguration) => block.with_reconfiguration(reconfiguration, secret_key),
+                None => block,
+            };
 
             // Make a qc for that block (it will be used for the next block).
             let qc = QC {
@@ -3,30 +3,30 @@ use crate::common::{committee_with_base_port, keys};
 use crate::config::Parameters;
 use crypto::SecretKey;
 use futures::future::try_join_all;
-use std::fs;
+use network::{MessageKind, Receiver as NetworkReceiver};
+use store::MemoryBackend;
 use tokio::sync::mpsc::channel;
 use tokio::task::JoinHandle;
 
-fn spawn_nodes(
-    keys: Vec<(PublicKey, SecretKey)>,
-    committee: Committee,
-    store_path: &str,
-) -> Vec<JoinHandle<Block>> {
+const VALIDATOR_ID: &str = "0";
+
+fn spawn_nodes(keys: Vec<(PublicKey, SecretKey)>, committee: Committee) -> Vec<JoinHandle<Block>> {
     keys.into_iter()
-        .enumerate()
-        .map(|(i, (name, secret))| {
+        .map(|(name, secret)| {
             let committee = committee.clone();
             let parameters = Parameters {
                 timeout_delay: 100,
                 ..Parameters::default()
             };
-            let store_path = format!("{}_{}", store_path, i);
-            let _ = fs::remove_dir_all(&store_path);
-            let store = Store::new(&store_path).unwrap();
+            let store = Store::from_backend(MemoryBackend::new());
             let signature_service = SignatureService::new(secret);
             let (tx_consensus_to_mempool, mut rx_consensus_to_mempool) = channel(10);
             let (_tx_mempool_to_consensus, rx_mempool_to_consensus) = channel(1);
             let (tx_commit, mut rx_commit) = channel(1);
+            let (_tx_reconfiguration, rx_reconfiguration) = channel(1);
+            let consensus_handler_map = Arc::new(RwLock::new(HashMap::new()));
+            let address = committee.address(&name).unwrap();
+            NetworkReceiver::spawn(address, MessageKind::Consensus, Arc::clone(&consensus_handler_map));
 
             // Sink the mempool channel.
             tokio::spawn(async move {
@@ -46,6 +46,9 @@ fn spawn_nodes(
                     rx_mempool_to_consensus,
                     tx_consensus_to_mempool,
                     tx_commit,
+                    rx_reconfiguration,
+                    VALIDATOR_ID.to_string(),
+                    consensus_handler_map,
                 );
 
                 rx_commit.recv().await.unwrap()
@@ -59,8 +62,7 @@ async fn end_to_end() {
     let committee = committee_with_base_port(15_000);
 
     // Run all nodes.
-    let store_path = ".db_test_end_to_end";
-    let handles = spawn_nodes(keys(), committee, store_path);
+    let handles = spawn_nodes(keys(), committee);
 
     // Ensure all threads terminated correctly.
     let blocks = try_join_all(handles).await.unwrap();
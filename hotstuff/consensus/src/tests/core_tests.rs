@@ -1,19 +1,47 @@
 use super::*;
-use crate::common::{chain, committee, committee_with_base_port, keys, listener};
-use crypto::SecretKey;
+use crate::common::{
+    chain, chain_with_reconfiguration, committee, committee_with_base_port, keys, listener,
+    mempool_committee,
+};
+use crate::leader::RRLeaderElector;
+use crypto::{Digest, SecretKey};
 use futures::future::try_join_all;
-use std::fs;
+use futures::stream::StreamExt as _;
+use store::MemoryBackend;
+use tokio::net::TcpListener;
 use tokio::sync::mpsc::channel;
+use tokio::sync::watch;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+const VALIDATOR_ID: &str = "0";
 
 fn core(
     name: PublicKey,
     secret: SecretKey,
     committee: Committee,
-    store_path: &str,
 ) -> (
     Sender<ConsensusMessage>,
     Receiver<ProposerMessage>,
     Receiver<Block>,
+) {
+    let store = Store::from_backend(MemoryBackend::new());
+    let (tx_epochs, _) = watch::channel(EpochSchedule::new(committee.clone()));
+    let (tx_core, rx_proposer, rx_commit, _) =
+        core_with_store(name, secret, committee, store, tx_epochs);
+    (tx_core, rx_proposer, rx_commit)
+}
+
+fn core_with_store(
+    name: PublicKey,
+    secret: SecretKey,
+    committee: Committee,
+    store: Store,
+    tx_epochs: watch::Sender<EpochSchedule>,
+) -> (
+    Sender<ConsensusMessage>,
+    Receiver<ProposerMessage>,
+    Receiver<Block>,
+    JoinHandle<()>,
 ) {
     let (tx_core, rx_core) = channel(1);
     let (tx_loopback, rx_loopback) = channel(1);
@@ -22,25 +50,27 @@ fn core(
     let (tx_commit, rx_commit) = channel(1);
 
     let signature_service = SignatureService::new(secret);
-    let _ = fs::remove_dir_all(store_path);
-    let store = Store::new(store_path).unwrap();
-    let leader_elector = LeaderElector::new(committee.clone());
+    let leader_elector = Box::new(RRLeaderElector::new(committee.clone()));
     let mempool_driver = MempoolDriver::new(store.clone(), tx_mempool, tx_loopback.clone());
     let synchronizer = Synchronizer::new(
         name,
-        committee.clone(),
+        tx_epochs.subscribe(),
         store.clone(),
         tx_loopback,
         /* sync_retry_delay */ 100_000,
+        VALIDATOR_ID.to_string(),
     );
 
+    let garbage_collector =
+        GarbageCollector::new(store.clone(), /* gc_depth */ 1_000, VALIDATOR_ID.to_string());
+
     tokio::spawn(async move {
         loop {
             rx_mempool.recv().await;
         }
     });
 
-    Core::spawn(
+    let handle = Core::spawn(
         name,
         committee,
         signature_service,
@@ -48,19 +78,22 @@ fn core(
         leader_elector,
         mempool_driver,
         synchronizer,
+        garbage_collector,
         /* timeout_delay */ 100,
         /* rx_message */ rx_core,
         rx_loopback,
         tx_proposer,
         tx_commit,
+        tx_epochs,
+        VALIDATOR_ID.to_string(),
     );
 
-    (tx_core, rx_proposer, rx_commit)
+    (tx_core, rx_proposer, rx_commit, handle)
 }
 
 fn leader_keys(round: Round) -> (PublicKey, SecretKey) {
-    let leader_elector = LeaderElector::new(committee());
-    let leader = leader_elector.get_leader(round);
+    let leader_elector = RRLeaderElector::new(committee());
+    let leader = leader_elector.get_leader(round, &Digest::default());
     keys()
         .into_iter()
         .find(|(public_key, _)| *public_key == leader)
@@ -75,12 +108,12 @@ async fn handle_proposal() {
     let block = chain(vec![leader_keys(1)]).pop().unwrap();
     let (public_key, secret_key) = keys().pop().unwrap();
     let vote = Vote::new_from_key(block.digest(), block.round, public_key, &secret_key);
-    let expected = bincode::serialize(&ConsensusMessage::Vote(vote)).unwrap();
+    let message = bincode::serialize(&ConsensusMessage::Vote(vote)).unwrap();
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Consensus, message);
 
     // Run a core instance.
-    let store_path = ".db_test_handle_proposal";
     let (tx_core, _rx_proposer, _rx_commit) =
-        core(public_key, secret_key, committee.clone(), store_path);
+        core(public_key, secret_key, committee.clone());
 
     // Send a block to the core.
     let message = ConsensusMessage::Propose(block.clone());
@@ -89,7 +122,7 @@ async fn handle_proposal() {
     // Ensure the next leaders gets the vote.
     let (next_leader, _) = leader_keys(2);
     let address = committee.address(&next_leader).unwrap();
-    let handle = listener(address, Some(Bytes::from(expected)));
+    let handle = listener(address, Some(expected));
     assert!(handle.await.is_ok());
 }
 
@@ -119,9 +152,8 @@ async fn generate_proposal() {
     };
 
     // Run a core instance.
-    let store_path = ".db_test_generate_proposal";
     let (tx_core, mut rx_proposer, _rx_commit) =
-        core(next_leader, next_leader_key, committee(), store_path);
+        core(next_leader, next_leader_key, committee());
 
     // Send all votes to the core.
     for vote in votes.clone() {
@@ -147,10 +179,9 @@ async fn commit_block() {
     let chain = chain(leaders);
 
     // Run a core instance.
-    let store_path = ".db_test_commit_block";
     let (public_key, secret_key) = keys().pop().unwrap();
     let (tx_core, mut rx_proposer, mut rx_commit) =
-        core(public_key, secret_key, committee(), store_path);
+        core(public_key, secret_key, committee());
 
     // Send a the blocks to the core.
     let committed = chain[0].clone();
@@ -175,18 +206,197 @@ async fn local_timeout_round() {
     // Make the timeout vote we expect to send.
     let (public_key, secret_key) = leader_keys(3);
     let timeout = Timeout::new_from_key(QC::genesis(), 1, public_key, &secret_key);
-    let expected = bincode::serialize(&ConsensusMessage::Timeout(timeout)).unwrap();
+    let message = bincode::serialize(&ConsensusMessage::Timeout(timeout)).unwrap();
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Consensus, message);
 
     // Run a core instance.
-    let store_path = ".db_test_local_timeout_round";
     let (_tx_core, _rx_proposer, _rx_commit) =
-        core(public_key, secret_key, committee.clone(), store_path);
+        core(public_key, secret_key, committee.clone());
 
     // Ensure the node broadcasts a timeout vote.
     let handles: Vec<_> = committee
         .broadcast_addresses(&public_key)
         .into_iter()
-        .map(|(_, address)| listener(address, Some(Bytes::from(expected.clone()))))
+        .map(|(_, address)| listener(address, Some(expected.clone())))
         .collect();
     assert!(try_join_all(handles).await.is_ok());
 }
+
+#[tokio::test]
+async fn restart_never_votes_twice() {
+    let committee = committee_with_base_port(16_200);
+    let (public_key, secret_key) = keys().pop().unwrap();
+    let (leader, leader_key) = leader_keys(1);
+    let (next_leader, _) = leader_keys(2);
+    let address = committee.address(&next_leader).unwrap();
+
+    let mut store = Store::from_backend(MemoryBackend::new());
+
+    // Run a core instance and ensure it votes for the block of round 1.
+    let block = chain(vec![(leader, leader_key.clone())]).pop().unwrap();
+    let vote = Vote::new_from_key(block.digest(), block.round, public_key, &secret_key);
+    let message = bincode::serialize(&ConsensusMessage::Vote(vote)).unwrap();
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Consensus, message);
+    let (tx_epochs, _) = watch::channel(EpochSchedule::new(committee.clone()));
+    let (tx_core, _rx_proposer, _rx_commit, handle) = core_with_store(
+        public_key,
+        secret_key.clone(),
+        committee.clone(),
+        store.clone(),
+        tx_epochs,
+    );
+    let vote_listener = listener(address, Some(expected));
+    tx_core.send(ConsensusMessage::Propose(block)).await.unwrap();
+    assert!(vote_listener.await.is_ok());
+
+    // Crash the core, then restart it on the same store.
+    handle.abort();
+    let _ = handle.await;
+    let (tx_epochs, _) = watch::channel(EpochSchedule::new(committee.clone()));
+    let (tx_core, _rx_proposer, _rx_commit, _) =
+        core_with_store(public_key, secret_key, committee.clone(), store.clone(), tx_epochs);
+
+    // The (equivocating) leader proposes a conflicting block for round 1.
+    let payload = Digest([1; 32]);
+    store.write(payload.to_vec(), Vec::new()).await;
+    let conflicting = Block::new_from_key(QC::genesis(), leader, 1, vec![payload], &leader_key);
+    let listener = TcpListener::bind(&address).await.unwrap();
+    tx_core.send(ConsensusMessage::Propose(conflicting)).await.unwrap();
+
+    // The restarted core does not vote for it: the next leader only hears its timeout.
+    let (socket, _) = listener.accept().await.unwrap();
+    let mut transport = Framed::new(socket, LengthDelimitedCodec::new());
+    let frame = transport.next().await.unwrap().unwrap().freeze();
+    let envelope = Envelope::decode(frame).unwrap();
+    match bincode::deserialize(&envelope.payload).unwrap() {
+        ConsensusMessage::Timeout(timeout) => assert_eq!(timeout.round, 1),
+        message => panic!("Unexpected message {:?}", message),
+    }
+}
+
+#[tokio::test]
+async fn commit_reconfiguration() {
+    // The committee of the next epoch moves all authorities to other ports.
+    let mut next = committee_with_base_port(16_300);
+    next.epoch += 1;
+    let reconfiguration = Reconfiguration {
+        activation_round: 1 + RECONFIGURATION_DELAY,
+        consensus: next,
+        mempool: mempool_committee(committee().epoch + 1),
+    };
+    let leaders = vec![leader_keys(1), leader_keys(2), leader_keys(3)];
+    let chain = chain_with_reconfiguration(leaders, Some(reconfiguration.clone()));
+
+    // Run a core instance.
+    let store = Store::from_backend(MemoryBackend::new());
+    let (public_key, secret_key) = keys().pop().unwrap();
+    let (tx_epochs, mut rx_epochs) = watch::channel(EpochSchedule::new(committee()));
+    let (tx_core, mut rx_proposer, mut rx_commit, handle) = core_with_store(
+        public_key,
+        secret_key.clone(),
+        committee(),
+        store.clone(),
+        tx_epochs,
+    );
+
+    // Send the blocks to the core.
+    for block in chain.clone() {
+        tx_core.send(ConsensusMessage::Propose(block)).await.unwrap();
+        let _ = rx_proposer.recv().await.unwrap();
+    }
+
+    // Ensure the new committee is scheduled once the block carrying it is committed.
+    match rx_commit.recv().await {
+        Some(b) => assert_eq!(b, chain[0]),
+        _ => assert!(false),
+    }
+    rx_epochs.changed().await.unwrap();
+    {
+        let epochs = rx_epochs.borrow();
+        let (activation_round, latest) = epochs.latest();
+        assert_eq!(activation_round, reconfiguration.activation_round);
+        assert_eq!(latest.epoch, reconfiguration.epoch());
+        assert_eq!(epochs.committee(activation_round - 1).epoch, committee().epoch);
+    }
+
+    // The schedule survives a restart.
+    handle.abort();
+    let _ = handle.await;
+    let (tx_epochs, mut rx_epochs) = watch::channel(EpochSchedule::new(committee()));
+    let _core = core_with_store(public_key, secret_key, committee(), store, tx_epochs);
+    rx_epochs.changed().await.unwrap();
+    assert_eq!(rx_epochs.borrow().latest().1.epoch, reconfiguration.epoch());
+}
+
+#[tokio::test]
+async fn drop_late_reconfiguration() {
+    let mut next = committee_with_base_port(16_400);
+    next.epoch += 1;
+    let reconfiguration = Reconfiguration {
+        activation_round: 1 + RECONFIGURATION_DELAY,
+        consensus: next,
+        mempool: mempool_committee(committee().epoch + 1),
+    };
+    let leaders = vec![leader_keys(1), leader_keys(2), leader_keys(3)];
+    let chain = chain_with_reconfiguration(leaders, Some(reconfiguration.clone()));
+
+    // Run a core instance.
+    let store = Store::from_backend(MemoryBackend::new());
+    let (public_key, secret_key) = keys().pop().unwrap();
+    let (tx_epochs, rx_epochs) = watch::channel(EpochSchedule::new(committee()));
+    let (tx_core, mut rx_proposer, mut rx_commit, _handle) =
+        core_with_store(public_key, secret_key, committee(), store, tx_epochs);
+    tokio::spawn(async move { while rx_proposer.recv().await.is_some() {} });
+
+    // Timeouts move the core past the activation round before the block is committed.
+    let tc = TC {
+        round: reconfiguration.activation_round,
+        votes: Vec::new(),
+    };
+    tx_core.send(ConsensusMessage::TC(tc)).await.unwrap();
+    for block in chain.clone() {
+        tx_core.send(ConsensusMessage::Propose(block)).await.unwrap();
+    }
+
+    // The block commits, but the rounds the old committee already ran are not handed over.
+    assert_eq!(rx_commit.recv().await.unwrap(), chain[0]);
+    assert_eq!(rx_epochs.borrow().latest().1.epoch, committee().epoch);
+}
+
+#[tokio::test]
+async fn bootstrap_joining_authority() {
+    let mut next = committee_with_base_port(16_500);
+    next.epoch += 1;
+    let reconfiguration = Reconfiguration {
+        activation_round: 1 + RECONFIGURATION_DELAY,
+        consensus: next,
+        mempool: mempool_committee(committee().epoch + 1),
+    };
+
+    let mut store = Store::from_backend(MemoryBackend::new());
+
+    // The history must follow from the genesis committee.
+    let mut skipped = reconfiguration.clone();
+    skipped.consensus.epoch += 1;
+    assert!(matches!(
+        bootstrap_epochs(&mut store, &committee(), &[skipped]).await,
+        Err(ConsensusError::InvalidEpochHistory(_))
+    ));
+    bootstrap_epochs(&mut store, &committee(), std::slice::from_ref(&reconfiguration))
+        .await
+        .unwrap();
+    let history = epoch_history(&mut store).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].epoch(), reconfiguration.epoch());
+
+    // The joining authority starts from the genesis committee and learns the next epochs.
+    let (public_key, secret_key) = keys().pop().unwrap();
+    let (tx_epochs, mut rx_epochs) = watch::channel(EpochSchedule::new(committee()));
+    let _core = core_with_store(public_key, secret_key, committee(), store, tx_epochs);
+    rx_epochs.changed().await.unwrap();
+    let epochs = rx_epochs.borrow();
+    let (activation_round, latest) = epochs.latest();
+    assert_eq!(activation_round, reconfiguration.activation_round);
+    assert_eq!(latest.epoch, reconfiguration.epoch());
+    assert_eq!(epochs.committee(1).epoch, committee().epoch);
+}
@@ -0,0 +1,120 @@
+use super::*;
+use crate::common::keys;
+use crate::messages::QC;
+use store::MemoryBackend;
+
+// Stores a block of each round from 1 to 4, each with a batch of its own.
+async fn store_blocks(store: &mut Store, garbage_collector: &mut GarbageCollector) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for (i, (public_key, secret_key)) in keys().into_iter().enumerate() {
+        let round = 1 + i as Round;
+        let batch = Digest([round as u8; 32]);
+        let block = Block::new_from_key(
+            QC::genesis(),
+            public_key,
+            round,
+            vec![batch.clone()],
+            &secret_key,
+        );
+        store.write(batch.to_vec(), vec![round as u8]).await;
+        store.write(block.digest().to_vec(), vec![round as u8]).await;
+        garbage_collector.index(&block).await;
+        blocks.push(block);
+    }
+    blocks
+}
+
+#[tokio::test]
+async fn collect_old_rounds() {
+    let mut store = Store::from_backend(MemoryBackend::new());
+    let mut garbage_collector =
+        GarbageCollector::new(store.clone(), /* gc_depth */ 2, "0".to_string());
+    let blocks = store_blocks(&mut store, &mut garbage_collector).await;
+
+    // Committing round 4 makes rounds 1 and 2 obsolete.
+    garbage_collector.collect(4).await;
+    let gc_round = store.notify_read(GC_ROUND_KEY.to_vec()).await.unwrap();
+    assert_eq!(bincode::deserialize::<Round>(&gc_round).unwrap(), 2);
+
+    for block in &blocks {
+        let collected = block.round <= 2;
+        let keys = std::iter::once(block.digest()).chain(block.payload.iter().cloned());
+        for key in keys {
+            let value = store.read(key.to_vec()).await.unwrap();
+            assert_eq!(value.is_none(), collected);
+        }
+        let index = store.read(gc_index_key(block.round)).await.unwrap();
+        assert_eq!(index.is_none(), collected);
+    }
+}
+
+#[tokio::test]
+async fn resume_collection() {
+    let mut store = Store::from_backend(MemoryBackend::new());
+    let mut garbage_collector =
+        GarbageCollector::new(store.clone(), /* gc_depth */ 2, "0".to_string());
+    let blocks = store_blocks(&mut store, &mut garbage_collector).await;
+    garbage_collector.collect(3).await;
+    store.notify_read(GC_ROUND_KEY.to_vec()).await.unwrap();
+
+    // A restarted collector resumes after the last round it collected, and immediately
+    // deletes blocks of rounds it already collected.
+    let mut garbage_collector =
+        GarbageCollector::new(store.clone(), /* gc_depth */ 2, "0".to_string());
+    let late = &blocks[0];
+    store.write(late.digest().to_vec(), Vec::new()).await;
+    garbage_collector.index(late).await;
+    garbage_collector.collect(4).await;
+
+    // Wait for round 2 to be collected.
+    loop {
+        let gc_round = store.read(GC_ROUND_KEY.to_vec()).await.unwrap().unwrap();
+        if bincode::deserialize::<Round>(&gc_round).unwrap() == 2 {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+    assert!(store.read(late.digest().to_vec()).await.unwrap().is_none());
+    assert!(store.read(blocks[1].digest().to_vec()).await.unwrap().is_none());
+    assert!(store.read(blocks[2].digest().to_vec()).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn collect_unreferenced_batches() {
+    let mut store = Store::from_backend(MemoryBackend::new());
+    let mut garbage_collector =
+        GarbageCollector::new(store.clone(), /* gc_depth */ 2, "0".to_string());
+    let blocks = store_blocks(&mut store, &mut garbage_collector).await;
+
+    // A batch no block references is indexed `gc_depth` rounds after the next round to commit.
+    let batch = Digest([0; 32]);
+    store.write(batch.to_vec(), Vec::new()).await;
+    garbage_collector.batch_indexer().index(batch.clone()).await;
+    garbage_collector.collect(4).await;
+    store.notify_read(GC_ROUND_KEY.to_vec()).await.unwrap();
+    assert!(store.read(batch.to_vec()).await.unwrap().is_some());
+
+    // The batches of blocks are still collected with them.
+    let referenced = blocks[3].payload[0].clone();
+    garbage_collector.batch_indexer().index(referenced.clone()).await;
+    garbage_collector.collect(6).await;
+    loop {
+        let gc_round = store.read(GC_ROUND_KEY.to_vec()).await.unwrap().unwrap();
+        if bincode::deserialize::<Round>(&gc_round).unwrap() == 4 {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+    assert!(store.read(referenced.to_vec()).await.unwrap().is_none());
+    assert!(store.read(batch.to_vec()).await.unwrap().is_some());
+
+    garbage_collector.collect(9).await;
+    loop {
+        let gc_round = store.read(GC_ROUND_KEY.to_vec()).await.unwrap().unwrap();
+        if bincode::deserialize::<Round>(&gc_round).unwrap() == 7 {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+    assert!(store.read(batch.to_vec()).await.unwrap().is_none());
+}
@@ -1,9 +1,11 @@
 use super::*;
 use crate::common::{block, committee_with_base_port, keys, listener};
 use crypto::Hash as _;
-use std::fs;
+use store::MemoryBackend;
 use tokio::sync::mpsc::channel;
 
+const VALIDATOR_ID: &str = "0";
+
 #[tokio::test]
 async fn sync_reply() {
     let (tx_request, rx_request) = channel(1);
@@ -11,9 +13,7 @@ async fn sync_reply() {
     let committee = committee_with_base_port(13_000);
 
     // Create a new test store.
-    let path = ".db_test_sync_reply";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::from_backend(MemoryBackend::new());
 
     // Add a batch to the store.
     let digest = block().digest();
@@ -21,12 +21,14 @@ async fn sync_reply() {
     store.write(digest.to_vec(), serialized.clone()).await;
 
     // Spawn an `Helper` instance.
-    Helper::spawn(committee.clone(), store, rx_request);
+    let (_tx_epochs, rx_epochs) = watch::channel(EpochSchedule::new(committee.clone()));
+    Helper::spawn(rx_epochs, store, rx_request, VALIDATOR_ID.to_string());
 
     // Spawn a listener to receive the sync reply.
     let address = committee.address(&requestor).unwrap();
     let message = ConsensusMessage::Propose(block());
-    let expected = Bytes::from(bincode::serialize(&message).unwrap());
+    let message = bincode::serialize(&message).unwrap();
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Consensus, message);
     let handle = listener(address, Some(expected));
 
     // Send a sync request.
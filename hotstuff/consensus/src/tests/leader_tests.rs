@@ -0,0 +1,105 @@
+use super::*;
+use crate::common::{committee, keys};
+use crate::messages::QC;
+use crypto::{Digest, SecretKey, Signature};
+
+// Add a block of `round` by `author` extending `parent`, certified by the votes of `voters`.
+fn extend(
+    elector: &mut ReputationLeaderElector,
+    parent: Option<&Block>,
+    round: Round,
+    author: &(PublicKey, SecretKey),
+    voters: &[(PublicKey, SecretKey)],
+) -> Block {
+    let qc = QC {
+        hash: parent.map_or_else(Digest::default, |parent| parent.digest()),
+        round: parent.map_or(0, |parent| parent.round),
+        votes: voters
+            .iter()
+            .map(|(name, _)| (*name, Signature::default()))
+            .collect(),
+    };
+    let block = Block::new_from_key(qc, author.0, round, Vec::new(), &author.1);
+    elector.add_block(&block);
+    block
+}
+
+// Add a chain of blocks of consecutive rounds, from `round` onwards.
+fn extend_chain(
+    elector: &mut ReputationLeaderElector,
+    mut parent: Option<Block>,
+    round: Round,
+    authors: &[(PublicKey, SecretKey)],
+    voters: &[(PublicKey, SecretKey)],
+) -> Vec<Block> {
+    let mut chain = Vec::new();
+    for (i, author) in authors.iter().enumerate() {
+        let block = extend(elector, parent.as_ref(), round + i as Round, author, voters);
+        parent = Some(block.clone());
+        chain.push(block);
+    }
+    chain
+}
+
+#[test]
+fn round_robin_without_history() {
+    let round_robin = RRLeaderElector::new(committee());
+    let reputation = ReputationLeaderElector::new(committee(), 10, 1);
+    for round in 0..8 {
+        assert_eq!(
+            reputation.get_leader(round, &Digest::default()),
+            round_robin.get_leader(round, &Digest::default())
+        );
+    }
+}
+
+#[test]
+fn skip_crashed_authority() {
+    // The last authority crashed: it neither proposes nor votes.
+    let mut keys = keys();
+    let (crashed, _) = keys.pop().unwrap();
+    let mut elector = ReputationLeaderElector::new(committee(), 10, 1);
+    let authors: Vec<_> = (1..=10).map(|i| keys[i % keys.len()].clone()).collect();
+    let chain = extend_chain(&mut elector, None, 1, &authors, &keys);
+
+    // The block of round 10 commits the block of round 9, whose author is excluded.
+    let leader = elector.get_leader(11, &chain[9].digest());
+    assert_ne!(leader, crashed);
+    assert_ne!(leader, chain[8].author);
+}
+
+#[test]
+fn round_robin_after_timeout() {
+    let keys = keys();
+    let round_robin = RRLeaderElector::new(committee());
+    let mut elector = ReputationLeaderElector::new(committee(), 10, 1);
+    let chain = extend_chain(&mut elector, None, 1, &vec![keys[0].clone(); 5], &keys[..3]);
+
+    // The leader of round 7 extends the block of round 5: nodes may disagree on that chain.
+    let digest = chain[4].digest();
+    assert_eq!(
+        elector.get_leader(7, &digest),
+        round_robin.get_leader(7, &digest)
+    );
+}
+
+#[test]
+fn elect_from_certified_chain() {
+    let keys = keys();
+    let mut elector = ReputationLeaderElector::new(committee(), 10, 1);
+    let chain = extend_chain(&mut elector, None, 1, &vec![keys[0].clone(); 5], &keys[..3]);
+    let leader = elector.get_leader(6, &chain[4].digest());
+
+    // Committing blocks locally does not change the leader: only the chain it extends does.
+    for block in &chain {
+        elector.commit(block);
+    }
+    assert_eq!(elector.get_leader(6, &chain[4].digest()), leader);
+
+    // A fork following a timeout elects from its own committed blocks.
+    let fork = extend(&mut elector, Some(&chain[3]), 6, &keys[3], &keys[1..]);
+    let fork = extend(&mut elector, Some(&fork), 7, &keys[2], &keys[1..]);
+    assert_ne!(elector.get_leader(8, &fork.digest()), keys[3].0);
+    let fork = extend(&mut elector, Some(&fork), 8, &keys[1], &keys[1..]);
+    assert_ne!(elector.get_leader(9, &fork.digest()), keys[2].0);
+}
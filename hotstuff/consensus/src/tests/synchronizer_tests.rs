@@ -1,6 +1,8 @@
 use super::*;
 use crate::common::{block, chain, committee, committee_with_base_port, keys, listener};
-use std::fs;
+use store::MemoryBackend;
+
+const VALIDATOR_ID: &str = "0";
 
 #[tokio::test]
 async fn get_existing_parent_block() {
@@ -9,9 +11,7 @@ async fn get_existing_parent_block() {
     let b2 = chain.pop().unwrap();
 
     // Add the block b2 to the store.
-    let path = ".db_test_get_existing_parent_block";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::from_backend(MemoryBackend::new());
     let key = b2.digest().to_vec();
     let value = bincode::serialize(&b2).unwrap();
     let _ = store.write(key, value).await;
@@ -19,12 +19,14 @@ async fn get_existing_parent_block() {
     // Make a new synchronizer.
     let (name, _) = keys().pop().unwrap();
     let (tx_loopback, _) = channel(10);
+    let (_tx_epochs, rx_epochs) = watch::channel(EpochSchedule::new(committee()));
     let mut synchronizer = Synchronizer::new(
         name,
-        committee(),
+        rx_epochs,
         store,
         tx_loopback,
         /* sync_retry_delay */ 10_000,
+        VALIDATOR_ID.to_string(),
     );
 
     // Ask the predecessor of 'block' to the synchronizer.
@@ -37,17 +39,17 @@ async fn get_existing_parent_block() {
 #[tokio::test]
 async fn get_genesis_parent_block() {
     // Make a new synchronizer.
-    let path = ".db_test_get_genesis_parent_block";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::from_backend(MemoryBackend::new());
     let (name, _) = keys().pop().unwrap();
     let (tx_loopback, _) = channel(1);
+    let (_tx_epochs, rx_epochs) = watch::channel(EpochSchedule::new(committee()));
     let mut synchronizer = Synchronizer::new(
         name,
-        committee(),
+        rx_epochs,
         store,
         tx_loopback,
         /* sync_retry_delay */ 10_000,
+        VALIDATOR_ID.to_string(),
     );
 
     // Ask the predecessor of 'block' to the synchronizer.
@@ -65,23 +67,24 @@ async fn get_missing_parent_block() {
     let parent_block = chain.pop().unwrap();
 
     // Make a new synchronizer.
-    let path = ".db_test_get_missing_parent_block";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::from_backend(MemoryBackend::new());
     let (name, _) = keys().pop().unwrap();
     let (tx_loopback, mut rx_loopback) = channel(1);
+    let (_tx_epochs, rx_epochs) = watch::channel(EpochSchedule::new(committee.clone()));
     let mut synchronizer = Synchronizer::new(
         name,
-        committee.clone(),
+        rx_epochs,
         store.clone(),
         tx_loopback,
         /* sync_retry_delay */ 10_000,
+        VALIDATOR_ID.to_string(),
     );
 
     // Spawn a listener to receive our sync request.
     let address = committee.address(&block.author).unwrap();
     let message = ConsensusMessage::SyncRequest(parent_block.digest(), name);
-    let expected = Bytes::from(bincode::serialize(&message).unwrap());
+    let message = bincode::serialize(&message).unwrap();
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Consensus, message);
     let listener_handle = listener(address, Some(expected.clone()));
 
     // Ask for the parent of a block to the synchronizer. The store does not have the parent yet.
@@ -90,13 +93,15 @@ async fn get_missing_parent_block() {
         let ret = synchronizer.get_parent_block(&copy).await;
         assert!(ret.is_ok());
         assert!(ret.unwrap().is_none());
+        synchronizer
     });
 
     // Ensure the other listeners correctly received the sync request.
     assert!(listener_handle.await.is_ok());
 
-    // Ensure the synchronizer returns None, thus suspending the processing of the block.
-    assert!(handle.await.is_ok());
+    // Ensure the synchronizer returns None, thus suspending the processing of the block. It only
+    // keeps syncing while it lives.
+    let _synchronizer = handle.await.unwrap();
 
     // Add the parent to the store.
     let key = parent_block.digest().to_vec();
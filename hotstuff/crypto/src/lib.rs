@@ -1,4 +1,8 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use curve25519_dalek::constants::X25519_BASEPOINT;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use curve25519_dalek::scalar::Scalar;
 use ed25519_dalek as dalek;
 use ed25519_dalek::ed25519;
 use ed25519_dalek::Signer as _;
@@ -77,6 +81,13 @@ impl PublicKey {
             .map_err(|_| base64::DecodeError::InvalidLength)?;
         Ok(Self(array))
     }
+
+    /// Returns the X25519 (Montgomery) form of the key, or `None` if it is not a valid point.
+    pub fn to_x25519(&self) -> Option<[u8; 32]> {
+        CompressedEdwardsY(self.0)
+            .decompress()
+            .map(|point| point.to_montgomery().to_bytes())
+    }
 }
 
 impl fmt::Debug for PublicKey {
@@ -133,6 +144,15 @@ impl SecretKey {
             .map_err(|_| base64::DecodeError::InvalidLength)?;
         Ok(Self(array))
     }
+
+    /// X25519 Diffie-Hellman between the scalar of this key and the X25519 public key
+    /// `their_public`.
+    pub fn diffie_hellman(&self, their_public: &[u8; 32]) -> [u8; 32] {
+        let secret = dalek::SecretKey::from_bytes(&self.0[..32]).expect("Unable to load secret key");
+        let expanded = dalek::ExpandedSecretKey::from(&secret).to_bytes();
+        let scalar = Scalar::from_bits(expanded[..32].try_into().expect("Unexpected scalar length"));
+        (MontgomeryPoint(*their_public) * scalar).to_bytes()
+    }
 }
 
 impl Serialize for SecretKey {
@@ -175,6 +195,29 @@ where
     (public, secret)
 }
 
+pub fn x25519_production_agreement(recipient: &PublicKey) -> Option<([u8; 32], [u8; 32])> {
+    x25519_ephemeral_agreement(recipient, &mut OsRng)
+}
+
+/// Generates an ephemeral X25519 key to agree on a secret with `recipient`, who can derive it
+/// with `SecretKey::diffie_hellman`. Returns the ephemeral public key and the shared secret, or
+/// `None` if `recipient` is not a valid point.
+pub fn x25519_ephemeral_agreement<R>(recipient: &PublicKey, csprng: &mut R) -> Option<([u8; 32], [u8; 32])>
+where
+    R: CryptoRng + RngCore,
+{
+    let recipient = MontgomeryPoint(recipient.to_x25519()?);
+    let mut bytes = [0u8; 32];
+    csprng.fill_bytes(&mut bytes);
+    bytes[0] &= 248;
+    bytes[31] &= 127;
+    bytes[31] |= 64;
+    let scalar = Scalar::from_bits(bytes);
+    let ephemeral_public = (X25519_BASEPOINT * scalar).to_bytes();
+    let shared = (recipient * scalar).to_bytes();
+    Some((ephemeral_public, shared))
+}
+
 /// Represents an ed25519 signature.
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct Signature {
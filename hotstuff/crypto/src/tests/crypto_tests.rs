@@ -130,3 +130,16 @@ async fn signature_service() {
     // Verify the signature we received.
     assert!(signature.verify(&digest, &public_key).is_ok());
 }
+
+#[test]
+fn x25519_key_agreement() {
+    let mut keys = keys();
+    let (public_key, secret_key) = keys.pop().unwrap();
+    let (_, other_secret_key) = keys.pop().unwrap();
+
+    // The recipient derives the same secret from the ephemeral key, and nobody else does.
+    let mut rng = StdRng::from_seed([1; 32]);
+    let (ephemeral_public, shared) = x25519_ephemeral_agreement(&public_key, &mut rng).unwrap();
+    assert_eq!(secret_key.diffie_hellman(&ephemeral_public), shared);
+    assert_ne!(other_secret_key.diffie_hellman(&ephemeral_public), shared);
+}
@@ -1,6 +1,6 @@
+use crate::config::Committee;
 use crate::mempool::MempoolMessage;
 use crate::quorum_waiter::QuorumWaiterMessage;
-use bytes::Bytes;
 #[cfg(feature = "benchmark")]
 use crypto::Digest;
 use crypto::PublicKey;
@@ -8,11 +8,13 @@ use crypto::PublicKey;
 use ed25519_dalek::{Digest as _, Sha512};
 #[cfg(feature = "benchmark")]
 use log::info;
-use network::ReliableSender;
+use network::{Envelope, MessageKind, ReliableSender};
 #[cfg(feature = "benchmark")]
 use std::convert::TryInto as _;
 use std::net::SocketAddr;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration, Instant};
 
 #[cfg(test)]
@@ -32,6 +34,10 @@ pub struct BatchMaker {
     rx_transaction: Receiver<Transaction>,
     /// Output channel to deliver sealed batches to the `QuorumWaiter`.
     tx_message: Sender<QuorumWaiterMessage>,
+    /// The public key of this authority.
+    name: PublicKey,
+    /// The committee currently in charge.
+    rx_committee: watch::Receiver<Committee>,
     /// The network addresses of the other mempools.
     mempool_addresses: Vec<(PublicKey, SocketAddr)>,
     /// Holds the current batch.
@@ -49,15 +55,19 @@ impl BatchMaker {
         max_batch_delay: u64,
         rx_transaction: Receiver<Transaction>,
         tx_message: Sender<QuorumWaiterMessage>,
-        mempool_addresses: Vec<(PublicKey, SocketAddr)>,
+        name: PublicKey,
+        rx_committee: watch::Receiver<Committee>,
         validator_id: String
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
+            let mempool_addresses = rx_committee.borrow().broadcast_addresses(&name);
             Self {
                 batch_size,
                 max_batch_delay,
                 rx_transaction,
                 tx_message,
+                name,
+                rx_committee,
                 mempool_addresses,
                 current_batch: Batch::with_capacity(batch_size * 2),
                 current_batch_size: 0,
@@ -66,7 +76,7 @@ impl BatchMaker {
             }
             .run()
             .await;
-        });
+        })
     }
 
     /// Main loop receiving incoming transactions and creating batches.
@@ -92,6 +102,11 @@ impl BatchMaker {
                         self.seal().await;
                     }
                     timer.as_mut().reset(Instant::now() + Duration::from_millis(self.max_batch_delay));
+                },
+
+                // Broadcast the next batches to the mempools of the new committee.
+                Ok(()) = self.rx_committee.changed() => {
+                    self.mempool_addresses = self.rx_committee.borrow().broadcast_addresses(&self.name);
                 }
             }
 
@@ -144,11 +159,8 @@ impl BatchMaker {
 
         // Broadcast the batch through the network.
         let (names, addresses): (Vec<_>, _) = self.mempool_addresses.iter().cloned().unzip();
-        let prefix = self.validator_id.clone().into_bytes();
-        let mut prefix_msg : Vec<u8> = Vec::new();
-        prefix_msg.extend(prefix);
-        prefix_msg.extend(serialized.clone());
-        let handlers = self.network.broadcast(addresses, Bytes::from(prefix_msg)).await;
+        let envelope = Envelope::seal(&self.validator_id, MessageKind::Mempool, serialized.clone());
+        let handlers = self.network.broadcast(addresses, envelope).await;
 
         // Send the batch through the deliver channel for further processing.
         self.tx_message
@@ -47,7 +47,7 @@ impl Parameters {
 pub type EpochNumber = u128;
 pub type Stake = u32;
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Authority {
     /// The voting power of this authority.
     pub stake: Stake,
@@ -61,7 +61,7 @@ pub struct Authority {
     pub signature_address: SocketAddr,
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Committee {
     pub authorities: HashMap<PublicKey, Authority>,
     pub epoch: EpochNumber,
@@ -1,10 +1,11 @@
 use crate::config::Committee;
-use bytes::Bytes;
 use crypto::{Digest, PublicKey};
 use log::{error, warn};
-use network::SimpleSender;
+use network::{Envelope, MessageKind, SimpleSender};
 use store::Store;
 use tokio::sync::mpsc::Receiver;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 #[cfg(test)]
 #[path = "tests/helper_tests.rs"]
@@ -12,8 +13,8 @@ pub mod helper_tests;
 
 /// A task dedicated to help other authorities by replying to their batch requests.
 pub struct Helper {
-    /// The committee information.
-    committee: Committee,
+    /// The committee currently in charge.
+    rx_committee: watch::Receiver<Committee>,
     /// The persistent storage.
     store: Store,
     /// Input channel to receive batch requests.
@@ -25,14 +26,14 @@ pub struct Helper {
 
 impl Helper {
     pub fn spawn(
-        committee: Committee,
+        rx_committee: watch::Receiver<Committee>,
         store: Store,
         rx_request: Receiver<(Vec<Digest>, PublicKey)>,
         validator_id: String
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
             Self {
-                committee,
+                rx_committee,
                 store,
                 rx_request,
                 network: SimpleSender::new(),
@@ -40,7 +41,7 @@ impl Helper {
             }
             .run()
             .await;
-        });
+        })
     }
 
     async fn run(&mut self) {
@@ -48,7 +49,7 @@ impl Helper {
             // TODO [issue #7]: Do some accounting to prevent bad nodes from monopolizing our resources.
 
             // get the requestors address.
-            let address = match self.committee.mempool_address(&origin) {
+            let address = match self.rx_committee.borrow().mempool_address(&origin) {
                 Some(x) => x,
                 None => {
                     warn!("Received batch request from unknown authority: {}", origin);
@@ -60,11 +61,8 @@ impl Helper {
             for digest in digests {
                 match self.store.read(digest.to_vec()).await {
                     Ok(Some(data)) => {
-                        let prefix = self.validator_id.clone().into_bytes();
-                        let mut prefix_msg : Vec<u8> = Vec::new();
-                        prefix_msg.extend(prefix);
-                        prefix_msg.extend(data);
-                        self.network.send(address, Bytes::from(prefix_msg)).await
+                        let envelope = Envelope::seal(&self.validator_id, MessageKind::Mempool, data);
+                        self.network.send(address, envelope).await
                     },
                     Ok(None) => (),
                     Err(e) => error!("{}", e),
@@ -15,7 +15,8 @@ use std::error::Error;
 use store::Store;
 use std::sync::Arc;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinHandle;
 use std::collections::HashMap;
 use futures::executor::block_on;
 #[cfg(test)]
@@ -42,6 +43,8 @@ pub enum ConsensusMempoolMessage {
     Synchronize(Vec<Digest>, /* target */ PublicKey),
     /// The consensus notifies the mempool of a round update.
     Cleanup(Round),
+    /// The consensus notifies the mempool that a new committee is in charge.
+    Reconfigure(Committee),
 }
 
 pub struct Mempool {
@@ -55,11 +58,16 @@ pub struct Mempool {
     store: Store,
     /// Send messages to consensus.
     tx_consensus: Sender<Digest>,
+    /// The committee currently in charge, updated upon reconfiguration.
+    rx_committee: watch::Receiver<Committee>,
     /// Validator id.
     validator_id: String
 }
 
 impl Mempool {
+    /// Spawn the mempool of `validator_id` and register its network handlers. Returns the handles
+    /// of the spawned tasks: aborting them (once the handlers are unregistered) stops the mempool.
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: PublicKey,
         committee: Committee,
@@ -70,25 +78,27 @@ impl Mempool {
         validator_id: String,
         tx_handler_map : Arc<RwLock<HashMap<String, TxReceiverHandler>>>,
         mempool_handler_map: Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>
-    ) {
+    ) -> Vec<JoinHandle<()>> {
         // NOTE: This log entry is used to compute performance.
         parameters.log();
 
         // Define a mempool instance.
+        let (tx_committee, rx_committee) = watch::channel(committee.clone());
         let mempool = Self {
             name,
             committee,
             parameters,
             store,
             tx_consensus,
+            rx_committee,
             validator_id : validator_id.clone(),
         };
 
         // Spawn all mempool tasks.
-        mempool.handle_consensus_messages(rx_consensus);
+        let mut handles = vec![mempool.handle_consensus_messages(rx_consensus, tx_committee)];
         
-        block_on(mempool.handle_clients_transactions(Arc::clone(&tx_handler_map)));
-        block_on(mempool.handle_mempool_messages(Arc::clone(&mempool_handler_map)));
+        handles.extend(block_on(mempool.handle_clients_transactions(Arc::clone(&tx_handler_map))));
+        handles.extend(block_on(mempool.handle_mempool_messages(Arc::clone(&mempool_handler_map))));
 
         info!(
             "Mempool successfully booted on {}",
@@ -98,12 +108,18 @@ impl Mempool {
                 .expect("Our public key is not in the committee")
                 .ip()
         );
+        handles
     }
 
     /// Spawn all tasks responsible to handle messages from the consensus.
-    fn handle_consensus_messages(&self, rx_consensus: Receiver<ConsensusMempoolMessage>) {
+    fn handle_consensus_messages(
+        &self,
+        rx_consensus: Receiver<ConsensusMempoolMessage>,
+        tx_committee: watch::Sender<Committee>,
+    ) -> JoinHandle<()> {
         // The `Synchronizer` is responsible to keep the mempool in sync with the others. It handles the commands
-        // it receives from the consensus (which are mainly notifications that we are out of sync).
+        // it receives from the consensus (which are mainly notifications that we are out of sync). It also hands
+        // the committees of new epochs over to the other tasks.
         Synchronizer::spawn(
             self.name,
             self.committee.clone(),
@@ -112,12 +128,13 @@ impl Mempool {
             self.parameters.sync_retry_delay,
             self.parameters.sync_retry_nodes,
             /* rx_message */ rx_consensus,
+            tx_committee,
             self.validator_id.clone()
-        );
+        )
     }
 
     /// Spawn all tasks responsible to handle clients transactions.
-    async fn handle_clients_transactions(&self, tx_handler_map: Arc<RwLock<HashMap<String, TxReceiverHandler>>>) {
+    async fn handle_clients_transactions(&self, tx_handler_map: Arc<RwLock<HashMap<String, TxReceiverHandler>>>) -> Vec<JoinHandle<()>> {
         let (tx_batch_maker, rx_batch_maker) = channel(CHANNEL_CAPACITY);
         let (tx_quorum_waiter, rx_quorum_waiter) = channel(CHANNEL_CAPACITY);
         let (tx_processor, rx_processor) = channel(CHANNEL_CAPACITY);
@@ -143,37 +160,38 @@ impl Mempool {
         // The transactions are sent to the `BatchMaker` that assembles them into batches. It then broadcasts
         // (in a reliable manner) the batches to all other mempools that share the same `id` as us. Finally,
         // it gathers the 'cancel handlers' of the messages and send them to the `QuorumWaiter`.
-        BatchMaker::spawn(
+        let batch_maker = BatchMaker::spawn(
             self.parameters.batch_size,
             self.parameters.max_batch_delay,
             /* rx_transaction */ rx_batch_maker,
             /* tx_message */ tx_quorum_waiter,
-            /* mempool_addresses */
-            self.committee.broadcast_addresses(&self.name),
+            self.name,
+            self.rx_committee.clone(),
             self.validator_id.clone()
         );
 
         // The `QuorumWaiter` waits for 2f authorities to acknowledge reception of the batch. It then forwards
         // the batch to the `Processor`.
-        QuorumWaiter::spawn(
-            self.committee.clone(),
-            /* stake */ self.committee.stake(&self.name),
+        let quorum_waiter = QuorumWaiter::spawn(
+            self.name,
+            self.rx_committee.clone(),
             /* rx_message */ rx_quorum_waiter,
             /* tx_batch */ tx_processor,
         );
 
         // The `Processor` hashes and stores the batch. It then forwards the batch's digest to the consensus.
-        Processor::spawn(
+        let processor = Processor::spawn(
             self.store.clone(),
             /* rx_batch */ rx_processor,
             /* tx_digest */ self.tx_consensus.clone(),
         );
 
         // info!("Mempool listening to client transactions on {}", address);
+        vec![batch_maker, quorum_waiter, processor]
     }
 
     /// Spawn all tasks responsible to handle messages from other mempools.
-    async fn handle_mempool_messages(&self, mempool_handler_map: Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>) {
+    async fn handle_mempool_messages(&self, mempool_handler_map: Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>) -> Vec<JoinHandle<()>> {
         let (tx_helper, rx_helper) = channel(CHANNEL_CAPACITY);
         let (tx_processor, rx_processor) = channel(CHANNEL_CAPACITY);
 
@@ -200,8 +218,8 @@ impl Mempool {
         // );
 
         // The `Helper` is dedicated to reply to batch requests from other mempools.
-        Helper::spawn(
-            self.committee.clone(),
+        let helper = Helper::spawn(
+            self.rx_committee.clone(),
             self.store.clone(),
             /* rx_request */ rx_helper,
             self.validator_id.clone()
@@ -209,13 +227,14 @@ impl Mempool {
 
         // This `Processor` hashes and stores the batches we receive from the other mempools. It then forwards the
         // batch's digest to the consensus.
-        Processor::spawn(
+        let processor = Processor::spawn(
             self.store.clone(),
             /* rx_batch */ rx_processor,
             /* tx_digest */ self.tx_consensus.clone(),
         );
 
         // info!("Mempool listening to mempool messages on {}", address);
+        vec![helper, processor]
     }
 }
 
@@ -4,6 +4,7 @@ use ed25519_dalek::Sha512;
 use std::convert::TryInto;
 use store::Store;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::JoinHandle;
 
 #[cfg(test)]
 #[path = "tests/processor_tests.rs"]
@@ -23,7 +24,7 @@ impl Processor {
         mut rx_batch: Receiver<SerializedBatchMessage>,
         // Output channel to send out batches' digests.
         tx_digest: Sender<Digest>,
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
             while let Some(batch) = rx_batch.recv().await {
                 // Hash the batch.
@@ -34,6 +35,6 @@ impl Processor {
 
                 tx_digest.send(digest).await.expect("Failed to send digest");
             }
-        });
+        })
     }
 }
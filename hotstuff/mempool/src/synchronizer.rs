@@ -1,15 +1,16 @@
 use crate::config::Committee;
 use crate::mempool::{ConsensusMempoolMessage, MempoolMessage, Round};
-use bytes::Bytes;
 use crypto::{Digest, PublicKey};
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
 use log::{debug, error};
-use network::SimpleSender;
+use network::{Envelope, MessageKind, SimpleSender};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use store::{Store, StoreError};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration, Instant};
 
 #[cfg(test)]
@@ -36,6 +37,8 @@ pub struct Synchronizer {
     sync_retry_nodes: usize,
     /// Input channel to receive the commands from the consensus.
     rx_message: Receiver<ConsensusMempoolMessage>,
+    /// Hands the committees of new epochs over to the other mempool tasks.
+    tx_committee: watch::Sender<Committee>,
     /// A network sender to send requests to the other mempools.
     network: SimpleSender,
     /// Loosely keep track of the consensus's round number (only used for cleanup).
@@ -58,8 +61,9 @@ impl Synchronizer {
         sync_retry_delay: u64,
         sync_retry_nodes: usize,
         rx_message: Receiver<ConsensusMempoolMessage>,
+        tx_committee: watch::Sender<Committee>,
         validator_id: String
-    ) {
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
             Self {
                 name,
@@ -69,6 +73,7 @@ impl Synchronizer {
                 sync_retry_delay,
                 sync_retry_nodes,
                 rx_message,
+                tx_committee,
                 network: SimpleSender::new(),
                 round: Round::default(),
                 pending: HashMap::new(),
@@ -76,7 +81,7 @@ impl Synchronizer {
             }
             .run()
             .await;
-        });
+        })
     }
 
     /// Helper function. It waits for a batch to become available in the storage
@@ -142,11 +147,8 @@ impl Synchronizer {
                         };
                         let message = MempoolMessage::BatchRequest(missing, self.name);
                         let serialized = bincode::serialize(&message).expect("Failed to serialize our own message");
-                        let prefix = self.validator_id.clone().into_bytes();
-                        let mut prefix_msg : Vec<u8> = Vec::new();
-                        prefix_msg.extend(prefix);
-                        prefix_msg.extend(serialized);
-                        self.network.send(address, Bytes::from(prefix_msg)).await;
+                        let envelope = Envelope::seal(&self.validator_id, MessageKind::Mempool, serialized);
+                        self.network.send(address, envelope).await;
                     },
                     ConsensusMempoolMessage::Cleanup(round) => {
                         // Keep track of the consensus' round number.
@@ -164,6 +166,11 @@ impl Synchronizer {
                             }
                         }
                         self.pending.retain(|_, (r, _, _)| r > &mut gc_round);
+                    },
+                    ConsensusMempoolMessage::Reconfigure(committee) => {
+                        debug!("Switching to the committee of epoch {}", committee.epoch);
+                        self.committee = committee.clone();
+                        let _ = self.tx_committee.send(committee);
                     }
                 },
 
@@ -204,12 +211,9 @@ impl Synchronizer {
                             .collect();
                         let message = MempoolMessage::BatchRequest(retry, self.name);
                         let serialized = bincode::serialize(&message).expect("Failed to serialize our own message");
-                        let prefix = self.validator_id.clone().into_bytes();
-                        let mut prefix_msg : Vec<u8> = Vec::new();
-                        prefix_msg.extend(prefix);
-                        prefix_msg.extend(serialized);
+                        let envelope = Envelope::seal(&self.validator_id, MessageKind::Mempool, serialized);
                         self.network
-                            .lucky_broadcast(addresses, Bytes::from(prefix_msg), self.sync_retry_nodes)
+                            .lucky_broadcast(addresses, envelope, self.sync_retry_nodes)
                             .await;
                     }
 
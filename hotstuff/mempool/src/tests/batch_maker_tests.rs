@@ -1,12 +1,13 @@
 use super::*;
-use crate::common::transaction;
+use crate::common::{committee_with_base_port, keys, transaction, VALIDATOR_ID};
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
 async fn make_batch() {
     let (tx_transaction, rx_transaction) = channel(1);
     let (tx_message, mut rx_message) = channel(1);
-    let dummy_addresses = vec![(PublicKey::default(), "127.0.0.1:0".parse().unwrap())];
+    let (name, _) = keys().pop().unwrap();
+    let (_tx_committee, rx_committee) = watch::channel(committee_with_base_port(6_000));
 
     // Spawn a `BatchMaker` instance.
     BatchMaker::spawn(
@@ -14,7 +15,9 @@ async fn make_batch() {
         /* max_batch_delay */ 1_000_000, // Ensure the timer is not triggered.
         rx_transaction,
         tx_message,
-        /* mempool_addresses */ dummy_addresses,
+        name,
+        rx_committee,
+        VALIDATOR_ID.to_string(),
     );
 
     // Send enough transactions to seal a batch.
@@ -34,7 +37,8 @@ async fn make_batch() {
 async fn batch_timeout() {
     let (tx_transaction, rx_transaction) = channel(1);
     let (tx_message, mut rx_message) = channel(1);
-    let dummy_addresses = vec![(PublicKey::default(), "127.0.0.1:0".parse().unwrap())];
+    let (name, _) = keys().pop().unwrap();
+    let (_tx_committee, rx_committee) = watch::channel(committee_with_base_port(6_100));
 
     // Spawn a `BatchMaker` instance.
     BatchMaker::spawn(
@@ -42,7 +46,9 @@ async fn batch_timeout() {
         /* max_batch_delay */ 50, // Ensure the timer is triggered.
         rx_transaction,
         tx_message,
-        /* mempool_addresses */ dummy_addresses,
+        name,
+        rx_committee,
+        VALIDATOR_ID.to_string(),
     );
 
     // Do not send enough transactions to seal a batch..
@@ -15,6 +15,9 @@ use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
+/// The validator of the mempools under test.
+pub const VALIDATOR_ID: &str = "0";
+
 // Fixture
 pub fn keys() -> Vec<(PublicKey, SecretKey)> {
     let mut rng = StdRng::from_seed([0; 32]);
@@ -31,7 +34,9 @@ pub fn committee() -> Committee {
                 let stake = 1;
                 let front = format!("127.0.0.1:{}", 100 + i).parse().unwrap();
                 let mempool = format!("127.0.0.1:{}", 100 + i).parse().unwrap();
-                (name, stake, front, mempool)
+                let dvf = format!("127.0.0.1:{}", 200 + i).parse().unwrap();
+                let signature = format!("127.0.0.1:{}", 300 + i).parse().unwrap();
+                (name, stake, front, mempool, dvf, signature)
             })
             .collect(),
         /*  epoch */ 100,
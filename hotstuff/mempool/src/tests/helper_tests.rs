@@ -1,6 +1,8 @@
 use super::*;
-use crate::common::{batch_digest, committee_with_base_port, keys, listener, serialized_batch};
-use std::fs;
+use crate::common::{
+    batch_digest, committee_with_base_port, keys, listener, serialized_batch, VALIDATOR_ID,
+};
+use store::MemoryBackend;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
@@ -10,9 +12,7 @@ async fn batch_reply() {
     let committee = committee_with_base_port(8_000);
 
     // Create a new test store.
-    let path = ".db_test_batch_reply";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::from_backend(MemoryBackend::new());
 
     // Add a batch to the store.
     store
@@ -20,11 +20,12 @@ async fn batch_reply() {
         .await;
 
     // Spawn an `Helper` instance.
-    Helper::spawn(committee.clone(), store, rx_request);
+    let (_tx_committee, rx_committee) = watch::channel(committee.clone());
+    Helper::spawn(rx_committee, store, rx_request, VALIDATOR_ID.to_string());
 
     // Spawn a listener to receive the batch reply.
     let address = committee.mempool_address(&requestor).unwrap();
-    let expected = Bytes::from(serialized_batch());
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Mempool, serialized_batch());
     let handle = listener(address, Some(expected));
 
     // Send a batch request.
@@ -1,7 +1,9 @@
 use super::*;
-use crate::common::{batch_digest, committee_with_base_port, keys, listener, transaction};
-use network::SimpleSender;
-use std::fs;
+use crate::common::{
+    batch_digest, committee_with_base_port, keys, listener, transaction, VALIDATOR_ID,
+};
+use network::{Envelope, MessageKind, Receiver as NetworkReceiver, SimpleSender};
+use store::MemoryBackend;
 
 #[tokio::test]
 async fn handle_clients_transactions() {
@@ -13,13 +15,13 @@ async fn handle_clients_transactions() {
     };
 
     // Create a new test store.
-    let path = ".db_test_handle_clients_transactions";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::from_backend(MemoryBackend::new());
 
-    // Spawn a `Mempool` instance.
+    // Spawn a `Mempool` instance, along with the network receiver of client transactions.
     let (_tx_consensus_to_mempool, rx_consensus_to_mempool) = channel(1);
     let (tx_mempool_to_consensus, mut rx_mempool_to_consensus) = channel(1);
+    let tx_handler_map = Arc::new(RwLock::new(HashMap::new()));
+    let mempool_handler_map = Arc::new(RwLock::new(HashMap::new()));
     Mempool::spawn(
         name,
         committee.clone(),
@@ -27,7 +29,12 @@ async fn handle_clients_transactions() {
         store,
         rx_consensus_to_mempool,
         tx_mempool_to_consensus,
+        VALIDATOR_ID.to_string(),
+        Arc::clone(&tx_handler_map),
+        mempool_handler_map,
     );
+    let address = committee.transactions_address(&name).unwrap();
+    NetworkReceiver::spawn(address, MessageKind::Transaction, tx_handler_map);
 
     // Spawn enough mempools' listeners to acknowledge our batches.
     for (_, address) in committee.broadcast_addresses(&name) {
@@ -36,9 +43,10 @@ async fn handle_clients_transactions() {
 
     // Send enough transactions to create a batch.
     let mut network = SimpleSender::new();
-    let address = committee.transactions_address(&name).unwrap();
-    network.send(address, Bytes::from(transaction())).await;
-    network.send(address, Bytes::from(transaction())).await;
+    for _ in 0..2 {
+        let envelope = Envelope::seal(VALIDATOR_ID, MessageKind::Transaction, transaction());
+        network.send(address, envelope).await;
+    }
 
     // Ensure the consensus got the batch digest.
     let received = rx_mempool_to_consensus.recv().await.unwrap();
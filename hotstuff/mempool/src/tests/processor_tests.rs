@@ -1,7 +1,7 @@
 use super::*;
 use crate::common::batch;
 use crate::mempool::MempoolMessage;
-use std::fs;
+use store::MemoryBackend;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
@@ -10,9 +10,7 @@ async fn hash_and_store() {
     let (tx_digest, mut rx_digest) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_hash_and_store";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::from_backend(MemoryBackend::new());
 
     // Spawn a new `Processor` instance.
     Processor::spawn(store.clone(), rx_batch, tx_digest);
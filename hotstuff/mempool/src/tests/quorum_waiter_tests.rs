@@ -12,9 +12,10 @@ async fn wait_for_quorum() {
     let (tx_batch, mut rx_batch) = channel(1);
     let (myself, _) = keys().pop().unwrap();
     let committee = committee_with_base_port(7_000);
+    let (_tx_committee, rx_committee) = watch::channel(committee.clone());
 
     // Spawn a `QuorumWaiter` instance.
-    QuorumWaiter::spawn(committee.clone(), /* stake */ 1, rx_message, tx_batch);
+    QuorumWaiter::spawn(myself, rx_committee, rx_message, tx_batch);
 
     // Make a batch.
     let message = MempoolMessage::Batch(batch());
@@ -1,6 +1,6 @@
 use super::*;
-use crate::common::{batch_digest, committee_with_base_port, keys, listener};
-use std::fs;
+use crate::common::{batch_digest, committee_with_base_port, keys, listener, VALIDATOR_ID};
+use store::MemoryBackend;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
@@ -12,9 +12,8 @@ async fn synchronize() {
     let committee = committee_with_base_port(9_000);
 
     // Create a new test store.
-    let path = ".db_test_synchronize";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::from_backend(MemoryBackend::new());
+    let (tx_committee, _rx_committee) = watch::channel(committee.clone());
 
     // Spawn a `Synchronizer` instance.
     Synchronizer::spawn(
@@ -25,6 +24,8 @@ async fn synchronize() {
         /* sync_retry_delay */ 1_000_000, // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
         rx_message,
+        tx_committee,
+        VALIDATOR_ID.to_string(),
     );
 
     // Spawn a listener to receive our batch requests.
@@ -33,7 +34,8 @@ async fn synchronize() {
     let missing = vec![batch_digest()];
     let message = MempoolMessage::BatchRequest(missing.clone(), name);
     let serialized = bincode::serialize(&message).unwrap();
-    let handle = listener(address, Some(Bytes::from(serialized)));
+    let expected = Envelope::seal(VALIDATOR_ID, MessageKind::Mempool, serialized);
+    let handle = listener(address, Some(expected));
 
     // Send a sync request.
     let message = ConsensusMempoolMessage::Synchronize(missing, target);
@@ -0,0 +1,118 @@
+use crate::error::EnvelopeError;
+use bytes::{Buf as _, BufMut as _, Bytes, BytesMut};
+use std::convert::TryFrom;
+
+#[cfg(test)]
+#[path = "tests/envelope_tests.rs"]
+pub mod envelope_tests;
+
+/// Version of the envelope format. Bump it on any incompatible change of the header.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Size of the fixed part of the header: version, kind, validator id length and payload length.
+pub const HEADER_LEN: usize = 1 + 1 + 2 + 4;
+
+/// Identifies the validator, hence the consensus instance, a message belongs to. This is the hex
+/// encoding of the validator's BLS public key.
+pub type ValidatorId = String;
+
+/// Validator id of messages addressed to the node itself rather than to one of its validators.
+pub const NODE_VALIDATOR_ID: &str = "";
+
+/// What an envelope carries, i.e., which receiver of the node it is meant for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Transaction = 0,
+    Mempool = 1,
+    Consensus = 2,
+    Signature = 3,
+    Dvf = 4,
+}
+
+impl TryFrom<u8> for MessageKind {
+    type Error = EnvelopeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Transaction),
+            1 => Ok(Self::Mempool),
+            2 => Ok(Self::Consensus),
+            3 => Ok(Self::Signature),
+            4 => Ok(Self::Dvf),
+            x => Err(EnvelopeError::UnknownKind(x)),
+        }
+    }
+}
+
+/// Frame exchanged between nodes, routing a payload to the handler of one validator.
+///
+/// Wire format (big endian):
+/// `version: u8 | kind: u8 | id_len: u16 | validator_id: [u8; id_len] | payload_len: u32 | payload`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope {
+    pub validator_id: ValidatorId,
+    pub kind: MessageKind,
+    pub payload: Bytes,
+}
+
+impl Envelope {
+    pub fn new<P: Into<Bytes>>(validator_id: &str, kind: MessageKind, payload: P) -> Self {
+        Self {
+            validator_id: validator_id.to_string(),
+            kind,
+            payload: payload.into(),
+        }
+    }
+
+    /// Shortcut for `Envelope::new(..).encode()`.
+    pub fn seal<P: Into<Bytes>>(validator_id: &str, kind: MessageKind, payload: P) -> Bytes {
+        Self::new(validator_id, kind, payload).encode()
+    }
+
+    pub fn encode(&self) -> Bytes {
+        let id = self.validator_id.as_bytes();
+        assert!(id.len() <= u16::MAX as usize, "Validator id too long");
+        assert!(self.payload.len() <= u32::MAX as usize, "Payload too long");
+
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + id.len() + self.payload.len());
+        buf.put_u8(ENVELOPE_VERSION);
+        buf.put_u8(self.kind as u8);
+        buf.put_u16(id.len() as u16);
+        buf.put_slice(id);
+        buf.put_u32(self.payload.len() as u32);
+        buf.put_slice(&self.payload);
+        buf.freeze()
+    }
+
+    /// Parse a frame, rejecting anything that is not exactly one well-formed envelope.
+    pub fn decode(mut frame: Bytes) -> Result<Self, EnvelopeError> {
+        if frame.len() < HEADER_LEN {
+            return Err(EnvelopeError::Truncated);
+        }
+        let version = frame.get_u8();
+        if version != ENVELOPE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+        let kind = MessageKind::try_from(frame.get_u8())?;
+
+        let id_len = frame.get_u16() as usize;
+        if frame.len() < id_len + 4 {
+            return Err(EnvelopeError::Truncated);
+        }
+        let validator_id = String::from_utf8(frame.split_to(id_len).to_vec())
+            .map_err(|_| EnvelopeError::InvalidValidatorId)?;
+
+        let payload_len = frame.get_u32() as usize;
+        if frame.len() != payload_len {
+            return Err(EnvelopeError::LengthMismatch {
+                expected: payload_len,
+                got: frame.len(),
+            });
+        }
+        Ok(Self {
+            validator_id,
+            kind,
+            payload: frame,
+        })
+    }
+}
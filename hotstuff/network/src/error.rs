@@ -23,3 +23,21 @@ pub enum NetworkError {
     #[error("Receive unexpected ACK from {0}")]
     UnexpectedAck(SocketAddr),
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EnvelopeError {
+    #[error("Envelope is truncated")]
+    Truncated,
+
+    #[error("Unsupported envelope version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Unknown message kind {0}")]
+    UnknownKind(u8),
+
+    #[error("Validator id is not valid UTF-8")]
+    InvalidValidatorId,
+
+    #[error("Payload length mismatch: header says {expected}, got {got}")]
+    LengthMismatch { expected: usize, got: usize },
+}
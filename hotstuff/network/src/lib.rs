@@ -1,4 +1,5 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+mod envelope;
 mod error;
 mod receiver;
 mod reliable_sender;
@@ -8,6 +9,10 @@ mod simple_sender;
 #[path = "tests/common.rs"]
 pub mod common;
 
-pub use crate::receiver::{MessageHandler, Receiver, Writer, PREFIX_LEN};
+pub use crate::envelope::{
+    Envelope, MessageKind, ValidatorId, ENVELOPE_VERSION, HEADER_LEN, NODE_VALIDATOR_ID,
+};
+pub use crate::error::EnvelopeError;
+pub use crate::receiver::{HandlerMap, MessageHandler, Receiver, Writer};
 pub use crate::reliable_sender::{CancelHandler, ReliableSender};
 pub use crate::simple_sender::SimpleSender;
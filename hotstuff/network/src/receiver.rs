@@ -1,10 +1,11 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::envelope::{Envelope, MessageKind, ValidatorId};
 use crate::error::NetworkError;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::stream::SplitSink;
 use futures::stream::StreamExt as _;
-use log::{debug, info, warn, error};
+use log::{debug, info, warn};
 use std::error::Error;
 use std::net::SocketAddr;
 use tokio::net::{TcpListener, TcpStream};
@@ -12,14 +13,16 @@ use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use std::collections::HashMap;
 use std::sync::{Arc};
 use tokio::sync::{RwLock};
-use futures::executor::block_on;
 #[cfg(test)]
 #[path = "tests/receiver_tests.rs"]
 pub mod receiver_tests;
 
 /// Convenient alias for the writer end of the TCP channel.
 pub type Writer = SplitSink<Framed<TcpStream, LengthDelimitedCodec>, Bytes>;
-pub const PREFIX_LEN: usize =  88;
+
+/// Handlers of the validators hosted by a node, indexed by validator id.
+pub type HandlerMap<Handler> = Arc<RwLock<HashMap<ValidatorId, Handler>>>;
+
 #[async_trait]
 pub trait MessageHandler: Clone + Send + Sync + 'static {
     /// Defines how to handle an incoming message. A typical usage is to define a `MessageHandler` with a
@@ -34,15 +37,18 @@ pub trait MessageHandler: Clone + Send + Sync + 'static {
 pub struct Receiver<Handler: MessageHandler> {
     /// Address to listen to.
     address: SocketAddr,
-    /// Struct responsible to define how to handle received messages.
-    handler_map: Arc<RwLock<HashMap<String, Handler>>>
+    /// Kind of the envelopes accepted by this receiver.
+    kind: MessageKind,
+    /// Struct responsible to define how to handle received messages, for each validator.
+    handler_map: HandlerMap<Handler>,
 }
 
 impl<Handler: MessageHandler> Receiver<Handler> {
-    /// Spawn a new network receiver handling connections from any incoming peer.
-    pub fn spawn(address: SocketAddr, handler_map: Arc<RwLock<HashMap<String, Handler>>>) {
+    /// Spawn a new network receiver handling connections from any incoming peer. Envelopes of `kind`
+    /// are routed to the handler of their validator; anything else is dropped.
+    pub fn spawn(address: SocketAddr, kind: MessageKind, handler_map: HandlerMap<Handler>) {
         tokio::spawn(async move {
-            Self { address, handler_map : Arc::clone(&handler_map) }.run().await;
+            Self { address, kind, handler_map }.run().await;
         });
     }
 
@@ -62,41 +68,43 @@ impl<Handler: MessageHandler> Receiver<Handler> {
                 }
             };
             info!("Incoming connection established with {}", peer);
-            Self::spawn_runner(socket, peer, Arc::clone(&self.handler_map)).await;
+            Self::spawn_runner(socket, peer, self.kind, Arc::clone(&self.handler_map));
         }
     }
 
-    async fn spawn_runner(socket: TcpStream, peer: SocketAddr, handler_map: Arc<RwLock<HashMap<String, Handler>>>) {
-        let msg_prefix_len: usize = PREFIX_LEN;
+    fn spawn_runner(socket: TcpStream, peer: SocketAddr, kind: MessageKind, handler_map: HandlerMap<Handler>) {
         tokio::spawn(async move {
             let transport = Framed::new(socket, LengthDelimitedCodec::new());
             let (mut writer, mut reader) = transport.split();
             while let Some(frame) = reader.next().await {
-                match frame.map_err(|e| NetworkError::FailedToReceiveMessage(peer, e)) {
-                    Ok(message) => {
-                        // get first msg_prefix_len
-                        let prefix = String::from_utf8(message[0..msg_prefix_len].to_vec()).unwrap();
-                        let handlers = handler_map.read().await;
-                        match handlers.get(&prefix) {
-                            Some(handler) => {
-                                // trunctate the prefix
-                                let mut mut_msg = message;
-                                let _ = mut_msg.split_to(msg_prefix_len);
-                                if let Err(e) = handler.dispatch(&mut writer, mut_msg.freeze()).await {
-                                    warn!("{}", e);
-                                    return;
-                                }
-                            },
-                            None => {
-                                error!("{:?}", message);
-                                error!("there is no handler for this prefix, some error happen!");
-                            } 
-                        }
-                    }
+                let message = match frame.map_err(|e| NetworkError::FailedToReceiveMessage(peer, e)) {
+                    Ok(message) => message,
                     Err(e) => {
                         warn!("{}", e);
                         return;
                     }
+                };
+                let envelope = match Envelope::decode(message.freeze()) {
+                    Ok(envelope) => envelope,
+                    Err(e) => {
+                        warn!("Dropping malformed frame from {}: {}", peer, e);
+                        continue;
+                    }
+                };
+                if envelope.kind != kind {
+                    warn!("Dropping {:?} envelope from {} on the {:?} receiver", envelope.kind, peer, kind);
+                    continue;
+                }
+                // Release the lock before dispatching: handlers may wait on the network.
+                let handler = handler_map.read().await.get(&envelope.validator_id).cloned();
+                match handler {
+                    Some(handler) => {
+                        if let Err(e) = handler.dispatch(&mut writer, envelope.payload).await {
+                            warn!("{}", e);
+                            return;
+                        }
+                    }
+                    None => warn!("No handler for validator {:?}, dropping message from {}", envelope.validator_id, peer),
                 }
             }
             warn!("Connection closed by peer {}", peer);
@@ -0,0 +1,46 @@
+use super::*;
+
+#[test]
+fn encode_decode() {
+    let envelope = Envelope::new("0xabcdef", MessageKind::Consensus, vec![1u8, 2, 3]);
+    let decoded = Envelope::decode(envelope.encode()).unwrap();
+    assert_eq!(decoded, envelope);
+
+    let envelope = Envelope::new(NODE_VALIDATOR_ID, MessageKind::Dvf, Vec::new());
+    assert_eq!(Envelope::decode(envelope.encode()).unwrap(), envelope);
+}
+
+#[test]
+fn reject_malformed_frames() {
+    let bytes = Envelope::seal("0xabcdef", MessageKind::Mempool, vec![1u8, 2, 3]);
+
+    assert_eq!(Envelope::decode(Bytes::new()), Err(EnvelopeError::Truncated));
+    assert_eq!(Envelope::decode(bytes.slice(..HEADER_LEN + 2)), Err(EnvelopeError::Truncated));
+    assert_eq!(
+        Envelope::decode(bytes.slice(..bytes.len() - 1)),
+        Err(EnvelopeError::LengthMismatch { expected: 3, got: 2 })
+    );
+
+    let mut wrong_version = bytes.to_vec();
+    wrong_version[0] = ENVELOPE_VERSION + 1;
+    assert_eq!(
+        Envelope::decode(Bytes::from(wrong_version)),
+        Err(EnvelopeError::UnsupportedVersion(ENVELOPE_VERSION + 1))
+    );
+
+    let mut wrong_kind = bytes.to_vec();
+    wrong_kind[1] = 42;
+    assert_eq!(Envelope::decode(Bytes::from(wrong_kind)), Err(EnvelopeError::UnknownKind(42)));
+
+    // The legacy 88-byte prefix is not an envelope, and must not panic.
+    let mut legacy = vec![50u8; 88];
+    legacy.extend_from_slice(&[0xff; 32]);
+    assert!(Envelope::decode(Bytes::from(legacy)).is_err());
+
+    let mut invalid_utf8 = bytes.to_vec();
+    invalid_utf8[HEADER_LEN - 4] = 0xff;
+    assert_eq!(
+        Envelope::decode(Bytes::from(invalid_utf8)),
+        Err(EnvelopeError::InvalidValidatorId)
+    );
+}
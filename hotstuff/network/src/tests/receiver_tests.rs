@@ -30,7 +30,9 @@ async fn receive() {
     // Make the network receiver.
     let address = "127.0.0.1:4000".parse::<SocketAddr>().unwrap();
     let (tx, mut rx) = channel(1);
-    Receiver::spawn(address, TestHandler { deliver: tx });
+    let handler_map: HandlerMap<TestHandler> = Arc::new(RwLock::new(HashMap::new()));
+    handler_map.write().await.insert("validator".to_string(), TestHandler { deliver: tx });
+    Receiver::spawn(address, MessageKind::Consensus, handler_map);
     sleep(Duration::from_millis(50)).await;
 
     // Send a message.
@@ -38,7 +40,10 @@ async fn receive() {
     let bytes = Bytes::from(bincode::serialize(sent).unwrap());
     let stream = TcpStream::connect(address).await.unwrap();
     let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
-    transport.send(bytes.clone()).await.unwrap();
+    transport
+        .send(Envelope::seal("validator", MessageKind::Consensus, bytes))
+        .await
+        .unwrap();
 
     // Ensure the message gets passed to the channel.
     let message = rx.recv().await;
@@ -46,3 +51,32 @@ async fn receive() {
     let received = message.unwrap();
     assert_eq!(received, sent);
 }
+
+#[tokio::test]
+async fn route_by_validator() {
+    let address = "127.0.0.1:4001".parse::<SocketAddr>().unwrap();
+    let (tx_a, mut rx_a) = channel(1);
+    let (tx_b, mut rx_b) = channel(1);
+    let handler_map: HandlerMap<TestHandler> = Arc::new(RwLock::new(HashMap::new()));
+    {
+        let mut handlers = handler_map.write().await;
+        handlers.insert("a".to_string(), TestHandler { deliver: tx_a });
+        handlers.insert("b".to_string(), TestHandler { deliver: tx_b });
+    }
+    Receiver::spawn(address, MessageKind::Consensus, handler_map);
+    sleep(Duration::from_millis(50)).await;
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
+    let payload = |s: &str| Bytes::from(bincode::serialize(s).unwrap());
+
+    // Malformed frames, wrong kinds and unknown validators are dropped without closing the connection.
+    transport.send(Bytes::from(vec![50u8; 88])).await.unwrap();
+    transport.send(Envelope::seal("a", MessageKind::Mempool, payload("wrong kind"))).await.unwrap();
+    transport.send(Envelope::seal("c", MessageKind::Consensus, payload("unknown"))).await.unwrap();
+    transport.send(Envelope::seal("b", MessageKind::Consensus, payload("to b"))).await.unwrap();
+    transport.send(Envelope::seal("a", MessageKind::Consensus, payload("to a"))).await.unwrap();
+
+    assert_eq!(rx_b.recv().await.unwrap(), "to b");
+    assert_eq!(rx_a.recv().await.unwrap(), "to a");
+}
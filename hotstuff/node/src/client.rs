@@ -6,6 +6,7 @@ use env_logger::Env;
 use futures::future::join_all;
 use futures::sink::SinkExt as _;
 use log::{info, warn};
+use network::{Envelope, MessageKind};
 use rand::Rng;
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
@@ -18,6 +19,7 @@ async fn main() -> Result<()> {
         .version(crate_version!())
         .about("Benchmark client for HotStuff nodes.")
         .args_from_usage("<ADDR> 'The network address of the node where to send txs'")
+        .args_from_usage("--validator=<STR> 'The id (public key) of the validator whose mempool receives the txs'")
         .args_from_usage("--timeout=<INT> 'The nodes timeout value'")
         .args_from_usage("--size=<INT> 'The size of each transaction in bytes'")
         .args_from_usage("--rate=<INT> 'The rate (txs/s) at which to send the transactions'")
@@ -49,6 +51,7 @@ async fn main() -> Result<()> {
         .unwrap()
         .parse::<u64>()
         .context("The timeout value must be a non-negative integer")?;
+    let validator_id = matches.value_of("validator").unwrap().to_string();
     let nodes = matches
         .values_of("nodes")
         .unwrap_or_default()
@@ -66,6 +69,7 @@ async fn main() -> Result<()> {
         rate,
         timeout,
         nodes,
+        validator_id,
     };
 
     // Wait for all nodes to be online and synchronized.
@@ -81,6 +85,7 @@ struct Client {
     rate: u64,
     timeout: u64,
     nodes: Vec<SocketAddr>,
+    validator_id: String,
 }
 
 impl Client {
@@ -131,11 +136,8 @@ impl Client {
                 };
                 tx.resize(self.size, 0u8);
                 let bytes = tx.split().freeze();
-                let validator_vec : Vec<u8>= vec![50; 88];
-                let mut prefix_msg : Vec<u8> = Vec::new();
-                prefix_msg.extend(validator_vec);
-                prefix_msg.extend(bytes);
-                if let Err(e) = transport.send(bytes).await {
+                let envelope = Envelope::seal(&self.validator_id, MessageKind::Transaction, bytes);
+                if let Err(e) = transport.send(envelope).await {
                     warn!("Failed to send transaction: {}", e);
                     break 'main;
                 }
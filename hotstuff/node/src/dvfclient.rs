@@ -1,11 +1,11 @@
 mod config;
 mod dvfcore;
 use anyhow::{Context, Result};
-use bytes::Bytes;
 use clap::{crate_name, crate_version, App, AppSettings};
 use env_logger::Env;
 use futures::sink::SinkExt as _;
 use log::{info, warn};
+use network::{Envelope, MessageKind, NODE_VALIDATOR_ID};
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
@@ -19,6 +19,7 @@ async fn main() -> Result<()> {
     .about("client for HotStuff nodes.")
     .args_from_usage("<ADDR> 'The network address of the node where to send dvf command'")
     .args_from_usage("<FILE> 'The file of committee info.'")
+    .args_from_usage("<VALIDATOR> 'The id (public key) of the validator to start a consensus instance for'")
     .setting(AppSettings::ArgRequiredElseHelp)
     .get_matches();
   env_logger::Builder::from_env(Env::default().default_filter_or("info"))
@@ -45,15 +46,15 @@ async fn main() -> Result<()> {
 
   let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
 
-  let validator_vec : Vec<u8>= vec![50; 88];
-  let validator_id = String::from_utf8(validator_vec).unwrap();
-  let dvfinfo = DvfInfo { validator_id, committee };
-  let empty_vec : Vec<u8>= vec![48;88];
-  let mut prefix_msg : Vec<u8> = Vec::new();
-  prefix_msg.extend(empty_vec);
+  let validator_id = matches
+    .value_of("VALIDATOR")
+    .unwrap()
+    .to_string();
+  let dvfinfo = DvfInfo { validator_id, committee, epoch_history: Vec::new() };
   let dvfinfo_bytes = serde_json::to_vec(&dvfinfo).unwrap();
-  prefix_msg.extend(dvfinfo_bytes);
-  if let Err(e) = transport.send(Bytes::from(prefix_msg)).await {
+  // Dvf commands are addressed to the node itself.
+  let envelope = Envelope::seal(NODE_VALIDATOR_ID, MessageKind::Dvf, dvfinfo_bytes);
+  if let Err(e) = transport.send(envelope).await {
     warn!("Failed to send dvf command: {}", e);
   }
   Ok(())
@@ -1,11 +1,12 @@
 use crate::config::{Committee, ConfigError, Parameters};
 use crypto::{PublicKey, SecretKey};
-use consensus::{Block, Consensus, ConsensusReceiverHandler};
+use consensus::{epoch_history, Block, Consensus, ConsensusReceiverHandler, ConsensusResult, Reconfiguration};
 use crypto::SignatureService;
-use log::info;
+use log::{debug, info, warn};
 use mempool::{Mempool, TxReceiverHandler, MempoolReceiverHandler};
 use store::Store;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::oneshot;
 use network::{MessageHandler, Writer};
 use std::sync::{Arc};
 use async_trait::async_trait;
@@ -13,6 +14,7 @@ use std::collections::HashMap;
 use bytes::Bytes;
 use std::error::Error;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 pub const CHANNEL_CAPACITY: usize = 1_000;
@@ -22,7 +24,12 @@ use types::Keypair;
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DvfInfo {
   pub validator_id : String,
-  pub committee: Committee
+  /// The genesis committee of the instance.
+  pub committee: Committee,
+  /// The reconfigurations committed since `committee`, for authorities joining the instance
+  /// after them. See `DvfCore::dvf_info`.
+  #[serde(default)]
+  pub epoch_history: Vec<Reconfiguration>,
 }
 
 impl fmt::Debug for DvfInfo {
@@ -36,6 +43,20 @@ impl fmt::Debug for DvfInfo {
   }
 }
 
+/// Commands changing the instances of a node, served by `Node::run`. Commands about an existing
+/// instance reply whether there is one.
+#[derive(Debug)]
+pub enum DvfCommand {
+  /// Create and start the instance of a validator, upon a dvf message.
+  Create(DvfInfo),
+  /// Start the instance of a validator again after a pause.
+  Start(String, oneshot::Sender<bool>),
+  /// Pause the instance of a validator.
+  Pause(String, oneshot::Sender<bool>),
+  /// Stop the instance of a validator and forget about it.
+  Remove(String, oneshot::Sender<bool>),
+}
+
 #[derive(Clone)]
 pub struct DvfReceiverHandler {
   // pub name: PublicKey,
@@ -44,14 +65,14 @@ pub struct DvfReceiverHandler {
   // pub tx_handler_map : Arc<RwLock<HashMap<String, TxReceiverHandler>>>,
   // pub mempool_handler_map : Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>,
   // pub consensus_handler_map: Arc<RwLock<HashMap<String, ConsensusReceiverHandler>>>,
-  pub tx_dvfinfo : Sender<DvfInfo>
+  pub tx_command : Sender<DvfCommand>
 }
 
 #[async_trait]
 impl MessageHandler for DvfReceiverHandler {
     async fn dispatch(&self, _writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>> {
         let dvfinfo = serde_json::from_slice(&message.to_vec())?;
-        self.tx_dvfinfo.send(dvfinfo).await.unwrap();
+        self.tx_command.send(DvfCommand::Create(dvfinfo)).await.unwrap();
         // Give the change to schedule other tasks.
         tokio::task::yield_now().await;
         Ok(())
@@ -64,17 +85,20 @@ impl MessageHandler for DvfReceiverHandler {
 pub struct SignatureInfo {
   pub from : bls::PublicKey,
   pub signature: Signature,
-  pub msg : Hash256
+  pub msg : Hash256,
+  /// Id of the signer in the threshold signature scheme.
+  pub id : u64
 }
 
 impl fmt::Debug for SignatureInfo {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
       write!(
           f,
-          "from: {:?}, signature: {:?}, msg: {:?}",
+          "from: {:?}, signature: {:?}, msg: {:?}, id: {}",
           self.from,
           self.signature,
-          self.msg
+          self.msg,
+          self.id
       )
   }
 }
@@ -100,28 +124,35 @@ pub struct DvfCore {
   pub store: Store,
   pub commit: Receiver<Block>,
   pub broadcast_signature_addresses : Vec<SocketAddr>,
-  pub validator_id: String
+  pub validator_id: String,
+  tx_reconfiguration: Sender<Reconfiguration>,
+  name: PublicKey,
+  committee: Committee,
+  signature_service: SignatureService,
+  /// Kept across restarts, so that `commit` keeps receiving the committed blocks.
+  tx_commit: Sender<Block>,
+  tx_handler_map : Arc<RwLock<HashMap<String, TxReceiverHandler>>>,
+  mempool_handler_map : Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>,
+  consensus_handler_map: Arc<RwLock<HashMap<String, ConsensusReceiverHandler>>>,
+  /// The mempool and consensus tasks, empty while paused.
+  tasks: Vec<JoinHandle<()>>,
 }
 
 impl DvfCore {
+  /// Create the instance of `validator_id` on top of `store` and start it.
   pub async fn new(
     committee: Committee,
     name: PublicKey,
     secret_key: SecretKey,
     validator_id: String,
-    base_store_path: String,
+    store: Store,
     tx_handler_map : Arc<RwLock<HashMap<String, TxReceiverHandler>>>,
     mempool_handler_map : Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>,
     consensus_handler_map: Arc<RwLock<HashMap<String, ConsensusReceiverHandler>>>,
   ) -> Result<Self, ConfigError> {
     let (tx_commit, rx_commit) = channel(CHANNEL_CAPACITY);
-    let (tx_consensus_to_mempool, rx_consensus_to_mempool) = channel(CHANNEL_CAPACITY);
-    let (tx_mempool_to_consensus, rx_mempool_to_consensus) = channel(CHANNEL_CAPACITY);
-
-    let parameters = Parameters::default();
-
-    let store_path = base_store_path + "/" + &validator_id;
-    let store = Store::new(&store_path).expect("Failed to create store");
+    // Replaced upon start.
+    let (tx_reconfiguration, _) = channel(1);
 
     // Run the signature service.
     let signature_service = SignatureService::new(secret_key);
@@ -133,33 +164,129 @@ impl DvfCore {
 
     let broadcast_signature_addresses = committee.mempool.broadcast_signature_addresses(&name);
 
-    Mempool::spawn(
+    let mut dvfcore = Self {
+      store,
+      commit: rx_commit,
+      broadcast_signature_addresses,
+      validator_id,
+      tx_reconfiguration,
       name,
-      committee.mempool,
+      committee,
+      signature_service,
+      tx_commit,
+      tx_handler_map,
+      mempool_handler_map,
+      consensus_handler_map,
+      tasks: Vec::new(),
+    };
+    dvfcore.start();
+    info!("dvfcore {} successfully booted", dvfcore.validator_id);
+    Ok(dvfcore)
+  }
+
+  pub fn is_running(&self) -> bool {
+    !self.tasks.is_empty()
+  }
+
+  /// Spawn the mempool and consensus tasks and register their handlers, unless they are running.
+  /// A restarted instance resumes from the state persisted in its store.
+  pub fn start(&mut self) {
+    if self.is_running() {
+      return;
+    }
+    let (tx_consensus_to_mempool, rx_consensus_to_mempool) = channel(CHANNEL_CAPACITY);
+    let (tx_mempool_to_consensus, rx_mempool_to_consensus) = channel(CHANNEL_CAPACITY);
+    let (tx_reconfiguration, rx_reconfiguration) = channel(CHANNEL_CAPACITY);
+    self.tx_reconfiguration = tx_reconfiguration;
+
+    let parameters = Parameters::default();
+
+    self.tasks = Mempool::spawn(
+      self.name,
+      self.committee.mempool.clone(),
       parameters.mempool,
-      store.clone(),
+      self.store.clone(),
       rx_consensus_to_mempool,
       tx_mempool_to_consensus,
-      validator_id.clone(),
-      Arc::clone(&tx_handler_map),
-      Arc::clone(&mempool_handler_map)
+      self.validator_id.clone(),
+      Arc::clone(&self.tx_handler_map),
+      Arc::clone(&self.mempool_handler_map)
     );
 
-    Consensus::spawn(
-      name,
-      committee.consensus,
+    self.tasks.extend(Consensus::spawn(
+      self.name,
+      self.committee.consensus.clone(),
       parameters.consensus,
-      signature_service,
-      store.clone(),
+      self.signature_service.clone(),
+      self.store.clone(),
       rx_mempool_to_consensus,
       tx_consensus_to_mempool,
-      tx_commit,
-      validator_id.clone(),
-      Arc::clone(&consensus_handler_map)
-    );
-    info!("dvfcore {} successfully booted", validator_id);
-    Ok(Self { commit: rx_commit, store: store, broadcast_signature_addresses: broadcast_signature_addresses, validator_id: validator_id})
-    
+      self.tx_commit.clone(),
+      rx_reconfiguration,
+      self.validator_id.clone(),
+      Arc::clone(&self.consensus_handler_map)
+    ));
+    info!("dvfcore {} started", self.validator_id);
+  }
+
+  /// Unregister the handlers of the instance and cancel its tasks. Its store stays open, so that
+  /// it can be started again.
+  pub async fn pause(&mut self) {
+    if !self.is_running() {
+      return;
+    }
+    // Messages are dropped from now on rather than sent to cancelled tasks.
+    self.tx_handler_map.write().await.remove(&self.validator_id);
+    self.mempool_handler_map.write().await.remove(&self.validator_id);
+    self.consensus_handler_map.write().await.remove(&self.validator_id);
+
+    for task in self.tasks.drain(..) {
+      task.abort();
+    }
+    info!("dvfcore {} paused", self.validator_id);
+  }
+
+  /// Pause the instance for good. Its store closes once the cancelled tasks release it, but its
+  /// data is kept: the safety state must survive in case the validator comes back.
+  pub async fn stop(mut self) {
+    self.pause().await;
+    info!("dvfcore {} stopped", self.validator_id);
+  }
+
+  /// Propose to hand this instance over to `committee`, whose epoch must follow the latest
+  /// scheduled one. The reconfiguration rides on the next block this authority leads, and
+  /// `committee` takes over from the activation round set in that block, at least
+  /// `RECONFIGURATION_DELAY` rounds later. If the block is not committed before that round, the
+  /// reconfiguration is dropped and proposed again with a later activation round. Authorities
+  /// joining with `committee` must be started from `dvf_info`.
+  pub async fn reconfigure(&self, committee: Committee) {
+    let reconfiguration = Reconfiguration::new(committee.consensus, committee.mempool);
+    if let Err(e) = self.tx_reconfiguration.send(reconfiguration).await {
+      warn!("Failed to reconfigure dvfcore {}: {}", self.validator_id, e);
+    }
+  }
+
+  /// What an authority needs to join this instance: its genesis committee and the
+  /// reconfigurations committed since.
+  pub async fn dvf_info(&mut self) -> ConsensusResult<DvfInfo> {
+    Ok(DvfInfo {
+      validator_id: self.validator_id.clone(),
+      committee: self.committee.clone(),
+      epoch_history: epoch_history(&mut self.store).await?,
+    })
+  }
+
+  /// Receive the committed blocks in the background, for nodes which do not process them:
+  /// consensus fails to send them to a closed channel. The drain ends with the instance.
+  pub fn drain_commits(&mut self) {
+    let (_, rx_commit) = channel(1);
+    let mut commit = std::mem::replace(&mut self.commit, rx_commit);
+    let validator_id = self.validator_id.clone();
+    tokio::spawn(async move {
+      while let Some(block) = commit.recv().await {
+        debug!("dvfcore {} committed {:?}", validator_id, block);
+      }
+    });
   }
 
   pub async fn analyze_block(&mut self) {
@@ -81,7 +81,7 @@ async fn main() {
                     // .await
                     // .expect("Failed to analyze committed blocks");
                     info!("start dvf node {} success", node.name);
-                    // node.process_dvfinfo().await;
+                    node.run().await;
                 }
                 Err(e) => error!("{}", e),
             }
@@ -173,10 +173,8 @@ fn deploy_testbed(nodes: usize) -> Result<Vec<JoinHandle<()>>, Box<dyn std::erro
             Ok(tokio::spawn(async move {
                 match Node::new(&tx_address.to_string(), &mem_address.to_string(), &consensus_address.to_string(), &dvf_address.to_string(), &signature_address.to_string(), secret, &store_path, None).await {
                     Ok(mut node) => {
-                        // Sink the commit channel.
-                        // while node.commit.recv().await.is_some() {}
                         info!("start dvf node {} success", name);
-                        // node.process_dvfinfo().await;
+                        node.run().await;
                     }
                     Err(e) => error!("{}", e),
                 }
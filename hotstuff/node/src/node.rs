@@ -1,27 +1,56 @@
 use crate::config::Export as _;
 use crate::config::{ConfigError, Secret};
-use log::{info, error};
-use consensus::{ConsensusReceiverHandler};
+use log::{info, error, warn};
+use consensus::{bootstrap_epochs, ConsensusReceiverHandler};
 use mempool::{TxReceiverHandler, MempoolReceiverHandler};
-use network::{Receiver as NetworkReceiver};
+use network::{HandlerMap, MessageKind, Receiver as NetworkReceiver, NODE_VALIDATOR_ID};
 use std::sync::{Arc};
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use tokio::sync::RwLock;
 use std::net::SocketAddr;
 use crate::dvfcore::DvfCore;
-use tokio::sync::mpsc::{channel, Receiver};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
 use crypto::{PublicKey, SecretKey};
-/// The default channel capacity for this module.
-use crate::dvfcore::{DvfInfo, DvfReceiverHandler, DvfSignatureReceiverHandler};
+use store::{SharedRocksDb, Store};
+use crate::dvfcore::{DvfCommand, DvfInfo, DvfReceiverHandler, DvfSignatureReceiverHandler, CHANNEL_CAPACITY};
+/// Moves the stores that validators used to keep in a database of their own, at
+/// `store_path/<validator id>`, to their namespace of `shared_store`. The databases of such
+/// stores are then moved to `<store_path>.legacy`, so they are migrated only once.
+fn migrate_legacy_stores(shared_store: &SharedRocksDb, store_path: &str) -> std::io::Result<()> {
+    let legacy_path = format!("{}.legacy", store_path);
+    for entry in fs::read_dir(store_path)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let validator_id = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        shared_store
+            .import(&validator_id, &path.to_string_lossy())
+            .map_err(std::io::Error::other)?;
+        fs::create_dir_all(&legacy_path)?;
+        fs::rename(&path, Path::new(&legacy_path).join(&validator_id))?;
+        info!("Migrated the store of validator {} to the shared store", validator_id);
+    }
+    Ok(())
+}
+
 pub struct Node {
     pub name : PublicKey,
     pub secret_key: SecretKey,
-    pub base_store_path: String,
-    pub rx_dvfinfo: Receiver<DvfInfo>,
-    pub tx_handler_map : Arc<RwLock<HashMap<String, TxReceiverHandler>>>,
-    pub mempool_handler_map : Arc<RwLock<HashMap<String, MempoolReceiverHandler>>>,
-    pub consensus_handler_map: Arc<RwLock<HashMap<String, ConsensusReceiverHandler>>>,
-    pub signature_handler_map: Arc<RwLock<HashMap<String, DvfSignatureReceiverHandler>>>,
+    /// The database shared by the stores of all validators of the node.
+    pub shared_store: SharedRocksDb,
+    /// Commands to the instances of the node, see `command_sender`.
+    rx_command: Receiver<DvfCommand>,
+    tx_command: Sender<DvfCommand>,
+    pub tx_handler_map : HandlerMap<TxReceiverHandler>,
+    pub mempool_handler_map : HandlerMap<MempoolReceiverHandler>,
+    pub consensus_handler_map: HandlerMap<ConsensusReceiverHandler>,
+    pub signature_handler_map: HandlerMap<DvfSignatureReceiverHandler>,
+    /// The instances created upon dvf messages, indexed by validator id.
+    pub dvfcores: HashMap<String, DvfCore>,
 }
 impl Node {
     pub async fn new(
@@ -37,7 +66,8 @@ impl Node {
         // secret key from file.
         let name = secret.name;
         let secret_key = secret.secret;
-        let base_store_path = store_path.to_string();
+        let shared_store = SharedRocksDb::open(store_path).expect("Failed to create store");
+        migrate_legacy_stores(&shared_store, store_path).expect("Failed to migrate stores");
         // Load default parameters if none are specified.
         // let parameters = match parameters {
         //     Some(filename) => Parameters::read(filename)?,
@@ -51,18 +81,18 @@ impl Node {
 
         let mut tx_network_address : SocketAddr = tx_receiver_address.parse().unwrap();
         tx_network_address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(tx_network_address, Arc::clone(&tx_handler_map));
+        NetworkReceiver::spawn(tx_network_address, MessageKind::Transaction, Arc::clone(&tx_handler_map));
         info!("Mempool listening to client transactions on {}", tx_network_address);
 
         let mut mempool_network_address : SocketAddr = mempool_receiver_address.parse().unwrap();
         mempool_network_address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(mempool_network_address, Arc::clone(&mempool_handler_map));
+        NetworkReceiver::spawn(mempool_network_address, MessageKind::Mempool, Arc::clone(&mempool_handler_map));
         info!("Mempool listening to mempool messages on {}", mempool_network_address);
 
 
         let mut consensus_network_address : SocketAddr = consensus_receiver_address.parse().unwrap();
         consensus_network_address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(consensus_network_address, Arc::clone(&consensus_handler_map));
+        NetworkReceiver::spawn(consensus_network_address, MessageKind::Consensus, Arc::clone(&consensus_handler_map));
         info!(
             "Node {} listening to consensus messages on {}",
             name, consensus_network_address
@@ -70,67 +100,134 @@ impl Node {
 
         let mut signature_network_address : SocketAddr = signature_receiver_address.parse().unwrap();
         signature_network_address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(signature_network_address, Arc::clone(&signature_handler_map));
+        NetworkReceiver::spawn(signature_network_address, MessageKind::Signature, Arc::clone(&signature_handler_map));
         info!(
             "Node {} listening to signature messages on {}",
             name, signature_network_address
         );
         
         // set dvfcore handler map
-        let dvfcore_handler_map : Arc<RwLock<HashMap<String, DvfReceiverHandler>>>= Arc::new(RwLock::new(HashMap::new()));
-        let (tx_dvfinfo, rx_dvfinfo) = channel(1);
+        let dvfcore_handler_map: HandlerMap<DvfReceiverHandler> = Arc::new(RwLock::new(HashMap::new()));
+        let (tx_command, rx_command) = channel(CHANNEL_CAPACITY);
         {
             let mut dvfcore_handlers = dvfcore_handler_map.write().await; 
-            let empty_vec: Vec<u8> = vec![48; 88];
-            let empty_id = String::from_utf8(empty_vec).unwrap();
-            
+            // Dvf commands are addressed to the node, not to one of its validators.
             dvfcore_handlers.insert(
-                empty_id,
+                NODE_VALIDATOR_ID.to_string(),
                 DvfReceiverHandler {
-                    tx_dvfinfo
+                    tx_command: tx_command.clone()
                 }
             );
         }
         
         let mut dvfcore_network_address : SocketAddr = dvfcore_receiver_address.parse().unwrap();
         dvfcore_network_address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(dvfcore_network_address, Arc::clone(&dvfcore_handler_map));
+        NetworkReceiver::spawn(dvfcore_network_address, MessageKind::Dvf, Arc::clone(&dvfcore_handler_map));
         info!("DvfCore listening to dvf messages on {}", dvfcore_network_address);
 
         info!("Node {} successfully booted", name);
-        Ok(Self { name, secret_key, base_store_path, rx_dvfinfo, tx_handler_map: Arc::clone(&tx_handler_map), mempool_handler_map: Arc::clone(&mempool_handler_map), consensus_handler_map: Arc::clone(&consensus_handler_map), signature_handler_map: Arc::clone(&signature_handler_map)})
+        Ok(Self { name, secret_key, shared_store, rx_command, tx_command, tx_handler_map: Arc::clone(&tx_handler_map), mempool_handler_map: Arc::clone(&mempool_handler_map), consensus_handler_map: Arc::clone(&consensus_handler_map), signature_handler_map: Arc::clone(&signature_handler_map), dvfcores: HashMap::new()})
+    }
+
+    /// Returns the store of validator `validator_id`, within the database of the node.
+    pub fn store(&self, validator_id: &str) -> Store {
+        Store::from_backend(self.shared_store.backend(validator_id))
     }
 
     pub fn print_key_file(filename: &str) -> Result<(), ConfigError> {
         Secret::new().write(filename)
     }
 
-    pub async fn process_dvfinfo(&mut self) {
-        while let Some(dvfinfo) = self.rx_dvfinfo.recv().await {
-            // This is where we can further process committed block.
-            info!("received validator {}", dvfinfo.validator_id);
-            match DvfCore::new(
-                dvfinfo.committee,
-                self.name.clone(),
-                self.secret_key.clone(),
-                dvfinfo.validator_id,
-                self.base_store_path.clone(),
-                Arc::clone(&self.tx_handler_map),
-                Arc::clone(&self.mempool_handler_map),
-                Arc::clone(&self.consensus_handler_map)
-              ).await {
-                Ok(mut dvfcore) => {
-                  tokio::spawn(async move {
-                    // dvfcore.analyze_block().await;
-                  })
-                  .await
-                  .expect("Failed to analyze committed blocks");
+    /// Returns a sender of commands to the instances of the node, served by `run`.
+    pub fn command_sender(&self) -> Sender<DvfCommand> {
+        self.tx_command.clone()
+    }
+
+    /// Serve the commands of dvf messages and of `command_sender`, for as long as the node lives.
+    pub async fn run(&mut self) {
+        while let Some(command) = self.rx_command.recv().await {
+            match command {
+                DvfCommand::Create(dvfinfo) => self.create_dvfcore(dvfinfo).await,
+                DvfCommand::Start(validator_id, reply) => {
+                    let _ = reply.send(self.start_dvfcore(&validator_id));
+                }
+                DvfCommand::Pause(validator_id, reply) => {
+                    let _ = reply.send(self.pause_dvfcore(&validator_id).await);
                 }
-                Err(e) => {
-                  error!("{}", e);
+                DvfCommand::Remove(validator_id, reply) => {
+                    let _ = reply.send(self.remove_dvfcore(&validator_id).await);
                 }
-              };
+            }
         }
-      }
-}
+    }
+
+    /// Create and start the instance described by `dvfinfo`, unless there is one already.
+    pub async fn create_dvfcore(&mut self, dvfinfo: DvfInfo) {
+        info!("received validator {}", dvfinfo.validator_id);
+        if self.dvfcores.contains_key(&dvfinfo.validator_id) {
+            warn!("dvfcore {} already exists, reconfigure it to change its committee", dvfinfo.validator_id);
+            return;
+        }
+        let mut store = self.store(&dvfinfo.validator_id);
+        // An authority joining after some reconfigurations learns the epochs it missed.
+        if let Err(e) = bootstrap_epochs(&mut store, &dvfinfo.committee.consensus, &dvfinfo.epoch_history).await {
+            error!("Failed to bootstrap the epochs of dvfcore {}: {}", dvfinfo.validator_id, e);
+            return;
+        }
+        match DvfCore::new(
+            dvfinfo.committee,
+            self.name.clone(),
+            self.secret_key.clone(),
+            dvfinfo.validator_id,
+            store,
+            Arc::clone(&self.tx_handler_map),
+            Arc::clone(&self.mempool_handler_map),
+            Arc::clone(&self.consensus_handler_map)
+          ).await {
+            Ok(mut dvfcore) => {
+              // Nothing processes the committed blocks of these instances yet.
+              dvfcore.drain_commits();
+              self.dvfcores.insert(dvfcore.validator_id.clone(), dvfcore);
+            }
+            Err(e) => {
+              error!("{}", e);
+            }
+          };
+    }
+
+    /// Start the instance of `validator_id` again after a pause. Returns false if there is none.
+    pub fn start_dvfcore(&mut self, validator_id: &str) -> bool {
+        match self.dvfcores.get_mut(validator_id) {
+            Some(dvfcore) => {
+                dvfcore.start();
+                true
+            }
+            None => false,
+        }
+    }
 
+    /// Pause the instance of `validator_id`. Returns false if there is none.
+    pub async fn pause_dvfcore(&mut self, validator_id: &str) -> bool {
+        match self.dvfcores.get_mut(validator_id) {
+            Some(dvfcore) => {
+                dvfcore.pause().await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop the instance of `validator_id` and forget about it, e.g., when the validator exits or
+    /// this node leaves its committee. Returns false if there is none.
+    pub async fn remove_dvfcore(&mut self, validator_id: &str) -> bool {
+        self.signature_handler_map.write().await.remove(validator_id);
+        match self.dvfcores.remove(validator_id) {
+            Some(dvfcore) => {
+                dvfcore.stop().await;
+                info!("dvfcore {} removed", validator_id);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use anyhow::{Context, Result};
-use bytes::Bytes;
 use clap::{crate_name, crate_version, App, AppSettings};
 use env_logger::Env;
 use futures::sink::SinkExt as _;
 use log::{warn};
+use network::{Envelope, MessageKind};
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
@@ -14,6 +14,7 @@ async fn main() -> Result<()> {
     .version(crate_version!())
     .about("client for HotStuff nodes.")
     .args_from_usage("<ADDR> 'The network address of the node where to send tx transaction'")
+    .args_from_usage("<VALIDATOR> 'The id (public key) of the validator whose mempool receives the transaction'")
     .setting(AppSettings::ArgRequiredElseHelp)
     .get_matches();
   env_logger::Builder::from_env(Env::default().default_filter_or("info"))
@@ -28,10 +29,10 @@ async fn main() -> Result<()> {
     .await
     .context(format!("failed to connect to {}", target))?;
   let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
-  let mut message : Vec<u8>= vec![50; 88];
+  let validator_id = matches.value_of("VALIDATOR").unwrap();
   let data: Vec<u8> = vec![96; 32];
-  message.extend(data);
-  if let Err(e) = transport.send(Bytes::from(message)).await {
+  let envelope = Envelope::seal(validator_id, MessageKind::Transaction, data);
+  if let Err(e) = transport.send(envelope).await {
     println!("Failed to send transaction: {}", e);
   } 
   Ok(())
@@ -0,0 +1,201 @@
+use crate::{Key, StoreMetrics, StoreResult, Value};
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Included};
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+#[path = "tests/backend_tests.rs"]
+pub mod backend_tests;
+
+/// The key-value storage a `Store` runs on. It is only accessed from the task of its store.
+pub trait StorageBackend: Send + 'static {
+    fn get(&mut self, key: &[u8]) -> StoreResult<Option<Value>>;
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> StoreResult<()>;
+
+    fn delete(&mut self, key: &[u8]) -> StoreResult<()>;
+
+    /// Delete all keys in `[from, to)`.
+    fn delete_range(&mut self, from: &[u8], to: &[u8]) -> StoreResult<()>;
+
+    fn metrics(&mut self) -> StoreResult<StoreMetrics>;
+}
+
+/// Reads the disk usage estimates of `db` as a whole.
+fn rocksdb_metrics(db: &rocksdb::DB) -> StoreResult<StoreMetrics> {
+    let property = |name| db.property_int_value(name).map(|x| x.unwrap_or_default());
+    Ok(StoreMetrics {
+        estimated_keys: property("rocksdb.estimate-num-keys")?,
+        live_data_size: property("rocksdb.estimate-live-data-size")?,
+        sst_files_size: property("rocksdb.total-sst-files-size")?,
+    })
+}
+
+/// A RocksDB database of its own.
+pub struct RocksDbBackend {
+    db: rocksdb::DB,
+}
+
+impl RocksDbBackend {
+    pub fn open(path: &str) -> StoreResult<Self> {
+        let db = rocksdb::DB::open_default(path)?;
+        Ok(Self { db })
+    }
+}
+
+impl StorageBackend for RocksDbBackend {
+    fn get(&mut self, key: &[u8]) -> StoreResult<Option<Value>> {
+        self.db.get(key)
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> StoreResult<()> {
+        self.db.put(key, value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> StoreResult<()> {
+        self.db.delete(key)
+    }
+
+    fn delete_range(&mut self, from: &[u8], to: &[u8]) -> StoreResult<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.delete_range(from, to);
+        self.db.write(batch)
+    }
+
+    fn metrics(&mut self) -> StoreResult<StoreMetrics> {
+        rocksdb_metrics(&self.db)
+    }
+}
+
+/// One RocksDB database shared by the stores of many validators, each under a key prefix of its
+/// own. Prefixes are used rather than column families since column families cannot be created
+/// on a database that is already shared.
+#[derive(Clone)]
+pub struct SharedRocksDb {
+    db: Arc<rocksdb::DB>,
+}
+
+impl SharedRocksDb {
+    pub fn open(path: &str) -> StoreResult<Self> {
+        let db = rocksdb::DB::open_default(path)?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// Returns the backend of the keys of `namespace` (e.g., a validator id).
+    pub fn backend(&self, namespace: &str) -> PrefixedRocksDbBackend {
+        // The prefix starts with the length of the namespace, so that no prefix is the start of
+        // another one.
+        let mut prefix = (namespace.len() as u64).to_be_bytes().to_vec();
+        prefix.extend_from_slice(namespace.as_bytes());
+        PrefixedRocksDbBackend {
+            db: Arc::clone(&self.db),
+            prefix,
+        }
+    }
+
+    /// Copies all keys of the database of its own at `path` (e.g., the store of a validator
+    /// before stores were shared) to the namespace `namespace`. The copy is flushed to disk
+    /// before returning, so the database at `path` may be discarded afterwards.
+    pub fn import(&self, namespace: &str, path: &str) -> StoreResult<()> {
+        let source = rocksdb::DB::open_default(path)?;
+        let backend = self.backend(namespace);
+        let mut batch = rocksdb::WriteBatch::default();
+        for (key, value) in source.iterator(rocksdb::IteratorMode::Start) {
+            batch.put(backend.key(&key), value);
+        }
+        self.db.write(batch)?;
+        self.db.flush()
+    }
+}
+
+/// The keys of one namespace of a `SharedRocksDb`.
+pub struct PrefixedRocksDbBackend {
+    db: Arc<rocksdb::DB>,
+    prefix: Vec<u8>,
+}
+
+impl PrefixedRocksDbBackend {
+    fn key(&self, key: &[u8]) -> Key {
+        let mut prefixed = self.prefix.clone();
+        prefixed.extend_from_slice(key);
+        prefixed
+    }
+}
+
+impl StorageBackend for PrefixedRocksDbBackend {
+    fn get(&mut self, key: &[u8]) -> StoreResult<Option<Value>> {
+        self.db.get(self.key(key))
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> StoreResult<()> {
+        self.db.put(self.key(key), value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> StoreResult<()> {
+        self.db.delete(self.key(key))
+    }
+
+    fn delete_range(&mut self, from: &[u8], to: &[u8]) -> StoreResult<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.delete_range(self.key(from), self.key(to));
+        self.db.write(batch)
+    }
+
+    /// The estimates are those of the whole shared database.
+    fn metrics(&mut self) -> StoreResult<StoreMetrics> {
+        rocksdb_metrics(&self.db)
+    }
+}
+
+/// Keeps everything in memory, e.g., for tests. Clones share the same data, so a store can be
+/// reopened on it.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    map: Arc<Mutex<BTreeMap<Key, Value>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn get(&mut self, key: &[u8]) -> StoreResult<Option<Value>> {
+        Ok(self.map.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> StoreResult<()> {
+        self.map.lock().unwrap().insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> StoreResult<()> {
+        self.map.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn delete_range(&mut self, from: &[u8], to: &[u8]) -> StoreResult<()> {
+        if from >= to {
+            return Ok(());
+        }
+        let mut map = self.map.lock().unwrap();
+        let keys: Vec<_> = map
+            .range::<[u8], _>((Included(from), Excluded(to)))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            map.remove(&key);
+        }
+        Ok(())
+    }
+
+    fn metrics(&mut self) -> StoreResult<StoreMetrics> {
+        let map = self.map.lock().unwrap();
+        Ok(StoreMetrics {
+            estimated_keys: map.len() as u64,
+            live_data_size: map.iter().map(|(k, v)| (k.len() + v.len()) as u64).sum(),
+            sst_files_size: 0,
+        })
+    }
+}
@@ -1,21 +1,42 @@
+mod backend;
+
 use std::collections::{HashMap, VecDeque};
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::sync::oneshot;
 
+pub use crate::backend::{
+    MemoryBackend, PrefixedRocksDbBackend, RocksDbBackend, SharedRocksDb, StorageBackend,
+};
+
 #[cfg(test)]
 #[path = "tests/store_tests.rs"]
 pub mod store_tests;
 
 pub type StoreError = rocksdb::Error;
-type StoreResult<T> = Result<T, StoreError>;
+pub type StoreResult<T> = Result<T, StoreError>;
 
-type Key = Vec<u8>;
-type Value = Vec<u8>;
+pub type Key = Vec<u8>;
+pub type Value = Vec<u8>;
 
 pub enum StoreCommand {
     Write(Key, Value),
     Read(Key, oneshot::Sender<StoreResult<Option<Value>>>),
     NotifyRead(Key, oneshot::Sender<StoreResult<Value>>),
+    Delete(Key),
+    /// Delete all keys in `[from, to)`.
+    DeleteRange(Key, Key),
+    Metrics(oneshot::Sender<StoreResult<StoreMetrics>>),
+}
+
+/// Estimates of the disk usage of the store, as reported by its backend.
+#[derive(Clone, Debug, Default)]
+pub struct StoreMetrics {
+    /// The estimated number of keys.
+    pub estimated_keys: u64,
+    /// The estimated size of the live data, in bytes.
+    pub live_data_size: u64,
+    /// The total size of the SST files on disk, in bytes. It only shrinks after compaction.
+    pub sst_files_size: u64,
 }
 
 #[derive(Clone)]
@@ -24,15 +45,20 @@ pub struct Store {
 }
 
 impl Store {
+    /// Open a RocksDB database of its own at `path`.
     pub fn new(path: &str) -> StoreResult<Self> {
-        let db = rocksdb::DB::open_default(path)?;
+        Ok(Self::from_backend(RocksDbBackend::open(path)?))
+    }
+
+    /// Run a store on `backend`.
+    pub fn from_backend<B: StorageBackend>(mut backend: B) -> Self {
         let mut obligations = HashMap::<_, VecDeque<oneshot::Sender<_>>>::new();
         let (tx, mut rx) = channel(100);
         tokio::spawn(async move {
             while let Some(command) = rx.recv().await {
                 match command {
                     StoreCommand::Write(key, value) => {
-                        let _ = db.put(&key, &value);
+                        let _ = backend.put(&key, &value);
                         if let Some(mut senders) = obligations.remove(&key) {
                             while let Some(s) = senders.pop_front() {
                                 let _ = s.send(Ok(value.clone()));
@@ -40,11 +66,11 @@ impl Store {
                         }
                     }
                     StoreCommand::Read(key, sender) => {
-                        let response = db.get(&key);
+                        let response = backend.get(&key);
                         let _ = sender.send(response);
                     }
                     StoreCommand::NotifyRead(key, sender) => {
-                        let response = db.get(&key);
+                        let response = backend.get(&key);
                         match response {
                             Ok(None) => obligations
                                 .entry(key)
@@ -55,10 +81,19 @@ impl Store {
                             }
                         }
                     }
+                    StoreCommand::Delete(key) => {
+                        let _ = backend.delete(&key);
+                    }
+                    StoreCommand::DeleteRange(from, to) => {
+                        let _ = backend.delete_range(&from, &to);
+                    }
+                    StoreCommand::Metrics(sender) => {
+                        let _ = sender.send(backend.metrics());
+                    }
                 }
             }
         });
-        Ok(Self { channel: tx })
+        Self { channel: tx }
     }
 
     pub async fn write(&mut self, key: Key, value: Value) {
@@ -90,4 +125,27 @@ impl Store {
             .await
             .expect("Failed to receive reply to NotifyRead command from store")
     }
+
+    pub async fn delete(&mut self, key: Key) {
+        if let Err(e) = self.channel.send(StoreCommand::Delete(key)).await {
+            panic!("Failed to send Delete command to store: {}", e);
+        }
+    }
+
+    /// Delete all keys from `from` (included) to `to` (excluded).
+    pub async fn delete_range(&mut self, from: Key, to: Key) {
+        if let Err(e) = self.channel.send(StoreCommand::DeleteRange(from, to)).await {
+            panic!("Failed to send DeleteRange command to store: {}", e);
+        }
+    }
+
+    pub async fn metrics(&mut self) -> StoreResult<StoreMetrics> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(StoreCommand::Metrics(sender)).await {
+            panic!("Failed to send Metrics command to store: {}", e);
+        }
+        receiver
+            .await
+            .expect("Failed to receive reply to Metrics command from store")
+    }
 }
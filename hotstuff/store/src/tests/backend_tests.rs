@@ -0,0 +1,70 @@
+use super::*;
+use crate::Store;
+use std::fs;
+
+#[tokio::test]
+async fn memory_backend() {
+    let backend = MemoryBackend::new();
+    let mut store = Store::from_backend(backend.clone());
+
+    // Write values and delete some of them.
+    for i in 0u8..4 {
+        store.write(vec![i], vec![i]).await;
+    }
+    store.delete(vec![0u8]).await;
+    store.delete_range(vec![1u8], vec![3u8]).await;
+    for i in 0u8..3 {
+        assert!(store.read(vec![i]).await.unwrap().is_none());
+    }
+    assert_eq!(store.read(vec![3u8]).await.unwrap(), Some(vec![3u8]));
+    assert_eq!(store.metrics().await.unwrap().estimated_keys, 1);
+
+    // A store reopened on the same backend sees the same values.
+    let mut store = Store::from_backend(backend);
+    assert_eq!(store.read(vec![3u8]).await.unwrap(), Some(vec![3u8]));
+}
+
+#[tokio::test]
+async fn shared_rocksdb_namespaces() {
+    let path = ".db_test_shared_rocksdb_namespaces";
+    let _ = fs::remove_dir_all(path);
+    let db = SharedRocksDb::open(path).unwrap();
+
+    // The namespace "1" with key "2x" does not collide with the namespace "12" with key "x".
+    let mut store_1 = Store::from_backend(db.backend("1"));
+    let mut store_12 = Store::from_backend(db.backend("12"));
+    store_1.write(b"2x".to_vec(), vec![1u8]).await;
+    store_12.write(b"x".to_vec(), vec![12u8]).await;
+    assert_eq!(store_1.read(b"2x".to_vec()).await.unwrap(), Some(vec![1u8]));
+    assert!(store_1.read(b"x".to_vec()).await.unwrap().is_none());
+    assert_eq!(store_12.read(b"x".to_vec()).await.unwrap(), Some(vec![12u8]));
+    assert!(store_12.read(b"2x".to_vec()).await.unwrap().is_none());
+
+    // Range deletions stay within their namespace.
+    store_1.delete_range(Vec::new(), vec![u8::MAX]).await;
+    assert!(store_1.read(b"2x".to_vec()).await.unwrap().is_none());
+    assert_eq!(store_12.read(b"x".to_vec()).await.unwrap(), Some(vec![12u8]));
+}
+
+#[tokio::test]
+async fn shared_rocksdb_import() {
+    let path = ".db_test_shared_rocksdb_import";
+    let legacy_path = ".db_test_shared_rocksdb_import_legacy";
+    let _ = fs::remove_dir_all(path);
+    let _ = fs::remove_dir_all(legacy_path);
+
+    // Write to a database of its own, as stores did before being shared.
+    let mut legacy = RocksDbBackend::open(legacy_path).unwrap();
+    legacy.put(b"x", &[1u8]).unwrap();
+    legacy.put(b"y", &[2u8]).unwrap();
+    drop(legacy);
+
+    // Its keys are found in the namespace it is imported to, and only there.
+    let db = SharedRocksDb::open(path).unwrap();
+    db.import("1", legacy_path).unwrap();
+    let mut store_1 = Store::from_backend(db.backend("1"));
+    let mut store_2 = Store::from_backend(db.backend("2"));
+    assert_eq!(store_1.read(b"x".to_vec()).await.unwrap(), Some(vec![1u8]));
+    assert_eq!(store_1.read(b"y".to_vec()).await.unwrap(), Some(vec![2u8]));
+    assert!(store_2.read(b"x".to_vec()).await.unwrap().is_none());
+}
@@ -71,3 +71,54 @@ async fn read_notify() {
     store.write(key, value).await;
     assert!(handle.await.is_ok());
 }
+
+#[tokio::test]
+async fn delete_value() {
+    // Create new store.
+    let path = ".db_test_delete_value";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Write a value and delete it.
+    let key = vec![0u8, 1u8, 2u8, 3u8];
+    let value = vec![4u8, 5u8, 6u8, 7u8];
+    store.write(key.clone(), value).await;
+    store.delete(key.clone()).await;
+
+    // Read the deleted value.
+    let result = store.read(key).await;
+    assert!(result.is_ok());
+    assert!(result.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn delete_range() {
+    // Create new store.
+    let path = ".db_test_delete_range";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Write a few values and delete all but the last one.
+    for i in 0u8..4 {
+        store.write(vec![0u8, i], vec![i]).await;
+    }
+    store.delete_range(vec![0u8, 0u8], vec![0u8, 3u8]).await;
+
+    // Only the last value is left.
+    for i in 0u8..3 {
+        assert!(store.read(vec![0u8, i]).await.unwrap().is_none());
+    }
+    assert_eq!(store.read(vec![0u8, 3u8]).await.unwrap(), Some(vec![3u8]));
+}
+
+#[tokio::test]
+async fn read_metrics() {
+    // Create new store.
+    let path = ".db_test_read_metrics";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Metrics are available even before anything is flushed to disk.
+    store.write(vec![0u8], vec![0u8]).await;
+    assert!(store.metrics().await.is_ok());
+}
@@ -266,4 +266,23 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                     execution payload construction during proposals.")
                 .takes_value(false),
         )
+        /*
+         * Operator of distributed validators
+         */
+        .arg(
+            Arg::with_name("node-key")
+                .long("node-key")
+                .value_name("NODE_KEY_PATH")
+                .help("Path to the file of the node key identifying this operator in its \
+                    committees. A new key is generated if the file does not exist.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("operator-address")
+                .long("operator-address")
+                .value_name("ADDRESS")
+                .help("Socket address on which to serve the signing requests of the other \
+                    operators of the committees, e.g. 0.0.0.0:25000.")
+                .takes_value(true),
+        )
 }
@@ -0,0 +1,199 @@
+//! Splits the key of a new distributed validator into one share bundle per operator.
+//!
+//! Each bundle is written to `--output-dir/{operator_id}`, ready to hand to the operator. The share
+//! in a bundle is sealed to the node key of its operator, so no password is written.
+
+use clap::{App, Arg, ArgMatches};
+use clap_utils::{get_eth2_network_config, parse_optional, parse_required};
+use crypto::PublicKey as NodePublicKey;
+use dvf::validation::account_utils::eth2_keystore::Keystore;
+use dvf::validation::account_utils::{read_password, read_password_from_user};
+use dvf::validation::validator_dir::share_bundle::ShareBundleBuilder;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use types::{Hash256, MainnetEthSpec};
+
+pub const COMMITTEE_INDEX_FLAG: &str = "committee-index";
+pub const THRESHOLD_FLAG: &str = "threshold";
+pub const OPERATORS_FLAG: &str = "operators";
+pub const NODE_PUBLIC_KEYS_FLAG: &str = "node-public-keys";
+pub const KEYSTORE_FLAG: &str = "keystore";
+pub const PASSWORD_FILE_FLAG: &str = "password-file";
+pub const WITHDRAWAL_CREDENTIALS_FLAG: &str = "withdrawal-credentials";
+pub const DEPOSIT_GWEI_FLAG: &str = "deposit-gwei";
+pub const OUTPUT_DIR_FLAG: &str = "output-dir";
+
+fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("create_distributed_validator")
+        .about(
+            "Splits the key of a new distributed validator into one key share per operator, sealed \
+            to the node key of the operator and bundled with the committee definition and the \
+            deposit data.",
+        )
+        .arg(
+            Arg::with_name(COMMITTEE_INDEX_FLAG)
+                .long(COMMITTEE_INDEX_FLAG)
+                .value_name("COMMITTEE_INDEX")
+                .help("The index of the operator committee of the validator.")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(THRESHOLD_FLAG)
+                .long(THRESHOLD_FLAG)
+                .value_name("THRESHOLD")
+                .help("The number of operators needed to sign for the validator.")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(OPERATORS_FLAG)
+                .long(OPERATORS_FLAG)
+                .value_name("SOCKET_ADDRESSES")
+                .help("Comma-separated socket addresses of the operators, in the order of their ids.")
+                .required(true)
+                .use_delimiter(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(NODE_PUBLIC_KEYS_FLAG)
+                .long(NODE_PUBLIC_KEYS_FLAG)
+                .value_name("NODE_PUBLIC_KEYS")
+                .help(
+                    "Comma-separated base64 node public keys of the operators, in the same order \
+                    as --operators. The share of each operator is sealed to its key.",
+                )
+                .required(true)
+                .use_delimiter(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(KEYSTORE_FLAG)
+                .long(KEYSTORE_FLAG)
+                .value_name("KEYSTORE_PATH")
+                .help("The EIP-2335 keystore of the validator. A random key is generated if absent.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(PASSWORD_FILE_FLAG)
+                .long(PASSWORD_FILE_FLAG)
+                .value_name("PASSWORD_FILE_PATH")
+                .help("A file containing the password of --keystore. Prompted for if absent.")
+                .requires(KEYSTORE_FLAG)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(WITHDRAWAL_CREDENTIALS_FLAG)
+                .long(WITHDRAWAL_CREDENTIALS_FLAG)
+                .value_name("WITHDRAWAL_CREDENTIALS")
+                .help("If present, create the deposit data of the validator with these credentials.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DEPOSIT_GWEI_FLAG)
+                .long(DEPOSIT_GWEI_FLAG)
+                .value_name("DEPOSIT_GWEI")
+                .help("The GWEI value of the deposit amount. Defaults to MAX_EFFECTIVE_BALANCE.")
+                .requires(WITHDRAWAL_CREDENTIALS_FLAG)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("network")
+                .long("network")
+                .value_name("NETWORK")
+                .help("The network of the deposit. Defaults to mainnet.")
+                .conflicts_with("testnet-dir")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("testnet-dir")
+                .long("testnet-dir")
+                .value_name("DIR")
+                .help("The testnet directory of the network of the deposit.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(OUTPUT_DIR_FLAG)
+                .long(OUTPUT_DIR_FLAG)
+                .value_name("OUTPUT_DIR")
+                .help("The directory to write one share bundle per operator into.")
+                .required(true)
+                .takes_value(true),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let committee_index: u64 = parse_required(matches, COMMITTEE_INDEX_FLAG)?;
+    let threshold: u64 = parse_required(matches, THRESHOLD_FLAG)?;
+    let socket_addresses = matches
+        .values_of(OPERATORS_FLAG)
+        .ok_or_else(|| format!("--{} is required", OPERATORS_FLAG))?
+        .map(|address| {
+            address
+                .parse::<SocketAddr>()
+                .map_err(|e| format!("Invalid operator address {}: {}", address, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let node_public_keys = matches
+        .values_of(NODE_PUBLIC_KEYS_FLAG)
+        .ok_or_else(|| format!("--{} is required", NODE_PUBLIC_KEYS_FLAG))?
+        .map(|key| {
+            NodePublicKey::decode_base64(key)
+                .map_err(|e| format!("Invalid node public key {}: {}", key, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let output_dir: PathBuf = parse_required(matches, OUTPUT_DIR_FLAG)?;
+    let spec = get_eth2_network_config(matches)?.chain_spec::<MainnetEthSpec>()?;
+
+    let mut builder = ShareBundleBuilder::new(committee_index, threshold, socket_addresses, node_public_keys);
+    if let Some(keystore_path) = parse_optional::<PathBuf>(matches, KEYSTORE_FLAG)? {
+        let keystore = Keystore::from_json_file(&keystore_path)
+            .map_err(|e| format!("Unable to read keystore {:?}: {:?}", keystore_path, e))?;
+        let keypair = match parse_optional::<PathBuf>(matches, PASSWORD_FILE_FLAG)? {
+            Some(password_path) => {
+                let password = read_password(&password_path)
+                    .map_err(|e| format!("Unable to read password {:?}: {:?}", password_path, e))?;
+                keystore.decrypt_keypair(password.as_bytes())
+            }
+            None => {
+                eprintln!("Enter the password of keystore {:?}:", keystore_path);
+                let password = read_password_from_user(false)?;
+                keystore.decrypt_keypair(password.as_ref())
+            }
+        }
+        .map_err(|e| format!("Unable to decrypt keystore: {:?}", e))?;
+        builder = builder.voting_keypair(keypair);
+    }
+    if let Some(withdrawal_credentials) = parse_optional::<Hash256>(matches, WITHDRAWAL_CREDENTIALS_FLAG)? {
+        let deposit_gwei = parse_optional(matches, DEPOSIT_GWEI_FLAG)?.unwrap_or(spec.max_effective_balance);
+        builder = builder.create_deposit_data(withdrawal_credentials, deposit_gwei, &spec);
+    }
+
+    let bundles = builder
+        .build()
+        .map_err(|e| format!("Unable to create share bundles: {:?}", e))?;
+    for bundle in &bundles {
+        let dir = bundle
+            .save(&output_dir)
+            .map_err(|e| format!("Unable to save share bundle: {:?}", e))?;
+        println!("Share bundle of operator {}: {:?}", bundle.operator_id(), dir);
+    }
+    if let Some(bundle) = bundles.first() {
+        println!("Voting public key: {}", bundle.committee_definition.voting_public_key);
+        if let Some(rlp) = bundle
+            .eth1_deposit_tx_data()
+            .map_err(|e| format!("Unable to encode deposit data: {:?}", e))?
+        {
+            println!("Eth1 deposit transaction data: 0x{}", hex::encode(rlp));
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let matches = cli_app().get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
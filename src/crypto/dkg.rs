@@ -0,0 +1,516 @@
+//! Distributed key generation (Gennaro, Jarecki, Krawczyk and Rabin) among the operators of a
+//! committee.
+//!
+//! The key is generated in two phases, so that no dealer can bias it by choosing whether to
+//! contribute after seeing the contributions of the others:
+//!
+//! 1. Every operator deals a random polynomial of degree `t - 1` with Pedersen VSS: it broadcasts
+//!    Pedersen commitments to the coefficients (hiding them behind a second, blinding, polynomial)
+//!    and privately sends one evaluation of both polynomials to every operator. Operators complain
+//!    about dealers whose share does not match the commitments, and a dealer answers a complaint by
+//!    revealing the disputed share. Dealers with an unanswered (or badly answered) complaint are
+//!    disqualified, the others are the qualified dealers.
+//! 2. Every qualified dealer reveals Feldman commitments to its coefficients. Operators complain,
+//!    with their share as evidence, about dealers whose Feldman commitments do not match it. The
+//!    polynomial of a dealer who is caught (or who does not reveal its commitments) is
+//!    reconstructed from the shares the operators reveal in turn: its contribution stays.
+//!
+//! The key share of an operator is the sum of the shares received from the qualified dealers, and
+//! the master public key is the sum of their constant-term Feldman commitments. The master secret
+//! key is never assembled anywhere.
+
+use bls::{AggregatePublicKey, Keypair, PublicKey, SecretKey};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::crypto::ThresholdSignature;
+use crate::crypto::define::MODULUS;
+use crate::crypto::generic_threshold::{bigint_to_secret_key, secret_key_to_bigint};
+use crate::math::bigint_ext::Ring;
+use crate::math::polynomial::Polynomial;
+use crate::utils::error::DvfError;
+use crate::utils::rand_utils::{RandUtilsRng, Sample};
+use crate::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use eth2_keystore::KeystoreBuilder;
+
+/// Messages exchanged during a DKG run.
+///
+/// Everything but `Share` must go through a broadcast channel so that all honest operators agree
+/// on the set of qualified dealers, and on the dealers to reconstruct.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum DkgMessage {
+    /// Pedersen commitments to the coefficients of `dealer`'s polynomial.
+    Commitments { dealer: u64, commitments: Vec<PublicKey> },
+    /// Evaluation of `dealer`'s polynomial at `receiver`, with the evaluation of the blinding
+    /// polynomial. Sent privately.
+    Share {
+        dealer: u64,
+        receiver: u64,
+        #[serde(with = "secret_key_hex")]
+        share: SecretKey,
+        #[serde(with = "secret_key_hex")]
+        blinding: SecretKey,
+    },
+    /// `accuser` claims it received no share, or a bad one, from `dealer`.
+    Complaint { dealer: u64, accuser: u64 },
+    /// `dealer` reveals the share of `accuser` in answer to a complaint.
+    Justification {
+        dealer: u64,
+        accuser: u64,
+        #[serde(with = "secret_key_hex")]
+        share: SecretKey,
+        #[serde(with = "secret_key_hex")]
+        blinding: SecretKey,
+    },
+    /// Feldman commitments to the coefficients of qualified `dealer`'s polynomial.
+    PublicCoefficients { dealer: u64, commitments: Vec<PublicKey> },
+    /// `accuser` reveals its share from `dealer`, which does not match the Feldman commitments of
+    /// `dealer`.
+    ExtractionComplaint {
+        dealer: u64,
+        accuser: u64,
+        #[serde(with = "secret_key_hex")]
+        share: SecretKey,
+        #[serde(with = "secret_key_hex")]
+        blinding: SecretKey,
+    },
+    /// `holder` reveals its share from `dealer`, whose polynomial is reconstructed.
+    Reveal {
+        dealer: u64,
+        holder: u64,
+        #[serde(with = "secret_key_hex")]
+        share: SecretKey,
+        #[serde(with = "secret_key_hex")]
+        blinding: SecretKey,
+    },
+}
+
+/// Hex encoding of the shares carried by `DkgMessage`s and `ReshareMessage`s.
+pub(crate) mod secret_key_hex {
+    use bls::SecretKey;
+    use serde::de::Error;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(sk: &SecretKey, serializer: S) -> Result<S::Ok, S::Error> {
+        eth2_serde_utils::hex_vec::serialize(sk.serialize().as_bytes(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SecretKey, D::Error> {
+        let bytes = eth2_serde_utils::hex_vec::deserialize(deserializer)?;
+        SecretKey::deserialize(&bytes).map_err(|e| D::Error::custom(format!("invalid share: {:?}", e)))
+    }
+}
+
+/// Result of a successful DKG run for one operator.
+pub struct DkgOutput {
+    /// Id of this operator.
+    pub id: u64,
+    /// Key share of this operator.
+    pub keypair: Keypair,
+    /// Public key of the distributed validator.
+    pub master_public_key: PublicKey,
+    /// Feldman commitments to the combined polynomial. The first one is `master_public_key`.
+    pub commitments: Vec<PublicKey>,
+    /// Ids of all operators of the committee.
+    pub ids: Vec<u64>,
+    /// Public keys of the key shares of all operators, in the order of `ids`.
+    pub share_public_keys: Vec<PublicKey>,
+    /// Dealers whose polynomial contributed to the key.
+    pub qualified: Vec<u64>,
+    /// Qualified dealers whose polynomial was reconstructed in the second phase.
+    pub reconstructed: Vec<u64>,
+}
+
+impl DkgOutput {
+    /// Encrypt the key share with `password` into a `KeystoreShare` for validator `master_id`.
+    pub fn keystore_share(&self, password: &[u8], master_id: u64) -> Result<KeystoreShare, eth2_keystore::Error> {
+        let keystore = KeystoreBuilder::new(&self.keypair, password, "".into())?.build()?;
+        Ok(KeystoreShare::new(
+            keystore,
+            self.master_public_key.clone(),
+            master_id,
+            self.id,
+            self.commitments.clone(),
+        ))
+    }
+}
+
+/// A share of a dealer's polynomial, with the matching share of its blinding polynomial.
+#[derive(Clone)]
+struct BlindedShare {
+    share: SecretKey,
+    blinding: SecretKey,
+}
+
+/// State of one operator taking part in a DKG run.
+pub struct DkgParty {
+    id: u64,
+    ids: Vec<u64>,
+    threshold: usize,
+    coeffs: Vec<BigInt>,
+    blinding_coeffs: Vec<BigInt>,
+    /// Pedersen commitments broadcast by each dealer.
+    commitments: HashMap<u64, Vec<PublicKey>>,
+    /// Shares received from each dealer.
+    shares: HashMap<u64, BlindedShare>,
+    /// Accusers of each dealer.
+    complaints: HashMap<u64, HashSet<u64>>,
+    /// Valid shares revealed by dealers, indexed by (dealer, accuser).
+    justifications: HashMap<(u64, u64), BlindedShare>,
+    /// Feldman commitments broadcast by each dealer.
+    public_coefficients: HashMap<u64, Vec<PublicKey>>,
+    /// Dealers whose Feldman commitments were proven wrong.
+    exposed: HashSet<u64>,
+    /// Valid shares revealed by the operators, by dealer and holder.
+    reveals: HashMap<u64, BTreeMap<u64, SecretKey>>,
+}
+
+impl DkgParty {
+    /// Prepare operator `id` for a `threshold`-out-of-`ids.len()` DKG.
+    pub fn new(id: u64, ids: Vec<u64>, threshold: usize) -> Result<Self, DvfError> {
+        let distinct: HashSet<u64> = ids.iter().copied().collect();
+        if distinct.len() != ids.len() || distinct.contains(&0) || !distinct.contains(&id) {
+            return Err(DvfError::InvalidOperatorIds);
+        }
+        if threshold == 0 || threshold > ids.len() {
+            return Err(DvfError::InsufficientDealers { got: ids.len(), expected: threshold });
+        }
+        let mut rng = RandUtilsRng::new();
+        let coeffs: Vec<BigInt> = rng.sample_vec(threshold, &MODULUS);
+        let blinding_coeffs: Vec<BigInt> = rng.sample_vec(threshold, &MODULUS);
+        Ok(Self {
+            id,
+            ids,
+            threshold,
+            coeffs,
+            blinding_coeffs,
+            commitments: HashMap::new(),
+            shares: HashMap::new(),
+            complaints: HashMap::new(),
+            justifications: HashMap::new(),
+            public_coefficients: HashMap::new(),
+            exposed: HashSet::new(),
+            reveals: HashMap::new(),
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn evaluate(&self, x: u64) -> Result<BlindedShare, DvfError> {
+        let x = BigInt::from(x);
+        let share = Polynomial::new(self.coeffs.clone()).eval(&x);
+        let blinding = Polynomial::new(self.blinding_coeffs.clone()).eval(&x);
+        Ok(BlindedShare {
+            share: bigint_to_secret_key(&share)?,
+            blinding: bigint_to_secret_key(&blinding)?,
+        })
+    }
+
+    /// Round 1: the Pedersen commitments to broadcast, followed by one private share per operator.
+    pub fn deal(&self) -> Result<Vec<DkgMessage>, DvfError> {
+        let commitments = self.coeffs
+            .iter()
+            .zip(self.blinding_coeffs.iter())
+            .map(|(c, b)| ThresholdSignature::pedersen_commit(&bigint_to_secret_key(c)?, &bigint_to_secret_key(b)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut msgs = vec![DkgMessage::Commitments { dealer: self.id, commitments }];
+        for receiver in &self.ids {
+            let BlindedShare { share, blinding } = self.evaluate(*receiver)?;
+            msgs.push(DkgMessage::Share { dealer: self.id, receiver: *receiver, share, blinding });
+        }
+        Ok(msgs)
+    }
+
+    /// Process a message from another operator (or from ourselves).
+    pub fn handle(&mut self, msg: DkgMessage) {
+        match msg {
+            DkgMessage::Commitments { dealer, commitments } => {
+                if self.ids.contains(&dealer) {
+                    // Only the first broadcast of a dealer counts.
+                    self.commitments.entry(dealer).or_insert(commitments);
+                }
+            }
+            DkgMessage::Share { dealer, receiver, share, blinding } => {
+                if receiver == self.id && self.ids.contains(&dealer) {
+                    self.shares.entry(dealer).or_insert(BlindedShare { share, blinding });
+                }
+            }
+            DkgMessage::Complaint { dealer, accuser } => {
+                if self.ids.contains(&dealer) && self.ids.contains(&accuser) {
+                    self.complaints.entry(dealer).or_default().insert(accuser);
+                }
+            }
+            DkgMessage::Justification { dealer, accuser, share, blinding } => {
+                if self.verify_blinded_share(dealer, accuser, &share, &blinding) {
+                    self.justifications.entry((dealer, accuser)).or_insert(BlindedShare { share, blinding });
+                }
+            }
+            DkgMessage::PublicCoefficients { dealer, commitments } => {
+                if self.ids.contains(&dealer) {
+                    self.public_coefficients.entry(dealer).or_insert(commitments);
+                }
+            }
+            DkgMessage::ExtractionComplaint { dealer, accuser, share, blinding } => {
+                // The share is bound to the dealer by its Pedersen commitments: if it does not match
+                // the Feldman commitments, the dealer lied about them.
+                let proven = self.verify_blinded_share(dealer, accuser, &share, &blinding)
+                    && self
+                        .public_coefficients
+                        .get(&dealer)
+                        .map_or(false, |c| ThresholdSignature::verify_share(accuser, &share, c).is_err());
+                if proven {
+                    self.exposed.insert(dealer);
+                    self.reveals.entry(dealer).or_default().entry(accuser).or_insert(share);
+                }
+            }
+            DkgMessage::Reveal { dealer, holder, share, blinding } => {
+                if self.verify_blinded_share(dealer, holder, &share, &blinding) {
+                    self.reveals.entry(dealer).or_default().entry(holder).or_insert(share);
+                }
+            }
+        }
+    }
+
+    /// Whether `share` is the share of `holder` committed to by `dealer` in the first phase.
+    fn verify_blinded_share(&self, dealer: u64, holder: u64, share: &SecretKey, blinding: &SecretKey) -> bool {
+        self.ids.contains(&holder)
+            && self.commitments.get(&dealer).map_or(false, |c| {
+                c.len() == self.threshold && ThresholdSignature::verify_pedersen_share(holder, share, blinding, c).is_ok()
+            })
+    }
+
+    fn share_is_valid(&self, dealer: u64) -> bool {
+        self.shares
+            .get(&dealer)
+            .map_or(false, |s| self.verify_blinded_share(dealer, self.id, &s.share, &s.blinding))
+    }
+
+    /// Our valid share from `dealer`, either received privately or revealed in a justification.
+    fn own_share(&self, dealer: u64) -> Option<&BlindedShare> {
+        match self.justifications.get(&(dealer, self.id)) {
+            Some(share) => Some(share),
+            None if self.share_is_valid(dealer) => self.shares.get(&dealer),
+            None => None,
+        }
+    }
+
+    /// Round 2: complaints against the dealers who broadcast commitments but sent us no share or a
+    /// share that does not match them.
+    pub fn complaints(&self) -> Vec<DkgMessage> {
+        self.ids
+            .iter()
+            .filter(|dealer| self.commitments.contains_key(dealer) && !self.share_is_valid(**dealer))
+            .map(|dealer| DkgMessage::Complaint { dealer: *dealer, accuser: self.id })
+            .collect()
+    }
+
+    /// Round 3: reveal the shares of the operators who complained about us.
+    pub fn justifications(&self) -> Result<Vec<DkgMessage>, DvfError> {
+        let mut accusers: Vec<u64> = self
+            .complaints
+            .get(&self.id)
+            .map(|a| a.iter().copied().collect())
+            .unwrap_or_default();
+        accusers.sort_unstable();
+        accusers
+            .into_iter()
+            .map(|accuser| {
+                let BlindedShare { share, blinding } = self.evaluate(accuser)?;
+                Ok(DkgMessage::Justification { dealer: self.id, accuser, share, blinding })
+            })
+            .collect()
+    }
+
+    /// Dealers with well-formed commitments and no pending complaint at the end of the first
+    /// phase. This only depends on broadcast messages, hence is the same for all honest operators.
+    pub fn qualified(&self) -> Vec<u64> {
+        let qualified: BTreeSet<u64> = self
+            .ids
+            .iter()
+            .copied()
+            .filter(|dealer| {
+                let well_formed = self
+                    .commitments
+                    .get(dealer)
+                    .map_or(false, |c| c.len() == self.threshold);
+                let answered = self.complaints.get(dealer).map_or(true, |accusers| {
+                    accusers.iter().all(|a| self.justifications.contains_key(&(*dealer, *a)))
+                });
+                well_formed && answered
+            })
+            .collect();
+        qualified.into_iter().collect()
+    }
+
+    /// Round 4: if we are a qualified dealer, the Feldman commitments to broadcast.
+    pub fn public_coefficients(&self) -> Result<Vec<DkgMessage>, DvfError> {
+        if !self.qualified().contains(&self.id) {
+            return Ok(vec![]);
+        }
+        let commitments = self.coeffs
+            .iter()
+            .map(|c| bigint_to_secret_key(c).map(|sk| sk.public_key()))
+            .collect::<Result<_, _>>()?;
+        Ok(vec![DkgMessage::PublicCoefficients { dealer: self.id, commitments }])
+    }
+
+    /// Round 5: complaints, with our share as evidence, against the qualified dealers whose
+    /// Feldman commitments do not match our share.
+    pub fn extraction_complaints(&self) -> Vec<DkgMessage> {
+        self.qualified()
+            .into_iter()
+            .filter_map(|dealer| {
+                let commitments = self.public_coefficients.get(&dealer)?;
+                let own = self.own_share(dealer)?;
+                if commitments.len() == self.threshold
+                    && ThresholdSignature::verify_share(self.id, &own.share, commitments).is_ok()
+                {
+                    return None;
+                }
+                Some(DkgMessage::ExtractionComplaint {
+                    dealer,
+                    accuser: self.id,
+                    share: own.share.clone(),
+                    blinding: own.blinding.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Qualified dealers whose polynomial must be reconstructed: those who did not broadcast
+    /// well-formed Feldman commitments, and those proven to have lied about them. This only
+    /// depends on broadcast messages, hence is the same for all honest operators.
+    pub fn reconstructed(&self) -> Vec<u64> {
+        self.qualified()
+            .into_iter()
+            .filter(|dealer| {
+                let well_formed = self
+                    .public_coefficients
+                    .get(dealer)
+                    .map_or(false, |c| c.len() == self.threshold);
+                !well_formed || self.exposed.contains(dealer)
+            })
+            .collect()
+    }
+
+    /// Round 6: reveal our shares from the dealers to reconstruct.
+    pub fn reveals(&self) -> Vec<DkgMessage> {
+        self.reconstructed()
+            .into_iter()
+            .filter_map(|dealer| {
+                let own = self.own_share(dealer)?;
+                Some(DkgMessage::Reveal {
+                    dealer,
+                    holder: self.id,
+                    share: own.share.clone(),
+                    blinding: own.blinding.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The coefficients of `dealer`'s polynomial, interpolated from `threshold` revealed shares.
+    fn reconstruct(&self, dealer: u64) -> Result<Vec<BigInt>, DvfError> {
+        let points: Vec<(u64, BigInt)> = self
+            .reveals
+            .get(&dealer)
+            .into_iter()
+            .flatten()
+            .take(self.threshold)
+            .map(|(holder, share)| (*holder, secret_key_to_bigint(share)))
+            .collect();
+        if points.len() < self.threshold {
+            return Err(DvfError::KeyRecoveryFailed);
+        }
+        Ok(interpolate_coefficients(&points))
+    }
+
+    /// Combine the shares of the qualified dealers into our key share.
+    pub fn finalize(&self) -> Result<DkgOutput, DvfError> {
+        let qualified = self.qualified();
+        if qualified.len() < self.threshold {
+            return Err(DvfError::InsufficientDealers { got: qualified.len(), expected: self.threshold });
+        }
+
+        let mut sum = BigInt::zero();
+        for dealer in &qualified {
+            // We complained, and the dealer answered someone else only: it cannot be qualified.
+            let own = self.own_share(*dealer).ok_or(DvfError::InvalidKeyShare { id: *dealer })?;
+            sum += secret_key_to_bigint(&own.share);
+        }
+        let sk = bigint_to_secret_key(&sum.reduce(&MODULUS))?;
+
+        // The coefficients of the reconstructed dealers are public, hence added up in the clear.
+        let reconstructed = self.reconstructed();
+        let mut revealed_coeffs = vec![BigInt::zero(); self.threshold];
+        for dealer in &reconstructed {
+            for (total, c) in revealed_coeffs.iter_mut().zip(self.reconstruct(*dealer)?) {
+                *total = (&*total + c).reduce(&MODULUS);
+            }
+        }
+
+        let mut commitments = Vec::with_capacity(self.threshold);
+        for (k, revealed) in revealed_coeffs.iter().enumerate() {
+            let mut points: Vec<PublicKey> = qualified
+                .iter()
+                .filter(|dealer| !reconstructed.contains(dealer))
+                .map(|dealer| self.public_coefficients[dealer][k].clone())
+                .collect();
+            if !revealed.is_zero() {
+                points.push(bigint_to_secret_key(revealed)?.public_key());
+            }
+            let agg = AggregatePublicKey::aggregate(&points).map_err(|_| DvfError::InvalidCommitment)?;
+            commitments.push(agg.to_public_key());
+        }
+
+        let keypair = Keypair::from_components(sk.public_key(), sk);
+        ThresholdSignature::verify_share(self.id, &keypair.sk, &commitments)?;
+
+        let share_public_keys = self
+            .ids
+            .iter()
+            .map(|id| ThresholdSignature::share_public_key(*id, &commitments))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(DkgOutput {
+            id: self.id,
+            keypair,
+            master_public_key: commitments[0].clone(),
+            commitments,
+            ids: self.ids.clone(),
+            share_public_keys,
+            qualified,
+            reconstructed,
+        })
+    }
+}
+
+/// The coefficients (modulo the curve order, lowest degree first) of the polynomial of degree
+/// `points.len() - 1` through `points`.
+fn interpolate_coefficients(points: &[(u64, BigInt)]) -> Vec<BigInt> {
+    let exponent = &*MODULUS - BigInt::from(2u8);
+    let mut coeffs = vec![BigInt::zero(); points.len()];
+    for (xi, yi) in points {
+        // Expand the Lagrange basis polynomial of `xi`, prod_{j != i} (x - xj) / (xi - xj).
+        let mut basis = vec![BigInt::from(1u8)];
+        let mut den = BigInt::from(1u8);
+        for (xj, _) in points.iter().filter(|(xj, _)| xj != xi) {
+            let mut next = vec![BigInt::zero(); basis.len() + 1];
+            for (k, c) in basis.iter().enumerate() {
+                next[k + 1] += c;
+                next[k] -= c * BigInt::from(*xj);
+            }
+            basis = next.into_iter().map(|c| c.reduce(&MODULUS)).collect();
+            den = (den * (BigInt::from(*xi) - BigInt::from(*xj))).reduce(&MODULUS);
+        }
+        // Fermat inverse, the modulus being prime
+        let scale = (yi * den.modpow(&exponent, &MODULUS)).reduce(&MODULUS);
+        for (coeff, c) in coeffs.iter_mut().zip(basis) {
+            *coeff = (&*coeff + c * &scale).reduce(&MODULUS);
+        }
+    }
+    coeffs
+}
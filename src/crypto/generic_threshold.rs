@@ -1,4 +1,4 @@
-use bls::{Signature, PublicKey, SecretKey, Keypair, SECRET_KEY_BYTES_LEN};
+use bls::{AggregatePublicKey, Signature, PublicKey, SecretKey, Keypair, SECRET_KEY_BYTES_LEN};
 use types::{Hash256}; 
 use crate::utils::error::{DvfError};
 use crate::crypto::define::{MODULUS};
@@ -11,14 +11,41 @@ use crate::utils::rand_utils::Sample;
 use std::collections::HashSet;
 
 
+/// Domain separation tag for hashing the second generator of Pedersen commitments to G1.
+pub const PEDERSEN_DST: &[u8] = b"DVF_PEDERSEN_BLS12381G1_XMD:SHA-256_SSWU_RO_";
+/// Hashed to G1 into the second generator of Pedersen commitments.
+pub const PEDERSEN_SEED: &[u8] = b"dvf pedersen generator";
+
 pub trait TThresholdSignature: Sized + Clone {
     fn infinity(threshold: usize) -> Self;
 
     fn threshold(&self) -> usize;
 
-    fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Signature;
+    /// Interpolate the signature shares `sigs` of operators `ids` at zero, without verifying them.
+    fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Result<Signature, DvfError>;
+
+    /// Returns the indices of the shares in `sigs` that are not valid signatures of `msg` under the
+    /// corresponding `pks`.
+    fn find_invalid_shares(sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> Vec<usize>;
+
+    /// Verify the signature shares against `pks` and interpolate `threshold()` valid ones. Also
+    /// returns the ids of the operators whose share is invalid.
+    fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<(Signature, Vec<u64>), DvfError>;
+
+    /// Evaluate the polynomial committed to by `commitments` (in G1) at `id`, i.e., 
+    /// compute `sum_j commitments[j] * id^j`.
+    fn eval_commitments(commitments: &[&PublicKey], id: u64) -> Result<PublicKey, DvfError>;
 
-    fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> Result<Signature, DvfError>;
+    /// Interpolate the points `(ids[i], pks[i])` at zero in the exponent, i.e., 
+    /// compute `sum_i pks[i] * lambda_i` where `lambda_i` are the Lagrange coefficients of `ids`.
+    fn interpolate_public_keys(pks: &[&PublicKey], ids: &[u64]) -> Result<PublicKey, DvfError>;
+
+    /// Hash `PEDERSEN_SEED` to G1 (with tag `PEDERSEN_DST`), giving a generator whose discrete
+    /// logarithm to the standard generator is unknown.
+    fn pedersen_generator() -> PublicKey;
+
+    /// Multiply the point `pk` (in G1) by the scalar `sk`.
+    fn mul_public_key(pk: &PublicKey, sk: &SecretKey) -> Result<PublicKey, DvfError>;
 }
 
 pub struct GenericThresholdSignature<ThresholdSig> {
@@ -43,99 +70,202 @@ where
         self.point.threshold()
     }
 
-    pub fn key_gen(&mut self, n: usize) -> (Keypair, Vec<Keypair>, Vec<u64>) {
+    pub fn key_gen(&mut self, n: usize) -> Result<(Keypair, Vec<Keypair>, Vec<u64>), DvfError> {
         let kp = Keypair::random();
-        let (kps, ids) = self.key_split(&kp.sk, n);
-        (kp, kps, ids)
+        let (kps, ids, _) = self.key_split(&kp.sk, n)?;
+        Ok((kp, kps, ids))
     }
 
-    pub fn key_split(&mut self, sk: &SecretKey, n: usize) -> (Vec<Keypair>, Vec<u64>) {
+    /// Split `sk` into `n` Shamir shares.
+    ///
+    /// Besides the key shares and their ids, returns the Feldman commitments to the polynomial
+    /// coefficients (in G1), which allow each operator to check its share with `verify_share`.
+    /// The first commitment is the master public key.
+    pub fn key_split(&mut self, sk: &SecretKey, n: usize) -> Result<(Vec<Keypair>, Vec<u64>, Vec<PublicKey>), DvfError> {
+        self.check_threshold(n)?;
         let mut rng = RandUtilsRng::new();
-
-        let mut coeffs: Vec<BigInt> = rng.sample_vec(self.threshold(), &MODULUS);
-        coeffs[0] = BigInt::from_bytes_be(Sign::Plus, &sk.serialize().as_bytes()); 
-        let poly = Polynomial::new(coeffs);
-
-        let mut kps: Vec<Keypair> = Vec::new();
-        let mut ids: Vec<u64> = Vec::new();
-        for i in 0..n {
-            let (_, mut sk_share) = poly.eval(&(i+1).to_bigint().unwrap()).reduce(&MODULUS).to_bytes_be();
-            if sk_share.len() < SECRET_KEY_BYTES_LEN {
-                (0..SECRET_KEY_BYTES_LEN-sk_share.len()).for_each(|_| sk_share.insert(0, 0));
-            }
-            let sk_share = SecretKey::deserialize(&sk_share[..]).unwrap();
-            kps.push(Keypair::from_components(sk_share.public_key(), sk_share));
-            ids.push((i + 1) as u64);
-        }
-        (kps, ids)
+        self.split(&mut rng, sk, n)
     }
 
     /// Split the key in a deterministic way.  
     ///
     /// **NEVER** use this in production! 
-    pub fn deterministic_key_split(&mut self, sk: &SecretKey, n: usize) -> (Vec<Keypair>, Vec<u64>) {
+    pub fn deterministic_key_split(&mut self, sk: &SecretKey, n: usize) -> Result<(Vec<Keypair>, Vec<u64>, Vec<PublicKey>), DvfError> {
+        self.check_threshold(n)?;
         let seed: [u8; 32] = [0; 32];
         let mut rng = RandUtilsRng::from_seed(&seed);
+        self.split(&mut rng, sk, n)
+    }
+
+    /// A key can only be split among `n` operators if `0 < threshold() <= n`.
+    fn check_threshold(&self, n: usize) -> Result<(), DvfError> {
+        if self.threshold() == 0 || self.threshold() > n {
+            return Err(DvfError::InvalidThreshold{threshold: self.threshold(), total: n});
+        }
+        Ok(())
+    }
 
+    fn split(&mut self, rng: &mut RandUtilsRng, sk: &SecretKey, n: usize) -> Result<(Vec<Keypair>, Vec<u64>, Vec<PublicKey>), DvfError> {
         let mut coeffs: Vec<BigInt> = rng.sample_vec(self.threshold(), &MODULUS);
         coeffs[0] = BigInt::from_bytes_be(Sign::Plus, &sk.serialize().as_bytes()); 
+        let commitments: Vec<PublicKey> = coeffs.iter()
+            .map(|c| bigint_to_secret_key(c).map(|sk| sk.public_key()))
+            .collect::<Result<_, _>>()?;
         let poly = Polynomial::new(coeffs);
 
         let mut kps: Vec<Keypair> = Vec::new();
         let mut ids: Vec<u64> = Vec::new();
         for i in 0..n {
-            let (_, mut sk_share) = poly.eval(&(i+1).to_bigint().unwrap()).reduce(&MODULUS).to_bytes_be();
-            if sk_share.len() < SECRET_KEY_BYTES_LEN {
-                (0..SECRET_KEY_BYTES_LEN-sk_share.len()).for_each(|_| sk_share.insert(0, 0));
-            }
-            let sk_share = SecretKey::deserialize(&sk_share[..]).unwrap();
+            let sk_share = bigint_to_secret_key(&poly.eval(&(i+1).to_bigint().unwrap()))?;
             kps.push(Keypair::from_components(sk_share.public_key(), sk_share));
             ids.push((i + 1) as u64);
         }
-        (kps, ids)
+        Ok((kps, ids, commitments))
     }
 
-    pub fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<Signature, DvfError> {
-        if sigs.len() != pks.len() {
-            return Err(DvfError::DifferentLength{x: sigs.len(), y: pks.len()}); 
+    /// Check that the key share of operator `id` is consistent with the Feldman `commitments`
+    /// published with the split.
+    pub fn verify_share(id: u64, share: &SecretKey, commitments: &[PublicKey]) -> Result<(), DvfError> {
+        let expected = Self::share_public_key(id, commitments)?;
+        if share.public_key() != expected {
+            return Err(DvfError::InvalidKeyShare{id});
         }
-        if sigs.len() != ids.len() {
-            return Err(DvfError::DifferentLength{x: sigs.len(), y: ids.len()}); 
-        }
-        if sigs.len() < self.threshold() {
-            return Err(DvfError::InsufficientSignatures{got: sigs.len(), expected: self.threshold()}); 
+        Ok(())
+    }
+
+    /// Returns the public key obtained by interpolating the public key shares `pks` of operators `ids`.
+    ///
+    /// For `threshold()` valid shares, this is the master public key.
+    pub fn interpolate_public_keys(pks: &[&PublicKey], ids: &[u64]) -> Result<PublicKey, DvfError> {
+        if pks.len() != ids.len() {
+            return Err(DvfError::DifferentLength{x: pks.len(), y: ids.len()});
         }
+        check_ids(ids)?;
+        ThresholdSig::interpolate_public_keys(pks, ids)
+    }
 
-        let mut pks_valid: Vec<&PublicKey> = Vec::new();
-        let mut sigs_valid: Vec<&Signature> = Vec::new();
-        let mut ids_valid: Vec<u64> = Vec::new();
-        let mut valid_set: HashSet<u64> = HashSet::new();
+    /// Reconstruct the secret key shared by `shares` of operators `ids` by interpolation at zero.
+    ///
+    /// At least `threshold()` correct shares are needed: the result must be checked against the
+    /// master public key, as fewer shares (or a bad one) silently yield a wrong key.
+    pub fn recover_secret(shares: &[&SecretKey], ids: &[u64]) -> Result<SecretKey, DvfError> {
+        if shares.len() != ids.len() {
+            return Err(DvfError::DifferentLength{x: shares.len(), y: ids.len()});
+        }
+        if shares.is_empty() {
+            return Err(DvfError::InsufficientSignatures{got: 0, expected: 1});
+        }
+        check_ids(ids)?;
+        let lambdas = lagrange_coefficients_at_zero(ids);
+        let sum = shares.iter()
+            .zip(lambdas.iter())
+            .fold(BigInt::from(0u8), |acc, (share, lambda)| acc + secret_key_to_bigint(share) * lambda);
+        bigint_to_secret_key(&sum)
+    }
 
-        let total = sigs.len();
-        for i in 0..total {
-            if valid_set.contains(&ids[i]) {
-                continue;
-            }
-            let status = sigs[i].verify(pks[i], msg);
-            if status {
-                pks_valid.push(pks[i]);
-                sigs_valid.push(sigs[i]);
-                ids_valid.push(ids[i]);
-                valid_set.insert(ids[i]);
-                if pks_valid.len() >= self.threshold() {
-                    break
-                }
-            }
+    /// Returns the public key that the share of operator `id` must have according to `commitments`.
+    pub fn share_public_key(id: u64, commitments: &[PublicKey]) -> Result<PublicKey, DvfError> {
+        if id == 0 {
+            return Err(DvfError::InvalidKeyShare{id});
         }
-        if pks_valid.len() < self.threshold() {
-            return Err(DvfError::InsufficientSignatures{got: pks_valid.len(), expected: self.threshold()}); 
+        let commitments: Vec<&PublicKey> = commitments.iter().collect();
+        ThresholdSig::eval_commitments(&commitments[..], id)
+    }
+
+    /// Returns the Pedersen commitment `value * g + blinding * h` (in G1), where `h` is the
+    /// generator hashed from `PEDERSEN_SEED`.
+    pub fn pedersen_commit(value: &SecretKey, blinding: &SecretKey) -> Result<PublicKey, DvfError> {
+        let blinded = ThresholdSig::mul_public_key(&ThresholdSig::pedersen_generator(), blinding)?;
+        AggregatePublicKey::aggregate(&[value.public_key(), blinded])
+            .map(|agg| agg.to_public_key())
+            .map_err(|_| DvfError::InvalidCommitment)
+    }
+
+    /// Check that the share of operator `id` and its `blinding` are consistent with the Pedersen
+    /// `commitments` to the pair of polynomials they were evaluated from.
+    pub fn verify_pedersen_share(id: u64, share: &SecretKey, blinding: &SecretKey, commitments: &[PublicKey]) -> Result<(), DvfError> {
+        let expected = Self::share_public_key(id, commitments)?;
+        if Self::pedersen_commit(share, blinding)? != expected {
+            return Err(DvfError::InvalidKeyShare{id});
         }
+        Ok(())
+    }
+
+    pub fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<Signature, DvfError> {
+        self.threshold_aggregate_with_invalid_ids(sigs, pks, ids, msg).map(|(sig, _)| sig)
+    }
+
+    /// Same as `threshold_aggregate`, but also returns the ids of the operators whose share is
+    /// invalid. The shares are verified once, all at once in the common case where they are valid.
+    ///
+    /// If too few shares are valid, the `InsufficientValidSignatures` error lists the invalid ones.
+    pub fn threshold_aggregate_with_invalid_ids(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<(Signature, Vec<u64>), DvfError> {
+        self.point.threshold_aggregate(sigs, pks, ids, msg)
+    }
 
-        Ok(self.unsafe_aggregate(&sigs_valid, &ids_valid[..]))
+    /// Returns the ids of the operators whose signature share of `msg` is invalid.
+    ///
+    /// All shares are checked at once in the common case where they are all valid.
+    pub fn invalid_share_ids(sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Vec<u64> {
+        ThresholdSig::find_invalid_shares(sigs, pks, msg)
+            .into_iter()
+            .filter_map(|i| ids.get(i).copied())
+            .collect()
     }
 
-    pub fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Signature {
+    pub fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Result<Signature, DvfError> {
         self.point.unsafe_aggregate(sigs, ids)
     }
 }
 
+/// Encode a scalar (reduced modulo the curve order) as a BLS secret key.
+///
+/// Fails if the scalar is zero modulo the curve order, which is not a valid secret key.
+pub fn bigint_to_secret_key(x: &BigInt) -> Result<SecretKey, DvfError> {
+    let (_, mut bytes) = x.reduce(&MODULUS).to_bytes_be();
+    if bytes.len() < SECRET_KEY_BYTES_LEN {
+        (0..SECRET_KEY_BYTES_LEN-bytes.len()).for_each(|_| bytes.insert(0, 0));
+    }
+    SecretKey::deserialize(&bytes[..]).map_err(|_| DvfError::ZeroSecretKey)
+}
+
+/// Decode a BLS secret key as a scalar.
+pub fn secret_key_to_bigint(sk: &SecretKey) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, sk.serialize().as_bytes())
+}
+
+/// Check that operator ids are non-zero and distinct, as required for Lagrange interpolation.
+pub fn check_ids(ids: &[u64]) -> Result<(), DvfError> {
+    let distinct: HashSet<u64> = ids.iter().copied().collect();
+    if distinct.len() != ids.len() || distinct.contains(&0) {
+        return Err(DvfError::InvalidOperatorIds);
+    }
+    Ok(())
+}
+
+/// Lagrange coefficients (modulo the curve order) for interpolating at zero from the points `ids`.
+///
+/// Assumes `ids` passed `check_ids`.
+pub fn lagrange_coefficients_at_zero(ids: &[u64]) -> Vec<BigInt> {
+    lagrange_coefficients_at(ids, 0)
+}
+
+/// Lagrange coefficients (modulo the curve order) for interpolating at `x` from the points `ids`.
+///
+/// Assumes `ids` passed `check_ids`.
+pub fn lagrange_coefficients_at(ids: &[u64], x: u64) -> Vec<BigInt> {
+    let exponent = &*MODULUS - BigInt::from(2u8);
+    let x = BigInt::from(x);
+    ids.iter()
+        .map(|xi| {
+            let mut num = BigInt::from(1u8);
+            let mut den = BigInt::from(1u8);
+            for xj in ids.iter().filter(|xj| *xj != xi) {
+                num = (num * (&x - BigInt::from(*xj))).reduce(&MODULUS);
+                den = (den * (BigInt::from(*xi) - BigInt::from(*xj))).reduce(&MODULUS);
+            }
+            // Fermat inverse, the modulus being prime
+            (num * den.modpow(&exponent, &MODULUS)).reduce(&MODULUS)
+        })
+        .collect()
+}
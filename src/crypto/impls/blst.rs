@@ -1,11 +1,12 @@
-use bls::{Signature, PublicKey, Hash256};
+use bls::{Signature, PublicKey, SecretKey, Hash256};
 use crate::{
-    crypto::generic_threshold::{TThresholdSignature},
+    crypto::generic_threshold::{TThresholdSignature, check_ids, PEDERSEN_DST, PEDERSEN_SEED},
 };
 use bls::{INFINITY_SIGNATURE};
+use rand::Rng;
 use crate::utils::error::{require, DvfError};
 pub use blst::min_pk as blst_core;
-use blst::{blst_scalar, blst_p2, blst_p2_affine};
+use blst::{blst_scalar, blst_p1, blst_p1_affine, blst_p2, blst_p2_affine, BLST_ERROR};
 
 pub const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
 pub const RAND_BITS: usize = 64;
@@ -46,6 +47,83 @@ fn lagrange_coeffs(coeffs: &mut [blst_scalar], x: &[u64]) {
     }
 }
 
+fn g1_from_public_key(pk: &PublicKey) -> Result<blst_p1, DvfError> {
+    let mut affine: blst_p1_affine = Default::default();
+    let mut p: blst_p1 = Default::default();
+    unsafe {
+        if blst::blst_p1_uncompress(&mut affine, pk.serialize().as_ptr()) != BLST_ERROR::BLST_SUCCESS {
+            return Err(DvfError::InvalidCommitment);
+        }
+        blst::blst_p1_from_affine(&mut p, &affine);
+    }
+    Ok(p)
+}
+
+fn g1_to_public_key(p: &blst_p1) -> Result<PublicKey, DvfError> {
+    let mut bytes: [u8; 48] = [0; 48];
+    unsafe {
+        blst::blst_p1_compress(bytes.as_mut_ptr(), p);
+    }
+    PublicKey::deserialize(&bytes).map_err(|_| DvfError::InvalidCommitment)
+}
+
+fn g2_from_signature(sig: &Signature) -> Option<blst_p2> {
+    let mut affine: blst_p2_affine = Default::default();
+    let mut p: blst_p2 = Default::default();
+    unsafe {
+        if blst::blst_p2_uncompress(&mut affine, sig.serialize().as_ptr()) != BLST_ERROR::BLST_SUCCESS {
+            return None;
+        }
+        blst::blst_p2_from_affine(&mut p, &affine);
+    }
+    Some(p)
+}
+
+/// Verify the shares `indices` of `sigs` on the same `msg` with a single multi-pairing, using a
+/// random linear combination so that invalid shares cannot cancel each other out.
+fn batch_verify(sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256, indices: &[usize]) -> bool {
+    let mut rng = rand::thread_rng();
+    let mut blst_sigs = Vec::with_capacity(indices.len());
+    let mut blst_pks = Vec::with_capacity(indices.len());
+    for i in indices {
+        match (
+            blst_core::Signature::from_bytes(&sigs[*i].serialize()),
+            blst_core::PublicKey::from_bytes(&pks[*i].serialize()),
+        ) {
+            (Ok(sig), Ok(pk)) => {
+                blst_sigs.push(sig);
+                blst_pks.push(pk);
+            }
+            _ => return false,
+        }
+    }
+    let rands: Vec<blst_scalar> = indices
+        .iter()
+        .map(|_| u64_to_blst_scalar(rng.gen_range(1..u64::MAX)))
+        .collect();
+    let msgs: Vec<&[u8]> = indices.iter().map(|_| msg.as_bytes()).collect();
+    let sig_refs: Vec<&blst_core::Signature> = blst_sigs.iter().collect();
+    let pk_refs: Vec<&blst_core::PublicKey> = blst_pks.iter().collect();
+
+    blst_core::Signature::verify_multiple_aggregate_signatures(
+        &msgs, DST, &pk_refs, false, &sig_refs, true, &rands, RAND_BITS,
+    ) == BLST_ERROR::BLST_SUCCESS
+}
+
+/// Collect the invalid shares among `indices`, splitting the batch in halves only when it fails.
+fn bisect_invalid(sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256, indices: &[usize], invalid: &mut Vec<usize>) {
+    if indices.is_empty() || batch_verify(sigs, pks, msg, indices) {
+        return;
+    }
+    if indices.len() == 1 {
+        invalid.push(indices[0]);
+        return;
+    }
+    let (left, right) = indices.split_at(indices.len() / 2);
+    bisect_invalid(sigs, pks, msg, left, invalid);
+    bisect_invalid(sigs, pks, msg, right, invalid);
+}
+
 pub struct BlstThresholdSignature(blst_core::AggregateSignature, usize);
 
 impl Clone for BlstThresholdSignature {
@@ -72,29 +150,131 @@ impl TThresholdSignature for BlstThresholdSignature {
         self.1
     }
 
-    fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Signature {
-        let mut coeffs = vec![blst_scalar::default(); self.threshold()];
-        lagrange_coeffs(&mut coeffs, &ids);
+    fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Result<Signature, DvfError> {
+        if sigs.len() != ids.len() {
+            return Err(DvfError::DifferentLength{x: sigs.len(), y: ids.len()});
+        }
+        if sigs.is_empty() {
+            return Err(DvfError::InsufficientSignatures{got: 0, expected: self.threshold()});
+        }
+        check_ids(ids)?;
+
+        let mut coeffs = vec![blst_scalar::default(); ids.len()];
+        lagrange_coeffs(&mut coeffs, ids);
 
-        let mut agg = Self::infinity(self.1);
-        for i in 0..self.threshold() {
-            let mut sig_in: [u8; 192] = [0; 192];
+        let mut acc: blst_p2 = Default::default();
+        for i in 0..sigs.len() {
+            let mut d = g2_from_signature(sigs[i]).ok_or(DvfError::InvalidSignatureShare{id: ids[i]})?;
             unsafe {
-                let mut d: blst_p2 = Default::default(); 
-                let mut tmp: blst_p2_affine = Default::default();
-                blst::blst_p2_deserialize(&mut tmp, sigs[i].serialize().as_ptr());
-                blst::blst_p2_from_affine(&mut d, &tmp);
                 blst::blst_p2_mult(&mut d, &d, coeffs[i].b.as_ptr(), 255);
+                if i == 0 {
+                    acc = d;
+                } else {
+                    blst::blst_p2_add_or_double(&mut acc, &acc, &d);
+                }
+            }
+        }
+
+        let mut sig_out: [u8; 96] = [0; 96];
+        unsafe {
+            blst::blst_p2_compress(sig_out.as_mut_ptr(), &acc);
+        }
+        Signature::deserialize(&sig_out).map_err(|_| DvfError::InvalidLength)
+    }
+
+    fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<(Signature, Vec<u64>), DvfError> {
+        if sigs.len() != pks.len() {
+            return Err(DvfError::DifferentLength{x: sigs.len(), y: pks.len()}); 
+        }
+        if sigs.len() != ids.len() {
+            return Err(DvfError::DifferentLength{x: sigs.len(), y: ids.len()}); 
+        }
+        check_ids(ids)?;
+        if sigs.len() < self.threshold() {
+            return Err(DvfError::InsufficientSignatures{got: sigs.len(), expected: self.threshold()}); 
+        }
+
+        // Invalid shares are skipped: any `threshold()` valid ones interpolate to the same signature.
+        let invalid = Self::find_invalid_shares(sigs, pks, msg);
+        let invalid_ids: Vec<u64> = invalid.iter().map(|i| ids[*i]).collect();
+        let valid: Vec<usize> = (0..sigs.len()).filter(|i| !invalid.contains(i)).collect();
+        if valid.len() < self.threshold() {
+            return Err(DvfError::InsufficientValidSignatures{got: valid.len(), expected: self.threshold(), invalid: invalid_ids});
+        }
+        let sigs_valid: Vec<&Signature> = valid.iter().take(self.threshold()).map(|i| sigs[*i]).collect();
+        let ids_valid: Vec<u64> = valid.iter().take(self.threshold()).map(|i| ids[*i]).collect();
+
+        let sig = self.unsafe_aggregate(&sigs_valid, &ids_valid)?;
+        Ok((sig, invalid_ids))
+    }
+
+    fn find_invalid_shares(sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> Vec<usize> {
+        let indices: Vec<usize> = (0..sigs.len().min(pks.len())).collect();
+        let mut invalid = Vec::new();
+        bisect_invalid(sigs, pks, msg, &indices, &mut invalid);
+        invalid
+    }
+
+    fn eval_commitments(commitments: &[&PublicKey], id: u64) -> Result<PublicKey, DvfError> {
+        let (last, rest) = commitments.split_last().ok_or(DvfError::InvalidLength)?;
+        let x = u64_to_blst_scalar(id);
+        // Horner's rule in G1
+        let mut acc = g1_from_public_key(last)?;
+        for c in rest.iter().rev() {
+            let c = g1_from_public_key(c)?;
+            unsafe {
+                blst::blst_p1_mult(&mut acc, &acc, x.b.as_ptr(), 64);
+                blst::blst_p1_add_or_double(&mut acc, &acc, &c);
+            }
+        }
+        g1_to_public_key(&acc)
+    }
+
+    fn interpolate_public_keys(pks: &[&PublicKey], ids: &[u64]) -> Result<PublicKey, DvfError> {
+        if pks.is_empty() || pks.len() != ids.len() {
+            return Err(DvfError::InvalidLength);
+        }
+        let mut coeffs = vec![blst_scalar::default(); ids.len()];
+        lagrange_coeffs(&mut coeffs, ids);
 
-                blst::blst_p2_serialize(sig_in.as_mut_ptr(), &d);
+        let mut acc: blst_p1 = Default::default();
+        for i in 0..pks.len() {
+            let mut p = g1_from_public_key(pks[i])?;
+            unsafe {
+                blst::blst_p1_mult(&mut p, &p, coeffs[i].b.as_ptr(), 255);
+                if i == 0 {
+                    acc = p;
+                } else {
+                    blst::blst_p1_add_or_double(&mut acc, &acc, &p);
+                }
             }
-            let sig = blst_core::Signature::deserialize(&sig_in);
-            agg.0.add_signature(&sig.unwrap(), false);
         }
-        Signature::deserialize(&agg.0.to_signature().to_bytes()).unwrap()
+        g1_to_public_key(&acc)
     }
 
-    fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> Result<Signature, DvfError> {
-        Ok(self.unsafe_aggregate(sigs, &[0, 2]))
+    fn pedersen_generator() -> PublicKey {
+        let mut p: blst_p1 = Default::default();
+        unsafe {
+            blst::blst_hash_to_g1(
+                &mut p,
+                PEDERSEN_SEED.as_ptr(),
+                PEDERSEN_SEED.len(),
+                PEDERSEN_DST.as_ptr(),
+                PEDERSEN_DST.len(),
+                std::ptr::null(),
+                0,
+            );
+        }
+        g1_to_public_key(&p).expect("should encode the pedersen generator")
+    }
+
+    fn mul_public_key(pk: &PublicKey, sk: &SecretKey) -> Result<PublicKey, DvfError> {
+        let mut p = g1_from_public_key(pk)?;
+        let mut scalar = blst_scalar::default();
+        unsafe {
+            blst::blst_scalar_from_bendian(&mut scalar, sk.serialize().as_bytes().as_ptr());
+            blst::blst_p1_mult(&mut p, &p, scalar.b.as_ptr(), 255);
+        }
+        g1_to_public_key(&p)
     }
 }
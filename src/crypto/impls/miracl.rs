@@ -0,0 +1,276 @@
+//! Threshold signature backend built on miracl_core, independent from blst.
+//!
+//! Points are exchanged with the rest of the code base in the compressed (ZCash) encoding used by
+//! lighthouse's `bls` types, and converted to and from miracl's own representation here.
+
+use bls::{Signature, PublicKey, SecretKey, Hash256};
+use crate::crypto::generic_threshold::{
+    TThresholdSignature, check_ids, lagrange_coefficients_at_zero, secret_key_to_bigint, PEDERSEN_DST, PEDERSEN_SEED,
+};
+use crate::crypto::define::{G1, G2, MB, MODULUS};
+use crate::math::bigint_ext::{Ring, ToBIG};
+use crate::utils::error::DvfError;
+use miracl_core::bls12381::{big::BIG, dbig::DBIG, ecp, fp::FP, fp2::FP2, pair, rom};
+use miracl_core::hmac;
+use num_bigint::BigInt;
+
+pub const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Provides the externally-facing, core BLS types.
+pub mod types {
+    pub use super::MiraclThresholdSignature as ThresholdSignature;
+}
+
+const COMPRESSED_FLAG: u8 = 0x80;
+const INFINITY_FLAG: u8 = 0x40;
+const SIGN_FLAG: u8 = 0x20;
+
+fn is_encoded_infinity(bytes: &[u8]) -> bool {
+    bytes[0] == COMPRESSED_FLAG | INFINITY_FLAG && bytes[1..].iter().all(|b| *b == 0)
+}
+
+fn g1_from_bytes(bytes: &[u8]) -> Option<G1> {
+    if bytes.len() != MB || bytes[0] & COMPRESSED_FLAG == 0 {
+        return None;
+    }
+    if bytes[0] & INFINITY_FLAG != 0 {
+        return if is_encoded_infinity(bytes) { Some(G1::new()) } else { None };
+    }
+    let mut t = [0u8; MB];
+    t.copy_from_slice(bytes);
+    t[0] &= 0x1f;
+    let x = BIG::frombytes(&t);
+    if BIG::comp(&x, &BIG::new_ints(&rom::MODULUS)) >= 0 {
+        return None;
+    }
+    let mut p = G1::new_bigint(&x, 0);
+    if p.is_infinity() {
+        return None;
+    }
+    if (p.getpy().islarger() == 1) != (bytes[0] & SIGN_FLAG != 0) {
+        p.neg();
+    }
+    if !pair::g1member(&p) {
+        return None;
+    }
+    Some(p)
+}
+
+fn g1_to_bytes(p: &G1) -> [u8; MB] {
+    let mut bytes = [0u8; MB];
+    if p.is_infinity() {
+        bytes[0] = COMPRESSED_FLAG | INFINITY_FLAG;
+        return bytes;
+    }
+    let mut affine = G1::new();
+    affine.copy(p);
+    affine.affine();
+    affine.getpx().redc().tobytes(&mut bytes);
+    bytes[0] |= COMPRESSED_FLAG;
+    if affine.getpy().islarger() == 1 {
+        bytes[0] |= SIGN_FLAG;
+    }
+    bytes
+}
+
+fn g2_from_bytes(bytes: &[u8]) -> Option<G2> {
+    if bytes.len() != 2 * MB || bytes[0] & COMPRESSED_FLAG == 0 {
+        return None;
+    }
+    if bytes[0] & INFINITY_FLAG != 0 {
+        return if is_encoded_infinity(bytes) { Some(G2::new()) } else { None };
+    }
+    let mut t = [0u8; 2 * MB];
+    t.copy_from_slice(bytes);
+    t[0] &= 0x1f;
+    let mut p = G2::new_fp2(&FP2::frombytes(&t), 0);
+    if p.is_infinity() {
+        return None;
+    }
+    if (p.getpy().islarger() == 1) != (bytes[0] & SIGN_FLAG != 0) {
+        p.neg();
+    }
+    if !pair::g2member(&p) {
+        return None;
+    }
+    Some(p)
+}
+
+fn g2_to_bytes(p: &G2) -> [u8; 2 * MB] {
+    let mut bytes = [0u8; 2 * MB];
+    if p.is_infinity() {
+        bytes[0] = COMPRESSED_FLAG | INFINITY_FLAG;
+        return bytes;
+    }
+    let mut affine = G2::new();
+    affine.copy(p);
+    affine.affine();
+    affine.getpx().tobytes(&mut bytes);
+    bytes[0] |= COMPRESSED_FLAG;
+    if affine.getpy().islarger() == 1 {
+        bytes[0] |= SIGN_FLAG;
+    }
+    bytes
+}
+
+fn g1_from_public_key(pk: &PublicKey) -> Result<G1, DvfError> {
+    g1_from_bytes(&pk.serialize()).ok_or(DvfError::InvalidCommitment)
+}
+
+fn g1_to_public_key(p: &G1) -> Result<PublicKey, DvfError> {
+    PublicKey::deserialize(&g1_to_bytes(p)).map_err(|_| DvfError::InvalidCommitment)
+}
+
+/// Hash `msg` to G2 as in the hash-to-curve draft (expand_message_xmd with SHA-256, simplified
+/// SWU map on an isogenous curve), with the proof-of-possession ciphersuite tag.
+fn hash_to_g2(msg: &[u8]) -> G2 {
+    // Two field elements of F_p^2, each made of two 64-byte chunks
+    const L: usize = 64;
+    let q = BIG::new_ints(&rom::MODULUS);
+    let mut okm = [0u8; 4 * L];
+    hmac::xmd_expand(hmac::MC_SHA2, ecp::HASH_TYPE, &mut okm, 4 * L, DST, msg);
+
+    let u: Vec<FP> = okm
+        .chunks(L)
+        .map(|chunk| FP::new_big(&DBIG::frombytes(chunk).ctdmod(&q, 8 * L - q.nbits())))
+        .collect();
+    let mut p = G2::map2point(&FP2::new_fps(&u[0], &u[1]));
+    p.add(&G2::map2point(&FP2::new_fps(&u[2], &u[3])));
+    p.cfp();
+    p.affine();
+    p
+}
+
+/// Hash `msg` to G1 as in the hash-to-curve draft (expand_message_xmd with SHA-256, simplified
+/// SWU map on an isogenous curve), with tag `dst`.
+fn hash_to_g1(msg: &[u8], dst: &[u8]) -> G1 {
+    // Two field elements, each made of one 64-byte chunk
+    const L: usize = 64;
+    let q = BIG::new_ints(&rom::MODULUS);
+    let mut okm = [0u8; 2 * L];
+    hmac::xmd_expand(hmac::MC_SHA2, ecp::HASH_TYPE, &mut okm, 2 * L, dst, msg);
+
+    let u: Vec<FP> = okm
+        .chunks(L)
+        .map(|chunk| FP::new_big(&DBIG::frombytes(chunk).ctdmod(&q, 8 * L - q.nbits())))
+        .collect();
+    let mut p = G1::map2point(&u[0]);
+    p.add(&G1::map2point(&u[1]));
+    p.cfp();
+    p.affine();
+    p
+}
+
+fn verify(sig: &Signature, pk: &PublicKey, msg: Hash256) -> bool {
+    let (sig, pk) = match (g2_from_bytes(&sig.serialize()), g1_from_bytes(&pk.serialize())) {
+        (Some(sig), Some(pk)) => (sig, pk),
+        _ => return false,
+    };
+    if pk.is_infinity() {
+        return false;
+    }
+    // e(pk, H(m)) * e(-g1, sig) == 1
+    let mut g = G1::generator();
+    g.neg();
+    let hm = hash_to_g2(msg.as_bytes());
+    pair::fexp(&pair::ate2(&hm, &pk, &sig, &g)).isunity()
+}
+
+#[derive(Clone)]
+pub struct MiraclThresholdSignature(usize);
+
+impl TThresholdSignature for MiraclThresholdSignature {
+    fn infinity(threshold: usize) -> Self {
+        Self(threshold)
+    }
+
+    fn threshold(&self) -> usize {
+        self.0
+    }
+
+    fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Result<Signature, DvfError> {
+        if sigs.len() != ids.len() {
+            return Err(DvfError::DifferentLength{x: sigs.len(), y: ids.len()});
+        }
+        if sigs.is_empty() {
+            return Err(DvfError::InsufficientSignatures{got: 0, expected: self.threshold()});
+        }
+        check_ids(ids)?;
+
+        let coeffs = lagrange_coefficients_at_zero(ids);
+        let mut acc = G2::new();
+        for i in 0..sigs.len() {
+            let d = g2_from_bytes(&sigs[i].serialize()).ok_or(DvfError::InvalidSignatureShare{id: ids[i]})?;
+            acc.add(&pair::g2mul(&d, &coeffs[i].to_BIG()));
+        }
+        Signature::deserialize(&g2_to_bytes(&acc)).map_err(|_| DvfError::InvalidLength)
+    }
+
+    fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<(Signature, Vec<u64>), DvfError> {
+        if sigs.len() != pks.len() {
+            return Err(DvfError::DifferentLength{x: sigs.len(), y: pks.len()});
+        }
+        if sigs.len() != ids.len() {
+            return Err(DvfError::DifferentLength{x: sigs.len(), y: ids.len()});
+        }
+        check_ids(ids)?;
+        if sigs.len() < self.threshold() {
+            return Err(DvfError::InsufficientSignatures{got: sigs.len(), expected: self.threshold()});
+        }
+
+        // Same selection as the blst backend, so that both produce the same signature.
+        let invalid = Self::find_invalid_shares(sigs, pks, msg);
+        let invalid_ids: Vec<u64> = invalid.iter().map(|i| ids[*i]).collect();
+        let valid: Vec<usize> = (0..sigs.len()).filter(|i| !invalid.contains(i)).collect();
+        if valid.len() < self.threshold() {
+            return Err(DvfError::InsufficientValidSignatures{got: valid.len(), expected: self.threshold(), invalid: invalid_ids});
+        }
+        let sigs_valid: Vec<&Signature> = valid.iter().take(self.threshold()).map(|i| sigs[*i]).collect();
+        let ids_valid: Vec<u64> = valid.iter().take(self.threshold()).map(|i| ids[*i]).collect();
+
+        let sig = self.unsafe_aggregate(&sigs_valid, &ids_valid)?;
+        Ok((sig, invalid_ids))
+    }
+
+    fn find_invalid_shares(sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> Vec<usize> {
+        (0..sigs.len().min(pks.len()))
+            .filter(|i| !verify(sigs[*i], pks[*i], msg))
+            .collect()
+    }
+
+    fn eval_commitments(commitments: &[&PublicKey], id: u64) -> Result<PublicKey, DvfError> {
+        if commitments.is_empty() {
+            return Err(DvfError::InvalidLength);
+        }
+        let x = BigInt::from(id);
+        let mut power = BigInt::from(1u8);
+        let mut acc = G1::new();
+        for c in commitments {
+            let c = g1_from_public_key(c)?;
+            acc.add(&pair::g1mul(&c, &power.to_BIG()));
+            power = (power * &x).reduce(&MODULUS);
+        }
+        g1_to_public_key(&acc)
+    }
+
+    fn interpolate_public_keys(pks: &[&PublicKey], ids: &[u64]) -> Result<PublicKey, DvfError> {
+        if pks.is_empty() || pks.len() != ids.len() {
+            return Err(DvfError::InvalidLength);
+        }
+        let coeffs = lagrange_coefficients_at_zero(ids);
+        let mut acc = G1::new();
+        for (pk, coeff) in pks.iter().zip(coeffs.iter()) {
+            acc.add(&pair::g1mul(&g1_from_public_key(pk)?, &coeff.to_BIG()));
+        }
+        g1_to_public_key(&acc)
+    }
+
+    fn pedersen_generator() -> PublicKey {
+        g1_to_public_key(&hash_to_g1(PEDERSEN_SEED, PEDERSEN_DST)).expect("should encode the pedersen generator")
+    }
+
+    fn mul_public_key(pk: &PublicKey, sk: &SecretKey) -> Result<PublicKey, DvfError> {
+        let p = g1_from_public_key(pk)?;
+        g1_to_public_key(&pair::g1mul(&p, &secret_key_to_bigint(sk).to_BIG()))
+    }
+}
@@ -1 +1,3 @@
 pub mod blst;
+#[cfg(feature = "miracl_threshold")]
+pub mod miracl;
@@ -2,6 +2,9 @@
 pub mod define;
 //pub mod threshold;
 pub mod generic_threshold;
+pub mod dkg;
+pub mod reshare;
+pub mod repair;
 pub mod impls;
 
 macro_rules! define_mod {
@@ -19,4 +22,10 @@ macro_rules! define_mod {
 }
 
 define_mod!(blst_threshold_implementations, crate::crypto::impls::blst::types);
+#[cfg(feature = "miracl_threshold")]
+define_mod!(miracl_threshold_implementations, crate::crypto::impls::miracl::types);
+
+#[cfg(not(feature = "miracl_threshold"))]
 pub use blst_threshold_implementations::*;
+#[cfg(feature = "miracl_threshold")]
+pub use miracl_threshold_implementations::*;
@@ -0,0 +1,194 @@
+//! Regenerates the key share of an operator (e.g., after a disk loss, or for a replacement
+//! operator taking over its id) with the help of `t` operators of the committee, following the
+//! enrollment protocol of Laing and Stinson.
+//!
+//! Helper `j` multiplies its share by its Lagrange coefficient `lambda_j(i)` at the id `i` being
+//! repaired, splits the result into random additive parts and privately sends one part to every
+//! helper. Each helper sums the parts it received and sends the sum to the operator being
+//! repaired, who adds them up to get `sum_j lambda_j(i) * s_j = s_i`. Every value a helper sees
+//! is masked by parts it does not know, so no share other than `s_i` is revealed.
+//!
+//! The repaired share is checked against the Feldman commitments of the committee, hence a wrong
+//! contribution is detected (but not attributed).
+
+use bls::{Keypair, PublicKey, SecretKey};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use std::collections::HashMap;
+use crate::crypto::ThresholdSignature;
+use crate::crypto::define::MODULUS;
+use crate::crypto::generic_threshold::{
+    bigint_to_secret_key, check_ids, lagrange_coefficients_at, secret_key_to_bigint,
+};
+use crate::math::bigint_ext::Ring;
+use crate::utils::error::DvfError;
+use crate::utils::rand_utils::{RandUtilsRng, Sample};
+use crate::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use eth2_keystore::KeystoreBuilder;
+
+/// Messages exchanged during a share repair. All of them are sent privately.
+#[derive(Clone)]
+pub enum RepairMessage {
+    /// Additive part of `from`'s masked contribution, for helper `to`.
+    Part { from: u64, to: u64, part: SecretKey },
+    /// Sum of the parts received by `helper`, for the operator being repaired.
+    Sum { helper: u64, sum: SecretKey },
+}
+
+/// An operator helping to regenerate the share of operator `target`.
+pub struct RepairHelper {
+    id: u64,
+    share: SecretKey,
+    helpers: Vec<u64>,
+    target: u64,
+    parts: HashMap<u64, SecretKey>,
+}
+
+impl RepairHelper {
+    /// `helpers` are the ids of the `t` operators taking part in the repair, including `id`.
+    pub fn new(id: u64, share: &SecretKey, helpers: Vec<u64>, target: u64) -> Result<Self, DvfError> {
+        check_ids(&helpers)?;
+        if !helpers.contains(&id) || helpers.contains(&target) || target == 0 {
+            return Err(DvfError::InvalidOperatorIds);
+        }
+        Ok(Self {
+            id,
+            share: share.clone(),
+            helpers,
+            target,
+            parts: HashMap::new(),
+        })
+    }
+
+    /// Round 1: one part of our masked contribution per helper (ourselves included).
+    pub fn deal(&self) -> Result<Vec<RepairMessage>, DvfError> {
+        let index = self.helpers.iter().position(|h| *h == self.id).unwrap();
+        let lambda = &lagrange_coefficients_at(&self.helpers, self.target)[index];
+        let contribution = (secret_key_to_bigint(&self.share) * lambda).reduce(&MODULUS);
+
+        let mut rng = RandUtilsRng::new();
+        let mut parts: Vec<BigInt> = rng.sample_vec(self.helpers.len() - 1, &MODULUS);
+        let masked: BigInt = parts.iter().fold(BigInt::zero(), |acc, p| acc + p);
+        parts.push((contribution - masked).reduce(&MODULUS));
+
+        self.helpers
+            .iter()
+            .zip(parts.iter())
+            .map(|(to, part)| {
+                Ok(RepairMessage::Part {
+                    from: self.id,
+                    to: *to,
+                    part: bigint_to_secret_key(part)?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn handle(&mut self, msg: RepairMessage) {
+        if let RepairMessage::Part { from, to, part } = msg {
+            if to == self.id && self.helpers.contains(&from) {
+                self.parts.entry(from).or_insert(part);
+            }
+        }
+    }
+
+    /// Round 2: the sum of the parts received from all helpers, for the operator being repaired.
+    pub fn finalize(&self) -> Result<RepairMessage, DvfError> {
+        if let Some(missing) = self.helpers.iter().find(|h| !self.parts.contains_key(h)) {
+            return Err(DvfError::InvalidKeyShare { id: *missing });
+        }
+        let sum = self.parts
+            .values()
+            .fold(BigInt::zero(), |acc, part| acc + secret_key_to_bigint(part));
+        Ok(RepairMessage::Sum { helper: self.id, sum: bigint_to_secret_key(&sum)? })
+    }
+}
+
+/// Result of a share repair.
+pub struct RepairOutput {
+    pub id: u64,
+    pub keypair: Keypair,
+    pub master_public_key: PublicKey,
+    pub commitments: Vec<PublicKey>,
+}
+
+impl RepairOutput {
+    /// Encrypt the repaired key share with `password` into a `KeystoreShare` for validator `master_id`.
+    pub fn keystore_share(&self, password: &[u8], master_id: u64) -> Result<KeystoreShare, eth2_keystore::Error> {
+        let keystore = KeystoreBuilder::new(&self.keypair, password, "".into())?.build()?;
+        Ok(KeystoreShare::new(
+            keystore,
+            self.master_public_key.clone(),
+            master_id,
+            self.id,
+            self.commitments.clone(),
+        ))
+    }
+}
+
+/// The operator whose share is being regenerated.
+pub struct RepairReceiver {
+    id: u64,
+    helpers: Vec<u64>,
+    master_public_key: PublicKey,
+    commitments: Vec<PublicKey>,
+    sums: HashMap<u64, SecretKey>,
+}
+
+impl RepairReceiver {
+    /// `commitments` are the Feldman commitments of the committee, as found in the keystore share
+    /// of any of its operators. The first one is `master_public_key`.
+    pub fn new(id: u64, helpers: Vec<u64>, commitments: Vec<PublicKey>) -> Result<Self, DvfError> {
+        check_ids(&helpers)?;
+        if helpers.contains(&id) || id == 0 {
+            return Err(DvfError::InvalidOperatorIds);
+        }
+        if commitments.is_empty() {
+            return Err(DvfError::InvalidCommitment);
+        }
+        if helpers.len() < commitments.len() {
+            return Err(DvfError::InsufficientDealers { got: helpers.len(), expected: commitments.len() });
+        }
+        Ok(Self {
+            id,
+            helpers,
+            master_public_key: commitments[0].clone(),
+            commitments,
+            sums: HashMap::new(),
+        })
+    }
+
+    /// Prepare the repair of `share_id` from the public part of another operator's keystore share.
+    pub fn from_keystore_share(share_id: u64, helpers: Vec<u64>, peer: &KeystoreShare) -> Result<Self, DvfError> {
+        if peer.commitments.first() != Some(&peer.master_public_key) {
+            return Err(DvfError::InvalidCommitment);
+        }
+        Self::new(share_id, helpers, peer.commitments.clone())
+    }
+
+    pub fn handle(&mut self, msg: RepairMessage) {
+        if let RepairMessage::Sum { helper, sum } = msg {
+            if self.helpers.contains(&helper) {
+                self.sums.entry(helper).or_insert(sum);
+            }
+        }
+    }
+
+    pub fn finalize(&self) -> Result<RepairOutput, DvfError> {
+        if let Some(missing) = self.helpers.iter().find(|h| !self.sums.contains_key(h)) {
+            return Err(DvfError::InvalidKeyShare { id: *missing });
+        }
+        let sum = self.sums
+            .values()
+            .fold(BigInt::zero(), |acc, s| acc + secret_key_to_bigint(s));
+        let sk = bigint_to_secret_key(&sum)?;
+        ThresholdSignature::verify_share(self.id, &sk, &self.commitments)?;
+
+        Ok(RepairOutput {
+            id: self.id,
+            keypair: Keypair::from_components(sk.public_key(), sk),
+            master_public_key: self.master_public_key.clone(),
+            commitments: self.commitments.clone(),
+        })
+    }
+}
@@ -0,0 +1,350 @@
+//! Moves a distributed validator key from an old `(t, n)` committee to a new `(t', n')` committee,
+//! or refreshes the shares of a committee in place, without changing the voting public key.
+//!
+//! Each old operator re-shares its own key share: it deals a random polynomial of degree `t' - 1`
+//! whose constant term is its share, broadcasts Feldman commitments and privately sends one
+//! sub-share to every new operator. The new operators agree (through complaints, as in
+//! `crate::crypto::dkg`) on a set of `t` qualified old operators and combine their sub-shares with
+//! the Lagrange coefficients of that set. The new shares lie on a fresh polynomial, so they cannot
+//! be combined with the old ones.
+
+use bls::{Keypair, PublicKey, SecretKey};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::SocketAddr;
+use crate::crypto::ThresholdSignature;
+use crate::crypto::define::MODULUS;
+use crate::crypto::dkg::secret_key_hex;
+use crate::crypto::generic_threshold::{
+    bigint_to_secret_key, check_ids, lagrange_coefficients_at_zero, secret_key_to_bigint,
+};
+use crate::math::bigint_ext::Ring;
+use crate::math::polynomial::Polynomial;
+use crate::utils::error::DvfError;
+use crate::utils::rand_utils::{RandUtilsRng, Sample};
+use crate::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use crate::validation::operator_committee_definitions::OperatorCommitteeDefinition;
+use eth2_keystore::KeystoreBuilder;
+use serde::{Deserialize, Serialize};
+use ::crypto::PublicKey as NodePublicKey;
+
+/// Messages exchanged during a resharing. Everything but `SubShare` must be broadcast.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ReshareMessage {
+    /// Commitments to the polynomial of old operator `dealer`. The first one is its share public key.
+    Commitments { dealer: u64, commitments: Vec<PublicKey> },
+    /// Sub-share of `dealer`'s share for new operator `receiver`. Sent privately.
+    SubShare {
+        dealer: u64,
+        receiver: u64,
+        #[serde(with = "secret_key_hex")]
+        share: SecretKey,
+    },
+    /// New operator `accuser` got no sub-share, or a bad one, from `dealer`.
+    Complaint { dealer: u64, accuser: u64 },
+    /// `dealer` reveals the sub-share of `accuser` in answer to a complaint.
+    Justification {
+        dealer: u64,
+        accuser: u64,
+        #[serde(with = "secret_key_hex")]
+        share: SecretKey,
+    },
+}
+
+/// Description of a committee taking part in a resharing.
+#[derive(Clone)]
+pub struct CommitteeShape {
+    pub ids: Vec<u64>,
+    pub threshold: usize,
+}
+
+impl CommitteeShape {
+    pub fn new(ids: Vec<u64>, threshold: usize) -> Result<Self, DvfError> {
+        check_ids(&ids)?;
+        if threshold == 0 || threshold > ids.len() {
+            return Err(DvfError::InsufficientDealers { got: ids.len(), expected: threshold });
+        }
+        Ok(Self { ids, threshold })
+    }
+}
+
+/// An old operator handing its share over to the new committee.
+pub struct ReshareDealer {
+    id: u64,
+    new_committee: CommitteeShape,
+    coeffs: Vec<BigInt>,
+}
+
+impl ReshareDealer {
+    pub fn new(id: u64, share: &SecretKey, new_committee: CommitteeShape) -> Self {
+        let mut rng = RandUtilsRng::new();
+        let mut coeffs: Vec<BigInt> = rng.sample_vec(new_committee.threshold, &MODULUS);
+        coeffs[0] = secret_key_to_bigint(share);
+        Self { id, new_committee, coeffs }
+    }
+
+    fn evaluate(&self, x: u64) -> Result<SecretKey, DvfError> {
+        let poly = Polynomial::new(self.coeffs.clone());
+        bigint_to_secret_key(&poly.eval(&BigInt::from(x)))
+    }
+
+    /// The commitments to broadcast, followed by one private sub-share per new operator.
+    pub fn deal(&self) -> Result<Vec<ReshareMessage>, DvfError> {
+        let commitments = self.coeffs
+            .iter()
+            .map(|c| bigint_to_secret_key(c).map(|sk| sk.public_key()))
+            .collect::<Result<_, _>>()?;
+        let mut msgs = vec![ReshareMessage::Commitments { dealer: self.id, commitments }];
+        for receiver in &self.new_committee.ids {
+            msgs.push(ReshareMessage::SubShare {
+                dealer: self.id,
+                receiver: *receiver,
+                share: self.evaluate(*receiver)?,
+            });
+        }
+        Ok(msgs)
+    }
+
+    /// Reveal the sub-shares of the new operators who complained about us.
+    pub fn justifications(&self, complaints: &[ReshareMessage]) -> Result<Vec<ReshareMessage>, DvfError> {
+        let accusers: BTreeSet<u64> = complaints
+            .iter()
+            .filter_map(|msg| match msg {
+                ReshareMessage::Complaint { dealer, accuser } if *dealer == self.id => Some(*accuser),
+                _ => None,
+            })
+            .collect();
+        accusers
+            .into_iter()
+            .map(|accuser| {
+                Ok(ReshareMessage::Justification {
+                    dealer: self.id,
+                    accuser,
+                    share: self.evaluate(accuser)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Result of a resharing for one new operator.
+pub struct ReshareOutput {
+    pub id: u64,
+    pub keypair: Keypair,
+    /// Unchanged voting public key.
+    pub voting_public_key: PublicKey,
+    /// Commitments to the new sharing polynomial. The first one is `voting_public_key`.
+    pub commitments: Vec<PublicKey>,
+    pub committee: CommitteeShape,
+    /// Public keys of the new shares, in the order of `committee.ids`.
+    pub share_public_keys: Vec<PublicKey>,
+    /// Old operators whose share was used.
+    pub dealers: Vec<u64>,
+}
+
+impl ReshareOutput {
+    /// Encrypt the new key share with `password` into a `KeystoreShare` for validator `master_id`.
+    pub fn keystore_share(&self, password: &[u8], master_id: u64) -> Result<KeystoreShare, eth2_keystore::Error> {
+        let keystore = KeystoreBuilder::new(&self.keypair, password, "".into())?.build()?;
+        Ok(KeystoreShare::new(
+            keystore,
+            self.voting_public_key.clone(),
+            master_id,
+            self.id,
+            self.commitments.clone(),
+        ))
+    }
+
+    /// The definition of the new committee, given the addresses and the node public keys of its
+    /// operators (in the order of `committee.ids`).
+    pub fn committee_definition(
+        &self,
+        committee_index: u64,
+        socket_addresses: Vec<SocketAddr>,
+        node_public_keys: Vec<NodePublicKey>,
+    ) -> Result<OperatorCommitteeDefinition, DvfError> {
+        if socket_addresses.len() != self.committee.ids.len() {
+            return Err(DvfError::DifferentLength { x: socket_addresses.len(), y: self.committee.ids.len() });
+        }
+        if node_public_keys.len() != self.committee.ids.len() {
+            return Err(DvfError::DifferentLength { x: node_public_keys.len(), y: self.committee.ids.len() });
+        }
+        Ok(OperatorCommitteeDefinition {
+            total: self.committee.ids.len() as u64,
+            threshold: self.committee.threshold as u64,
+            committee_index,
+            voting_public_key: self.voting_public_key.clone(),
+            ids: self.committee.ids.clone(),
+            public_keys: self.share_public_keys.clone(),
+            socket_addresses,
+            commitments: self.commitments.clone(),
+            node_public_keys,
+        })
+    }
+}
+
+/// A new operator collecting sub-shares from the old committee.
+pub struct ReshareReceiver {
+    id: u64,
+    voting_public_key: PublicKey,
+    old_committee: CommitteeShape,
+    /// Share public keys of the old operators.
+    old_share_public_keys: HashMap<u64, PublicKey>,
+    new_committee: CommitteeShape,
+    commitments: HashMap<u64, Vec<PublicKey>>,
+    shares: HashMap<u64, SecretKey>,
+    complaints: HashMap<u64, HashSet<u64>>,
+    justifications: HashMap<(u64, u64), SecretKey>,
+}
+
+impl ReshareReceiver {
+    /// `old_definition` is the committee the validator is moving from.
+    pub fn new(
+        id: u64,
+        old_definition: &OperatorCommitteeDefinition,
+        new_committee: CommitteeShape,
+    ) -> Result<Self, DvfError> {
+        if !new_committee.ids.contains(&id) {
+            return Err(DvfError::InvalidOperatorIds);
+        }
+        let old_committee = CommitteeShape::new(old_definition.ids.clone(), old_definition.threshold as usize)?;
+        if old_definition.public_keys.len() != old_committee.ids.len() {
+            return Err(DvfError::DifferentLength { x: old_definition.public_keys.len(), y: old_committee.ids.len() });
+        }
+        let old_share_public_keys = old_definition.ids
+            .iter()
+            .copied()
+            .zip(old_definition.public_keys.iter().cloned())
+            .collect();
+        Ok(Self {
+            id,
+            voting_public_key: old_definition.voting_public_key.clone(),
+            old_committee,
+            old_share_public_keys,
+            new_committee,
+            commitments: HashMap::new(),
+            shares: HashMap::new(),
+            complaints: HashMap::new(),
+            justifications: HashMap::new(),
+        })
+    }
+
+    pub fn handle(&mut self, msg: ReshareMessage) {
+        match msg {
+            ReshareMessage::Commitments { dealer, commitments } => {
+                if self.old_share_public_keys.contains_key(&dealer) {
+                    self.commitments.entry(dealer).or_insert(commitments);
+                }
+            }
+            ReshareMessage::SubShare { dealer, receiver, share } => {
+                if receiver == self.id && self.old_share_public_keys.contains_key(&dealer) {
+                    self.shares.entry(dealer).or_insert(share);
+                }
+            }
+            ReshareMessage::Complaint { dealer, accuser } => {
+                if self.new_committee.ids.contains(&accuser) {
+                    self.complaints.entry(dealer).or_default().insert(accuser);
+                }
+            }
+            ReshareMessage::Justification { dealer, accuser, share } => {
+                let valid = self
+                    .commitments
+                    .get(&dealer)
+                    .map_or(false, |c| ThresholdSignature::verify_share(accuser, &share, c).is_ok());
+                if valid {
+                    self.justifications.entry((dealer, accuser)).or_insert(share);
+                }
+            }
+        }
+    }
+
+    /// Commitments must have the right degree and re-share the dealer's actual key share.
+    fn well_formed(&self, dealer: u64) -> bool {
+        match (self.commitments.get(&dealer), self.old_share_public_keys.get(&dealer)) {
+            (Some(c), Some(pk)) => c.len() == self.new_committee.threshold && &c[0] == pk,
+            _ => false,
+        }
+    }
+
+    fn share_is_valid(&self, dealer: u64) -> bool {
+        match (self.commitments.get(&dealer), self.shares.get(&dealer)) {
+            (Some(c), Some(share)) => ThresholdSignature::verify_share(self.id, share, c).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Complaints against well-formed dealers whose sub-share is missing or wrong.
+    pub fn complaints(&self) -> Vec<ReshareMessage> {
+        self.old_committee.ids
+            .iter()
+            .filter(|dealer| self.well_formed(**dealer) && !self.share_is_valid(**dealer))
+            .map(|dealer| ReshareMessage::Complaint { dealer: *dealer, accuser: self.id })
+            .collect()
+    }
+
+    /// The first `t` old operators (by id) with well-formed commitments and no pending complaint.
+    pub fn dealers(&self) -> Vec<u64> {
+        let mut dealers: Vec<u64> = self.old_committee.ids
+            .iter()
+            .copied()
+            .filter(|dealer| {
+                self.well_formed(*dealer)
+                    && self.complaints.get(dealer).map_or(true, |accusers| {
+                        accusers.iter().all(|a| self.justifications.contains_key(&(*dealer, *a)))
+                    })
+            })
+            .collect();
+        dealers.sort_unstable();
+        dealers.truncate(self.old_committee.threshold);
+        dealers
+    }
+
+    pub fn finalize(&self) -> Result<ReshareOutput, DvfError> {
+        let dealers = self.dealers();
+        if dealers.len() < self.old_committee.threshold {
+            return Err(DvfError::InsufficientDealers { got: dealers.len(), expected: self.old_committee.threshold });
+        }
+        let lambdas = lagrange_coefficients_at_zero(&dealers);
+
+        let mut sum = BigInt::zero();
+        for (dealer, lambda) in dealers.iter().zip(lambdas.iter()) {
+            let share = match self.justifications.get(&(*dealer, self.id)) {
+                Some(share) => share,
+                None if self.share_is_valid(*dealer) => &self.shares[dealer],
+                None => return Err(DvfError::InvalidKeyShare { id: *dealer }),
+            };
+            sum += secret_key_to_bigint(share) * lambda;
+        }
+        let sk = bigint_to_secret_key(&sum.reduce(&MODULUS))?;
+
+        // New commitments are the same Lagrange combination of the dealers' commitments.
+        let mut commitments = Vec::with_capacity(self.new_committee.threshold);
+        for k in 0..self.new_committee.threshold {
+            let points: Vec<&PublicKey> = dealers.iter().map(|d| &self.commitments[d][k]).collect();
+            commitments.push(ThresholdSignature::interpolate_public_keys(&points[..], &dealers[..])?);
+        }
+        if commitments[0] != self.voting_public_key {
+            return Err(DvfError::InvalidCommitment);
+        }
+
+        let keypair = Keypair::from_components(sk.public_key(), sk);
+        ThresholdSignature::verify_share(self.id, &keypair.sk, &commitments)?;
+
+        let share_public_keys = self.new_committee.ids
+            .iter()
+            .map(|id| ThresholdSignature::share_public_key(*id, &commitments))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ReshareOutput {
+            id: self.id,
+            keypair,
+            voting_public_key: self.voting_public_key.clone(),
+            commitments,
+            committee: self.new_committee.clone(),
+            share_public_keys,
+            dealers,
+        })
+    }
+}
+
@@ -1,8 +1,10 @@
 
 use dvf::validation::{OperatorCommittee};
-use dvf::validation::operator::TOperator;
 use dvf::validation::operator::{HotStuffOperator, LocalOperator};
 use dvf::crypto::{ThresholdSignature};
+use dvf::validation::duty_proposal::{DutyObject, DutyProposal, DutyProposalChecker};
+use dvf::validation::committed_duties::CommittedDutySigner;
+use dvf::validation::{SlashingDatabase, SLASHING_PROTECTION_FILENAME};
 use std::sync::Arc;
 use types::Hash256;
 use eth2_hashing::{Context, Sha256Context};
@@ -20,16 +22,20 @@ use futures::future::join_all;
 use node::dvfcore::{DvfCore, SignatureInfo, DvfSignatureReceiverHandler};
 use tokio::net::TcpStream;
 use network::SimpleSender;
-use bytes::Bytes;
 use std::net::SocketAddr;
-use mempool::{MempoolMessage, Batch, Transaction};
-use parking_lot::{RwLock};
+use mempool::{Batch, Transaction};
 use tokio::sync::mpsc::{channel, Sender};
-use types::Keypair;
+use tokio::sync::RwLock;
+use types::{AttestationData, ChainSpec, Checkpoint, Epoch, Keypair, MainnetEthSpec, PublicKeyBytes, Slot};
+use slot_clock::SystemTimeSlotClock;
+use std::path::Path;
 use std::{thread, time};
 use env_logger::Env;
 
-fn deploy_testbed(nodes: usize, kps: &Vec<Keypair>, tx_signature: Sender<SignatureInfo>, ids: &Vec<u64>) -> Result<Vec<JoinHandle<()>>, Box<dyn std::error::Error>> {
+/// Checker of the testbed operators: there is no beacon node, only the slashing protection database.
+type TestbedChecker = DutyProposalChecker<SystemTimeSlotClock, MainnetEthSpec>;
+
+fn deploy_testbed(nodes: usize, validator_id: &str, validator: PublicKeyBytes, kps: &Vec<Keypair>, tx_signature: Sender<SignatureInfo>, ids: &Vec<u64>) -> Result<Vec<JoinHandle<()>>, Box<dyn std::error::Error>> {
 
   let mut logger = env_logger::Builder::from_env(Env::default().default_filter_or("error"));
   logger.format_timestamp_millis();
@@ -84,6 +90,13 @@ fn deploy_testbed(nodes: usize, kps: &Vec<Keypair>, tx_signature: Sender<Signatu
           let secret = keypair.clone();
           let store_path = format!("db_{}", i);
           let _ = fs::remove_dir_all(&store_path);
+          fs::create_dir_all(&store_path)?;
+          let slashing_db_path = Path::new(&store_path).join(SLASHING_PROTECTION_FILENAME);
+          let slashing_protection = SlashingDatabase::create(&slashing_db_path)
+              .map_err(|e| format!("Failed to create slashing protection database: {:?}", e))?;
+          slashing_protection.register_validator(validator)
+              .map_err(|e| format!("Failed to register validator: {:?}", e))?;
+          let checker = TestbedChecker::new(ChainSpec::mainnet(), Hash256::zero(), slashing_protection);
           let name = keypair.name.clone();
           let mem_address = committee.mempool
           .mempool_address(&name)
@@ -103,22 +116,15 @@ fn deploy_testbed(nodes: usize, kps: &Vec<Keypair>, tx_signature: Sender<Signatu
           let kp = kps[i].clone();
           let sender_signature = tx_signature.clone();
           let id = ids[i].clone();
+          let validator_id = validator_id.to_string();
           Ok(tokio::spawn(async move {
               match Node::new(&tx_address.to_string(), &mem_address.to_string(), &consensus_address.to_string(), &dvf_address.to_string(), &signature_address.to_string(), secret, &store_path, None).await {
                   Ok(mut node) => {
-                      // Sink the commit channel.
-                      // while node.commit.recv().await.is_some() {}
-
-                      // node.process_dvfinfo().await;
-
                       info!("start dvf node {} success", name);
 
                       let committee_file = "committee.json";
                       let mut network = SimpleSender::new();
                       let committee = Committee::read(&committee_file).unwrap();
-                      let validator_vec : Vec<u8>= vec![50; 88];
-                      let validator_id = String::from_utf8(validator_vec).unwrap();
-                      // println!("received validator {}", validator_id);
                       {
                         let mut handler_map = node.signature_handler_map.write().await;
                         handler_map.insert(validator_id.clone(), DvfSignatureReceiverHandler{tx_signature : sender_signature});
@@ -130,14 +136,17 @@ fn deploy_testbed(nodes: usize, kps: &Vec<Keypair>, tx_signature: Sender<Signatu
                         committee,
                         node.name.clone(),
                         node.secret_key.clone(),
-                        validator_id,
-                        node.base_store_path.clone(),
+                        validator_id.clone(),
+                        node.store(&validator_id),
                         Arc::clone(&node.tx_handler_map),
                         Arc::clone(&node.mempool_handler_map),
                         Arc::clone(&node.consensus_handler_map),
                       ).await {
                         Ok(mut dvfcore) => {
-                          process_consensus_block(&mut dvfcore, Arc::new(kp), id).await;
+                          // The node serves the dvf messages of the other validators.
+                          tokio::spawn(async move { node.run().await });
+                          let signer = CommittedDutySigner::new(id, Arc::new(kp), validator, Arc::new(checker));
+                          signer.run(&mut dvfcore).await;
                           // dvfcore.analyze_block(Arc::clone(&kps).await;
                         }
                         Err(e) => {
@@ -153,59 +162,6 @@ fn deploy_testbed(nodes: usize, kps: &Vec<Keypair>, tx_signature: Sender<Signatu
       .collect::<Result<_, Box<dyn std::error::Error>>>()
 }
 
-async fn process_consensus_block(dvfcore: &mut DvfCore, keypair: Arc<Keypair>, id: u64) {
-  let operator = LocalOperator::new(id.into(), keypair);
-  let mut network = SimpleSender::new();
-  let boradcast_address = dvfcore.broadcast_signature_addresses.clone();
-  
-  while let Some(block) = dvfcore.commit.recv().await {
-    // This is where we can further process committed block.
-    if !block.payload.is_empty() {
-      for payload in block.payload {
-        match dvfcore.store.read(payload.to_vec()).await {
-          Ok(value) => {
-            match value {
-              Some(data) => {
-                let message: MempoolMessage  = bincode::deserialize(&data[..]).expect("Failed to deserialize our own block");
-                match message {
-                  MempoolMessage::Batch(batches) => {
-                    for batch in batches {
-                      let msg = Hash256::from_slice(&batch[..]);
-                      println!(" broadcast msg {:02x?}", msg);
-                      let sig = operator.sign(msg.clone()).unwrap();
-                      let pk = operator.public_key();
-                      let sig_info = SignatureInfo { from: pk, signature: sig, msg: msg, id: id};
-                      let siginfo_data = serde_json::to_vec(&sig_info).unwrap();
-                      let mut prefix_msg : Vec<u8> = Vec::new();
-                      prefix_msg.extend(dvfcore.validator_id.clone().into_bytes());
-                      prefix_msg.extend(siginfo_data);
-                      for address in &boradcast_address {
-                        let add = address.clone();
-                        network.send(add, Bytes::from(prefix_msg.clone())).await;
-                      }
-                      // consensus batch origin data
-                      //  
-                      // get msg Hash256
-
-                      // sign msg and construct signature info 
-                      // broadcast to network
-                    }
-                  }
-                  MempoolMessage::BatchRequest(_, _) => { }
-                }
-
-                     
-              }
-              None => { }
-            }
-          }
-          Err(e) => println!("{}", e)
-        }
-      }
-    }
-  }
-}
-
 async fn start_dvf_committee(node: &mut Node, tx_signature: Sender<SignatureInfo>, keypair: Arc<Keypair>) {
 
     
@@ -217,19 +173,24 @@ async fn main() {
     let t: usize = 5;
     let n: usize = 10;
     let mut m_threshold = ThresholdSignature::new(t);
-    let (kp, kps, ids) = m_threshold.key_gen(n);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
     let (tx_signature, mut rx_signature) = channel(n + 1);
     let self_kp = kps[0].clone();
+    let validator_id = kp.pk.as_hex_string();
 
           let mut committee = OperatorCommittee::new(0, kp.pk.clone(), t);
       //     // transaction address
           let address = "127.0.0.1:25001".parse::<SocketAddr>().unwrap();
           let operator = Arc::new(
-            RwLock::new(HotStuffOperator::new(Arc::new(self_kp), address, rx_signature)));  
+            RwLock::new(HotStuffOperator::new(validator_id.clone(), Arc::new(self_kp), address, rx_signature)));  
           committee.add_operator(ids[0], operator);
+          // The other members sign through their own node: only their ids matter to the committee.
+          for i in 1..n {
+            committee.add_operator(ids[i], Arc::new(RwLock::new(LocalOperator::new(ids[i], Arc::new(kps[i].clone())))));
+          }
 
     if n > 1 {
-      match deploy_testbed(n, &kps, tx_signature, &ids) {
+      match deploy_testbed(n, &validator_id, kp.pk.compress(), &kps, tx_signature, &ids) {
         Ok(handles) => {
 
           let ten_millis = time::Duration::from_millis(10);
@@ -239,9 +200,22 @@ async fn main() {
           let message = "hello world";
           let mut context = Context::new();
           context.update(message.as_bytes());
-          let message = Hash256::from_slice(&context.finalize());
+          let data = AttestationData {
+            slot: Slot::new(0),
+            index: 0,
+            beacon_block_root: Hash256::from_slice(&context.finalize()),
+            source: Checkpoint { epoch: Epoch::new(0), root: Hash256::zero() },
+            target: Checkpoint { epoch: Epoch::new(0), root: Hash256::zero() },
+          };
+          let proposal = DutyProposal::<MainnetEthSpec> {
+            slot: data.slot,
+            validator: kp.pk.compress(),
+            object: DutyObject::Attestation(data),
+          };
+          let message = proposal.signing_root(&ChainSpec::mainnet(), Hash256::zero());
             println!("propose {:02x?}", message);
-          let sig1 = committee.sign(message).unwrap();
+          // No slot clock in the testbed: allow a third of a mainnet slot.
+          let sig1 = committee.sign_proposal(&proposal.to_bytes(), message, time::Duration::from_secs(4)).await.unwrap();
           let sig2 = kp.sk.sign(message);
 
           let status1 = sig1.verify(&kp.pk, message);
@@ -0,0 +1,190 @@
+//! Runs a distributed key generation among operators living in the current process, each on its
+//! own task, talking over channels. Messages travel encoded, as they would over the network.
+//!
+//! Intended for use in testing and simulation. Not for production.
+
+use crate::crypto::dkg::{DkgMessage, DkgOutput, DkgParty};
+use crate::utils::error::DvfError;
+use bls::{Keypair, SecretKey};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Barrier;
+
+/// How a simulated operator behaves as a dealer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DealerBehaviour {
+    Honest,
+    /// Send a wrong share to the given operator, then answer its complaint with the right one.
+    BadShareThenJustify(u64),
+    /// Send a wrong share to the given operator and ignore its complaint.
+    BadShareNoJustify(u64),
+    /// Never deal.
+    Silent,
+    /// Deal honestly, then broadcast random Feldman commitments in the second phase.
+    BadPublicCoefficients,
+    /// Deal honestly, then never broadcast Feldman commitments in the second phase.
+    SilentPublicCoefficients,
+}
+
+/// Number of rounds of a DKG run, each ending on a barrier.
+const ROUNDS: usize = 6;
+
+/// Run a `threshold`-out-of-`ids.len()` DKG. Operators missing from `behaviours` are honest.
+///
+/// Returns the outcome of each operator, in the order of `ids`.
+pub async fn run_local_dkg(
+    ids: Vec<u64>,
+    threshold: usize,
+    behaviours: HashMap<u64, DealerBehaviour>,
+) -> Vec<Result<DkgOutput, DvfError>> {
+    let mut senders: HashMap<u64, UnboundedSender<Vec<u8>>> = HashMap::new();
+    let mut receivers: Vec<UnboundedReceiver<Vec<u8>>> = Vec::new();
+    for id in &ids {
+        let (tx, rx) = unbounded_channel();
+        senders.insert(*id, tx);
+        receivers.push(rx);
+    }
+    let senders = Arc::new(senders);
+    let barrier = Arc::new(Barrier::new(ids.len()));
+
+    let mut handles = Vec::new();
+    for (id, inbox) in ids.iter().copied().zip(receivers) {
+        let behaviour = behaviours.get(&id).cloned().unwrap_or(DealerBehaviour::Honest);
+        let party = DkgParty::new(id, ids.clone(), threshold);
+        let senders = Arc::clone(&senders);
+        let barrier = Arc::clone(&barrier);
+        handles.push(tokio::spawn(async move {
+            run_party(party, behaviour, inbox, senders, barrier).await
+        }));
+    }
+
+    let mut outputs = Vec::new();
+    for handle in handles {
+        outputs.push(handle.await.expect("DKG party panicked"));
+    }
+    outputs
+}
+
+async fn run_party(
+    party: Result<DkgParty, DvfError>,
+    behaviour: DealerBehaviour,
+    mut inbox: UnboundedReceiver<Vec<u8>>,
+    senders: Arc<HashMap<u64, UnboundedSender<Vec<u8>>>>,
+    barrier: Arc<Barrier>,
+) -> Result<DkgOutput, DvfError> {
+    let mut party = match party {
+        Ok(party) => party,
+        Err(e) => {
+            // Still take part in the rounds so that the others are not stuck on the barrier.
+            for _ in 0..ROUNDS {
+                barrier.wait().await;
+            }
+            return Err(e);
+        }
+    };
+
+    // Round 1: deal
+    let msgs = match behaviour {
+        DealerBehaviour::Silent => vec![],
+        // A party unable to deal stays silent, and keeps up with the rounds of the others.
+        _ => party.deal().unwrap_or_default(),
+    };
+    for msg in msgs {
+        match msg {
+            DkgMessage::Share { dealer, receiver, share, blinding } => {
+                let share = match behaviour {
+                    DealerBehaviour::BadShareThenJustify(victim)
+                    | DealerBehaviour::BadShareNoJustify(victim) if victim == receiver => SecretKey::random(),
+                    _ => share,
+                };
+                send(&senders, receiver, DkgMessage::Share { dealer, receiver, share, blinding });
+            }
+            msg => broadcast(&senders, msg),
+        }
+    }
+    barrier.wait().await;
+    drain(&mut party, &mut inbox);
+
+    // Round 2: complain
+    for msg in party.complaints() {
+        broadcast(&senders, msg);
+    }
+    barrier.wait().await;
+    drain(&mut party, &mut inbox);
+
+    // Round 3: answer complaints
+    if !matches!(behaviour, DealerBehaviour::BadShareNoJustify(_)) {
+        for msg in party.justifications().unwrap_or_default() {
+            broadcast(&senders, msg);
+        }
+    }
+    barrier.wait().await;
+    drain(&mut party, &mut inbox);
+
+    // Round 4: reveal the public coefficients
+    let msgs = match behaviour {
+        DealerBehaviour::SilentPublicCoefficients => vec![],
+        DealerBehaviour::BadPublicCoefficients => party
+            .public_coefficients()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|msg| match msg {
+                DkgMessage::PublicCoefficients { dealer, commitments } => DkgMessage::PublicCoefficients {
+                    dealer,
+                    commitments: commitments.iter().map(|_| Keypair::random().pk).collect(),
+                },
+                msg => msg,
+            })
+            .collect(),
+        _ => party.public_coefficients().unwrap_or_default(),
+    };
+    for msg in msgs {
+        broadcast(&senders, msg);
+    }
+    barrier.wait().await;
+    drain(&mut party, &mut inbox);
+
+    // Round 5: complain about the public coefficients
+    for msg in party.extraction_complaints() {
+        broadcast(&senders, msg);
+    }
+    barrier.wait().await;
+    drain(&mut party, &mut inbox);
+
+    // Round 6: reveal the shares of the dealers to reconstruct
+    for msg in party.reveals() {
+        broadcast(&senders, msg);
+    }
+    barrier.wait().await;
+    drain(&mut party, &mut inbox);
+
+    party.finalize()
+}
+
+fn send(senders: &HashMap<u64, UnboundedSender<Vec<u8>>>, to: u64, msg: DkgMessage) {
+    if let Some(tx) = senders.get(&to) {
+        let _ = tx.send(encode(&msg));
+    }
+}
+
+fn broadcast(senders: &HashMap<u64, UnboundedSender<Vec<u8>>>, msg: DkgMessage) {
+    let bytes = encode(&msg);
+    for tx in senders.values() {
+        let _ = tx.send(bytes.clone());
+    }
+}
+
+fn encode(msg: &DkgMessage) -> Vec<u8> {
+    serde_json::to_vec(msg).expect("DKG messages should encode")
+}
+
+/// Handle the messages received so far. Messages that do not decode are dropped, as from a
+/// misbehaving operator.
+fn drain(party: &mut DkgParty, inbox: &mut UnboundedReceiver<Vec<u8>>) {
+    while let Ok(bytes) = inbox.try_recv() {
+        if let Ok(msg) = serde_json::from_slice(&bytes) {
+            party.handle(msg);
+        }
+    }
+}
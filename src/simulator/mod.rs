@@ -8,6 +8,7 @@ pub mod local_validator_client;
 pub mod local_network;
 pub mod validator_files;
 pub mod checks;
+pub mod local_dkg;
 
 pub use crate::validation::Config as ValidatorConfig;
 
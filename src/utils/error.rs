@@ -12,10 +12,43 @@ pub enum DvfError {
     ConsensusFailure,
     /// Threshold signature aggregation failed due to insufficient valid signatures.
     InsufficientSignatures {got: usize, expected: usize},
+    /// Too few signature shares are valid to aggregate them. `invalid` lists the operators whose
+    /// share is invalid.
+    InsufficientValidSignatures {got: usize, expected: usize, invalid: Vec<u64>},
+    /// The threshold is zero or larger than the number of operators
+    InvalidThreshold {threshold: usize, total: usize},
     /// Invalid operator signature
     InvalidSignatureShare {id: u64},
+    /// Key share does not match the published commitments
+    InvalidKeyShare {id: u64},
+    /// Commitment is not a valid G1 point
+    InvalidCommitment,
+    /// Not enough dealers were qualified at the end of the distributed key generation
+    InsufficientDealers {got: usize, expected: usize},
+    /// Operator ids are zero, duplicated, or do not match the committee
+    InvalidOperatorIds,
+    /// The shares do not belong to the same validator, or do not interpolate to its public key
+    KeyRecoveryFailed,
+    /// The scalar is zero, which is not a valid secret key
+    ZeroSecretKey,
     /// Different length
     DifferentLength {x: usize, y: usize},
+    /// The operator could not be reached over the network
+    OperatorUnreachable {id: u64, reason: String},
+    /// The operator did not answer before the deadline
+    OperatorTimeout {id: u64},
+    /// The operator answered with an error or an unexpected message
+    OperatorRejected {id: u64, reason: String},
+    /// The duty proposal could not be decoded
+    MalformedDutyProposal,
+    /// The duty proposal does not match what the operator sees on its own beacon node
+    DutyProposalRejected {slot: u64, reason: String},
+    /// Signing the duty proposal could get the validator slashed
+    SlashableDutyProposal {slot: u64, reason: String},
+    /// The committee only agrees on typed duty proposals
+    UntypedDuty,
+    /// Not enough valid signature shares arrived before the deadline of the duty
+    SigningDeadlineExceeded {got: usize, expected: usize},
     /// 
     InvalidLength,
     ///
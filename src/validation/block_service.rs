@@ -288,7 +288,7 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
     }
 
     /// Produce a block at the given slot for validator_pubkey
-    async fn publish_block<Payload: ExecPayload<E>>(
+    async fn publish_block<Payload: ExecPayload<E> + Clone>(
         self,
         slot: Slot,
         validator_pubkey: PublicKeyBytes,
@@ -0,0 +1,103 @@
+//! Signing of the duties committed by the HotStuff instance of a validator.
+//!
+//! Every operator of a `HotstuffOperatorCommittee` runs a `CommittedDutySigner` next to the
+//! `DvfCore` of the validator. Committed proposals are checked by the `DutyProposalChecker` of the
+//! operator (slashing protection included) before its share is broadcast: consensus only orders
+//! the proposals, it does not vouch for them.
+
+use crate::validation::duty_proposal::DutyProposalChecker;
+use crate::DvfOperatorTsid;
+use log::{debug, error, warn};
+use mempool::MempoolMessage;
+use network::{Envelope, MessageKind, SimpleSender};
+use node::dvfcore::{DvfCore, SignatureInfo};
+use slot_clock::SlotClock;
+use std::sync::Arc;
+use types::{EthSpec, Keypair, PublicKeyBytes};
+
+pub struct CommittedDutySigner<T, E: EthSpec> {
+    /// Id of the operator in the threshold signature scheme.
+    id: DvfOperatorTsid,
+    /// The key share of the operator.
+    keypair: Arc<Keypair>,
+    /// The validator the key share belongs to.
+    validator: PublicKeyBytes,
+    checker: Arc<DutyProposalChecker<T, E>>,
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> CommittedDutySigner<T, E> {
+    pub fn new(
+        id: DvfOperatorTsid,
+        keypair: Arc<Keypair>,
+        validator: PublicKeyBytes,
+        checker: Arc<DutyProposalChecker<T, E>>,
+    ) -> Self {
+        Self {
+            id,
+            keypair,
+            validator,
+            checker,
+        }
+    }
+
+    /// Check a committed `proposal` and sign it. Returns `None` if the operator refuses to.
+    pub async fn sign(&self, proposal: &[u8]) -> Option<SignatureInfo> {
+        let signing_root = match self.checker.check_bytes(proposal).await {
+            Ok((validator, _)) if validator != self.validator => {
+                error!("Committed duty is for another validator {:?}", validator);
+                return None;
+            }
+            Ok((_, signing_root)) => signing_root,
+            Err(e) => {
+                error!("Refusing to sign committed duty: {:?}", e);
+                return None;
+            }
+        };
+        Some(SignatureInfo {
+            from: self.keypair.pk.clone(),
+            signature: self.keypair.sk.sign(signing_root),
+            msg: signing_root,
+            id: self.id,
+        })
+    }
+
+    /// Sign the duties of the blocks committed by `dvfcore` and broadcast the shares to the other
+    /// operators, until its commit channel closes.
+    pub async fn run(&self, dvfcore: &mut DvfCore) {
+        let mut network = SimpleSender::new();
+        while let Some(block) = dvfcore.commit.recv().await {
+            for digest in &block.payload {
+                let batch = match dvfcore.store.read(digest.to_vec()).await {
+                    Ok(Some(data)) => match bincode::deserialize(&data) {
+                        Ok(MempoolMessage::Batch(batch)) => batch,
+                        Ok(MempoolMessage::BatchRequest(..)) => continue,
+                        Err(e) => {
+                            warn!("Failed to deserialize committed batch {}: {}", digest, e);
+                            continue;
+                        }
+                    },
+                    Ok(None) => {
+                        warn!("Committed batch {} is missing from the store", digest);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Failed to read committed batch {}: {}", digest, e);
+                        continue;
+                    }
+                };
+                for proposal in batch {
+                    let share = match self.sign(&proposal).await {
+                        Some(share) => share,
+                        None => continue,
+                    };
+                    debug!("Operator {} broadcasts its share of {:?}", self.id, share.msg);
+                    let data = serde_json::to_vec(&share).expect("Failed to serialize share");
+                    let envelope = Envelope::seal(&dvfcore.validator_id, MessageKind::Signature, data);
+                    for address in &dvfcore.broadcast_signature_addresses {
+                        network.send(*address, envelope.clone()).await;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::validation::operator_committee_definitions::CONFIG_FILENAME;
+use crate::validation::ProductionValidatorClient;
+use slog::{error, info, warn};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{sleep, Duration};
+use types::EthSpec;
+
+/// How often the operator committee definitions file is checked for changes.
+pub const COMMITTEE_RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Spawns a service which reloads the operator committee definitions when their file changes or
+/// when the process receives `SIGHUP`.
+pub fn spawn_committee_reloader<T: EthSpec>(client: &ProductionValidatorClient<T>) -> Result<(), String> {
+    let context = client.context.service_context("committee_reloader".into());
+    let executor = context.executor.clone();
+    let validators = client.validator_store.initialized_validators();
+    let path = client.config.validator_dir.join(CONFIG_FILENAME);
+
+    let mut hangup = signal(SignalKind::hangup()).map_err(|e| format!("Unable to listen for SIGHUP: {}", e))?;
+
+    let interval_fut = async move {
+        let log = context.log();
+        let mut last_modified = modified(&path);
+
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => {
+                    info!(log, "Reloading operator committees"; "reason" => "SIGHUP");
+                }
+                () = sleep(COMMITTEE_RELOAD_POLL_INTERVAL) => {
+                    if modified(&path) == last_modified {
+                        continue;
+                    }
+                    info!(log, "Reloading operator committees"; "reason" => "file changed");
+                }
+            }
+            last_modified = modified(&path);
+
+            // `InitializedValidators` is behind a blocking lock, reconcile off the async workers.
+            // The lock is not held while the rebuilt committees wait for the duties in flight.
+            let validators = validators.clone();
+            let runtime = context.executor.runtime();
+            let reload = context.executor.spawn_blocking_handle(
+                move || match runtime.upgrade() {
+                    Some(runtime) => {
+                        let result = validators.read().prepare_committee_reload();
+                        result
+                            .and_then(|mut reconciliation| {
+                                runtime.block_on(reconciliation.swap());
+                                validators.write().apply_committee_reload(reconciliation)
+                            })
+                            .map_err(|e| format!("{:?}", e))
+                    }
+                    None => Err("Runtime shutdown".to_string()),
+                },
+                "committee_reload",
+            );
+            match reload {
+                Some(reload) => match reload.await {
+                    Ok(Ok(changes)) if changes.is_empty() => (),
+                    Ok(Ok(changes)) => info!(
+                        log,
+                        "Reloaded operator committees";
+                        "added" => ?changes.added,
+                        "rebuilt" => ?changes.rebuilt,
+                        "retired" => ?changes.retired,
+                        "unserved" => ?changes.unserved,
+                    ),
+                    // Invalid definitions leave the running committees as they are. Concurrent
+                    // changes are saved to file, which is reloaded again.
+                    Ok(Err(e)) => warn!(log, "Unable to reload operator committees"; "error" => e),
+                    Err(e) => error!(log, "Operator committee reload failed"; "error" => %e),
+                },
+                None => return,
+            }
+        }
+    };
+
+    executor.spawn(interval_fut, "committee_reloader");
+    Ok(())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -12,7 +12,7 @@ use sensitive_url::SensitiveUrl;
 use serde_derive::{Deserialize, Serialize};
 use slog::{info, warn, Logger};
 use std::fs;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use types::{Address, GRAFFITI_BYTES_LEN};
 
@@ -59,6 +59,12 @@ pub struct Config {
     /// A list of custom certificates that the validator client will additionally use when
     /// connecting to a beacon node over SSL/TLS.
     pub beacon_nodes_tls_certs: Option<Vec<PathBuf>>,
+    /// The hotstuff key of this operator, authenticating its requests to the other operators of
+    /// its committees. Defaults to `NODE_KEY_FILENAME` in `validator_dir`, created if missing.
+    pub node_key_path: Option<PathBuf>,
+    /// Address on which to answer the signing requests of the other operators of our committees,
+    /// if any.
+    pub operator_service_address: Option<SocketAddr>,
 }
 
 impl Default for Config {
@@ -93,6 +99,8 @@ impl Default for Config {
             enable_doppelganger_protection: false,
             beacon_nodes_tls_certs: None,
             private_tx_proposals: false,
+            node_key_path: None,
+            operator_service_address: None,
         }
     }
 }
@@ -312,6 +320,21 @@ impl Config {
             config.private_tx_proposals = true;
         }
 
+        /*
+         * Operator of distributed validators
+         */
+        if let Some(node_key_path) = parse_optional(cli_args, "node-key")? {
+            config.node_key_path = Some(node_key_path);
+        }
+
+        if let Some(address) = cli_args.value_of("operator-address") {
+            config.operator_service_address = Some(
+                address
+                    .parse::<SocketAddr>()
+                    .map_err(|_| "operator-address is not a valid socket address.")?,
+            );
+        }
+
         Ok(config)
     }
 }
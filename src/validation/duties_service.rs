@@ -25,6 +25,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use sync::poll_sync_committee_duties;
 use sync::SyncDutiesMap;
+pub use sync::AGGREGATION_PRE_COMPUTE_EPOCHS;
 use tokio::{sync::mpsc::Sender, time::sleep};
 use types::{ChainSpec, Epoch, EthSpec, Hash256, PublicKeyBytes, SelectionProof, Slot};
 
@@ -0,0 +1,420 @@
+//! Typed duties agreed on by the operators of a committee.
+//!
+//! The operator starting a signing round does not propose an opaque signing root, but the full
+//! object to sign (`DutyProposal`). Once consensus commits a proposal, every operator checks it
+//! against its own beacon node and slashing protection database (`DutyProposalChecker`), and
+//! signs its share over a signing root it computes itself.
+
+use crate::utils::error::DvfError;
+use crate::validation::beacon_node_fallback::{BeaconNodeFallback, RequireSynced};
+use crate::validation::duties_service::AGGREGATION_PRE_COMPUTE_EPOCHS;
+use crate::validation::signing_method::{SignableMessage, SigningContext};
+use eth2::types::BlockId;
+use serde::{Deserialize, Serialize};
+use slashing_protection::{NotSafe, Safe, SlashingDatabase};
+use slot_clock::SlotClock;
+use std::sync::Arc;
+use std::time::Duration;
+use types::*;
+
+/// Kinds of duties a distributed validator signs for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DutyKind {
+    RandaoReveal,
+    BeaconBlock,
+    Attestation,
+    AggregateAndProof,
+    SelectionProof,
+    SyncSelectionProof,
+    SyncCommitteeSignature,
+    ContributionAndProof,
+}
+
+/// The object to sign for a duty. Mirrors `SignableMessage`, but owns its content so that it can
+/// be sent to the other operators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "E: EthSpec, Payload: ExecPayload<E>")]
+pub enum DutyObject<E: EthSpec, Payload: ExecPayload<E> = FullPayload<E>> {
+    RandaoReveal(Epoch),
+    BeaconBlock(BeaconBlock<E, Payload>),
+    Attestation(AttestationData),
+    AggregateAndProof(AggregateAndProof<E>),
+    SelectionProof(Slot),
+    SyncSelectionProof(SyncAggregatorSelectionData),
+    SyncCommitteeSignature {
+        beacon_block_root: Hash256,
+        slot: Slot,
+    },
+    ContributionAndProof(ContributionAndProof<E>),
+}
+
+/// A duty proposed to the operator committee of a validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "E: EthSpec, Payload: ExecPayload<E>")]
+pub struct DutyProposal<E: EthSpec, Payload: ExecPayload<E> = FullPayload<E>> {
+    /// Slot of the duty. For a RANDAO reveal, the first slot of its epoch.
+    pub slot: Slot,
+    /// Voting public key of the distributed validator.
+    pub validator: PublicKeyBytes,
+    pub object: DutyObject<E, Payload>,
+}
+
+impl<E: EthSpec, Payload: ExecPayload<E>> DutyProposal<E, Payload> {
+    pub fn new(validator: PublicKeyBytes, message: &SignableMessage<'_, E, Payload>) -> Self
+    where
+        Payload: Clone,
+    {
+        let (slot, object) = match message {
+            SignableMessage::RandaoReveal(epoch) => (
+                epoch.start_slot(E::slots_per_epoch()),
+                DutyObject::RandaoReveal(*epoch),
+            ),
+            SignableMessage::BeaconBlock(b) => (b.slot(), DutyObject::BeaconBlock((*b).clone())),
+            SignableMessage::AttestationData(a) => (a.slot, DutyObject::Attestation((*a).clone())),
+            SignableMessage::SignedAggregateAndProof(a) => (
+                a.aggregate.data.slot,
+                DutyObject::AggregateAndProof((*a).clone()),
+            ),
+            SignableMessage::SelectionProof(slot) => (*slot, DutyObject::SelectionProof(*slot)),
+            SignableMessage::SyncSelectionProof(s) => {
+                (s.slot, DutyObject::SyncSelectionProof((*s).clone()))
+            }
+            SignableMessage::SyncCommitteeSignature {
+                beacon_block_root,
+                slot,
+            } => (
+                *slot,
+                DutyObject::SyncCommitteeSignature {
+                    beacon_block_root: *beacon_block_root,
+                    slot: *slot,
+                },
+            ),
+            SignableMessage::SignedContributionAndProof(c) => (
+                c.contribution.slot,
+                DutyObject::ContributionAndProof((*c).clone()),
+            ),
+        };
+        Self {
+            slot,
+            validator,
+            object,
+        }
+    }
+
+    pub fn kind(&self) -> DutyKind {
+        match &self.object {
+            DutyObject::RandaoReveal(_) => DutyKind::RandaoReveal,
+            DutyObject::BeaconBlock(_) => DutyKind::BeaconBlock,
+            DutyObject::Attestation(_) => DutyKind::Attestation,
+            DutyObject::AggregateAndProof(_) => DutyKind::AggregateAndProof,
+            DutyObject::SelectionProof(_) => DutyKind::SelectionProof,
+            DutyObject::SyncSelectionProof(_) => DutyKind::SyncSelectionProof,
+            DutyObject::SyncCommitteeSignature { .. } => DutyKind::SyncCommitteeSignature,
+            DutyObject::ContributionAndProof(_) => DutyKind::ContributionAndProof,
+        }
+    }
+
+    pub fn signable_message(&self) -> SignableMessage<'_, E, Payload> {
+        match &self.object {
+            DutyObject::RandaoReveal(epoch) => SignableMessage::RandaoReveal(*epoch),
+            DutyObject::BeaconBlock(b) => SignableMessage::BeaconBlock(b),
+            DutyObject::Attestation(a) => SignableMessage::AttestationData(a),
+            DutyObject::AggregateAndProof(a) => SignableMessage::SignedAggregateAndProof(a),
+            DutyObject::SelectionProof(slot) => SignableMessage::SelectionProof(*slot),
+            DutyObject::SyncSelectionProof(s) => SignableMessage::SyncSelectionProof(s),
+            DutyObject::SyncCommitteeSignature {
+                beacon_block_root,
+                slot,
+            } => SignableMessage::SyncCommitteeSignature {
+                beacon_block_root: *beacon_block_root,
+                slot: *slot,
+            },
+            DutyObject::ContributionAndProof(c) => SignableMessage::SignedContributionAndProof(c),
+        }
+    }
+
+    /// Domain and epoch the object is signed under, as in `ValidatorStore`.
+    pub fn signing_context(&self, spec: &ChainSpec, genesis_validators_root: Hash256) -> SigningContext {
+        let slots_per_epoch = E::slots_per_epoch();
+        let (domain, epoch) = match &self.object {
+            DutyObject::RandaoReveal(epoch) => (Domain::Randao, *epoch),
+            DutyObject::BeaconBlock(b) => (Domain::BeaconProposer, b.epoch()),
+            DutyObject::Attestation(a) => (Domain::BeaconAttester, a.target.epoch),
+            DutyObject::AggregateAndProof(a) => {
+                (Domain::AggregateAndProof, a.aggregate.data.target.epoch)
+            }
+            DutyObject::SelectionProof(slot) => (Domain::SelectionProof, slot.epoch(slots_per_epoch)),
+            DutyObject::SyncSelectionProof(s) => {
+                (Domain::SyncCommitteeSelectionProof, s.slot.epoch(slots_per_epoch))
+            }
+            DutyObject::SyncCommitteeSignature { slot, .. } => {
+                (Domain::SyncCommittee, slot.epoch(slots_per_epoch))
+            }
+            DutyObject::ContributionAndProof(c) => {
+                (Domain::ContributionAndProof, c.contribution.slot.epoch(slots_per_epoch))
+            }
+        };
+        SigningContext {
+            domain,
+            epoch,
+            fork: spec.fork_at_epoch(epoch),
+            genesis_validators_root,
+        }
+    }
+
+    pub fn signing_root(&self, spec: &ChainSpec, genesis_validators_root: Hash256) -> Hash256 {
+        let domain_hash = self
+            .signing_context(spec, genesis_validators_root)
+            .domain_hash(spec);
+        self.signable_message().signing_root(domain_hash)
+    }
+
+    /// Whether `self.slot` is the slot the object itself refers to.
+    pub fn is_consistent(&self) -> bool {
+        let object_slot = match &self.object {
+            DutyObject::RandaoReveal(epoch) => epoch.start_slot(E::slots_per_epoch()),
+            DutyObject::BeaconBlock(b) => b.slot(),
+            DutyObject::Attestation(a) => a.slot,
+            DutyObject::AggregateAndProof(a) => a.aggregate.data.slot,
+            DutyObject::SelectionProof(slot) => *slot,
+            DutyObject::SyncSelectionProof(s) => s.slot,
+            DutyObject::SyncCommitteeSignature { slot, .. } => *slot,
+            DutyObject::ContributionAndProof(c) => c.contribution.slot,
+        };
+        object_slot == self.slot
+    }
+
+    /// Time left to sign the duty according to `slot_clock`, or `None` if the clock cannot tell.
+    ///
+    /// Past the deadline, the signature is of no use anymore: blocks must be out in the first third
+    /// of their slot, attestations and sync committee messages before aggregation starts at two
+    /// thirds, aggregates before the end of the slot. A RANDAO reveal is good for its whole epoch.
+    pub fn time_to_deadline<S: SlotClock>(&self, slot_clock: &S) -> Option<Duration> {
+        let thirds = match self.kind() {
+            DutyKind::RandaoReveal => 3 * E::slots_per_epoch() as u32,
+            DutyKind::BeaconBlock => 1,
+            DutyKind::Attestation | DutyKind::SyncCommitteeSignature => 2,
+            DutyKind::AggregateAndProof
+            | DutyKind::SelectionProof
+            | DutyKind::SyncSelectionProof
+            | DutyKind::ContributionAndProof => 3,
+        };
+        let deadline = slot_clock.start_of(self.slot)? + slot_clock.slot_duration() * thirds / 3;
+        Some(deadline.saturating_sub(slot_clock.now_duration()?))
+    }
+
+    /// Encoding used for the transactions of the consensus instance of the validator.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Failed to serialize duty proposal")
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, DvfError> {
+        serde_json::from_slice(data).map_err(|_| DvfError::MalformedDutyProposal)
+    }
+}
+
+/// The fields of an encoded `DutyProposal` that do not depend on the `EthSpec`, so that committees
+/// can schedule a duty without decoding its object.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DutyProposalHeader {
+    pub slot: Slot,
+    pub validator: PublicKeyBytes,
+}
+
+impl DutyProposalHeader {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, DvfError> {
+        serde_json::from_slice(data).map_err(|_| DvfError::MalformedDutyProposal)
+    }
+}
+
+/// Checks committed proposals on behalf of one operator before it signs its share.
+pub struct DutyProposalChecker<T, E: EthSpec> {
+    spec: ChainSpec,
+    genesis_validators_root: Hash256,
+    slashing_protection: SlashingDatabase,
+    slot_clock: Option<T>,
+    beacon_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
+}
+
+impl<T: SlotClock, E: EthSpec> DutyProposalChecker<T, E> {
+    /// Checker relying on the slashing protection database only. Use `slot_clock` and
+    /// `beacon_nodes` to enable the other checks.
+    pub fn new(spec: ChainSpec, genesis_validators_root: Hash256, slashing_protection: SlashingDatabase) -> Self {
+        Self {
+            spec,
+            genesis_validators_root,
+            slashing_protection,
+            slot_clock: None,
+            beacon_nodes: None,
+        }
+    }
+
+    /// Reject proposals for slots that have not started yet. Selection proofs are pre-computed by
+    /// the duties service, so they may be up to `AGGREGATION_PRE_COMPUTE_EPOCHS` epochs ahead.
+    pub fn slot_clock(mut self, slot_clock: T) -> Self {
+        self.slot_clock = Some(slot_clock);
+        self
+    }
+
+    /// Compare proposals with the view of our own beacon nodes.
+    pub fn beacon_nodes(mut self, beacon_nodes: Arc<BeaconNodeFallback<T, E>>) -> Self {
+        self.beacon_nodes = Some(beacon_nodes);
+        self
+    }
+
+    /// Decode a committed transaction and check it. Returns the validator and the root to sign.
+    pub async fn check_bytes(&self, data: &[u8]) -> Result<(PublicKeyBytes, Hash256), DvfError> {
+        // Blocks may be proposed with their full payload or blinded.
+        if let Ok(proposal) = DutyProposal::<E>::from_bytes(data) {
+            let signing_root = self.check(&proposal).await?;
+            return Ok((proposal.validator, signing_root));
+        }
+        let proposal = DutyProposal::<E, BlindedPayload<E>>::from_bytes(data)?;
+        let signing_root = self.check(&proposal).await?;
+        Ok((proposal.validator, signing_root))
+    }
+
+    /// Check `proposal` and record it in the slashing protection database. Returns the root to
+    /// sign.
+    pub async fn check<Payload: ExecPayload<E>>(
+        &self,
+        proposal: &DutyProposal<E, Payload>,
+    ) -> Result<Hash256, DvfError> {
+        if !proposal.is_consistent() {
+            return Err(self.reject(proposal, "slot does not match the object".into()));
+        }
+        if let Some(current_slot) = self.slot_clock.as_ref().and_then(|clock| clock.now()) {
+            let latest_slot = match proposal.kind() {
+                DutyKind::SelectionProof | DutyKind::SyncSelectionProof => {
+                    let slots_per_epoch = E::slots_per_epoch();
+                    (current_slot.epoch(slots_per_epoch) + AGGREGATION_PRE_COMPUTE_EPOCHS)
+                        .end_slot(slots_per_epoch)
+                }
+                _ => current_slot,
+            };
+            if proposal.slot > latest_slot {
+                return Err(self.reject(
+                    proposal,
+                    format!("slot is greater than current slot {}", current_slot),
+                ));
+            }
+        }
+        if let Some(beacon_nodes) = &self.beacon_nodes {
+            self.check_beacon_node(beacon_nodes, proposal).await?;
+        }
+
+        let signing_context = proposal.signing_context(&self.spec, self.genesis_validators_root);
+        let domain_hash = signing_context.domain_hash(&self.spec);
+        let slashing_status = match &proposal.object {
+            DutyObject::BeaconBlock(b) => Some(self.slashing_protection.check_and_insert_block_proposal(
+                &proposal.validator,
+                &b.block_header(),
+                domain_hash,
+            )),
+            DutyObject::Attestation(a) => Some(self.slashing_protection.check_and_insert_attestation(
+                &proposal.validator,
+                a,
+                domain_hash,
+            )),
+            _ => None,
+        };
+        match slashing_status {
+            // Signing the same data twice is harmless: it only yields the same share.
+            None | Some(Ok(Safe::Valid)) | Some(Ok(Safe::SameData)) => {}
+            Some(Err(e)) => return Err(Self::slashable(proposal, e)),
+        }
+        Ok(proposal.signable_message().signing_root(domain_hash))
+    }
+
+    async fn check_beacon_node<Payload: ExecPayload<E>>(
+        &self,
+        beacon_nodes: &BeaconNodeFallback<T, E>,
+        proposal: &DutyProposal<E, Payload>,
+    ) -> Result<(), DvfError> {
+        match &proposal.object {
+            DutyObject::BeaconBlock(b) => {
+                let (slot, epoch, proposer_index) = (b.slot(), b.epoch(), b.proposer_index());
+                let duties = beacon_nodes
+                    .first_success(RequireSynced::No, |beacon_node| async move {
+                        beacon_node.get_validator_duties_proposer(epoch).await
+                    })
+                    .await
+                    .map_err(|e| self.reject(proposal, e.to_string()))?;
+                let is_proposer = duties.data.iter().any(|duty| {
+                    duty.slot == slot
+                        && duty.pubkey == proposal.validator
+                        && duty.validator_index == proposer_index
+                });
+                if !is_proposer {
+                    return Err(self.reject(proposal, "validator is not the proposer".into()));
+                }
+                self.check_known_block(beacon_nodes, proposal, b.parent_root()).await
+            }
+            DutyObject::Attestation(a) => {
+                let (slot, index) = (a.slot, a.index);
+                let expected = beacon_nodes
+                    .first_success(RequireSynced::No, |beacon_node| async move {
+                        beacon_node.get_validator_attestation_data(slot, index).await
+                    })
+                    .await
+                    .map_err(|e| self.reject(proposal, e.to_string()))?
+                    .data;
+                // The head may legitimately differ between beacon nodes, the checkpoints may not.
+                if expected.source != a.source || expected.target != a.target {
+                    return Err(self.reject(proposal, "checkpoints do not match our beacon node".into()));
+                }
+                self.check_known_block(beacon_nodes, proposal, a.beacon_block_root).await
+            }
+            DutyObject::AggregateAndProof(a) => {
+                self.check_known_block(beacon_nodes, proposal, a.aggregate.data.beacon_block_root)
+                    .await
+            }
+            DutyObject::SyncCommitteeSignature {
+                beacon_block_root, ..
+            } => {
+                self.check_known_block(beacon_nodes, proposal, *beacon_block_root)
+                    .await
+            }
+            DutyObject::ContributionAndProof(c) => {
+                self.check_known_block(beacon_nodes, proposal, c.contribution.beacon_block_root)
+                    .await
+            }
+            // Only depend on the slot, which has already been checked.
+            DutyObject::RandaoReveal(_)
+            | DutyObject::SelectionProof(_)
+            | DutyObject::SyncSelectionProof(_) => Ok(()),
+        }
+    }
+
+    async fn check_known_block<Payload: ExecPayload<E>>(
+        &self,
+        beacon_nodes: &BeaconNodeFallback<T, E>,
+        proposal: &DutyProposal<E, Payload>,
+        root: Hash256,
+    ) -> Result<(), DvfError> {
+        let header = beacon_nodes
+            .first_success(RequireSynced::No, |beacon_node| async move {
+                beacon_node.get_beacon_headers_block_id(BlockId::Root(root)).await
+            })
+            .await
+            .map_err(|e| self.reject(proposal, e.to_string()))?;
+        match header {
+            Some(_) => Ok(()),
+            None => Err(self.reject(proposal, format!("unknown block {:?}", root))),
+        }
+    }
+
+    fn reject<Payload: ExecPayload<E>>(&self, proposal: &DutyProposal<E, Payload>, reason: String) -> DvfError {
+        DvfError::DutyProposalRejected {
+            slot: proposal.slot.as_u64(),
+            reason: format!("{:?}: {}", proposal.kind(), reason),
+        }
+    }
+
+    fn slashable<Payload: ExecPayload<E>>(proposal: &DutyProposal<E, Payload>, e: NotSafe) -> DvfError {
+        DvfError::SlashableDutyProposal {
+            slot: proposal.slot.as_u64(),
+            reason: format!("{:?}", e),
+        }
+    }
+}
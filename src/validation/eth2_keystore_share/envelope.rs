@@ -0,0 +1,199 @@
+//! Provides an envelope which encrypts a key share to the hotstuff key of its operator.
+//!
+//! The password of a `KeystoreShare` has to be handed to its operator out of band. An envelope
+//! instead can only be opened with the node `Secret` of the operator it is sealed to: the share
+//! is encrypted with ChaCha20-Poly1305 under a key agreed between an ephemeral X25519 key and the
+//! X25519 form of the operator's ed25519 key. The rest of the keystore share is sent in the clear
+//! but authenticated along with the share.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use ::crypto::{x25519_production_agreement, PublicKey as NodePublicKey};
+use bls::{Keypair, PublicKey, SecretKey};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use eth2_hashing::hash;
+use eth2_keystore::{Error as KeyStoreError, KeystoreBuilder, PlainText};
+use node::config::Secret;
+use crate::crypto::ThresholdSignature;
+use crate::utils::error::DvfError;
+use super::keystore_share::KeystoreShare;
+
+/// Separates the keys derived for envelopes from any other use of the shared secret.
+pub const ENVELOPE_DOMAIN: &[u8] = b"dvf-keystore-share-envelope-v1";
+
+#[derive(Debug)]
+pub enum Error {
+    /// The recipient is not a valid ed25519 public key.
+    InvalidRecipient(NodePublicKey),
+    /// The envelope is sealed to another operator than the one opening it.
+    WrongRecipient {
+        recipient: NodePublicKey,
+        operator: NodePublicKey,
+    },
+    /// The ephemeral public key is malformed or of low order.
+    InvalidEphemeralKey,
+    UnableToDecryptKeystore(KeyStoreError),
+    UnableToBuildKeystore(KeyStoreError),
+    UnableToEncryptShare,
+    /// The share or its metadata were tampered with, or the envelope is not for this key.
+    UnableToDecryptShare,
+    InvalidSecretKey(bls::Error),
+    /// The key share does not match the commitments published with the split.
+    InvalidShare(DvfError),
+    UnableToReadEnvelope(io::Error),
+    UnableToDecodeEnvelope(serde_json::Error),
+    UnableToSaveEnvelope(io::Error),
+    UnableToEncodeEnvelope(serde_json::Error),
+}
+
+/// A key share sealed to the hotstuff key `recipient` of its operator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeystoreShareEnvelope {
+    pub recipient: NodePublicKey,
+    /// The X25519 public key of the sender, used for this envelope only.
+    #[serde(with = "eth2_serde_utils::hex_vec")]
+    pub ephemeral_public_key: Vec<u8>,
+    /// The encrypted secret key share, followed by its authentication tag.
+    #[serde(with = "eth2_serde_utils::hex_vec")]
+    pub ciphertext: Vec<u8>,
+    pub master_public_key: PublicKey,
+    pub master_id: u64,
+    pub share_id: u64,
+    /// Feldman commitments to the coefficients of the sharing polynomial (in G1).
+    #[serde(default)]
+    pub commitments: Vec<PublicKey>,
+}
+
+impl KeystoreShareEnvelope {
+    /// Decrypt `keystore_share` with `password` and seal its key share to `recipient`.
+    pub fn seal(keystore_share: &KeystoreShare, password: &[u8], recipient: &NodePublicKey) -> Result<Self, Error> {
+        let keypair = keystore_share
+            .keystore
+            .decrypt_keypair(password)
+            .map_err(Error::UnableToDecryptKeystore)?;
+        let (ephemeral_public_key, shared_secret) =
+            x25519_production_agreement(recipient).ok_or(Error::InvalidRecipient(*recipient))?;
+
+        let mut envelope = Self {
+            recipient: *recipient,
+            ephemeral_public_key: ephemeral_public_key.to_vec(),
+            ciphertext: vec![],
+            master_public_key: keystore_share.master_public_key.clone(),
+            master_id: keystore_share.master_id,
+            share_id: keystore_share.share_id,
+            commitments: keystore_share.commitments.clone(),
+        };
+        let sk = keypair.sk.serialize();
+        let payload = Payload {
+            msg: sk.as_bytes(),
+            aad: &envelope.associated_data(),
+        };
+        envelope.ciphertext = envelope
+            .cipher(&shared_secret)
+            .encrypt(&Nonce::default(), payload)
+            .map_err(|_| Error::UnableToEncryptShare)?;
+        Ok(envelope)
+    }
+
+    /// Open the envelope with the node `secret` of its recipient, returning the key share once
+    /// checked against the commitments.
+    pub fn open(&self, secret: &Secret) -> Result<Keypair, Error> {
+        if secret.name != self.recipient {
+            return Err(Error::WrongRecipient {
+                recipient: self.recipient,
+                operator: secret.name,
+            });
+        }
+        let ephemeral_public_key: [u8; 32] = self
+            .ephemeral_public_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::InvalidEphemeralKey)?;
+        let shared_secret = secret.secret.diffie_hellman(&ephemeral_public_key);
+        if shared_secret == [0; 32] {
+            return Err(Error::InvalidEphemeralKey);
+        }
+
+        let payload = Payload {
+            msg: self.ciphertext.as_slice(),
+            aad: &self.associated_data(),
+        };
+        let plain_text = PlainText::from(
+            self.cipher(&shared_secret)
+                .decrypt(&Nonce::default(), payload)
+                .map_err(|_| Error::UnableToDecryptShare)?,
+        );
+        let sk = SecretKey::deserialize(plain_text.as_bytes()).map_err(Error::InvalidSecretKey)?;
+        let keypair = Keypair::from_components(sk.public_key(), sk);
+        self.verify_share(&keypair)?;
+        Ok(keypair)
+    }
+
+    /// Consumes `self`, returning a keystore share of the opened `keypair` encrypted with
+    /// `password`.
+    pub fn into_keystore_share(self, keypair: &Keypair, password: &[u8]) -> Result<KeystoreShare, Error> {
+        let keystore = KeystoreBuilder::new(keypair, password, "".into())
+            .and_then(|builder| builder.build())
+            .map_err(Error::UnableToBuildKeystore)?;
+        Ok(KeystoreShare::new(
+            keystore,
+            self.master_public_key,
+            self.master_id,
+            self.share_id,
+            self.commitments,
+        ))
+    }
+
+    /// Write `self` to a new JSON file at `path`.
+    pub fn to_json_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(Error::UnableToSaveEnvelope)?;
+        serde_json::to_writer_pretty(file, self).map_err(Error::UnableToEncodeEnvelope)
+    }
+
+    /// Instantiates `self` by reading a JSON file at `path`.
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::UnableToReadEnvelope)?;
+        serde_json::from_reader(file).map_err(Error::UnableToDecodeEnvelope)
+    }
+
+    /// Same check as `KeystoreShare::verify_share`, before any keystore exists.
+    fn verify_share(&self, keypair: &Keypair) -> Result<(), Error> {
+        if self.commitments.is_empty() {
+            return Ok(());
+        }
+        if self.commitments[0] != self.master_public_key {
+            return Err(Error::InvalidShare(DvfError::InvalidKeyShare { id: self.share_id }));
+        }
+        ThresholdSignature::verify_share(self.share_id, &keypair.sk, &self.commitments).map_err(Error::InvalidShare)
+    }
+
+    /// The key of an envelope is bound to both X25519 public keys of the agreement.
+    fn cipher(&self, shared_secret: &[u8; 32]) -> ChaCha20Poly1305 {
+        let mut preimage = ENVELOPE_DOMAIN.to_vec();
+        preimage.extend_from_slice(shared_secret);
+        preimage.extend_from_slice(&self.ephemeral_public_key);
+        preimage.extend_from_slice(&self.recipient.0);
+        let key = PlainText::from(hash(&preimage));
+        preimage.iter_mut().for_each(|x| *x = 0);
+        ChaCha20Poly1305::new(Key::from_slice(key.as_bytes()))
+    }
+
+    /// Everything but the share itself, which is authenticated along with it.
+    fn associated_data(&self) -> Vec<u8> {
+        let mut aad = self.master_public_key.serialize().to_vec();
+        aad.extend_from_slice(&self.master_id.to_le_bytes());
+        aad.extend_from_slice(&self.share_id.to_le_bytes());
+        for commitment in &self.commitments {
+            aad.extend_from_slice(&commitment.serialize());
+        }
+        aad
+    }
+}
@@ -7,7 +7,9 @@ use std::fs::File;
 use std::io::{Read, Write};
 use serde::{Deserialize, Serialize};
 use eth2_keystore::{Keystore, Uuid};
-use bls::PublicKey;
+use bls::{Keypair, PublicKey, SecretKey};
+use crate::crypto::ThresholdSignature;
+use crate::utils::error::DvfError;
 
 use eth2_keystore::Error as KeyStoreError;
 
@@ -21,19 +23,61 @@ pub struct KeystoreShare {
     pub master_public_key: PublicKey,
     pub master_id: u64,
     pub share_id: u64,
+    /// Feldman commitments to the coefficients of the sharing polynomial (in G1).
+    #[serde(default)]
+    pub commitments: Vec<PublicKey>,
 }
 
 impl KeystoreShare {
 
-    pub fn new(keystore: Keystore, master_public_key: PublicKey, master_id: u64, share_id: u64) -> Self {
+    pub fn new(keystore: Keystore, master_public_key: PublicKey, master_id: u64, share_id: u64, commitments: Vec<PublicKey>) -> Self {
         Self {
             keystore,
             master_public_key,
             master_id,
             share_id,
+            commitments,
         }
     }
 
+    /// Check the decrypted key share `keypair` against the commitments stored in `self`.
+    ///
+    /// Shares created without commitments cannot be checked and are accepted as is.
+    pub fn verify_share(&self, keypair: &Keypair) -> Result<(), DvfError> {
+        if self.commitments.is_empty() {
+            return Ok(());
+        }
+        if self.commitments[0] != self.master_public_key {
+            return Err(DvfError::InvalidKeyShare { id: self.share_id });
+        }
+        ThresholdSignature::verify_share(self.share_id, &keypair.sk, &self.commitments)
+    }
+
+    /// Reconstruct the master keypair of a distributed validator from at least `threshold` keystore
+    /// shares and their decrypted keypairs. For emergency exits only.
+    ///
+    /// Fails if the shares belong to different validators or do not interpolate to the master
+    /// public key.
+    pub fn recover_master_keypair(shares: &[(&KeystoreShare, &Keypair)]) -> Result<Keypair, DvfError> {
+        let master_public_key = match shares.first() {
+            Some((share, _)) => &share.master_public_key,
+            None => return Err(DvfError::KeyRecoveryFailed),
+        };
+        if shares.iter().any(|(share, _)| &share.master_public_key != master_public_key) {
+            return Err(DvfError::KeyRecoveryFailed);
+        }
+        for (share, keypair) in shares {
+            share.verify_share(keypair)?;
+        }
+        let sks: Vec<&SecretKey> = shares.iter().map(|(_, keypair)| &keypair.sk).collect();
+        let ids: Vec<u64> = shares.iter().map(|(share, _)| share.share_id).collect();
+        let sk = ThresholdSignature::recover_secret(&sks[..], &ids[..])?;
+        if &sk.public_key() != master_public_key {
+            return Err(DvfError::KeyRecoveryFailed);
+        }
+        Ok(Keypair::from_components(sk.public_key(), sk))
+    }
+
     /// Returns the UUID for the keystore.
     pub fn uuid(&self) -> &Uuid {
         self.keystore.uuid()
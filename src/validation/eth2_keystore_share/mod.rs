@@ -1 +1,2 @@
 pub mod keystore_share;
+pub mod envelope;
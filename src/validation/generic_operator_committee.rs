@@ -3,14 +3,26 @@ use crate::utils::error::DvfError;
 use crate::{DvfCommitteeIndex, DvfOperatorTsid};
 use crate::validation::operator::{TOperator};
 use types::{Hash256, Signature, PublicKey};
-use parking_lot::{RwLock};
+use tokio::sync::{RwLock};
+use std::time::Duration;
+use async_trait::async_trait;
 
 /// Operator committee for a validator. 
-pub trait TOperatorCommittee {
+///
+/// Committees are driven on the tokio executor. Signing is cancel-safe: dropping the future
+/// returned by `sign` or `sign_proposal` abandons the duty, and the committee can be used for
+/// the next one.
+#[async_trait]
+pub trait TOperatorCommittee: Send + Sync {
     fn new(id: DvfCommitteeIndex, voting_public_key: PublicKey, t: usize) -> Self;
     fn add_operator(&mut self, id: DvfOperatorTsid, operator: Arc<RwLock<dyn TOperator>>); 
-    fn consensus(&self, msg: Hash256) -> bool;
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError>;
+    /// Agree on `proposal` with the other operators. Returns whether it committed, i.e., whether
+    /// operators started signing `signing_root`, within `timeout`.
+    async fn consensus(&self, proposal: &[u8], signing_root: Hash256, timeout: Duration) -> bool;
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError>;
+    /// Agree on `proposal` (an encoded `DutyProposal`) with the other operators, then sign `signing_root`.
+    /// Fails if the signature is not ready within `timeout`.
+    async fn sign_proposal(&self, proposal: &[u8], signing_root: Hash256, timeout: Duration) -> Result<Signature, DvfError>;
     fn threshold(&self) -> usize;
 }
 
@@ -37,9 +49,12 @@ where
         self.cmt.threshold()
     }
 
-    pub fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
-        self.cmt.sign(msg)
+    pub async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
+        self.cmt.sign(msg).await
     }
-}
 
+    pub async fn sign_proposal(&self, proposal: &[u8], signing_root: Hash256, timeout: Duration) -> Result<Signature, DvfError> {
+        self.cmt.sign_proposal(proposal, signing_root, timeout).await
+    }
+}
 
@@ -0,0 +1,226 @@
+//! Implementation of the operator committee management API.
+use crate::validation::initialized_validators::{Error, InitializedValidators};
+use crate::validation::operator_committee_definitions::OperatorCommitteeDefinition;
+use crate::validation::operator_committees;
+use crate::validation::validator_dir::share_bundle::ShareBundle;
+use crate::validation::ValidatorStore;
+use crate::DvfCommitteeIndex;
+use eth2::lighthouse_vc::types as api_types;
+use serde::{Deserialize, Serialize};
+use slog::{info, Logger};
+use slot_clock::SlotClock;
+use std::sync::{Arc, Weak};
+use tokio::runtime::Runtime;
+use types::{EthSpec, PublicKey};
+use warp::Rejection;
+use warp_utils::reject::{custom_bad_request, custom_not_found, custom_server_error};
+
+/// Response item of `GET lighthouse/committees`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorCommitteeData {
+    #[serde(flatten)]
+    pub definition: OperatorCommitteeDefinition,
+    /// The enabled validators attached to the committee.
+    pub validators: Vec<PublicKey>,
+}
+
+pub fn list<T: SlotClock + 'static, E: EthSpec>(
+    validator_store: Arc<ValidatorStore<T, E>>,
+) -> Vec<OperatorCommitteeData> {
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let initialized_validators = initialized_validators_rwlock.read();
+
+    initialized_validators
+        .committee_definitions()
+        .iter()
+        .map(|def| committee_data(&initialized_validators, def))
+        .collect()
+}
+
+pub fn get<T: SlotClock + 'static, E: EthSpec>(
+    committee_index: DvfCommitteeIndex,
+    validator_store: Arc<ValidatorStore<T, E>>,
+) -> Result<OperatorCommitteeData, Rejection> {
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let initialized_validators = initialized_validators_rwlock.read();
+
+    initialized_validators
+        .committee_definitions()
+        .iter()
+        .find(|def| def.committee_index == committee_index)
+        .map(|def| committee_data(&initialized_validators, def))
+        .ok_or_else(|| custom_not_found(format!("no committee for index {}", committee_index)))
+}
+
+pub fn create<T: SlotClock + 'static, E: EthSpec>(
+    def: OperatorCommitteeDefinition,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    log: Logger,
+) -> Result<(), Rejection> {
+    let committee_index = def.committee_index;
+    validator_store
+        .initialized_validators()
+        .write()
+        .add_committee_definition(def)
+        .map_err(to_rejection)?;
+
+    info!(log, "Added operator committee via HTTP API"; "committee_index" => committee_index);
+    Ok(())
+}
+
+pub fn update<T: SlotClock + 'static, E: EthSpec>(
+    committee_index: DvfCommitteeIndex,
+    def: OperatorCommitteeDefinition,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    runtime: Weak<Runtime>,
+    log: Logger,
+) -> Result<(), Rejection> {
+    if def.committee_index != committee_index {
+        return Err(custom_bad_request(format!(
+            "committee index in the body ({}) does not match the path ({})",
+            def.committee_index, committee_index
+        )));
+    }
+    let runtime = runtime
+        .upgrade()
+        .ok_or_else(|| custom_server_error("Runtime shutdown".into()))?;
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let mut initialized_validators = initialized_validators_rwlock.write();
+    runtime
+        .block_on(initialized_validators.update_committee_definition(def))
+        .map_err(to_rejection)?;
+
+    info!(log, "Updated operator committee via HTTP API"; "committee_index" => committee_index);
+    Ok(())
+}
+
+pub fn delete<T: SlotClock + 'static, E: EthSpec>(
+    committee_index: DvfCommitteeIndex,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    log: Logger,
+) -> Result<(), Rejection> {
+    validator_store
+        .initialized_validators()
+        .write()
+        .remove_committee_definition(committee_index)
+        .map_err(to_rejection)?;
+
+    info!(log, "Removed operator committee via HTTP API"; "committee_index" => committee_index);
+    Ok(())
+}
+
+pub fn attach<T: SlotClock + 'static, E: EthSpec>(
+    committee_index: DvfCommitteeIndex,
+    voting_public_key: PublicKey,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    runtime: Weak<Runtime>,
+    log: Logger,
+) -> Result<(), Rejection> {
+    let runtime = runtime
+        .upgrade()
+        .ok_or_else(|| custom_server_error("Runtime shutdown".into()))?;
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let mut initialized_validators = initialized_validators_rwlock.write();
+    runtime
+        .block_on(initialized_validators.attach_validator(&voting_public_key, committee_index))
+        .map_err(to_rejection)?;
+
+    info!(
+        log,
+        "Attached validator to operator committee via HTTP API";
+        "committee_index" => committee_index,
+        "voting_pubkey" => ?voting_public_key,
+    );
+    Ok(())
+}
+
+pub fn detach<T: SlotClock + 'static, E: EthSpec>(
+    committee_index: DvfCommitteeIndex,
+    voting_public_key: PublicKey,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    runtime: Weak<Runtime>,
+    log: Logger,
+) -> Result<(), Rejection> {
+    let runtime = runtime
+        .upgrade()
+        .ok_or_else(|| custom_server_error("Runtime shutdown".into()))?;
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let mut initialized_validators = initialized_validators_rwlock.write();
+    runtime
+        .block_on(initialized_validators.detach_validator(&voting_public_key, committee_index))
+        .map_err(to_rejection)?;
+
+    info!(
+        log,
+        "Detached validator from operator committee via HTTP API";
+        "committee_index" => committee_index,
+        "voting_pubkey" => ?voting_public_key,
+    );
+    Ok(())
+}
+
+pub fn import<T: SlotClock + 'static, E: EthSpec>(
+    bundle: ShareBundle,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    runtime: Weak<Runtime>,
+    log: Logger,
+) -> Result<api_types::ValidatorData, Rejection> {
+    let runtime = runtime
+        .upgrade()
+        .ok_or_else(|| custom_server_error("Runtime shutdown".into()))?;
+    let committee_index = bundle.committee_definition.committee_index;
+    let operator_id = bundle.operator_id();
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let mut initialized_validators = initialized_validators_rwlock.write();
+    let voting_public_key = runtime
+        .block_on(initialized_validators.import_share_bundle(bundle))
+        .map_err(to_rejection)?;
+
+    info!(
+        log,
+        "Imported distributed validator via HTTP API";
+        "committee_index" => committee_index,
+        "operator_id" => operator_id,
+        "voting_pubkey" => ?voting_public_key,
+    );
+    Ok(api_types::ValidatorData {
+        enabled: true,
+        description: "".into(),
+        voting_pubkey: voting_public_key.into(),
+    })
+}
+
+fn committee_data(
+    initialized_validators: &InitializedValidators,
+    def: &OperatorCommitteeDefinition,
+) -> OperatorCommitteeData {
+    OperatorCommitteeData {
+        definition: def.clone(),
+        validators: initialized_validators.committee_validators(def.committee_index),
+    }
+}
+
+/// Unknown committees and validators are not found, invalid requests are bad requests and
+/// anything else is a server error.
+fn to_rejection(e: Error) -> Rejection {
+    use operator_committees::Error as CommitteeError;
+    match e {
+        Error::UnknownCommittee(_)
+        | Error::ValidatorNotInitialized(_)
+        | Error::UnableToUpdateCommittees(CommitteeError::UnknownCommittee(_)) => {
+            custom_not_found(format!("{:?}", e))
+        }
+        Error::CommitteeInUse(_)
+        | Error::NotADistributedValidator
+        | Error::NotAttachedToCommittee(_)
+        | Error::CommitteeKeyMismatch(_)
+        | Error::DuplicatePublicKey
+        | Error::InvalidKeystoreShare(_)
+        | Error::UnableToOpenEnvelope(_)
+        | Error::UnableToUpdateCommittees(CommitteeError::DuplicateCommittee(_))
+        | Error::UnableToUpdateCommittees(CommitteeError::InvalidDefinition(..)) => {
+            custom_bad_request(format!("{:?}", e))
+        }
+        e => custom_server_error(format!("{:?}", e)),
+    }
+}
@@ -1,16 +1,57 @@
 use crate::validation::ValidatorStore;
 use crate::validation::account_utils::validator_definitions::{SigningDefinition, ValidatorDefinition};
 use crate::validation::account_utils::{
+    eth2_keystore::Keystore,
     eth2_wallet::{bip39::Mnemonic, WalletBuilder},
     random_mnemonic, random_password, ZeroizeString,
 };
+use crate::validation::validator_dir::share_bundle::{Error as ShareBundleError, ShareBundle, ShareBundleBuilder};
+use crate::DvfCommitteeIndex;
+use ::crypto::PublicKey as NodePublicKey;
 use eth2::lighthouse_vc::types::{self as api_types};
+use serde::{Deserialize, Serialize};
 use slot_clock::SlotClock;
+use std::net::SocketAddr;
 use std::path::Path;
 use types::ChainSpec;
-use types::EthSpec;
+use types::{EthSpec, Hash256, PublicKey};
 use validator_dir::Builder as ValidatorDirBuilder;
 
+/// Request of `POST lighthouse/validators/distributed`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DistributedValidatorRequest {
+    pub committee_index: DvfCommitteeIndex,
+    pub threshold: u64,
+    /// One operator per address, in the order of their ids.
+    pub socket_addresses: Vec<SocketAddr>,
+    /// The node public key of each operator, in the same order. The share of the operator is
+    /// sealed to it.
+    pub node_public_keys: Vec<NodePublicKey>,
+    /// The keystore of the validator to distribute, a random key is generated if there is none.
+    #[serde(default)]
+    pub keystore: Option<Keystore>,
+    #[serde(default)]
+    pub password: Option<ZeroizeString>,
+    #[serde(default)]
+    pub deposit: Option<DistributedDepositRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributedDepositRequest {
+    pub withdrawal_credentials: Hash256,
+    #[serde(with = "eth2_serde_utils::quoted_u64")]
+    pub deposit_gwei: u64,
+}
+
+/// Response of `POST lighthouse/validators/distributed`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DistributedValidatorData {
+    pub voting_pubkey: PublicKey,
+    pub eth1_deposit_tx_data: Option<String>,
+    /// One bundle per operator, which only the operator can open.
+    pub operators: Vec<ShareBundle>,
+}
+
 /// Create some validator EIP-2335 keystores and store them on disk. Then, enroll the validators in
 /// this validator client.
 ///
@@ -193,3 +234,70 @@ pub async fn create_validators_web3signer<T: 'static + SlotClock, E: EthSpec>(
 
     Ok(())
 }
+
+/// Split the key of a new distributed validator into one `ShareBundle` per operator of its
+/// committee.
+///
+/// ## Detail
+///
+/// Nothing is stored on disk nor enrolled in this validator client: the bundles are returned, to
+/// be handed to their operators. Each share is sealed to the node key of its operator, so no
+/// password is returned.
+pub fn create_distributed_validator(
+    request: &DistributedValidatorRequest,
+    spec: &ChainSpec,
+) -> Result<DistributedValidatorData, warp::Rejection> {
+    let mut builder = ShareBundleBuilder::new(
+        request.committee_index,
+        request.threshold,
+        request.socket_addresses.clone(),
+        request.node_public_keys.clone(),
+    );
+    match (&request.keystore, &request.password) {
+        (Some(keystore), Some(password)) => {
+            let keypair = keystore.decrypt_keypair(password.as_ref()).map_err(|e| {
+                warp_utils::reject::custom_bad_request(format!("invalid keystore: {:?}", e))
+            })?;
+            builder = builder.voting_keypair(keypair);
+        }
+        (None, None) => (),
+        _ => {
+            return Err(warp_utils::reject::custom_bad_request(
+                "keystore and password must be supplied together".to_string(),
+            ))
+        }
+    }
+    if let Some(deposit) = &request.deposit {
+        builder = builder.create_deposit_data(deposit.withdrawal_credentials, deposit.deposit_gwei, spec);
+    }
+
+    let operators = builder.build().map_err(|e| match e {
+        ShareBundleError::InvalidCommittee(e) => {
+            warp_utils::reject::custom_bad_request(format!("invalid committee: {:?}", e))
+        }
+        e => warp_utils::reject::custom_server_error(format!(
+            "unable to create share bundles: {:?}",
+            e
+        )),
+    })?;
+
+    let first = operators.first().ok_or_else(|| {
+        warp_utils::reject::custom_server_error("no share bundle created".to_string())
+    })?;
+    let voting_pubkey = first.committee_definition.voting_public_key.clone();
+    let eth1_deposit_tx_data = first
+        .eth1_deposit_tx_data()
+        .map_err(|e| {
+            warp_utils::reject::custom_server_error(format!(
+                "unable to encode deposit data: {:?}",
+                e
+            ))
+        })?
+        .map(|rlp| eth2_serde_utils::hex::encode(&rlp));
+
+    Ok(DistributedValidatorData {
+        voting_pubkey,
+        eth1_deposit_tx_data,
+        operators,
+    })
+}
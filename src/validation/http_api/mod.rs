@@ -1,11 +1,18 @@
 mod api_secret;
+mod committees;
 mod create_validator;
 mod keystores;
 mod tests;
 
 use crate::validation::ValidatorStore;
+use crate::validation::operator_committee_definitions::OperatorCommitteeDefinition;
+use crate::validation::validator_dir::share_bundle::ShareBundle;
+use crate::validation::operator_performance::{OperatorPerformance, OPERATOR_PERFORMANCES};
+use crate::{DvfCommitteeIndex, DvfOperatorTsid};
 use crate::validation::account_utils::mnemonic_from_phrase;
-use create_validator::{create_validators_mnemonic, create_validators_web3signer};
+use create_validator::{
+    create_distributed_validator, create_validators_mnemonic, create_validators_web3signer,
+};
 use eth2::lighthouse_vc::{
     std_types::AuthResponse,
     types::{self as api_types, PublicKey, PublicKeyBytes},
@@ -32,6 +39,10 @@ use warp::{
 };
 
 pub use api_secret::ApiSecret;
+pub use committees::OperatorCommitteeData;
+pub use create_validator::{
+    DistributedDepositRequest, DistributedValidatorData, DistributedValidatorRequest,
+};
 
 #[derive(Debug)]
 pub enum Error {
@@ -65,6 +76,16 @@ pub struct Context<T: SlotClock, E: EthSpec> {
     pub _phantom: PhantomData<E>,
 }
 
+/// Response item of `GET lighthouse/committees/{committee_index}/operators`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorPerformanceData {
+    pub id: DvfOperatorTsid,
+    pub score: Option<f64>,
+    pub mean_latency_ms: Option<u64>,
+    #[serde(flatten)]
+    pub performance: OperatorPerformance,
+}
+
 /// Configuration for the HTTP server.
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -110,7 +131,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
     // Configure CORS.
     let cors_builder = {
         let builder = warp::cors()
-            .allow_methods(vec!["GET", "POST", "PATCH", "DELETE"])
+            .allow_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
             .allow_headers(vec!["Content-Type", "Authorization"]);
 
         warp_utils::cors::set_builder_origins(
@@ -277,6 +298,179 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
             },
         );
 
+    // GET lighthouse/committees/{committee_index}/operators
+    let get_lighthouse_committees_operators = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::param::<DvfCommitteeIndex>())
+        .and(warp::path("operators"))
+        .and(warp::path::end())
+        .and(signer.clone())
+        .and_then(|committee_index: DvfCommitteeIndex, signer| {
+            blocking_signed_json_task(signer, move || {
+                let operators = OPERATOR_PERFORMANCES
+                    .read()
+                    .committee(committee_index)
+                    .map(|operators| {
+                        operators
+                            .iter()
+                            .map(|(id, performance)| OperatorPerformanceData {
+                                id: *id,
+                                score: performance.score(),
+                                mean_latency_ms: performance.mean_latency_ms(),
+                                performance: performance.clone(),
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .ok_or_else(|| {
+                        warp_utils::reject::custom_not_found(format!(
+                            "no operator performance for committee {}",
+                            committee_index
+                        ))
+                    })?;
+
+                Ok(api_types::GenericResponse::from(operators))
+            })
+        });
+
+    // GET lighthouse/committees
+    let get_lighthouse_committees = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and_then(|validator_store: Arc<ValidatorStore<T, E>>, signer| {
+            blocking_signed_json_task(signer, move || {
+                Ok(api_types::GenericResponse::from(committees::list(validator_store)))
+            })
+        });
+
+    // GET lighthouse/committees/{committee_index}
+    let get_lighthouse_committees_index = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::param::<DvfCommitteeIndex>())
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and_then(
+            |committee_index: DvfCommitteeIndex,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer| {
+                blocking_signed_json_task(signer, move || {
+                    committees::get(committee_index, validator_store)
+                        .map(api_types::GenericResponse::from)
+                })
+            },
+        );
+
+    // POST lighthouse/committees
+    let post_committees = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |body: OperatorCommitteeDefinition,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer,
+             log| {
+                blocking_signed_json_task(signer, move || {
+                    committees::create(body, validator_store, log)
+                })
+            },
+        );
+
+    // PUT lighthouse/committees/{committee_index}
+    let put_committees = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::param::<DvfCommitteeIndex>())
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and(runtime_filter.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |committee_index: DvfCommitteeIndex,
+             body: OperatorCommitteeDefinition,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer,
+             runtime: Weak<Runtime>,
+             log| {
+                blocking_signed_json_task(signer, move || {
+                    committees::update(committee_index, body, validator_store, runtime, log)
+                })
+            },
+        );
+
+    // DELETE lighthouse/committees/{committee_index}
+    let delete_committees = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::param::<DvfCommitteeIndex>())
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |committee_index: DvfCommitteeIndex,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer,
+             log| {
+                blocking_signed_json_task(signer, move || {
+                    committees::delete(committee_index, validator_store, log)
+                })
+            },
+        );
+
+    // POST lighthouse/committees/{committee_index}/validators/{validator_pubkey}
+    let post_committees_validators = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::param::<DvfCommitteeIndex>())
+        .and(warp::path("validators"))
+        .and(warp::path::param::<PublicKey>())
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and(runtime_filter.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |committee_index: DvfCommitteeIndex,
+             validator_pubkey: PublicKey,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer,
+             runtime: Weak<Runtime>,
+             log| {
+                blocking_signed_json_task(signer, move || {
+                    committees::attach(committee_index, validator_pubkey, validator_store, runtime, log)
+                })
+            },
+        );
+
+    // DELETE lighthouse/committees/{committee_index}/validators/{validator_pubkey}
+    let delete_committees_validators = warp::path("lighthouse")
+        .and(warp::path("committees"))
+        .and(warp::path::param::<DvfCommitteeIndex>())
+        .and(warp::path("validators"))
+        .and(warp::path::param::<PublicKey>())
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and(runtime_filter.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |committee_index: DvfCommitteeIndex,
+             validator_pubkey: PublicKey,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer,
+             runtime: Weak<Runtime>,
+             log| {
+                blocking_signed_json_task(signer, move || {
+                    committees::detach(committee_index, validator_pubkey, validator_store, runtime, log)
+                })
+            },
+        );
+
     // POST lighthouse/validators/
     let post_validators = warp::path("lighthouse")
         .and(warp::path("validators"))
@@ -327,7 +521,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
         .and(warp::body::json())
         .and(validator_dir_filter.clone())
         .and(validator_store_filter.clone())
-        .and(spec_filter)
+        .and(spec_filter.clone())
         .and(signer.clone())
         .and(runtime_filter.clone())
         .and_then(
@@ -443,6 +637,54 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
             },
         );
 
+    // POST lighthouse/validators/distributed
+    let post_validators_distributed = warp::path("lighthouse")
+        .and(warp::path("validators"))
+        .and(warp::path("distributed"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(spec_filter)
+        .and(signer.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |body: DistributedValidatorRequest, spec: Arc<ChainSpec>, signer, log: Logger| {
+                blocking_signed_json_task(signer, move || {
+                    let data = create_distributed_validator(&body, &spec)?;
+                    info!(
+                        log,
+                        "Created distributed validator via HTTP API";
+                        "voting_pubkey" => ?data.voting_pubkey,
+                        "committee_index" => body.committee_index,
+                    );
+                    Ok(api_types::GenericResponse::from(data))
+                })
+            },
+        );
+
+    // POST lighthouse/validators/distributed/import
+    let post_validators_distributed_import = warp::path("lighthouse")
+        .and(warp::path("validators"))
+        .and(warp::path("distributed"))
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and(runtime_filter.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |body: ShareBundle,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             signer,
+             runtime: Weak<Runtime>,
+             log| {
+                blocking_signed_json_task(signer, move || {
+                    committees::import(body, validator_store, runtime, log)
+                        .map(api_types::GenericResponse::from)
+                })
+            },
+        );
+
     // POST lighthouse/validators/web3signer
     let post_validators_web3signer = warp::path("lighthouse")
         .and(warp::path("validators"))
@@ -588,6 +830,9 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                         .or(get_lighthouse_spec)
                         .or(get_lighthouse_validators)
                         .or(get_lighthouse_validators_pubkey)
+                        .or(get_lighthouse_committees)
+                        .or(get_lighthouse_committees_index)
+                        .or(get_lighthouse_committees_operators)
                         .or(get_std_keystores),
                 )
                 .or(warp::post().and(
@@ -595,10 +840,19 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                         .or(post_validators_keystore)
                         .or(post_validators_mnemonic)
                         .or(post_validators_web3signer)
+                        .or(post_validators_distributed)
+                        .or(post_validators_distributed_import)
+                        .or(post_committees)
+                        .or(post_committees_validators)
                         .or(post_std_keystores),
                 ))
+                .or(warp::put().and(put_committees))
                 .or(warp::patch().and(patch_validators))
-                .or(warp::delete().and(delete_std_keystores)),
+                .or(warp::delete().and(
+                    delete_committees
+                        .or(delete_committees_validators)
+                        .or(delete_std_keystores),
+                )),
         )
         // The auth route is the only route that is allowed to be accessed without the API token.
         .or(warp::get().and(get_auth))
@@ -21,6 +21,7 @@ use eth2::{
 };
 use eth2_keystore::KeystoreBuilder;
 use logging::test_logger;
+use node::config::Secret;
 use parking_lot::RwLock;
 use sensitive_url::SensitiveUrl;
 use slashing_protection::{SlashingDatabase, SLASHING_PROTECTION_FILENAME};
@@ -71,6 +72,7 @@ impl ApiTester {
         let initialized_validators = InitializedValidators::from_definitions(
             validator_defs,
             validator_dir.path().into(),
+            Arc::new(Secret::new()),
             log.clone(),
         )
         .await
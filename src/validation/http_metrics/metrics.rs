@@ -32,6 +32,10 @@ pub const UPDATE_PROPOSERS: &str = "update_proposers";
 pub const SUBSCRIPTIONS: &str = "subscriptions";
 pub const LOCAL_KEYSTORE: &str = "local_keystore";
 pub const WEB3SIGNER: &str = "web3signer";
+pub const DISTRIBUTED_KEYSTORE: &str = "distributed_keystore";
+pub const OPERATOR_SIGNED: &str = "signed";
+pub const OPERATOR_MISSED: &str = "missed";
+pub const OPERATOR_INVALID_SHARE: &str = "invalid_share";
 
 pub use lighthouse_metrics::*;
 
@@ -148,6 +152,29 @@ lazy_static::lazy_static! {
         "Duration to obtain a signature",
         &["type"]
     );
+    /*
+     * Operator Metrics
+     */
+    pub static ref OPERATOR_DUTIES_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_operator_duties_total",
+        "Total count of duties per committee operator, by outcome",
+        &["committee", "operator", "outcome"]
+    );
+    pub static ref OPERATOR_SHARE_LATENCY: Result<HistogramVec> = try_create_histogram_vec(
+        "vc_operator_share_latency_seconds",
+        "Duration for a committee operator to send its signature share",
+        &["committee", "operator"]
+    );
+    pub static ref OPERATOR_SCORE: Result<GaugeVec> = try_create_float_gauge_vec(
+        "vc_operator_score",
+        "Fraction of the recent duties for which a committee operator sent a valid share",
+        &["committee", "operator"]
+    );
+    pub static ref COMMITTEE_UNATTRIBUTED_SHARES_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_committee_unattributed_shares_total",
+        "Total count of shares received for a committee that do not verify against the key of the operator they claim",
+        &["committee"]
+    );
 }
 
 pub fn gather_prometheus_metrics<T: EthSpec>(
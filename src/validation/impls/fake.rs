@@ -3,12 +3,18 @@ use std::sync::{Arc};
 use crate::validation::{
     generic_operator_committee::{TOperatorCommittee},
     operator::{TOperator},
+    operator_performance::{self, DutyOutcome},
 };
 use crate::crypto::ThresholdSignature;
 use crate::{DvfCommitteeIndex, DvfOperatorTsid};
 use crate::utils::error::DvfError;
 use bls::{Hash256, Signature, PublicKey};
-use parking_lot::{RwLock};
+use tokio::sync::{RwLock};
+use tokio::time;
+use std::time::{Duration, Instant};
+use futures::stream::{FuturesUnordered, StreamExt};
+use async_trait::async_trait;
+use log::{debug, warn};
 /// Provides the externally-facing operator committee type.
 pub mod types {
     pub use super::FakeOperatorCommittee as OperatorCommittee;
@@ -22,6 +28,7 @@ pub struct FakeOperatorCommittee {
     threshold_: usize,
 }
 
+#[async_trait]
 impl TOperatorCommittee for FakeOperatorCommittee {
     fn new(id: DvfCommitteeIndex, voting_public_key: PublicKey, t: usize) -> Self {
         Self {
@@ -33,50 +40,135 @@ impl TOperatorCommittee for FakeOperatorCommittee {
     }
 
     fn add_operator(&mut self, id: DvfOperatorTsid, operator: Arc<RwLock<dyn TOperator>>) {
-        self.operators.write().insert(id, operator);
+        self.operators.get_mut().insert(id, operator);
     }
 
     fn threshold(&self) -> usize {
         self.threshold_
     }
 
-    fn consensus(&self, msg: Hash256) -> bool {
+    async fn consensus(&self, _proposal: &[u8], _signing_root: Hash256, _timeout: Duration) -> bool {
         return true;
     }
 
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
-        println!("<<<<<<<[Duty]: {:02x?}>>>>>>", msg);
-        println!("<<<<<<<[Committee Sign]>>>>>>");
-        println!("<<<<<<<[Start Consensus]>>>>>>");
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
+        self.threshold_sign(msg, None, None).await
+    }
+
+    async fn sign_proposal(&self, proposal: &[u8], signing_root: Hash256, timeout: Duration) -> Result<Signature, DvfError> {
+        // Remote operators check the proposal and compute the root to sign on their own.
+        self.threshold_sign(signing_root, Some(proposal), Some(timeout)).await
+    }
+}
+
+impl FakeOperatorCommittee {
+    /// Ask all operators for their share of `msg`, along with the proposal it is the root of, if
+    /// any, and aggregate the first `threshold()` valid ones. Operators that did not answer within
+    /// `timeout`, if any, are given up on.
+    async fn threshold_sign(&self, msg: Hash256, proposal: Option<&[u8]>, timeout: Option<Duration>) -> Result<Signature, DvfError> {
+        debug!("Committee {} signs duty {:?}", self.id, msg);
         // Run consensus protocol 
-        let status = self.consensus(msg);
+        let status = self.consensus(proposal.unwrap_or_else(|| msg.as_bytes()), msg, Duration::ZERO).await;
         if !status {
             return Err(DvfError::ConsensusFailure);
         }
-        println!("<<<<<<<[Consensus Succeeds]>>>>>>");
         
         // If consensus is achieved, aggregate the valid signatures
-        let operators = self.operators.read();
-        let ids: Vec<DvfOperatorTsid> = operators.keys().map(|k| *k).collect();
-        let operators: Vec<&Arc<RwLock<dyn TOperator>>> = ids.iter().map(|k| operators.get(&k).unwrap()).collect(); 
-        let mut pks: Vec<PublicKey> = operators.iter().map(|x| x.read().public_key()).collect();
-        let mut pk_refs: Vec<&PublicKey> = pks.iter().map(|x| x).collect();
+        let operators = self.operators.read().await;
+        // Ask all operators at once, so that a slow one does not delay the others.
+        let start = Instant::now();
+        let mut requests: FuturesUnordered<_> = operators.iter().map(|(id, op)| async move {
+            let request = async {
+                let op = op.read().await;
+                let sig = match proposal {
+                    Some(proposal) => op.sign_proposal(proposal, msg).await,
+                    None => op.sign(msg).await,
+                }?;
+                Ok::<_, DvfError>((op.public_key(), sig))
+            };
+            let result = match timeout {
+                Some(timeout) => time::timeout(timeout, request).await.unwrap_or(Err(DvfError::OperatorTimeout { id: *id })),
+                None => request.await,
+            };
+            (*id, result, start.elapsed())
+        })
+        .collect();
 
-        let mut sigs: Vec<Signature> = Vec::<_>::new();
-        for op in operators {
-            match op.read().sign(msg) {
-                Ok(sig) => sigs.push(sig),
-                Err(_) => {} 
+        let threshold_sig = ThresholdSignature::new(self.threshold());
+        // Shares of distinct operators, verified all at once when there are `threshold()` of them.
+        let mut batch: Vec<Share> = Vec::new();
+        let mut invalid: Vec<DvfOperatorTsid> = Vec::new();
+        let mut timed_out = false;
+        while let Some((id, result, latency)) = requests.next().await {
+            match result {
+                Ok((pk, sig)) => batch.push((id, pk, sig, latency)),
+                Err(e) => {
+                    warn!("Operator {} of committee {} failed to sign: {:?}", id, self.id, e);
+                    timed_out |= e == DvfError::OperatorTimeout { id };
+                    operator_performance::record(self.id, id, DutyOutcome::Missed, None);
+                    continue;
+                }
+            }
+            if batch.len() < self.threshold() {
+                continue;
+            }
+            let (ids, pks, sigs) = share_batch(&batch);
+            match threshold_sig.threshold_aggregate_with_invalid_ids(&sigs[..], &pks[..], &ids[..], msg) {
+                Ok((sig, _)) => {
+                    self.record_signed(&batch);
+                    return Ok(sig);
+                }
+                // Wait for other operators to replace the invalid shares.
+                Err(DvfError::InsufficientValidSignatures { invalid: found, .. }) => {
+                    self.reject(&mut batch, &found);
+                    invalid.extend(found);
+                }
+                Err(e) => return Err(e),
             }
         }
-        let sigs: Vec<&Signature> = sigs.iter().map(|x| x).collect();
-        println!("<<<<<<<[Got {} signatures]>>>>>>", sigs.len());
 
-        let threshold_sig = ThresholdSignature::new(self.threshold());
-        
-        let sig = threshold_sig.threshold_aggregate(&sigs[..], &pk_refs[..], &ids[..], msg);
+        // Too few operators answered: only count the valid shares.
+        let (ids, pks, sigs) = share_batch(&batch);
+        let found = ThresholdSignature::invalid_share_ids(&sigs[..], &pks[..], &ids[..], msg);
+        self.reject(&mut batch, &found);
+        self.record_signed(&batch);
+        invalid.extend(found);
+        let (got, expected) = (batch.len(), self.threshold());
+        Err(if !invalid.is_empty() {
+            DvfError::InsufficientValidSignatures { got, expected, invalid }
+        } else if timed_out {
+            DvfError::SigningDeadlineExceeded { got, expected }
+        } else {
+            DvfError::InsufficientSignatures { got, expected }
+        })
+    }
 
-        println!("<<<<<<<[Threshold Committee Signing Succeeds]>>>>>>");
-        sig
+    /// Drop the shares of the operators `invalid` from `batch`, and keep track of them.
+    fn reject(&self, batch: &mut Vec<Share>, invalid: &[DvfOperatorTsid]) {
+        batch.retain(|(id, _, _, latency)| {
+            if invalid.contains(id) {
+                operator_performance::record(self.id, *id, DutyOutcome::InvalidShare, Some(*latency));
+                return false;
+            }
+            true
+        });
     }
+
+    /// Keep track of the operators whose valid share is in `batch`.
+    fn record_signed(&self, batch: &[Share]) {
+        for (id, _, _, latency) in batch {
+            operator_performance::record(self.id, *id, DutyOutcome::Signed, Some(*latency));
+        }
+    }
+}
+
+/// A share of an operator, with its public key and the time it took to arrive.
+type Share = (DvfOperatorTsid, PublicKey, Signature, Duration);
+
+/// The ids, public keys and signatures of the shares of `batch`.
+fn share_batch(batch: &[Share]) -> (Vec<DvfOperatorTsid>, Vec<&PublicKey>, Vec<&Signature>) {
+    let ids = batch.iter().map(|(id, _, _, _)| *id).collect();
+    let pks = batch.iter().map(|(_, pk, _, _)| pk).collect();
+    let sigs = batch.iter().map(|(_, _, sig, _)| sig).collect();
+    (ids, pks, sigs)
 }
@@ -2,26 +2,32 @@ use std::collections::HashMap;
 use std::sync::{Arc};
 use crate::validation::{
     generic_operator_committee::{TOperatorCommittee},
-    operator::{TOperator, HotStuffOperator},
+    operator::{TOperator, HotStuffOperator, ShareCollection},
+    duty_proposal::DutyProposalHeader,
+    operator_performance::{self, DutyOutcome},
+    http_metrics::metrics,
 };
-use crate::crypto::ThresholdSignature;
 use crate::DvfOperatorTsid;
 use crate::DvfCommitteeIndex;
 use crate::utils::error::DvfError;
 use bls::{Hash256, Signature, PublicKey};
-use parking_lot::{RwLock};
-use std::net::SocketAddr;
-use tokio::net::TcpStream;
-use futures::executor::block_on;
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
-use bytes::Bytes;
-use futures::sink::SinkExt as _;
+use tokio::sync::{RwLock};
+use tokio::time::Instant;
+use std::time::Duration;
+use async_trait::async_trait;
+use log::{debug, error, warn};
 /// Provides the externally-facing operator committee type.
 pub mod types {
     pub use super::HotstuffOperatorCommittee as OperatorCommittee;
 }
 
-/// Hotstuff operator committee whose consensus protocol is dummy 
+/// Longest time an operator waits for the operator before it in the rotation to get a duty committed.
+pub const PROPOSER_TAKEOVER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Hotstuff operator committee: duties are agreed on by the HotStuff instance of the validator,
+/// which this committee reaches through its local `HotStuffOperator`. The other operators of the
+/// committee only matter for their ids: their shares are broadcast by the `CommittedDutySigner`
+/// each of them runs on the committed duties.
 pub struct HotstuffOperatorCommittee {
     id: DvfCommitteeIndex,
     voting_public_key: PublicKey,
@@ -29,6 +35,67 @@ pub struct HotstuffOperatorCommittee {
     threshold_: usize,
 }
 
+impl HotstuffOperatorCommittee {
+    /// Position of operator `id` in the proposer rotation of the duties of `slot`: the operator of
+    /// rank 0 proposes the duty, the next ones take over in turn if it does not commit.
+    pub fn proposer_rank(&self, id: DvfOperatorTsid, slot: u64, members: &[DvfOperatorTsid]) -> Option<usize> {
+        let mut members = members.to_vec();
+        members.sort_unstable();
+        let position = members.iter().position(|x| *x == id)?;
+        let leader = (slot.wrapping_add(self.id) % members.len() as u64) as usize;
+        Some((position + members.len() - leader) % members.len())
+    }
+
+    /// Record who sent a valid share for the duty. Operators who did not are only blamed if the
+    /// threshold was not reached, as the committee stops listening once it is.
+    ///
+    /// Shares reach us through the HotStuff network, which does not authenticate their sender: a
+    /// share that does not verify against the key of the id it claims may come from anyone, so it
+    /// is only counted for the committee, never against the operator.
+    async fn record_performance(&self, collection: &ShareCollection) {
+        for (share, latency) in &collection.shares {
+            operator_performance::record(self.id, share.id, DutyOutcome::Signed, Some(*latency));
+        }
+        if !collection.invalid.is_empty() {
+            metrics::inc_counter_vec_by(
+                &metrics::COMMITTEE_UNATTRIBUTED_SHARES_TOTAL,
+                &[&self.id.to_string()],
+                collection.invalid.len() as u64,
+            );
+        }
+        if collection.error.is_some() {
+            for id in self.operators.read().await.keys() {
+                if !collection.shares.iter().any(|(share, _)| share.id == *id) {
+                    operator_performance::record(self.id, *id, DutyOutcome::Missed, None);
+                }
+            }
+        }
+    }
+
+    /// The public keys of the operators of this committee, by id: the only keys shares are checked
+    /// against.
+    async fn operator_keys(&self) -> HashMap<DvfOperatorTsid, PublicKey> {
+        let operators = self.operators.read().await;
+        let mut keys = HashMap::with_capacity(operators.len());
+        for (id, operator) in operators.iter() {
+            keys.insert(*id, operator.read().await.public_key());
+        }
+        keys
+    }
+
+    /// The operator of this committee that takes part in the HotStuff instance, with its id.
+    async fn local_operator(&self) -> Option<(DvfOperatorTsid, Arc<RwLock<dyn TOperator>>)> {
+        let operators = self.operators.read().await;
+        for (id, operator) in operators.iter() {
+            if operator.read().await.is::<HotStuffOperator>() {
+                return Some((*id, operator.clone()));
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
 impl TOperatorCommittee for HotstuffOperatorCommittee {
     fn new(id: DvfCommitteeIndex, voting_public_key: PublicKey, t: usize) -> Self {
         Self {
@@ -41,59 +108,83 @@ impl TOperatorCommittee for HotstuffOperatorCommittee {
 
     fn add_operator(&mut self, id: DvfOperatorTsid, operator: Arc<RwLock<dyn TOperator>>) {
 
-        self.operators.write().insert(id, operator);
+        self.operators.get_mut().insert(id, operator);
     }
 
     fn threshold(&self) -> usize {
         self.threshold_
     }
 
-    fn consensus(&self, msg: Hash256) -> bool {
-        // send network request
-        let operators = self.operators.write();
-        let ids : Vec<DvfOperatorTsid> = operators.keys().map(|k| *k).collect();
-        let id = ids[0];
-        let mut operator = operators.get(&id).unwrap().write();
-        let hotstuff_operator = operator.downcast_mut::<HotStuffOperator>().unwrap(); 
-        println!("propose msg by id { }", id);
-        if id == 1 {
-            println!("propose msg by id { }", id);
-            block_on(hotstuff_operator.propose(msg));
+    async fn consensus(&self, proposal: &[u8], signing_root: Hash256, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let slot = match DutyProposalHeader::from_bytes(proposal) {
+            Ok(header) => header.slot.as_u64(),
+            Err(e) => {
+                warn!("Invalid proposal for committee {}: {:?}", self.id, e);
+                return false;
+            }
+        };
+        let (id, operator) = match self.local_operator().await {
+            Some(local) => local,
+            None => {
+                error!("No HotStuff operator in committee {}", self.id);
+                return false;
+            }
+        };
+        let keys = self.operator_keys().await;
+        let members: Vec<DvfOperatorTsid> = keys.keys().copied().collect();
+        let rank = self.proposer_rank(id, slot, &members).expect("Local operator is a member");
+        let mut operator = operator.write().await;
+        let hotstuff_operator = operator.downcast_mut::<HotStuffOperator>().unwrap();
+
+        // Everyone before us in the rotation gets a turn, and the last one still leaves as much
+        // time as the others to the committee.
+        let turn = PROPOSER_TAKEOVER_TIMEOUT.min(timeout / members.len() as u32);
+        if rank > 0 && hotstuff_operator.wait_commit(signing_root, &keys, turn * rank as u32).await {
+            return true;
         }
-            
-        return true;
+        debug!("Operator {} proposes duty {:?} of committee {} (rank {})", id, signing_root, self.id, rank);
+        hotstuff_operator.propose(proposal).await;
+        hotstuff_operator.wait_commit(signing_root, &keys, deadline.saturating_duration_since(Instant::now())).await
     }
 
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
-        // Run consensus protocol 
-        println!("<<<<<<<[Duty]: {:02x?}>>>>>>", msg);
-        println!("<<<<<<<[Committee Sign]>>>>>>");
-        println!("<<<<<<<[Start Consensus]>>>>>>");
-        let status = self.consensus(msg);
-        println!("<<<<<<<[Consensus Succeeds]>>>>>>");
+    async fn sign(&self, _msg: Hash256) -> Result<Signature, DvfError> {
+        // Operators refuse to sign a bare root they cannot check, see `sign_proposal`.
+        Err(DvfError::UntypedDuty)
+    }
 
-        let operators = self.operators.write();
-        let ids : Vec<DvfOperatorTsid> = operators.keys().map(|k| *k).collect();
+    async fn sign_proposal(&self, proposal: &[u8], signing_root: Hash256, timeout: Duration) -> Result<Signature, DvfError> {
+        let start = Instant::now();
+        let deadline = start + timeout;
+        // Run consensus protocol
+        debug!("Committee {} starts consensus on duty {:?}", self.id, signing_root);
+        if !self.consensus(proposal, signing_root, timeout).await {
+            return Err(DvfError::ConsensusFailure);
+        }
+        debug!("Committee {} committed duty {:?}", self.id, signing_root);
 
-        let id :u64 = ids[0];
-        let mut operator = operators.get(&id).unwrap().write();
+        let keys = self.operator_keys().await;
+        let (_, operator) = self.local_operator().await.ok_or(DvfError::ConsensusFailure)?;
+        let mut operator = operator.write().await;
         let hotstuff_operator = operator.downcast_mut::<HotStuffOperator>().unwrap();
-        // wait 
-        let signatures = block_on(hotstuff_operator.wait_signature());
-
-        let ids : Vec<DvfOperatorTsid> = signatures.iter().map(|x| x.id as u64).collect();
-        // ids.push(id);
-        println!("{:?}", &ids);
-        let pks: Vec<&PublicKey> = signatures.iter().map(|x| &x.from).collect();
-        // pks.push(self_pk);
-        let sigs: Vec<&Signature> = signatures.iter().map(|x| &x.signature).collect();
-        println!("<<<<<<<[Got {} signatures]>>>>>>", sigs.len());
-        // sigs.push(&self_signature);
-        let threshold_sig = ThresholdSignature::new(self.threshold());
-        let sig = threshold_sig.threshold_aggregate(&sigs[..], &pks[..], &ids[..], msg);
-        println!("<<<<<<<[Threshold Committee Signing Succeeds]>>>>>>");
-        sig
+        // Operators sign the root they computed from the committed proposal: only the shares of
+        // those who agree with us count.
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let collection = hotstuff_operator.collect_shares(signing_root, &keys, self.threshold(), start, timeout).await;
+        drop(operator);
+        self.record_performance(&collection).await;
+        // The shares were aggregated with the registered keys, never with the ones claimed by the
+        // senders.
+        match (collection.signature, collection.error) {
+            (Some(sig), _) => {
+                let ids: Vec<DvfOperatorTsid> = collection.shares.iter().map(|(share, _)| share.id).collect();
+                debug!("Committee {} signed duty {:?} with the shares of operators {:?}", self.id, signing_root, ids);
+                Ok(sig)
+            }
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(DvfError::InsufficientSignatures { got: collection.shares.len(), expected: self.threshold() }),
+        }
     }
 
-    
+
 }
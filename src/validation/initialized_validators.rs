@@ -10,14 +10,19 @@
 
 use crate::validation::signing_method::SigningMethod;
 use crate::validation::account_utils::{
-    read_password, read_password_from_user,
+    random_password_string, read_password, read_password_from_user,
     validator_definitions::{
         self, SigningDefinition, ValidatorDefinition, ValidatorDefinitions, CONFIG_FILENAME,
     },
     ZeroizeString,
 };
-use crate::validation::operator_committee_definitions::{self, OperatorCommitteeDefinition};
+use crate::validation::operator_committee_definitions::{self, OperatorCommitteeDefinition, OperatorCommitteeDefinitions};
+use crate::validation::operator_committees::{self, CommitteeChanges, OperatorCommittees, Reconciliation};
+use crate::validation::operator_service::KeyShare;
 use crate::validation::eth2_keystore_share::keystore_share::{KeystoreShare};
+use crate::validation::eth2_keystore_share::envelope::{self, KeystoreShareEnvelope};
+use crate::validation::validator_dir::share_builder::{ShareBuilder, VOTING_KEYSTORE_SHARE_FILE};
+use crate::validation::validator_dir::share_bundle::ShareBundle;
 use eth2::lighthouse_vc::std_types::DeleteKeystoreStatus;
 use eth2_keystore::Keystore;
 use lighthouse_metrics::set_gauge;
@@ -33,16 +38,17 @@ use std::sync::Arc;
 use std::time::Duration;
 use types::{Address, Graffiti, Keypair, PublicKey, PublicKeyBytes};
 use url::{ParseError, Url};
-use validator_dir::Builder as ValidatorDirBuilder;
+use validator_dir::{Builder as ValidatorDirBuilder, BuilderError};
 
 use crate::validation::key_cache;
 use crate::validation::key_cache::KeyCache;
 
 use crate::crypto::{ThresholdSignature};
+use crate::utils::error::DvfError;
 use crate::validation::{
-    operator::LocalOperator,
-    OperatorCommittee};
-use parking_lot::{RwLock};
+    operator::LocalOperator};
+use tokio::sync::{RwLock};
+use node::config::Secret;
 /// Default timeout for a request to a remote signer for a signature.
 ///
 /// Set to 12 seconds since that's the duration of a slot. A remote signer that cannot sign within
@@ -103,6 +109,29 @@ pub enum Error {
     NoCommitteeDefinition,
     UnableToParseCommitteeDefinition(operator_committee_definitions::Error),
     UnableToBuildCommittee,
+    /// There was an error updating the operator committees or their on-disk definitions.
+    UnableToUpdateCommittees(operator_committees::Error),
+    /// The committee index does not exist in the set of operator committees.
+    UnknownCommittee(u64),
+    /// The committee definition found next to a key share is for another committee.
+    CommitteeIndexMismatch {
+        definition: u64,
+        keystore_share: u64,
+    },
+    /// Unable to remove a committee because enabled validators are still attached to it.
+    CommitteeInUse(u64),
+    /// Unable to attach or detach a validator which does not sign with a distributed keystore.
+    NotADistributedValidator,
+    /// The validator is not attached to the committee it is detached from.
+    NotAttachedToCommittee(u64),
+    /// The validator is attached to a committee which signs for another validator.
+    CommitteeKeyMismatch(u64),
+    /// The key share does not match the commitments published with the split.
+    InvalidKeystoreShare(DvfError),
+    /// The keystore share envelope could not be opened with the node secret.
+    UnableToOpenEnvelope(envelope::Error),
+    /// There was an error writing the keystore share of an envelope to the validators dir.
+    UnableToBuildValidatorDir(BuilderError),
     /// Unable to apply an action to a validator because it is using a remote signer.
     InvalidActionOnRemoteValidator,
 }
@@ -177,7 +206,7 @@ impl InitializedValidator {
         def: ValidatorDefinition,
         key_cache: &mut KeyCache,
         key_stores: &mut HashMap<PathBuf, Keystore>,
-        committee_cache: &mut HashMap<u64, Arc<RwLock<OperatorCommittee>>>,
+        committees: &mut OperatorCommittees,
     ) -> Result<Self, Error> {
         if !def.enabled {
             return Err(Error::UnableToInitializeDisabledValidator);
@@ -354,6 +383,11 @@ impl InitializedValidator {
                     keypair
                 };
 
+                // Reject shares that do not match the commitments published by the dealer.
+                open_keystore_share(&voting_keystore_share_path)?
+                    .verify_share(&voting_keypair)
+                    .map_err(Error::InvalidKeystoreShare)?;
+
                 // [TODO] Zico: revisit this check, because def.voting_public_key is the master public key, while voting_keypair is a share.
                 //if voting_keypair.pk != def.voting_public_key {
                     //return Err(Error::VotingPublicKeyMismatch {
@@ -370,27 +404,43 @@ impl InitializedValidator {
                 // [TODO] Zico: End copying from LocalKeystore. Find a way to reuse.
 
 
-                let committee_def_path = operator_committee_definition_path.ok_or(Error::NoCommitteeDefinition)?;
-                let committee_def = OperatorCommitteeDefinition::from_file(committee_def_path).map_err(Error::UnableToParseCommitteeDefinition)?; 
-
-                let committee = match committee_cache.entry(committee_def.committee_index) {
-                    Vacant(entry) => {
-                        let committee = OperatorCommittee::from_definition(committee_def.clone()).map_err(|_| Error::UnableToBuildCommittee)?;
-                        let committee = Arc::new(RwLock::new(committee));
-                        entry.insert(committee)
-                    },
-                    Occupied(entry) => entry.into_mut(),
+                // A committee defined next to the key share is registered the first time it is used,
+                // afterwards the registry is the source of truth.
+                let committee = match committees.committee(operator_committee_index) {
+                    Some(committee) => committee,
+                    None => {
+                        let committee_def_path = operator_committee_definition_path.ok_or(Error::NoCommitteeDefinition)?;
+                        let committee_def = OperatorCommitteeDefinition::from_file(committee_def_path).map_err(Error::UnableToParseCommitteeDefinition)?; 
+                        if committee_def.committee_index != operator_committee_index {
+                            return Err(Error::CommitteeIndexMismatch {
+                                definition: committee_def.committee_index,
+                                keystore_share: operator_committee_index,
+                            });
+                        }
+                        committees.add_definition(committee_def).map_err(Error::UnableToUpdateCommittees)?
+                    }
                 };
-
+                let voting_public_key = committees
+                    .definition(operator_committee_index)
+                    .ok_or(Error::UnknownCommittee(operator_committee_index))?
+                    .voting_public_key
+                    .clone();
+
+                let voting_keypair = Arc::new(voting_keypair);
+                // The other operators ask for the same share through our `OperatorService`.
+                committees.add_key_share(operator_committee_index, KeyShare {
+                    keypair: voting_keypair.clone(),
+                    validator: voting_public_key.compress(),
+                });
                 let local_operator = Arc::new(
-                    RwLock::new(LocalOperator::new(operator_id, Arc::new(voting_keypair))));  
-                committee.write().add_operator(operator_id, local_operator);
+                    RwLock::new(LocalOperator::new(operator_id, voting_keypair)));  
+                committee.write().await.add_operator(operator_id, local_operator);
 
 
                 SigningMethod::DistributedKeystore {
                     voting_keystore_lockfile: <_>::default(),
-                    voting_public_key: committee_def.voting_public_key,
-                    operator_committee: committee.clone(),
+                    voting_public_key,
+                    operator_committee: committee,
                 }
             }
         };
@@ -429,6 +479,31 @@ fn build_web3_signer_url(base_url: &str, voting_public_key: &PublicKey) -> Resul
     Url::parse(base_url)?.join(&format!("api/v1/eth2/sign/{}", voting_public_key))
 }
 
+/// Warn if the threshold of the committee does not tolerate Byzantine operators.
+fn warn_if_byzantine_unsafe(log: &Logger, def: &OperatorCommitteeDefinition) {
+    if !def.is_byzantine_safe() {
+        warn!(
+            log,
+            "Operator committee threshold is not Byzantine fault tolerant";
+            "committee_index" => def.committee_index,
+            "threshold" => def.threshold,
+            "total" => def.total,
+            "advice" => "use n >= 3f + 1 operators and a threshold between 2f + 1 and n - f",
+        );
+    }
+}
+
+/// Returns the operator committee of a distributed validator.
+fn committee_index_of(def: &ValidatorDefinition) -> Option<u64> {
+    match def.signing_definition {
+        SigningDefinition::DistributedKeystore {
+            operator_committee_index,
+            ..
+        } => Some(operator_committee_index),
+        _ => None,
+    }
+}
+
 /// Try to unlock `keystore` at `keystore_path` by prompting the user via `stdin`.
 fn unlock_keystore_via_stdin_password(
     keystore: &Keystore,
@@ -479,24 +554,39 @@ pub struct InitializedValidators {
     validators_dir: PathBuf,
     /// The canonical set of validators.
     validators: HashMap<PublicKeyBytes, InitializedValidator>,
+    /// The operator committees of the distributed validators, saved into `self.validators_dir`.
+    committees: OperatorCommittees,
     /// For logging via `slog`.
     log: Logger,
 }
 
 impl InitializedValidators {
     /// Instantiates `Self`, initializing all validators in `definitions`.
+    ///
+    /// `identity` is the hotstuff key of this operator, with which it asks the other operators of
+    /// its committees for their shares.
     pub async fn from_definitions(
         definitions: ValidatorDefinitions,
         validators_dir: PathBuf,
+        identity: Arc<Secret>,
         log: Logger,
     ) -> Result<Self, Error> {
+        let committee_definitions = OperatorCommitteeDefinitions::open_or_create(&validators_dir)
+            .map_err(Error::UnableToParseCommitteeDefinition)?;
+        let committees = OperatorCommittees::from_definitions(committee_definitions, validators_dir.clone(), identity)
+            .await
+            .map_err(Error::UnableToUpdateCommittees)?;
         let mut this = Self {
             validators_dir,
             definitions,
             validators: HashMap::default(),
+            committees,
             log,
         };
         this.update_validators().await?;
+        for def in this.committees.definitions() {
+            warn_if_byzantine_unsafe(&this.log, def);
+        }
         Ok(this)
     }
 
@@ -566,6 +656,112 @@ impl InitializedValidators {
         Ok(())
     }
 
+    /// Open a keystore share `envelope` sealed to this operator with its node `secret`, and add
+    /// the share as a distributed validator of the committee it was split for.
+    ///
+    /// The committee must already be defined. The share is saved into `self.validators_dir` under
+    /// a new random password, stored in the validator definition.
+    pub async fn import_keystore_share_envelope(
+        &mut self,
+        envelope: KeystoreShareEnvelope,
+        secret: &Secret,
+    ) -> Result<PublicKey, Error> {
+        if self
+            .definitions
+            .as_slice()
+            .iter()
+            .any(|def| def.voting_public_key == envelope.master_public_key)
+        {
+            return Err(Error::DuplicatePublicKey);
+        }
+        let keypair = envelope.open(secret).map_err(Error::UnableToOpenEnvelope)?;
+        let (committee_index, operator_id) = (envelope.master_id, envelope.share_id);
+        let committee = self
+            .committee_definitions()
+            .iter()
+            .find(|def| def.committee_index == committee_index)
+            .ok_or(Error::UnknownCommittee(committee_index))?;
+        if committee.voting_public_key != envelope.master_public_key {
+            return Err(Error::InvalidKeystoreShare(DvfError::InvalidKeyShare { id: operator_id }));
+        }
+        let in_committee = committee
+            .ids
+            .iter()
+            .zip(committee.public_keys.iter())
+            .any(|(id, pk)| *id == operator_id && *pk == keypair.pk);
+        if !in_committee {
+            return Err(Error::InvalidKeystoreShare(DvfError::InvalidKeyShare { id: operator_id }));
+        }
+
+        let password = random_password_string();
+        let keystore_share = envelope
+            .into_keystore_share(&keypair, password.as_ref())
+            .map_err(Error::UnableToOpenEnvelope)?;
+        let voting_public_key = keystore_share.master_public_key.clone();
+        let validator_dir = ShareBuilder::new(self.validators_dir.clone())
+            .voting_keystore_share(keystore_share, password.as_ref())
+            .build()
+            .map_err(Error::UnableToBuildValidatorDir)?;
+        let voting_keystore_share_path = validator_dir.dir().join(VOTING_KEYSTORE_SHARE_FILE);
+        // Drop the validator dir (and its lockfile) before the share is initialized from it.
+        drop(validator_dir);
+
+        self.add_definition(ValidatorDefinition {
+            enabled: true,
+            voting_public_key: voting_public_key.clone(),
+            description: "".into(),
+            graffiti: None,
+            suggested_fee_recipient: None,
+            signing_definition: SigningDefinition::DistributedKeystore {
+                voting_keystore_share_path,
+                voting_keystore_share_password_path: None,
+                voting_keystore_share_password: Some(password),
+                operator_committee_definition_path: None,
+                operator_committee_index: committee_index,
+                operator_id,
+            },
+        })
+        .await?;
+
+        info!(
+            self.log,
+            "Imported keystore share envelope";
+            "committee_index" => committee_index,
+            "operator_id" => operator_id,
+            "voting_pubkey" => ?voting_public_key,
+        );
+        Ok(voting_public_key)
+    }
+
+    /// Import the share of this operator from a `bundle`, opening its envelope with the node key of
+    /// this operator.
+    ///
+    /// The committee of the bundle is added if it is unknown, and removed again if the share cannot
+    /// be imported. A known committee is not replaced: the share must belong to it.
+    pub async fn import_share_bundle(&mut self, bundle: ShareBundle) -> Result<PublicKey, Error> {
+        let committee_index = bundle.committee_definition.committee_index;
+        let added = self.committees.definition(committee_index).is_none();
+        if added {
+            self.add_committee_definition(bundle.committee_definition)?;
+        }
+        let identity = self.committees.identity();
+        let result = self
+            .import_keystore_share_envelope(bundle.keystore_share_envelope, &identity)
+            .await;
+        if let (Err(e), true) = (&result, added) {
+            if let Err(remove_error) = self.remove_committee_definition(committee_index) {
+                warn!(
+                    self.log,
+                    "Unable to remove the committee of a failed import";
+                    "committee_index" => committee_index,
+                    "import_error" => ?e,
+                    "error" => ?remove_error,
+                );
+            }
+        }
+        result
+    }
+
     /// Delete the validator definition and keystore for `pubkey`.
     ///
     /// The delete is carried out in stages so that the filesystem is never left in an inconsistent
@@ -719,6 +915,191 @@ impl InitializedValidators {
         Ok(())
     }
 
+    /// Returns the operator committees of the distributed validators.
+    pub fn committees(&self) -> &OperatorCommittees {
+        &self.committees
+    }
+
+    /// Returns a slice of all defined operator committees.
+    pub fn committee_definitions(&self) -> &[OperatorCommitteeDefinition] {
+        self.committees.definitions()
+    }
+
+    /// Returns the voting public keys of the enabled validators attached to committee
+    /// `committee_index`.
+    pub fn committee_validators(&self, committee_index: u64) -> Vec<PublicKey> {
+        self.definitions
+            .as_slice()
+            .iter()
+            .filter(|def| def.enabled && committee_index_of(def) == Some(committee_index))
+            .map(|def| def.voting_public_key.clone())
+            .collect()
+    }
+
+    /// Add an operator committee to `self`, overwriting the on-disk committee definitions.
+    pub fn add_committee_definition(&mut self, def: OperatorCommitteeDefinition) -> Result<(), Error> {
+        warn_if_byzantine_unsafe(&self.log, &def);
+        self.committees
+            .add_definition(def)
+            .map(|_| ())
+            .map_err(Error::UnableToUpdateCommittees)
+    }
+
+    /// Replace the definition of an operator committee, overwriting the on-disk committee
+    /// definitions.
+    ///
+    /// The validators attached to the committee are initialized again, so that they sign with the
+    /// new committee right away.
+    pub async fn update_committee_definition(&mut self, def: OperatorCommitteeDefinition) -> Result<(), Error> {
+        let committee_index = def.committee_index;
+        warn_if_byzantine_unsafe(&self.log, &def);
+        self.committees
+            .update_definition(def)
+            .await
+            .map_err(Error::UnableToUpdateCommittees)?;
+        let attached = self.committee_validators(committee_index);
+        self.reinitialize_validators(&attached).await
+    }
+
+    /// Remove an operator committee from `self`, overwriting the on-disk committee definitions.
+    ///
+    /// Fails if enabled validators are still attached to the committee.
+    pub fn remove_committee_definition(&mut self, committee_index: u64) -> Result<(), Error> {
+        if !self.committee_validators(committee_index).is_empty() {
+            return Err(Error::CommitteeInUse(committee_index));
+        }
+        self.committees
+            .remove_definition(committee_index)
+            .map_err(Error::UnableToUpdateCommittees)
+    }
+
+    /// Attach the distributed validator `voting_public_key` to committee `committee_index` and
+    /// enable it.
+    ///
+    /// Saves the `ValidatorDefinitions` to file.
+    pub async fn attach_validator(&mut self, voting_public_key: &PublicKey, committee_index: u64) -> Result<(), Error> {
+        match self.committees.definition(committee_index) {
+            Some(def) if def.voting_public_key == *voting_public_key => (),
+            Some(_) => return Err(Error::CommitteeKeyMismatch(committee_index)),
+            None => return Err(Error::UnknownCommittee(committee_index)),
+        }
+        let def = self
+            .definitions
+            .as_mut_slice()
+            .iter_mut()
+            .find(|def| def.voting_public_key == *voting_public_key)
+            .ok_or_else(|| Error::ValidatorNotInitialized(voting_public_key.clone()))?;
+        match &mut def.signing_definition {
+            SigningDefinition::DistributedKeystore {
+                operator_committee_definition_path,
+                operator_committee_index,
+                ..
+            } => {
+                // The committee is now resolved through the registry only.
+                *operator_committee_definition_path = None;
+                *operator_committee_index = committee_index;
+            }
+            _ => return Err(Error::NotADistributedValidator),
+        }
+        def.enabled = true;
+
+        self.reinitialize_validators(&[voting_public_key.clone()]).await?;
+
+        self.definitions
+            .save(&self.validators_dir)
+            .map_err(Error::UnableToSaveDefinitions)?;
+
+        Ok(())
+    }
+
+    /// Detach the distributed validator `voting_public_key` from committee `committee_index`. The
+    /// validator is disabled, as it cannot sign without a committee.
+    ///
+    /// Saves the `ValidatorDefinitions` to file.
+    pub async fn detach_validator(&mut self, voting_public_key: &PublicKey, committee_index: u64) -> Result<(), Error> {
+        let def = self
+            .definitions
+            .as_slice()
+            .iter()
+            .find(|def| def.voting_public_key == *voting_public_key)
+            .ok_or_else(|| Error::ValidatorNotInitialized(voting_public_key.clone()))?;
+        match committee_index_of(def) {
+            Some(index) if index == committee_index => (),
+            Some(_) => return Err(Error::NotAttachedToCommittee(committee_index)),
+            None => return Err(Error::NotADistributedValidator),
+        }
+
+        self.set_validator_status(voting_public_key, false).await
+    }
+
+    /// Read the operator committee definitions from disk and build the committees that changed.
+    ///
+    /// The rebuilt committees must be swapped in before the reload is applied with
+    /// `apply_committee_reload`. Swapping waits for the duties in flight: do not hold the lock on
+    /// `self` meanwhile.
+    pub fn prepare_committee_reload(&self) -> Result<Reconciliation, Error> {
+        let definitions = OperatorCommitteeDefinitions::open(&self.validators_dir)
+            .map_err(Error::UnableToParseCommitteeDefinition)?;
+        self.committees
+            .prepare(definitions)
+            .map_err(Error::UnableToUpdateCommittees)
+    }
+
+    /// Apply a reload prepared by `prepare_committee_reload`, once its committees are swapped in.
+    ///
+    /// The validators of retired committees, and of rebuilt committees this operator no longer
+    /// holds a key share of, are disabled as they cannot sign anymore, and the
+    /// `ValidatorDefinitions` are saved to file.
+    pub fn apply_committee_reload(&mut self, reconciliation: Reconciliation) -> Result<CommitteeChanges, Error> {
+        let changes = self
+            .committees
+            .apply(reconciliation)
+            .map_err(Error::UnableToUpdateCommittees)?;
+        for committee_index in changes.added.iter().chain(changes.rebuilt.iter()) {
+            if let Some(def) = self.committees.definition(*committee_index) {
+                warn_if_byzantine_unsafe(&self.log, def);
+            }
+        }
+
+        let disabled: Vec<PublicKey> = changes
+            .retired
+            .iter()
+            .chain(changes.unserved.iter())
+            .flat_map(|committee_index| self.committee_validators(*committee_index))
+            .collect();
+        for def in self.definitions.as_mut_slice() {
+            if disabled.contains(&def.voting_public_key) {
+                def.enabled = false;
+                warn!(
+                    self.log,
+                    "Disabled validator of a committee it cannot sign with";
+                    "voting_pubkey" => format!("{:?}", def.voting_public_key),
+                    "committee_index" => committee_index_of(def),
+                );
+            }
+        }
+        for voting_public_key in &disabled {
+            self.validators.remove(&voting_public_key.compress());
+        }
+
+        if !disabled.is_empty() {
+            self.definitions
+                .save(&self.validators_dir)
+                .map_err(Error::UnableToSaveDefinitions)?;
+        }
+
+        Ok(changes)
+    }
+
+    /// Drop the initialized `voting_public_keys` and initialize them again from their definitions.
+    async fn reinitialize_validators(&mut self, voting_public_keys: &[PublicKey]) -> Result<(), Error> {
+        for voting_public_key in voting_public_keys {
+            // Dropping the validator releases its lockfile.
+            self.validators.remove(&voting_public_key.compress());
+        }
+        self.update_validators().await
+    }
+
     /// Tries to decrypt the key cache.
     ///
     /// Returns the decrypted cache if decryption was successful, or an error if a required password
@@ -827,7 +1208,6 @@ impl InitializedValidators {
     pub(crate) async fn update_validators(&mut self) -> Result<(), Error> {
         //use key cache if available
         let mut key_stores = HashMap::new();
-        let mut committee_cache = HashMap::new();
 
         // Create a lock file for the cache
         let key_cache_path = KeyCache::cache_file_path(&self.validators_dir);
@@ -861,7 +1241,7 @@ impl InitializedValidators {
                             def.clone(),
                             &mut key_cache,
                             &mut key_stores,
-                            &mut committee_cache,
+                            &mut self.committees,
                         )
                         .await
                         {
@@ -911,7 +1291,7 @@ impl InitializedValidators {
                             def.clone(),
                             &mut key_cache,
                             &mut key_stores,
-                            &mut committee_cache,
+                            &mut self.committees,
                         )
                         .await
                         {
@@ -960,7 +1340,7 @@ impl InitializedValidators {
                             def.clone(),
                             &mut key_cache,
                             &mut key_stores,
-                            &mut committee_cache,
+                            &mut self.committees,
                         )
                         .await
                         {
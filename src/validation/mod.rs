@@ -2,6 +2,7 @@ mod attestation_service;
 mod beacon_node_fallback;
 mod block_service;
 mod check_synced;
+mod committee_reloader;
 //mod cli;
 mod config;
 mod duties_service;
@@ -42,7 +43,11 @@ use duties_service::DutiesService;
 use environment::RuntimeContext;
 use eth2::{reqwest::ClientBuilder, BeaconNodeHttpClient, StatusCode, Timeouts};
 use http_api::ApiSecret;
+use committee_reloader::spawn_committee_reloader;
+use duty_proposal::DutyProposalChecker;
 use notifier::spawn_notifier;
+use operator_performance::{OperatorPerformances, OPERATOR_PERFORMANCES};
+use operator_service::{load_or_create_node_key, spawn_operator_service, NODE_KEY_FILENAME};
 use parking_lot::RwLock;
 use preparation_service::{PreparationService, PreparationServiceBuilder};
 use reqwest::Certificate;
@@ -91,6 +96,7 @@ pub struct ProductionValidatorClient<T: EthSpec> {
     doppelganger_service: Option<Arc<DoppelgangerService>>,
     preparation_service: PreparationService<SystemTimeSlotClock, T>,
     validator_store: Arc<ValidatorStore<SystemTimeSlotClock, T>>,
+    duty_proposal_checker: Arc<DutyProposalChecker<SystemTimeSlotClock, T>>,
     http_api_listen_addr: Option<SocketAddr>,
     config: Config,
 }
@@ -181,9 +187,17 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             );
         }
 
+        // The hotstuff key of this operator, with which it authenticates to the other operators.
+        let node_key_path = config
+            .node_key_path
+            .clone()
+            .unwrap_or_else(|| config.validator_dir.join(NODE_KEY_FILENAME));
+        let identity = Arc::new(load_or_create_node_key(&node_key_path)?);
+
         let validators = InitializedValidators::from_definitions(
             validator_defs,
             config.validator_dir.clone(),
+            identity,
             log.clone(),
         )
         .await
@@ -206,6 +220,16 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             );
         }
 
+        // Restore the history of the operators of the committees, if any.
+        match OperatorPerformances::open_or_default(&config.validator_dir) {
+            Ok(performances) => *OPERATOR_PERFORMANCES.write() = performances,
+            Err(e) => warn!(
+                log,
+                "Unable to load operator performance";
+                "error" => format!("{:?}", e)
+            ),
+        }
+
         // Initialize slashing protection.
         //
         // Create the slashing database if there are no validators, even if
@@ -351,6 +375,17 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             None
         };
 
+        // Checks the duties the other operators ask this operator to sign.
+        let duty_proposal_checker = Arc::new(
+            DutyProposalChecker::new(
+                context.eth2_config.spec.clone(),
+                genesis_validators_root,
+                slashing_protection.clone(),
+            )
+            .slot_clock(slot_clock.clone())
+            .beacon_nodes(beacon_nodes.clone()),
+        );
+
         let validator_store = Arc::new(ValidatorStore::new(
             validators,
             slashing_protection,
@@ -451,11 +486,19 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             doppelganger_service,
             preparation_service,
             validator_store,
+            duty_proposal_checker,
             config,
             http_api_listen_addr: None,
         })
     }
 
+    /// The checker of the duties this operator signs on behalf of its committees, sharing the
+    /// slashing protection database of the validator client. `CommittedDutySigner`s of the
+    /// HotStuff instances of the validators must use it.
+    pub fn duty_proposal_checker(&self) -> Arc<DutyProposalChecker<SystemTimeSlotClock, T>> {
+        self.duty_proposal_checker.clone()
+    }
+
     pub fn start_service(&mut self) -> Result<(), String> {
         // We use `SLOTS_PER_EPOCH` as the capacity of the block notification channel, because
         // we don't except notifications to be delayed by more than a single slot, let alone a
@@ -502,6 +545,18 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
 
         spawn_notifier(self).map_err(|e| format!("Failed to start notifier: {}", e))?;
 
+        operator_performance::spawn_saver(self)
+            .map_err(|e| format!("Failed to start operator performance saver: {}", e))?;
+
+        spawn_committee_reloader(self)
+            .map_err(|e| format!("Failed to start committee reloader: {}", e))?;
+
+        if let Some(address) = self.config.operator_service_address {
+            let address = spawn_operator_service(self, address)
+                .map_err(|e| format!("Failed to start operator service: {}", e))?;
+            info!(log, "Operator service started"; "listen_address" => %address);
+        }
+
         let api_secret = ApiSecret::create_or_open(&self.config.validator_dir)?;
 
         self.http_api_listen_addr = if self.config.http_api.enabled {
@@ -709,10 +764,14 @@ pub fn load_pem_certificate<P: AsRef<Path>>(pem_path: P) -> Result<Certificate,
 
 // DVF
 pub mod operator;
+pub mod operator_service;
 pub mod generic_operator_committee;
 pub mod impls;
 pub mod operator_committee_definitions;
 pub mod operator_committees;
+pub mod duty_proposal;
+pub mod committed_duties;
+pub mod operator_performance;
 
 macro_rules! define_mod {
     ($name: ident, $mod: path) => {
@@ -1,20 +1,126 @@
 use types::{Hash256, Signature, Keypair, PublicKey};
 use std::sync::Arc;
 use crate::utils::error::DvfError;
+use crate::crypto::ThresholdSignature;
+use crate::DvfCommitteeIndex;
 use std::net::SocketAddr;
+use std::io;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use node::config::Secret;
 use node::dvfcore::SignatureInfo;
-use network::SimpleSender;
-use bytes::Bytes;
+use ::crypto::{Digest, PublicKey as NodePublicKey, Signature as NodeSignature};
+use eth2_hashing::{Context, Sha256Context};
+use network::{Envelope, MessageKind, SimpleSender};
+use tokio::net::TcpStream;
 use tokio::sync::mpsc::{self, Receiver};
-use futures::executor::block_on;
-use std::collections::HashSet;
+use tokio::time::{self, Instant};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use bytes::Bytes;
+use futures::sink::SinkExt as _;
+use futures::stream::StreamExt as _;
+use std::collections::{HashMap, VecDeque};
 use downcast_rs::DowncastSync;
+use async_trait::async_trait;
+
+/// Default deadline for a remote operator to answer a signing request.
+pub const REMOTE_SIGN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Upper bound on the size of an SSZ-encoded block, which is the gossip limit after the merge.
+pub const MAX_BLOCK_SSZ_LEN: usize = 10 * 1_048_576;
+
+/// Upper bound on the size of a frame exchanged with a remote operator. A `SignRequest` carries
+/// the JSON encoding of a `DutyProposal`, in which a block takes less than four times its SSZ size.
+pub const MAX_OPERATOR_FRAME_LEN: usize = 4 * MAX_BLOCK_SSZ_LEN;
 
+/// Upper bound on the number of duties whose shares a `HotStuffOperator` keeps before they are
+/// collected.
+pub const MAX_PENDING_DUTIES: usize = 64;
+
+/// Upper bound on the number of shares a `HotStuffOperator` keeps for a duty before it is collected.
+pub const MAX_PENDING_SHARES_PER_DUTY: usize = 256;
+
+/// Separates the digests signed to authenticate signing requests from any other use of the
+/// hotstuff keys.
+pub const REQUEST_DOMAIN: &[u8] = b"dvf-operator-sign-request-v1";
+
+/// Messages exchanged between a validator client and the operators of its committee.
+///
+/// Frames are length-delimited (4-byte big-endian length followed by the bincode payload), which
+/// is the same framing as `tokio_util::codec::LengthDelimitedCodec`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OperatorMessage {
+    /// Sent by the operator when a connection opens and after each answer: the next request is
+    /// authenticated over it, so that requests cannot be replayed.
+    Challenge([u8; 32]),
+    /// Ask the operator for its signature share of the encoded `DutyProposal` `proposal` for the
+    /// validator of `committee_index`. `signature` is the signature of `client`, the hotstuff key
+    /// of the operator asking, over `request_digest`.
+    SignRequest {
+        committee_index: DvfCommitteeIndex,
+        proposal: Vec<u8>,
+        client: NodePublicKey,
+        signature: NodeSignature,
+    },
+    /// Signature share returned by the operator.
+    SignResponse(Signature),
+    /// The operator refused or failed to sign.
+    SignError(String),
+}
+
+/// The digest a client signs to authenticate its request for a share of `proposal`, after the
+/// operator sent `challenge`.
+pub fn request_digest(challenge: &[u8; 32], committee_index: DvfCommitteeIndex, proposal: &[u8]) -> Digest {
+    let mut context = Context::new();
+    context.update(REQUEST_DOMAIN);
+    context.update(challenge);
+    context.update(&committee_index.to_be_bytes());
+    context.update(proposal);
+    Digest(context.finalize())
 }
 
+/// Send `message` as a single frame.
+pub(crate) async fn send_message(
+    transport: &mut Framed<TcpStream, LengthDelimitedCodec>,
+    message: &OperatorMessage,
+) -> io::Result<()> {
+    let data = bincode::serialize(message)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    transport.send(Bytes::from(data)).await
+}
+
+/// Wait for the next frame and decode it.
+pub(crate) async fn receive_message(
+    transport: &mut Framed<TcpStream, LengthDelimitedCodec>,
+) -> io::Result<OperatorMessage> {
+    let data = transport.next().await.unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"))
+    })?;
+    bincode::deserialize(&data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The codec of the connections between validator clients and operators.
+pub(crate) fn operator_codec() -> LengthDelimitedCodec {
+    LengthDelimitedCodec::builder()
+        .max_frame_length(MAX_OPERATOR_FRAME_LEN)
+        .new_codec()
+}
+
+/// An operator holding a key share of a validator.
+///
+/// Signing is cancel-safe: dropping the future returned by `sign` abandons the request and leaves
+/// the operator usable for the next one.
+#[async_trait]
 pub trait TOperator: DowncastSync + Sync + Send {
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError>; 
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError>;
+
+    /// Sign `signing_root`, the root of the encoded `DutyProposal` `proposal`. Operators that do
+    /// not trust the caller compute the root from the proposal on their own.
+    async fn sign_proposal(&self, _proposal: &[u8], signing_root: Hash256) -> Result<Signature, DvfError> {
+        self.sign(signing_root).await
+    }
+
     fn public_key(&self) -> PublicKey;
 }
 impl_downcast!(sync TOperator);
@@ -26,9 +132,10 @@ pub struct LocalOperator {
     pub recv_channel: mpsc::UnboundedReceiver<OperatorMessage>,
 }
 
+#[async_trait]
 impl TOperator for LocalOperator {
 
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
         Ok(self.voting_keypair.sk.sign(msg))
     }
 
@@ -50,15 +157,21 @@ impl LocalOperator {
 }
 
 pub struct HotStuffOperator {
+    /// Id of the validator whose consensus instance this operator takes part in, i.e., the hex
+    /// encoding of its voting public key.
+    pub validator_id: String,
     pub voting_keypair: Arc<Keypair>,
     pub network: SimpleSender,
     pub address: SocketAddr,
-    pub rx_signature: Receiver<SignatureInfo>
+    pub rx_signature: Receiver<SignatureInfo>,
+    /// Shares received while waiting for a duty, by signing root, kept for the duty they belong to.
+    pending: HashMap<Hash256, Vec<ReceivedShare>>,
 }
 
+#[async_trait]
 impl TOperator for HotStuffOperator {
 
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
         Ok(self.voting_keypair.sk.sign(msg))
     }
 
@@ -68,59 +181,239 @@ impl TOperator for HotStuffOperator {
 }
 
 impl HotStuffOperator {
-    pub fn new(keypair: Arc<Keypair>, address: SocketAddr, rx_signature: Receiver<SignatureInfo>) -> Self {
+    pub fn new(validator_id: String, keypair: Arc<Keypair>, address: SocketAddr, rx_signature: Receiver<SignatureInfo>) -> Self {
         Self {
+            validator_id,
             voting_keypair: keypair,
             network: SimpleSender::new(),
             address: address,
-            rx_signature: rx_signature
+            rx_signature: rx_signature,
+            pending: HashMap::new(),
         }
     }
 
-    /// send msg to network for consensus
-    pub async fn propose(&mut self, msg: Hash256) {
-        // prefix id is fixed 
-        // update
-        let validator_vec : Vec<u8>= vec![50; 88];
-        let validator_id = String::from_utf8(validator_vec).unwrap();
-        let mut prefix_msg : Vec<u8> = Vec::new();
-        prefix_msg.extend(validator_id.into_bytes());
-        prefix_msg.extend(msg.to_fixed_bytes().to_vec());
-        self.network.send(self.address, Bytes::from(prefix_msg)).await;
-    }
-
-    pub async fn wait_signature(&mut self) -> Vec<SignatureInfo>{
-        let mut received = 0;
-        let mut ids = HashSet::<u64>::new();
-        let mut res = Vec::new();
-        loop {
-            if let Some(signature_info) = self.rx_signature.recv().await {
-                if ids.contains(&signature_info.id)  {
-                    continue;
-                } else {
-                    ids.insert(signature_info.id);
-                    res.push(signature_info);
-                    received+=1;
-                }
-                if received == 10 {
-                    break;
+    /// Send an encoded `DutyProposal` to the network for consensus.
+    pub async fn propose(&mut self, proposal: &[u8]) {
+        let envelope = Envelope::seal(&self.validator_id, MessageKind::Transaction, proposal.to_vec());
+        self.network.send(self.address, envelope).await;
+    }
+
+    /// Wait (at most `timeout`) until a share of `msg` is received from an operator of `keys`, i.e.,
+    /// until the duty whose signing root is `msg` committed and an operator signed it.
+    pub async fn wait_commit(&mut self, msg: Hash256, keys: &HashMap<u64, PublicKey>, timeout: Duration) -> bool {
+        if self.pending.get(&msg).map_or(false, |shares| shares.iter().any(|share| share.verify(keys) == Some(true))) {
+            return true;
+        }
+        let deadline = Instant::now() + timeout;
+        while let Ok(Some(signature_info)) = time::timeout_at(deadline, self.rx_signature.recv()).await {
+            let share = ReceivedShare::new(signature_info);
+            let valid = share.info.msg == msg && share.verify(keys) == Some(true);
+            self.buffer(share);
+            if valid {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Collect valid shares of `msg` from `threshold` distinct operators of `keys`, giving up after
+    /// `timeout`.
+    pub async fn wait_signature(&mut self, msg: Hash256, keys: &HashMap<u64, PublicKey>, threshold: usize, timeout: Duration) -> Result<Vec<SignatureInfo>, DvfError> {
+        let collection = self.collect_shares(msg, keys, threshold, Instant::now(), timeout).await;
+        match collection.error {
+            Some(e) => Err(e),
+            None => Ok(collection.shares.into_iter().map(|(share, _)| share).collect()),
+        }
+    }
+
+    /// Same as `wait_signature`, but also aggregates the shares and reports the operators whose
+    /// share does not verify, and how long after `since` each share arrived.
+    ///
+    /// `keys` are the public keys of the operators of the committee, by id: shares are verified
+    /// against the key registered for the id they claim, and dropped if the id is not in `keys`.
+    /// The `from` of the returned shares is the registered key.
+    ///
+    /// Shares are not verified one by one: they are aggregated once `threshold` operators sent
+    /// one, and only if that fails are the invalid ones located and replaced.
+    pub async fn collect_shares(&mut self, msg: Hash256, keys: &HashMap<u64, PublicKey>, threshold: usize, since: Instant, timeout: Duration) -> ShareCollection {
+        let deadline = Instant::now() + timeout;
+        let threshold_sig = ThresholdSignature::new(threshold);
+        let mut collection = ShareCollection::default();
+        // Shares of distinct operators, and the other shares claiming the id of one of them, in
+        // case the first one is invalid.
+        let mut batch: Vec<ReceivedShare> = Vec::new();
+        let mut spares: Vec<ReceivedShare> = Vec::new();
+        // Shares that arrived while waiting for this duty or for another one.
+        let mut pending: VecDeque<ReceivedShare> = self.pending.remove(&msg).unwrap_or_default().into();
+        let closed = loop {
+            if batch.len() == threshold {
+                let (ids, pks, sigs) = share_batch(&batch, keys);
+                match threshold_sig.threshold_aggregate_with_invalid_ids(&sigs, &pks, &ids, msg) {
+                    Ok((signature, _)) => {
+                        collection.signature = Some(signature);
+                        collection.accept(batch, keys, since);
+                        return collection;
+                    }
+                    Err(DvfError::InsufficientValidSignatures { invalid, .. }) => {
+                        collection.reject(&mut batch, &invalid);
+                        pending.extend(spares.drain(..));
+                        continue;
+                    }
+                    Err(e) => {
+                        collection.error = Some(e);
+                        return collection;
+                    }
                 }
-                    
             }
+            let share = match pending.pop_front() {
+                Some(share) => share,
+                None => match time::timeout_at(deadline, self.rx_signature.recv()).await {
+                    // Shares of other duties may be in flight, e.g., of a duty waiting for the
+                    // operator lock: keep them for it.
+                    Ok(Some(signature_info)) if signature_info.msg != msg => {
+                        self.buffer(ReceivedShare::new(signature_info));
+                        continue;
+                    }
+                    Ok(Some(signature_info)) => ReceivedShare::new(signature_info),
+                    Ok(None) => break true,
+                    Err(_) => break false,
+                },
+            };
+            if !keys.contains_key(&share.info.id) {
+                // Not an operator of the committee.
+                continue;
+            }
+            if batch.iter().any(|other| other.info.id == share.info.id) {
+                spares.push(share);
+            } else {
+                batch.push(share);
+            }
+        };
+
+        // Too few operators answered: only report the valid shares.
+        let (ids, pks, sigs) = share_batch(&batch, keys);
+        let invalid = ThresholdSignature::invalid_share_ids(&sigs, &pks, &ids, msg);
+        collection.reject(&mut batch, &invalid);
+        let got = batch.len();
+        collection.error = Some(if closed {
+            DvfError::InsufficientSignatures { got, expected: threshold }
+        } else {
+            DvfError::SigningDeadlineExceeded { got, expected: threshold }
+        });
+        collection.accept(batch, keys, since);
+        collection
+    }
+
+    /// Keep `share` until its duty is collected, forgetting the duty that was buffered first if
+    /// there are too many of them.
+    fn buffer(&mut self, share: ReceivedShare) {
+        let msg = share.info.msg;
+        if !self.pending.contains_key(&msg) && self.pending.len() >= MAX_PENDING_DUTIES {
+            let oldest = self.pending
+                .iter()
+                .min_by_key(|(_, shares)| shares.first().map(|share| share.at))
+                .map(|(msg, _)| *msg);
+            if let Some(oldest) = oldest {
+                self.pending.remove(&oldest);
+            }
+        }
+        let shares = self.pending.entry(msg).or_default();
+        if shares.len() < MAX_PENDING_SHARES_PER_DUTY {
+            shares.push(share);
         }
-        res
     }
 }
 
+/// A share of a duty, with the time at which it arrived.
+struct ReceivedShare {
+    info: SignatureInfo,
+    at: Instant,
+}
+
+impl ReceivedShare {
+    fn new(info: SignatureInfo) -> Self {
+        Self { info, at: Instant::now() }
+    }
+
+    /// Whether the share verifies against the key registered for its id in `keys`, or `None` if
+    /// the id is unknown. The key claimed by the sender is ignored.
+    fn verify(&self, keys: &HashMap<u64, PublicKey>) -> Option<bool> {
+        keys.get(&self.info.id).map(|pk| self.info.signature.verify(pk, self.info.msg))
+    }
+}
+
+/// The ids, registered public keys and signatures of `batch`, whose ids must be in `keys`.
+fn share_batch<'a>(batch: &'a [ReceivedShare], keys: &'a HashMap<u64, PublicKey>) -> (Vec<u64>, Vec<&'a PublicKey>, Vec<&'a Signature>) {
+    let ids: Vec<u64> = batch.iter().map(|share| share.info.id).collect();
+    let pks = ids.iter().map(|id| &keys[id]).collect();
+    let sigs = batch.iter().map(|share| &share.info.signature).collect();
+    (ids, pks, sigs)
+}
+
+/// Shares of a duty collected by `HotStuffOperator::collect_shares`.
+#[derive(Default)]
+pub struct ShareCollection {
+    /// Valid shares from distinct operators, in the order they arrived, with the time each took
+    /// to arrive.
+    pub shares: Vec<(SignatureInfo, Duration)>,
+    /// Ids of the operators of the committee for which a share did not verify.
+    pub invalid: Vec<u64>,
+    /// The aggregate of `shares`, if the threshold was reached.
+    pub signature: Option<Signature>,
+    /// Why the threshold was not reached, if it was not.
+    pub error: Option<DvfError>,
+}
+
+impl ShareCollection {
+    /// Drop the shares of the operators `invalid` from `batch`, and report them.
+    fn reject(&mut self, batch: &mut Vec<ReceivedShare>, invalid: &[u64]) {
+        batch.retain(|share| !invalid.contains(&share.info.id));
+        for id in invalid {
+            if !self.invalid.contains(id) {
+                self.invalid.push(*id);
+            }
+        }
+    }
+
+    /// Keep the valid shares of `batch`, with the registered key of their operator.
+    fn accept(&mut self, mut batch: Vec<ReceivedShare>, keys: &HashMap<u64, PublicKey>, since: Instant) {
+        batch.sort_by_key(|share| share.at);
+        for mut share in batch {
+            share.info.from = keys[&share.info.id].clone();
+            let latency = share.at.saturating_duration_since(since);
+            self.shares.push((share.info, latency));
+        }
+    }
+}
+
+/// An operator living in another process, reached through its `socket_address`. Requests are
+/// authenticated with `identity`, the hotstuff key of this validator client's operator.
 pub struct RemoteOperator {
     pub id: u64,
+    pub committee_index: DvfCommitteeIndex,
     pub public_key: PublicKey,
     pub socket_address: SocketAddr,
+    pub identity: Arc<Secret>,
+    pub timeout: Duration,
 }
 
+#[async_trait]
 impl TOperator for RemoteOperator {
-    fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> { 
-        Err(DvfError::Unknown)
+    async fn sign(&self, _msg: Hash256) -> Result<Signature, DvfError> {
+        // Remote operators refuse to sign a bare root they cannot check.
+        Err(DvfError::UntypedDuty)
+    }
+
+    async fn sign_proposal(&self, proposal: &[u8], _signing_root: Hash256) -> Result<Signature, DvfError> {
+        let response = self.request(proposal).await.map_err(|e| self.io_error(e))?;
+        match response {
+            OperatorMessage::SignResponse(sig) => Ok(sig),
+            OperatorMessage::SignError(reason) => Err(DvfError::OperatorRejected { id: self.id, reason }),
+            other => Err(DvfError::OperatorRejected {
+                id: self.id,
+                reason: format!("unexpected response {:?}", other),
+            }),
+        }
     }
 
     fn public_key(&self) -> PublicKey {
@@ -128,3 +421,60 @@ impl TOperator for RemoteOperator {
     }
 }
 
+impl RemoteOperator {
+    pub fn new(id: u64, committee_index: DvfCommitteeIndex, public_key: PublicKey, socket_address: SocketAddr, identity: Arc<Secret>) -> Self {
+        Self {
+            id,
+            committee_index,
+            public_key,
+            socket_address,
+            identity,
+            timeout: REMOTE_SIGN_TIMEOUT,
+        }
+    }
+
+    /// Ask for a share of `proposal` over a fresh connection and wait (at most `self.timeout` per
+    /// step) for the reply.
+    async fn request(&self, proposal: &[u8]) -> io::Result<OperatorMessage> {
+        let stream = with_timeout(self.timeout, TcpStream::connect(self.socket_address)).await?;
+        stream.set_nodelay(true)?;
+        let mut transport = Framed::new(stream, operator_codec());
+
+        let challenge = match with_timeout(self.timeout, receive_message(&mut transport)).await? {
+            OperatorMessage::Challenge(challenge) => challenge,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected a challenge, got {:?}", other),
+                ))
+            }
+        };
+        let digest = request_digest(&challenge, self.committee_index, proposal);
+        let request = OperatorMessage::SignRequest {
+            committee_index: self.committee_index,
+            proposal: proposal.to_vec(),
+            client: self.identity.name,
+            signature: NodeSignature::new(&digest, &self.identity.secret),
+        };
+        with_timeout(self.timeout, send_message(&mut transport, &request)).await?;
+        with_timeout(self.timeout, receive_message(&mut transport)).await
+    }
+
+    fn io_error(&self, e: io::Error) -> DvfError {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DvfError::OperatorTimeout { id: self.id },
+            io::ErrorKind::InvalidData => DvfError::OperatorRejected { id: self.id, reason: e.to_string() },
+            _ => DvfError::OperatorUnreachable { id: self.id, reason: e.to_string() },
+        }
+    }
+}
+
+/// Run an I/O step, failing with `io::ErrorKind::TimedOut` if it takes longer than `timeout`.
+async fn with_timeout<T, F>(timeout: Duration, step: F) -> io::Result<T>
+where
+    F: std::future::Future<Output = io::Result<T>>,
+{
+    time::timeout(timeout, step)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "operator timed out"))?
+}
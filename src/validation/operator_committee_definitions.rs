@@ -4,12 +4,15 @@
 //! attempt) to load into the `crate::intialized_validators::InitializedValidators` struct.
 
 use crate::validation::account_utils::{write_file_via_temporary};
+use crate::crypto::ThresholdSignature;
+use std::collections::HashSet;
 use directory::ensure_dir_exists;
 use serde_derive::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io;
 use std::path::{Path, PathBuf};
 use types::{PublicKey};
+use ::crypto::PublicKey as NodePublicKey;
 use std::net::{SocketAddr};
 
 /// The file name for the serialized `OperatorCommitteeDefinition` struct.
@@ -37,8 +40,43 @@ pub enum Error {
     UnableToWriteFile(filesystem::Error),
     /// The committee directory could not be created.
     UnableToCreateCommitteeDir(PathBuf),
-    /// Invalid file
-    InvalidFile,
+    /// A committee definition in the file is invalid.
+    InvalidDefinition {
+        committee_index: u64,
+        error: DefinitionError,
+    },
+}
+
+/// Why an `OperatorCommitteeDefinition` is invalid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionError {
+    /// `ids`, `public_keys`, `socket_addresses` or `node_public_keys` does not have `total` entries.
+    LengthMismatch {
+        field: &'static str,
+        got: usize,
+        expected: u64,
+    },
+    /// The threshold is zero or larger than the committee.
+    InvalidThreshold {
+        threshold: u64,
+        total: u64,
+    },
+    /// Operator ids are evaluation points of the sharing polynomial, so they cannot be zero.
+    ZeroOperatorId,
+    DuplicateOperatorId(u64),
+    DuplicatePublicKey(PublicKey),
+    DuplicateNodePublicKey(NodePublicKey),
+    /// A sharing polynomial of degree `threshold - 1` has `threshold` commitments.
+    CommitmentsLength {
+        got: usize,
+        expected: u64,
+    },
+    /// The first commitment is not `voting_public_key`.
+    CommitmentsMismatch,
+    /// The public key of operator `id` is not its share of `voting_public_key`.
+    InvalidShareKey {
+        id: u64,
+    },
 }
 
 
@@ -47,7 +85,7 @@ pub enum Error {
 ///
 /// Presently there is only a single variant, however we expect more variants to arise (e.g.,
 /// remote signing).
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OperatorCommitteeDefinition {
     pub total: u64,
     pub threshold: u64,
@@ -56,6 +94,15 @@ pub struct OperatorCommitteeDefinition {
     pub ids: Vec<u64>,
     pub public_keys: Vec<PublicKey>,
     pub socket_addresses: Vec<SocketAddr>,
+    /// Feldman commitments to the sharing polynomial, when known. The first one is
+    /// `voting_public_key`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commitments: Vec<PublicKey>,
+    /// The hotstuff keys of the operators (in the order of `ids`), with which they authenticate
+    /// their signing requests to each other. Requests for the committee are refused until they
+    /// are known.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node_public_keys: Vec<NodePublicKey>,
 }
 
 //impl ValidatorDefinition {
@@ -112,11 +159,86 @@ impl OperatorCommitteeDefinition {
             .map_err(Error::UnableToOpenFile)?;
         serde_yaml::to_writer(file, self).map_err(Error::UnableToEncodeFile)
     }
+
+    /// Check that `self` describes a committee able to sign for `voting_public_key`.
+    ///
+    /// The operator public keys are only checked against `voting_public_key` when commitments are
+    /// available.
+    pub fn validate(&self) -> Result<(), DefinitionError> {
+        let lengths = [
+            ("ids", self.ids.len()),
+            ("public_keys", self.public_keys.len()),
+            ("socket_addresses", self.socket_addresses.len()),
+        ];
+        for (field, got) in lengths {
+            if got as u64 != self.total {
+                return Err(DefinitionError::LengthMismatch { field, got, expected: self.total });
+            }
+        }
+        if !self.node_public_keys.is_empty() && self.node_public_keys.len() as u64 != self.total {
+            return Err(DefinitionError::LengthMismatch {
+                field: "node_public_keys",
+                got: self.node_public_keys.len(),
+                expected: self.total,
+            });
+        }
+        if self.threshold == 0 || self.threshold > self.total {
+            return Err(DefinitionError::InvalidThreshold { threshold: self.threshold, total: self.total });
+        }
+
+        let mut ids = HashSet::new();
+        for id in &self.ids {
+            if *id == 0 {
+                return Err(DefinitionError::ZeroOperatorId);
+            }
+            if !ids.insert(*id) {
+                return Err(DefinitionError::DuplicateOperatorId(*id));
+            }
+        }
+        let mut public_keys = HashSet::new();
+        for public_key in &self.public_keys {
+            if !public_keys.insert(public_key.compress()) {
+                return Err(DefinitionError::DuplicatePublicKey(public_key.clone()));
+            }
+        }
+
+        let mut node_public_keys = HashSet::new();
+        for node_public_key in &self.node_public_keys {
+            if !node_public_keys.insert(*node_public_key) {
+                return Err(DefinitionError::DuplicateNodePublicKey(*node_public_key));
+            }
+        }
+
+        if self.commitments.is_empty() {
+            return Ok(());
+        }
+        if self.commitments.len() as u64 != self.threshold {
+            return Err(DefinitionError::CommitmentsLength { got: self.commitments.len(), expected: self.threshold });
+        }
+        if self.commitments[0] != self.voting_public_key {
+            return Err(DefinitionError::CommitmentsMismatch);
+        }
+        for (id, public_key) in self.ids.iter().zip(self.public_keys.iter()) {
+            match ThresholdSignature::share_public_key(*id, &self.commitments) {
+                Ok(expected) if expected == *public_key => (),
+                _ => return Err(DefinitionError::InvalidShareKey { id: *id }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the committee stays safe and live with up to `f = (total - 1) / 3`
+    /// Byzantine operators, i.e., if `2f + 1 <= threshold <= total - f`: the `f` operators cannot
+    /// sign with fewer than `f + 1` honest ones, nor block signing by staying silent.
+    pub fn is_byzantine_safe(&self) -> bool {
+        let f = self.total.saturating_sub(1) / 3;
+        2 * f + 1 <= self.threshold && self.threshold <= self.total - f
+    }
 }
 
 /// A list of `OperatorCommitteeDefinition` that serves as a serde-able configuration file which defines a
 /// list of operator committees.
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct OperatorCommitteeDefinitions(Vec<OperatorCommitteeDefinition>);
 
 impl From<Vec<OperatorCommitteeDefinition>> for OperatorCommitteeDefinitions {
@@ -149,17 +271,11 @@ impl OperatorCommitteeDefinitions {
             .open(&config_path)
             .map_err(Error::UnableToOpenFile)?;
         let defs: Self = serde_yaml::from_reader(file).map_err(Error::UnableToParseFile)?;
-        // Validate simple constraints
-        for i in 0..defs.0.len() {
-            if defs.0[i].ids.len() != (defs.0[i].total as usize) {
-                return Err(Error::InvalidFile);
-            }
-            if defs.0[i].public_keys.len() != (defs.0[i].total as usize) {
-                return Err(Error::InvalidFile);
-            }
-            if defs.0[i].socket_addresses.len() != (defs.0[i].total as usize) {
-                return Err(Error::InvalidFile);
-            }
+        for def in defs.as_slice() {
+            def.validate().map_err(|error| Error::InvalidDefinition {
+                committee_index: def.committee_index,
+                error,
+            })?;
         }
         Ok(defs)
     }
@@ -410,25 +526,20 @@ impl OperatorCommitteeDefinitions {
         Ok(())
     }
 
-    ///// Retain only the definitions matching the given predicate.
-    //pub fn retain(&mut self, f: impl FnMut(&ValidatorDefinition) -> bool) {
-        //self.0.retain(f);
-    //}
+    /// Retain only the definitions matching the given predicate.
+    pub fn retain(&mut self, f: impl FnMut(&OperatorCommitteeDefinition) -> bool) {
+        self.0.retain(f);
+    }
 
     /// Adds a new `OperatorCommitteeDefinition` to `self`.
     pub fn push(&mut self, def: OperatorCommitteeDefinition) {
         self.0.push(def)
     }
 
-    ///// Returns a slice of all `ValidatorDefinition` in `self`.
-    //pub fn as_slice(&self) -> &[ValidatorDefinition] {
-        //self.0.as_slice()
-    //}
-
-    ///// Returns a mutable slice of all `ValidatorDefinition` in `self`.
-    //pub fn as_mut_slice(&mut self) -> &mut [ValidatorDefinition] {
-        //self.0.as_mut_slice()
-    //}
+    /// Returns a mutable slice of all `OperatorCommitteeDefinition` in `self`.
+    pub fn as_mut_slice(&mut self) -> &mut [OperatorCommitteeDefinition] {
+        self.0.as_mut_slice()
+    }
 }
 
 ///// Perform an exhaustive tree search of `dir`, adding any discovered voting keystore paths to
@@ -1,25 +1,32 @@
 use crate::validation::OperatorCommittee;
 use crate::utils::error::DvfError;
-use crate::validation::operator_committee_definitions::{OperatorCommitteeDefinition, OperatorCommitteeDefinitions};
-use crate::validation::operator::RemoteOperator;
-use crate::DvfCommitteeIndex;
+use crate::validation::operator_committee_definitions::{self, DefinitionError, OperatorCommitteeDefinition, OperatorCommitteeDefinitions};
+use crate::validation::operator::{LocalOperator, RemoteOperator, TOperator};
+use crate::validation::operator_service::{ClientMap, KeyShare, KeyShareMap};
+use node::config::Secret;
+use crate::{DvfCommitteeIndex, DvfOperatorTsid};
 use std::sync::Arc;
 use std::convert::TryInto;
 use std::path::PathBuf;
 use std::collections::HashMap;
-use parking_lot::{RwLock};
+use tokio::sync::{RwLock};
 
 impl OperatorCommittee { 
+    /// Build the committee of `def`, whose remote operators are asked for shares on behalf of the
+    /// operator of hotstuff key `identity`.
     pub fn from_definition(
         def: OperatorCommitteeDefinition,
+        identity: Arc<Secret>,
     ) -> Result<Self, DvfError> {
         let mut committee = Self::new(def.committee_index, def.voting_public_key.clone(), def.threshold.try_into().unwrap());
         for i in 0..(def.total as usize) {
-            let operator = RemoteOperator {
-                id: def.ids[i],
-                public_key: def.public_keys[i].clone(),
-                socket_address: def.socket_addresses[i],
-            };
+            let operator = RemoteOperator::new(
+                def.ids[i],
+                def.committee_index,
+                def.public_keys[i].clone(),
+                def.socket_addresses[i],
+                identity.clone(),
+            );
             committee.add_operator(def.ids[i], Arc::new(RwLock::new(operator)));
         }
         Ok(committee)
@@ -27,42 +34,316 @@ impl OperatorCommittee {
 }
 
 pub struct OperatorCommittees {
-    /// A list of validator definitions which can be stored on-disk.
+    /// A list of operator committee definitions which can be stored on-disk.
     definitions: OperatorCommitteeDefinitions,
     /// The directory that the `self.definitions` will be saved into.
     committees_dir: PathBuf,
-    /// The canonical set of validators.
-    pub committee_map: HashMap<DvfCommitteeIndex, OperatorCommittee>,
+    /// The canonical set of committees. Signing methods share these, so that changes to a
+    /// committee apply to the validators attached to it without reloading them.
+    pub committee_map: HashMap<DvfCommitteeIndex, Arc<RwLock<OperatorCommittee>>>,
+    /// The hotstuff key of this operator, which authenticates its requests to the others.
+    identity: Arc<Secret>,
+    /// The key shares of this operator, answering the requests of the other operators.
+    key_shares: KeyShareMap,
+    /// The operators allowed to ask for the shares of each committee, following `definitions`.
+    clients: ClientMap,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// There was an error updating the on-disk committee definitions file.
+    UnableToSaveDefinitions(operator_committee_definitions::Error),
+    /// The committee could not be built from its definition.
+    UnableToBuildCommittee(DvfError),
+    /// The definition of the committee is invalid.
+    InvalidDefinition(DvfCommitteeIndex, DefinitionError),
+    /// Cannot define the same committee twice.
+    DuplicateCommittee(DvfCommitteeIndex),
+    /// The committee index does not exist in the set of committees.
+    UnknownCommittee(DvfCommitteeIndex),
+    /// The committee definitions changed while a reconciliation was in progress.
+    ConcurrentChange,
+}
+
+/// Committees changed by `OperatorCommittees::reconcile`, by index.
+#[derive(Debug, Default, PartialEq)]
+pub struct CommitteeChanges {
+    /// Committees that were not running before.
+    pub added: Vec<DvfCommitteeIndex>,
+    /// Running committees whose definition changed, rebuilt in place.
+    pub rebuilt: Vec<DvfCommitteeIndex>,
+    /// Running committees that are no longer defined.
+    pub retired: Vec<DvfCommitteeIndex>,
+    /// Rebuilt committees of which this operator no longer holds a key share.
+    pub unserved: Vec<DvfCommitteeIndex>,
+}
+
+impl CommitteeChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.rebuilt.is_empty() && self.retired.is_empty()
+    }
+}
+
+/// Committees built by `OperatorCommittees::prepare`, to be swapped in and applied.
+pub struct Reconciliation {
+    /// The definitions of the running committees when the reconciliation was prepared.
+    previous: OperatorCommitteeDefinitions,
+    definitions: OperatorCommitteeDefinitions,
+    added: Vec<(DvfCommitteeIndex, OperatorCommittee)>,
+    /// Running committees, with the committees to rebuild them with.
+    rebuilt: Vec<(Arc<RwLock<OperatorCommittee>>, OperatorCommittee)>,
+    changes: CommitteeChanges,
+}
+
+impl Reconciliation {
+    /// Rebuild the running committees in place, once the duties holding them are done.
+    pub async fn swap(&mut self) {
+        for (running, committee) in self.rebuilt.drain(..) {
+            *running.write().await = committee;
+        }
+    }
+}
+
+/// The local operator of the operator holding `key_share` in the committee of `def`, unless the
+/// committee signs for another validator or does not include the key share anymore.
+fn local_operator(def: &OperatorCommitteeDefinition, key_share: KeyShare) -> Option<(DvfOperatorTsid, Arc<RwLock<dyn TOperator>>)> {
+    if key_share.validator != def.voting_public_key.compress() {
+        return None;
+    }
+    let i = def.public_keys.iter().position(|pk| *pk == key_share.keypair.pk)?;
+    Some((def.ids[i], Arc::new(RwLock::new(LocalOperator::new(def.ids[i], key_share.keypair)))))
 }
 
 impl OperatorCommittees {
     pub async fn from_definitions(
         definitions: OperatorCommitteeDefinitions,
         committees_dir: PathBuf,
-    ) -> Result<Self, DvfError> {
+        identity: Arc<Secret>,
+    ) -> Result<Self, Error> {
         let mut this = Self {
-            definitions,
+            definitions: OperatorCommitteeDefinitions::default(),
             committees_dir,
             committee_map: HashMap::default(),
+            identity,
+            key_shares: KeyShareMap::default(),
+            clients: ClientMap::default(),
         };
-        this.update_committees().await?;
+        this.reconcile(definitions).await?;
         Ok(this)
     }
 
-    pub async fn update_committees(&mut self) -> Result<(), DvfError> {
-        for def in self.definitions.as_slice() { 
-            if self.committee_map.contains_key(&def.committee_index) {
-                continue;
+    /// Bring the running committees in line with `definitions`: committees are added, rebuilt if
+    /// their definition changed, or retired if they are no longer defined.
+    ///
+    /// ## Notes
+    ///
+    /// All committees are built before any is replaced, so that an invalid definition leaves the
+    /// running committees untouched.
+    ///
+    /// Duties in flight are not dropped: a committee is rebuilt in place once the duties holding
+    /// it are done, and a retired committee lives on with the signing methods that hold it. Callers
+    /// holding `self` behind a lock should `prepare` and `apply` the reconciliation themselves, and
+    /// wait for the duties in between without holding the lock.
+    pub async fn reconcile(&mut self, definitions: OperatorCommitteeDefinitions) -> Result<CommitteeChanges, Error> {
+        let mut reconciliation = self.prepare(definitions)?;
+        reconciliation.swap().await;
+        self.apply(reconciliation)
+    }
+
+    /// Build the committees of `definitions` which differ from the running ones.
+    ///
+    /// Rebuilt committees keep the local operator of this operator, if it still holds a key share
+    /// of the committee, so that they can sign as soon as they are swapped in.
+    pub fn prepare(&self, definitions: OperatorCommitteeDefinitions) -> Result<Reconciliation, Error> {
+        let mut changes = CommitteeChanges::default();
+        let mut added = Vec::new();
+        let mut rebuilt = Vec::new();
+        for (i, def) in definitions.as_slice().iter().enumerate() {
+            if definitions.as_slice()[..i]
+                .iter()
+                .any(|other| other.committee_index == def.committee_index)
+            {
+                return Err(Error::DuplicateCommittee(def.committee_index));
             }
-            match OperatorCommittee::from_definition(def.clone()) {
-                Ok(committee) => {
-                    self.committee_map.insert(def.committee_index, committee);
+            def.validate().map_err(|e| Error::InvalidDefinition(def.committee_index, e))?;
+            let mut committee = match self.definition(def.committee_index) {
+                Some(existing) if existing == def => continue,
+                _ => OperatorCommittee::from_definition(def.clone(), self.identity.clone()).map_err(Error::UnableToBuildCommittee)?,
+            };
+            match self.committee(def.committee_index) {
+                Some(running) => {
+                    let key_share = self.key_shares.read().get(&def.committee_index).cloned();
+                    match key_share.and_then(|key_share| local_operator(def, key_share)) {
+                        Some((id, operator)) => committee.add_operator(id, operator),
+                        None => changes.unserved.push(def.committee_index),
+                    }
+                    changes.rebuilt.push(def.committee_index);
+                    rebuilt.push((running, committee));
                 }
-                Err(e) => {
-                    return Err(e);
+                None => {
+                    changes.added.push(def.committee_index);
+                    added.push((def.committee_index, committee));
                 }
             }
         }
+        changes.retired = self
+            .definitions
+            .as_slice()
+            .iter()
+            .map(|def| def.committee_index)
+            .filter(|index| !definitions.as_slice().iter().any(|def| def.committee_index == *index))
+            .collect();
+
+        Ok(Reconciliation {
+            previous: self.definitions.clone(),
+            definitions,
+            added,
+            rebuilt,
+            changes,
+        })
+    }
+
+    /// Register the committees of a `reconciliation` whose rebuilt committees are swapped in.
+    ///
+    /// Fails if the definitions changed since the reconciliation was prepared: they are saved to
+    /// file, which will be reloaded again.
+    pub fn apply(&mut self, reconciliation: Reconciliation) -> Result<CommitteeChanges, Error> {
+        if reconciliation.previous.as_slice() != self.definitions.as_slice() {
+            return Err(Error::ConcurrentChange);
+        }
+        for (committee_index, committee) in reconciliation.added {
+            self.committee_map.insert(committee_index, Arc::new(RwLock::new(committee)));
+        }
+        let changes = reconciliation.changes;
+        for committee_index in &changes.retired {
+            self.committee_map.remove(committee_index);
+            self.key_shares.write().remove(committee_index);
+        }
+        for committee_index in &changes.unserved {
+            self.key_shares.write().remove(committee_index);
+        }
+        self.definitions = reconciliation.definitions;
+        self.update_clients();
+        Ok(changes)
+    }
+
+    /// Returns a slice of all `OperatorCommitteeDefinition` in `self`.
+    pub fn definitions(&self) -> &[OperatorCommitteeDefinition] {
+        self.definitions.as_slice()
+    }
+
+    /// Returns the definition of committee `committee_index`, if it is known.
+    pub fn definition(&self, committee_index: DvfCommitteeIndex) -> Option<&OperatorCommitteeDefinition> {
+        self.definitions
+            .as_slice()
+            .iter()
+            .find(|def| def.committee_index == committee_index)
+    }
+
+    /// The hotstuff key of this operator.
+    pub fn identity(&self) -> Arc<Secret> {
+        self.identity.clone()
+    }
+
+    /// The key shares this operator holds, shared with its `OperatorService`.
+    pub fn key_shares(&self) -> KeyShareMap {
+        self.key_shares.clone()
+    }
+
+    /// The operators allowed to ask for shares, shared with the `OperatorService`.
+    pub fn clients(&self) -> ClientMap {
+        self.clients.clone()
+    }
+
+    /// Serve `key_share` to the other operators of committee `committee_index`.
+    pub fn add_key_share(&self, committee_index: DvfCommitteeIndex, key_share: KeyShare) {
+        self.key_shares.write().insert(committee_index, key_share);
+    }
+
+    /// Returns committee `committee_index`, if it is known.
+    pub fn committee(&self, committee_index: DvfCommitteeIndex) -> Option<Arc<RwLock<OperatorCommittee>>> {
+        self.committee_map.get(&committee_index).cloned()
+    }
+
+    /// Add a committee definition to `self`, overwriting the on-disk representation of `self`.
+    pub fn add_definition(&mut self, def: OperatorCommitteeDefinition) -> Result<Arc<RwLock<OperatorCommittee>>, Error> {
+        if self.definition(def.committee_index).is_some() {
+            return Err(Error::DuplicateCommittee(def.committee_index));
+        }
+        def.validate().map_err(|e| Error::InvalidDefinition(def.committee_index, e))?;
+
+        let committee_index = def.committee_index;
+        let committee = OperatorCommittee::from_definition(def.clone(), self.identity.clone()).map_err(Error::UnableToBuildCommittee)?;
+        let committee = Arc::new(RwLock::new(committee));
+        let mut definitions = self.definitions.clone();
+        definitions.push(def);
+        self.save(&definitions)?;
+        self.definitions = definitions;
+        self.update_clients();
+        self.committee_map.insert(committee_index, committee.clone());
+        Ok(committee)
+    }
+
+    /// Replace the definition of an existing committee, overwriting the on-disk representation of
+    /// `self`.
+    ///
+    /// ## Notes
+    ///
+    /// The committee is rebuilt in place from its definition: it only holds the remote operators
+    /// afterwards, the local operators of the validators attached to it must be added again.
+    pub async fn update_definition(&mut self, def: OperatorCommitteeDefinition) -> Result<Arc<RwLock<OperatorCommittee>>, Error> {
+        let committee_index = def.committee_index;
+        let committee = self.committee(committee_index).ok_or(Error::UnknownCommittee(committee_index))?;
+        def.validate().map_err(|e| Error::InvalidDefinition(committee_index, e))?;
+
+        let rebuilt = OperatorCommittee::from_definition(def.clone(), self.identity.clone()).map_err(Error::UnableToBuildCommittee)?;
+        let mut definitions = self.definitions.clone();
+        if let Some(existing) = definitions
+            .as_mut_slice()
+            .iter_mut()
+            .find(|existing| existing.committee_index == committee_index)
+        {
+            *existing = def;
+        }
+        self.save(&definitions)?;
+        self.definitions = definitions;
+        self.update_clients();
+        *committee.write().await = rebuilt;
+        Ok(committee)
+    }
+
+    /// Remove committee `committee_index` from `self`, overwriting the on-disk representation of
+    /// `self`.
+    pub fn remove_definition(&mut self, committee_index: DvfCommitteeIndex) -> Result<(), Error> {
+        if self.definition(committee_index).is_none() {
+            return Err(Error::UnknownCommittee(committee_index));
+        }
+
+        let mut definitions = self.definitions.clone();
+        definitions.retain(|def| def.committee_index != committee_index);
+        self.save(&definitions)?;
+        self.definitions = definitions;
+        self.update_clients();
+        self.committee_map.remove(&committee_index);
+        self.key_shares.write().remove(&committee_index);
         Ok(())
     }
+
+    /// Allow the operators of each committee, and only them, to ask for its shares.
+    fn update_clients(&self) {
+        *self.clients.write() = self
+            .definitions
+            .as_slice()
+            .iter()
+            .map(|def| (def.committee_index, def.node_public_keys.iter().copied().collect()))
+            .collect();
+    }
+
+    /// Atomically writes `definitions` to the `committees_dir` directory. Changes are saved before
+    /// they are applied to `self`, which is left untouched if saving fails.
+    fn save(&self, definitions: &OperatorCommitteeDefinitions) -> Result<(), Error> {
+        definitions
+            .save(&self.committees_dir)
+            .map_err(Error::UnableToSaveDefinitions)
+    }
 }
@@ -0,0 +1,213 @@
+//! Liveness and performance of the operators of each committee, as observed by this validator
+//! client when it asks them for signature shares.
+//!
+//! Every duty leaves one `DutyRecord` per operator: whether the operator signed in time, missed the
+//! duty or sent an invalid share. A rolling history of these records is kept for each operator and
+//! saved in the validator directory, so that bad operators can be spotted and replaced.
+
+use crate::validation::account_utils::write_file_via_temporary;
+use crate::validation::http_metrics::metrics;
+use crate::validation::ProductionValidatorClient;
+use crate::{DvfCommitteeIndex, DvfOperatorTsid};
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde_derive::{Deserialize, Serialize};
+use slog::warn;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{sleep, Duration};
+use types::EthSpec;
+
+/// The file name for the serialized `OperatorPerformances` struct.
+pub const OPERATOR_PERFORMANCE_FILENAME: &str = "operator_performance.json";
+
+/// The temporary file name for the serialized `OperatorPerformances` struct.
+pub const OPERATOR_PERFORMANCE_TEMP_FILENAME: &str = ".operator_performance.json.tmp";
+
+/// Number of duties remembered for each operator.
+pub const OPERATOR_HISTORY_LEN: usize = 256;
+
+lazy_static! {
+    /// Performance of the operators of all the committees of this validator client.
+    pub static ref OPERATOR_PERFORMANCES: RwLock<OperatorPerformances> = RwLock::new(OperatorPerformances::default());
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be opened.
+    UnableToOpenFile(io::Error),
+    /// The file could not be parsed as JSON.
+    UnableToParseFile(serde_json::Error),
+    /// The file could not be serialized as JSON.
+    UnableToEncodeFile(serde_json::Error),
+    /// The file or temp file could not be written to the filesystem.
+    UnableToWriteFile(filesystem::Error),
+}
+
+/// What an operator did for a duty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DutyOutcome {
+    /// The operator sent a valid signature share.
+    Signed,
+    /// The operator did not answer (in time), or refused to sign.
+    Missed,
+    /// The operator sent a share that does not verify against its public key. Only recorded when
+    /// the share is known to come from the operator, i.e., in answer to a request made to it.
+    InvalidShare,
+}
+
+impl DutyOutcome {
+    /// Label of the outcome in the metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DutyOutcome::Signed => metrics::OPERATOR_SIGNED,
+            DutyOutcome::Missed => metrics::OPERATOR_MISSED,
+            DutyOutcome::InvalidShare => metrics::OPERATOR_INVALID_SHARE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DutyRecord {
+    /// Seconds since the UNIX epoch at which the duty was recorded.
+    pub timestamp: u64,
+    pub outcome: DutyOutcome,
+    /// Time taken by the operator to send its share, if it sent one.
+    pub latency_ms: Option<u64>,
+}
+
+/// Counters over the lifetime of an operator, and the history of its most recent duties.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperatorPerformance {
+    pub signed: u64,
+    pub missed: u64,
+    pub invalid_shares: u64,
+    pub history: VecDeque<DutyRecord>,
+}
+
+impl OperatorPerformance {
+    pub fn record(&mut self, record: DutyRecord) {
+        match record.outcome {
+            DutyOutcome::Signed => self.signed += 1,
+            DutyOutcome::Missed => self.missed += 1,
+            DutyOutcome::InvalidShare => self.invalid_shares += 1,
+        }
+        if self.history.len() == OPERATOR_HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(record);
+    }
+
+    /// Mean latency of the shares in the history.
+    pub fn mean_latency_ms(&self) -> Option<u64> {
+        let latencies: Vec<u64> = self.history.iter().filter_map(|r| r.latency_ms).collect();
+        if latencies.is_empty() {
+            return None;
+        }
+        Some(latencies.iter().sum::<u64>() / latencies.len() as u64)
+    }
+
+    /// Fraction of the duties in the history for which the operator sent a valid share.
+    pub fn score(&self) -> Option<f64> {
+        if self.history.is_empty() {
+            return None;
+        }
+        let signed = self.history.iter().filter(|r| r.outcome == DutyOutcome::Signed).count();
+        Some(signed as f64 / self.history.len() as f64)
+    }
+}
+
+/// Performance of the operators, by committee index and operator id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperatorPerformances(HashMap<DvfCommitteeIndex, BTreeMap<DvfOperatorTsid, OperatorPerformance>>);
+
+impl OperatorPerformances {
+    /// Load the performances saved in `validator_dir`, if any.
+    pub fn open_or_default<P: AsRef<Path>>(validator_dir: P) -> Result<Self, Error> {
+        let path = validator_dir.as_ref().join(OPERATOR_PERFORMANCE_FILENAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(path).map_err(Error::UnableToOpenFile)?;
+        serde_json::from_reader(file).map_err(Error::UnableToParseFile)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, validator_dir: P) -> Result<(), Error> {
+        let path = validator_dir.as_ref().join(OPERATOR_PERFORMANCE_FILENAME);
+        let temp_path = validator_dir.as_ref().join(OPERATOR_PERFORMANCE_TEMP_FILENAME);
+        let bytes = serde_json::to_vec(self).map_err(Error::UnableToEncodeFile)?;
+
+        write_file_via_temporary(&path, &temp_path, &bytes).map_err(Error::UnableToWriteFile)
+    }
+
+    /// Record what operator `id` of committee `committee_index` did for a duty, and update the
+    /// metrics accordingly.
+    pub fn record(
+        &mut self,
+        committee_index: DvfCommitteeIndex,
+        id: DvfOperatorTsid,
+        outcome: DutyOutcome,
+        latency: Option<Duration>,
+    ) {
+        let committee = committee_index.to_string();
+        let operator = id.to_string();
+        let labels = [committee.as_str(), operator.as_str()];
+        metrics::inc_counter_vec(&metrics::OPERATOR_DUTIES_TOTAL, &[&committee, &operator, outcome.as_str()]);
+        if let Some(latency) = latency {
+            metrics::observe_timer_vec(&metrics::OPERATOR_SHARE_LATENCY, &labels, latency);
+        }
+
+        let performance = self.0.entry(committee_index).or_default().entry(id).or_default();
+        performance.record(DutyRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            outcome,
+            latency_ms: latency.map(|l| l.as_millis() as u64),
+        });
+        if let Some(score) = performance.score() {
+            metrics::set_float_gauge_vec(&metrics::OPERATOR_SCORE, &labels, score);
+        }
+    }
+
+    /// Performance of the operators of committee `committee_index`, by operator id.
+    pub fn committee(&self, committee_index: DvfCommitteeIndex) -> Option<&BTreeMap<DvfOperatorTsid, OperatorPerformance>> {
+        self.0.get(&committee_index)
+    }
+}
+
+/// Shortcut to record a duty in `OPERATOR_PERFORMANCES`.
+pub fn record(committee_index: DvfCommitteeIndex, id: DvfOperatorTsid, outcome: DutyOutcome, latency: Option<Duration>) {
+    OPERATOR_PERFORMANCES.write().record(committee_index, id, outcome, latency);
+}
+
+/// Spawns a service saving `OPERATOR_PERFORMANCES` in the validator directory once per epoch.
+pub fn spawn_saver<T: EthSpec>(client: &ProductionValidatorClient<T>) -> Result<(), String> {
+    let context = client.context.service_context("operator_performance".into());
+    let executor = context.executor.clone();
+    let validator_dir = client.config.validator_dir.clone();
+
+    let epoch_duration =
+        Duration::from_secs(context.eth2_config.spec.seconds_per_slot * T::slots_per_epoch());
+
+    let interval_fut = async move {
+        let log = context.log();
+
+        loop {
+            sleep(epoch_duration).await;
+            // Write the file without holding the lock.
+            let performances = OPERATOR_PERFORMANCES.read().clone();
+            if let Err(e) = performances.save(&validator_dir) {
+                warn!(log, "Unable to save operator performance"; "error" => format!("{:?}", e));
+            }
+        }
+    };
+
+    executor.spawn(interval_fut, "operator_performance_saver");
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//! Operator side of the remote signing protocol.
+//!
+//! An operator holds key shares for the validators (operator committees) it participates in and
+//! answers `OperatorMessage::SignRequest`s coming from the other operators of these committees
+//! with its signature share. Requests are only answered if they are signed by the hotstuff key of
+//! an operator of the committee, over a challenge sent by this service, and if the duty proposal
+//! they carry passes the checks of the `DutyProposalChecker` (slashing protection included). The
+//! client side of the protocol is `crate::validation::operator::RemoteOperator`.
+
+use crate::validation::duty_proposal::DutyProposalChecker;
+use crate::validation::operator::{
+    operator_codec, receive_message, request_digest, send_message, OperatorMessage,
+};
+use crate::validation::ProductionValidatorClient;
+use crate::DvfCommitteeIndex;
+use ::crypto::{PublicKey as NodePublicKey, Signature as NodeSignature};
+use log::{debug, info, warn};
+use node::config::{Export as _, Secret};
+use parking_lot::RwLock;
+use rand::RngCore as _;
+use slot_clock::SlotClock;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+use types::{EthSpec, Keypair, PublicKeyBytes, Signature};
+
+/// The file name of the hotstuff key of the operator, in the validator directory.
+pub const NODE_KEY_FILENAME: &str = "node-key.json";
+
+/// A key share held by this operator, with the validator it belongs to.
+#[derive(Clone)]
+pub struct KeyShare {
+    pub keypair: Arc<Keypair>,
+    pub validator: PublicKeyBytes,
+}
+
+/// Key shares held by this operator, indexed by the committee they belong to.
+pub type KeyShareMap = Arc<RwLock<HashMap<DvfCommitteeIndex, KeyShare>>>;
+
+/// Hotstuff keys of the operators allowed to ask for shares, indexed by committee.
+pub type ClientMap = Arc<RwLock<HashMap<DvfCommitteeIndex, HashSet<NodePublicKey>>>>;
+
+/// Load the hotstuff key of the operator at `path`, generating it on first use.
+pub fn load_or_create_node_key(path: &Path) -> Result<Secret, String> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| format!("Invalid node key path {:?}", path))?;
+    if Path::new(path).exists() {
+        return Secret::read(path).map_err(|e| format!("Unable to read node key: {}", e));
+    }
+    let secret = Secret::new();
+    secret
+        .write(path)
+        .map_err(|e| format!("Unable to write node key: {}", e))?;
+    Ok(secret)
+}
+
+pub struct OperatorService<T, E: EthSpec> {
+    listener: TcpListener,
+    /// Key shares used to answer signing requests.
+    key_shares: KeyShareMap,
+    /// Operators allowed to ask for the shares of each committee.
+    clients: ClientMap,
+    /// Checks the proposals before signing them.
+    checker: Arc<DutyProposalChecker<T, E>>,
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> OperatorService<T, E> {
+    pub fn new(
+        listener: TcpListener,
+        key_shares: KeyShareMap,
+        clients: ClientMap,
+        checker: Arc<DutyProposalChecker<T, E>>,
+    ) -> Self {
+        Self {
+            listener,
+            key_shares,
+            clients,
+            checker,
+        }
+    }
+
+    /// Bind `address`, ready to `run`.
+    pub async fn bind(
+        address: SocketAddr,
+        key_shares: KeyShareMap,
+        clients: ClientMap,
+        checker: Arc<DutyProposalChecker<T, E>>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        Ok(Self::new(listener, key_shares, clients, checker))
+    }
+
+    /// Bind `address` and spawn the service. Returns the address it listens to.
+    pub async fn spawn(
+        address: SocketAddr,
+        key_shares: KeyShareMap,
+        clients: ClientMap,
+        checker: Arc<DutyProposalChecker<T, E>>,
+    ) -> io::Result<SocketAddr> {
+        let service = Self::bind(address, key_shares, clients, checker).await?;
+        let address = service.local_addr()?;
+        tokio::spawn(service.run());
+        Ok(address)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Main loop responsible to accept incoming connections and spawn a new runner to handle it.
+    pub async fn run(self) {
+        info!(
+            "Operator signing service listening on {:?}",
+            self.listener.local_addr()
+        );
+        let listener = self.listener;
+        let handler = Arc::new(RequestHandler {
+            key_shares: self.key_shares,
+            clients: self.clients,
+            checker: self.checker,
+        });
+        loop {
+            let (socket, peer) = match listener.accept().await {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+            debug!("Incoming signing connection from {}", peer);
+            Self::spawn_runner(socket, peer, Arc::clone(&handler));
+        }
+    }
+
+    fn spawn_runner(socket: TcpStream, peer: SocketAddr, handler: Arc<RequestHandler<T, E>>) {
+        tokio::spawn(async move {
+            let mut transport = Framed::new(socket, operator_codec());
+            loop {
+                let mut challenge = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut challenge);
+                if let Err(e) = send_message(&mut transport, &OperatorMessage::Challenge(challenge)).await {
+                    warn!("Failed to send challenge to {}: {}", peer, e);
+                    return;
+                }
+                let reply = match receive_message(&mut transport).await {
+                    Ok(request) => handler.handle(request, &challenge).await,
+                    Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                        OperatorMessage::SignError(format!("malformed request: {}", e))
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+                    Err(e) => {
+                        warn!("Failed to receive message from {}: {}", peer, e);
+                        return;
+                    }
+                };
+                if let Err(e) = send_message(&mut transport, &reply).await {
+                    warn!("Failed to send reply to {}: {}", peer, e);
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// Spawns the service answering the signing requests of the other operators of the committees
+/// of `client`, on `address`.
+pub fn spawn_operator_service<E: EthSpec>(
+    client: &ProductionValidatorClient<E>,
+    address: SocketAddr,
+) -> Result<SocketAddr, String> {
+    let context = client.context.service_context("operator_service".into());
+    let (key_shares, clients) = {
+        let validators = client.validator_store.initialized_validators();
+        let validators = validators.read();
+        (validators.committees().key_shares(), validators.committees().clients())
+    };
+
+    // Bind right away so that a bad address fails the start of the validator client.
+    let listener = std::net::TcpListener::bind(address)
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .and_then(TcpListener::from_std)
+        .map_err(|e| format!("Unable to bind operator service to {}: {}", address, e))?;
+    let service = OperatorService::new(
+        listener,
+        key_shares,
+        clients,
+        client.duty_proposal_checker.clone(),
+    );
+    let address = service
+        .local_addr()
+        .map_err(|e| format!("Unable to read operator service address: {}", e))?;
+
+    context.executor.spawn(service.run(), "operator_service");
+    Ok(address)
+}
+
+struct RequestHandler<T, E: EthSpec> {
+    key_shares: KeyShareMap,
+    clients: ClientMap,
+    checker: Arc<DutyProposalChecker<T, E>>,
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> RequestHandler<T, E> {
+    async fn handle(&self, request: OperatorMessage, challenge: &[u8; 32]) -> OperatorMessage {
+        match request {
+            OperatorMessage::SignRequest {
+                committee_index,
+                proposal,
+                client,
+                signature,
+            } => match self
+                .sign(committee_index, &proposal, &client, &signature, challenge)
+                .await
+            {
+                Ok(signature) => OperatorMessage::SignResponse(signature),
+                Err(reason) => {
+                    warn!(
+                        "Refused to sign for committee {} on behalf of {}: {}",
+                        committee_index, client, reason
+                    );
+                    OperatorMessage::SignError(reason)
+                }
+            },
+            other => OperatorMessage::SignError(format!("unexpected request {:?}", other)),
+        }
+    }
+
+    async fn sign(
+        &self,
+        committee_index: DvfCommitteeIndex,
+        proposal: &[u8],
+        client: &NodePublicKey,
+        signature: &NodeSignature,
+        challenge: &[u8; 32],
+    ) -> Result<Signature, String> {
+        let digest = request_digest(challenge, committee_index, proposal);
+        signature
+            .verify(&digest, client)
+            .map_err(|_| "invalid request signature".to_string())?;
+        let is_client = self
+            .clients
+            .read()
+            .get(&committee_index)
+            .map_or(false, |clients| clients.contains(client));
+        if !is_client {
+            return Err(format!(
+                "{} is not an operator of committee {}",
+                client, committee_index
+            ));
+        }
+        let key_share = self
+            .key_shares
+            .read()
+            .get(&committee_index)
+            .cloned()
+            .ok_or_else(|| format!("no key share for committee {}", committee_index))?;
+
+        let (validator, signing_root) = self
+            .checker
+            .check_bytes(proposal)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        if validator != key_share.validator {
+            return Err(format!("proposal is for another validator {:?}", validator));
+        }
+        Ok(key_share.keypair.sk.sign(signing_root))
+    }
+}
@@ -8,17 +8,20 @@
 
 use crate::validation::http_metrics::metrics;
 use crate::validation::{OperatorCommittee};
+use crate::validation::duty_proposal::DutyProposal;
+use crate::utils::error::DvfError;
 use eth2_keystore::Keystore;
 use lockfile::Lockfile;
 use parking_lot::Mutex;
 use reqwest::Client;
+use slot_clock::SlotClock;
 use std::path::PathBuf;
 use std::sync::Arc;
 use task_executor::TaskExecutor;
 use types::*;
 use url::Url;
 use web3signer::{ForkInfo, SigningRequest, SigningResponse};
-use parking_lot::{RwLock};
+use tokio::sync::{RwLock};
 
 pub use web3signer::Web3SignerObject;
 
@@ -36,6 +39,7 @@ pub enum Error {
     TokioJoin(String),
     MergeForkNotSupported,
     CommitteeSignFailed,
+    CommitteeSignTimeout,
 }
 
 /// Enumerates all messages that can be signed by a validator.
@@ -124,11 +128,12 @@ impl SigningContext {
 
 impl SigningMethod {
     /// Return the signature of `signable_message`, with respect to the `signing_context`.
-    pub async fn get_signature<T: EthSpec, Payload: ExecPayload<T>>(
+    pub async fn get_signature<S: SlotClock, T: EthSpec, Payload: ExecPayload<T> + Clone>(
         &self,
         signable_message: SignableMessage<'_, T, Payload>,
         signing_context: SigningContext,
         spec: &ChainSpec,
+        slot_clock: &S,
         executor: &TaskExecutor,
     ) -> Result<Signature, Error> {
         let domain_hash = signing_context.domain_hash(spec);
@@ -230,26 +235,31 @@ impl SigningMethod {
 
                 Ok(response.signature)
             }
-            SigningMethod::DistributedKeystore { operator_committee, .. } => {
+            SigningMethod::DistributedKeystore { operator_committee, voting_public_key, .. } => {
                 let _timer =
-                    metrics::start_timer_vec(&metrics::SIGNING_TIMES, &[metrics::LOCAL_KEYSTORE]);
+                    metrics::start_timer_vec(&metrics::SIGNING_TIMES, &[metrics::DISTRIBUTED_KEYSTORE]);
 
-                let operator_committee = operator_committee.clone();
-                // Spawn a blocking task to produce the signature. This avoids blocking the core
-                // tokio executor.
-                let signature = executor
-                    .spawn_blocking_handle(
-                        move || operator_committee.read().sign(signing_root),
-                        "distributed_keystore_signer",
-                    )
-                    .ok_or(Error::ShuttingDown)?
-                    .await
-                    .map_err(|e| Error::TokioJoin(e.to_string()))?;
+                // The other operators check and sign the full object, not just its root.
+                let proposal = DutyProposal::new(voting_public_key.compress(), &signable_message);
+                // Do not wait on dead operators past the point where the signature is useful.
+                let timeout = proposal
+                    .time_to_deadline(slot_clock)
+                    .unwrap_or_else(|| slot_clock.slot_duration());
+                let proposal = proposal.to_bytes();
+                let operator_committee = operator_committee.read().await;
+                // Abandon the duty on shutdown rather than waiting on the other operators.
+                let signature = tokio::select! {
+                    signature = operator_committee.sign_proposal(&proposal, signing_root, timeout) => signature,
+                    () = executor.exit() => return Err(Error::ShuttingDown),
+                };
 
                 match signature {
                     Ok(signature) => {
                         Ok(signature)
                     }
+                    Err(DvfError::SigningDeadlineExceeded { .. }) => {
+                        Err(Error::CommitteeSignTimeout)
+                    }
                     Err(_) => {
                         Err(Error::CommitteeSignFailed)
                     }
@@ -1 +1,2 @@
 pub mod share_builder;
+pub mod share_bundle;
@@ -125,7 +125,9 @@ impl ShareBuilder {
         let keypair = generate_deterministic_keypair(deterministic_key_index);
 
         let mut m_threshold = ThresholdSignature::new(threshold);  
-        let (kps, ids) = m_threshold.deterministic_key_split(&keypair.sk, total_splits);
+        let (kps, ids, commitments) = m_threshold
+            .deterministic_key_split(&keypair.sk, total_splits)
+            .map_err(|e| BuilderError::InsecureKeysError(format!("Unable to split key: {:?}", e)))?;
 
         for i in 0..total_splits {
 
@@ -134,7 +136,7 @@ impl ShareBuilder {
                 .kdf(insecure_kdf())
                 .build()
                 .map_err(|e| BuilderError::InsecureKeysError(format!("Unable to build keystore: {:?}", e)))?;
-            let keystore_share = KeystoreShare::new(keystore, keypair.pk.clone(), deterministic_key_index as u64, ids[i]);
+            let keystore_share = KeystoreShare::new(keystore, keypair.pk.clone(), deterministic_key_index as u64, ids[i], commitments.clone());
 
             ShareBuilder::new(base_validators_dir.clone())
                 .password_dir(password_dir.clone())
@@ -150,6 +152,8 @@ impl ShareBuilder {
             ids: ids,
             public_keys: kps.iter().map(|x| x.pk.clone()).collect(),
             socket_addresses: (0..total_splits).map(|j| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), (4000 + j) as u16)).collect(),
+            commitments,
+            node_public_keys: Vec::new(),
         };
         let committee_def_path = default_operator_committee_definition_path(
             &keypair.pk,
@@ -273,7 +277,9 @@ pub fn build_deterministic_committees_file(
         let keypair = generate_deterministic_keypair(indices[i]);
 
         let mut m_threshold = ThresholdSignature::new(threshold);  
-        let (kps, ids) = m_threshold.deterministic_key_split(&keypair.sk, total_splits);
+        let (kps, ids, commitments) = m_threshold
+            .deterministic_key_split(&keypair.sk, total_splits)
+            .map_err(|e| format!("Unable to split key: {:?}", e))?;
 
         defs.push(
             OperatorCommitteeDefinition {
@@ -284,6 +290,8 @@ pub fn build_deterministic_committees_file(
                 ids: ids,
                 public_keys: kps.iter().map(|x| x.pk.clone()).collect(),
                 socket_addresses: (0..total_splits).map(|j| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), (4000 + j) as u16)).collect(),
+                commitments,
+                node_public_keys: Vec::new(),
             }
         );
     } 
@@ -0,0 +1,221 @@
+//! Splits the key of a new distributed validator into one bundle per operator.
+//!
+//! A bundle holds everything an operator needs to run its share of the validator: its key share
+//! sealed to the node key of the operator, the definition of the committee and, optionally, the
+//! deposit data of the validator. No password is handed out: only the operator can open its
+//! share.
+
+use crate::crypto::ThresholdSignature;
+use crate::utils::error::DvfError;
+use crate::validation::account_utils::random_password;
+use crate::validation::eth2_keystore_share::envelope::{Error as EnvelopeError, KeystoreShareEnvelope};
+use crate::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use crate::validation::operator_committee_definitions::{DefinitionError, OperatorCommitteeDefinition};
+use crate::DvfCommitteeIndex;
+use ::crypto::PublicKey as NodePublicKey;
+use deposit_contract::{encode_eth1_tx_data, Error as DepositError};
+use eth2_keystore::{json_keystore::Kdf, Error as KeystoreError, KeystoreBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, File};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use types::{ChainSpec, DepositData, Hash256, Keypair, Signature};
+
+pub const SHARE_BUNDLE_FILE: &str = "share-bundle.json";
+
+#[derive(Debug)]
+pub enum Error {
+    /// The committee of the validator would be invalid.
+    InvalidCommittee(DefinitionError),
+    UnableToSplitKey(DvfError),
+    UnableToBuildKeystore(KeystoreError),
+    UnableToSealShare(EnvelopeError),
+    UnableToEncodeDeposit(DepositError),
+    UnableToCreateDir(io::Error),
+    BundleAlreadyExists(PathBuf),
+    UnableToSaveBundle(io::Error),
+    UnableToEncodeBundle(serde_json::Error),
+    UnableToReadBundle(io::Error),
+    UnableToDecodeBundle(serde_json::Error),
+}
+
+/// Everything operator `operator_id()` needs to run its share of a distributed validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareBundle {
+    /// The key share of the operator, sealed to its node key.
+    pub keystore_share_envelope: KeystoreShareEnvelope,
+    pub committee_definition: OperatorCommitteeDefinition,
+    /// Signed with the key of the validator, which no operator holds afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deposit_data: Option<DepositData>,
+}
+
+impl ShareBundle {
+    /// Returns the id of the operator the bundle is for.
+    pub fn operator_id(&self) -> u64 {
+        self.keystore_share_envelope.share_id
+    }
+
+    /// Returns the RLP-encoded transaction data of the deposit, if any.
+    pub fn eth1_deposit_tx_data(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.deposit_data
+            .as_ref()
+            .map(|deposit_data| encode_eth1_tx_data(deposit_data).map_err(Error::UnableToEncodeDeposit))
+            .transpose()
+    }
+
+    /// Write `self` to `dir/{operator_id}`, which must not exist yet.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf, Error> {
+        let dir = dir.as_ref().join(format!("{}", self.operator_id()));
+        if dir.exists() {
+            return Err(Error::BundleAlreadyExists(dir));
+        }
+        create_dir_all(&dir).map_err(Error::UnableToCreateDir)?;
+
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(SHARE_BUNDLE_FILE))
+            .map_err(Error::UnableToSaveBundle)?;
+        serde_json::to_writer_pretty(file, self).map_err(Error::UnableToEncodeBundle)?;
+        Ok(dir)
+    }
+
+    /// Instantiates `self` by reading a JSON file at `path`.
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::UnableToReadBundle)?;
+        serde_json::from_reader(file).map_err(Error::UnableToDecodeBundle)
+    }
+}
+
+/// A builder for splitting the key of a distributed validator into `ShareBundle`s.
+pub struct ShareBundleBuilder<'a> {
+    committee_index: DvfCommitteeIndex,
+    threshold: u64,
+    socket_addresses: Vec<SocketAddr>,
+    node_public_keys: Vec<NodePublicKey>,
+    voting_keypair: Option<Keypair>,
+    kdf: Option<Kdf>,
+    deposit_info: Option<(Hash256, u64, &'a ChainSpec)>,
+}
+
+impl<'a> ShareBundleBuilder<'a> {
+    /// Instantiate a new builder for a committee of one operator per socket address, any
+    /// `threshold` of which can sign for the validator. The share of each operator is sealed to
+    /// its node public key, given in the same order.
+    pub fn new(
+        committee_index: DvfCommitteeIndex,
+        threshold: u64,
+        socket_addresses: Vec<SocketAddr>,
+        node_public_keys: Vec<NodePublicKey>,
+    ) -> Self {
+        Self {
+            committee_index,
+            threshold,
+            socket_addresses,
+            node_public_keys,
+            voting_keypair: None,
+            kdf: None,
+            deposit_info: None,
+        }
+    }
+
+    /// Split `keypair` instead of a random one.
+    pub fn voting_keypair(mut self, keypair: Keypair) -> Self {
+        self.voting_keypair = Some(keypair);
+        self
+    }
+
+    /// Encrypt the keystore shares with `kdf` instead of the default one, before they are sealed.
+    pub fn kdf(mut self, kdf: Kdf) -> Self {
+        self.kdf = Some(kdf);
+        self
+    }
+
+    /// Upon build, create the deposit of `deposit_amount` gwei to `withdrawal_credentials`.
+    pub fn create_deposit_data(mut self, withdrawal_credentials: Hash256, deposit_amount: u64, spec: &'a ChainSpec) -> Self {
+        self.deposit_info = Some((withdrawal_credentials, deposit_amount, spec));
+        self
+    }
+
+    /// Consumes `self`, returning one bundle per operator.
+    pub fn build(self) -> Result<Vec<ShareBundle>, Error> {
+        let total = self.socket_addresses.len() as u64;
+        if self.threshold == 0 || self.threshold > total {
+            return Err(Error::InvalidCommittee(DefinitionError::InvalidThreshold {
+                threshold: self.threshold,
+                total,
+            }));
+        }
+        // Unlike in committee definitions, node public keys are mandatory: shares are sealed to
+        // them.
+        if self.node_public_keys.len() as u64 != total {
+            return Err(Error::InvalidCommittee(DefinitionError::LengthMismatch {
+                field: "node_public_keys",
+                got: self.node_public_keys.len(),
+                expected: total,
+            }));
+        }
+        let keypair = self.voting_keypair.unwrap_or_else(Keypair::random);
+
+        let mut m_threshold = ThresholdSignature::new(self.threshold as usize);
+        let (kps, ids, commitments) = m_threshold
+            .key_split(&keypair.sk, total as usize)
+            .map_err(Error::UnableToSplitKey)?;
+        let committee_definition = OperatorCommitteeDefinition {
+            total,
+            threshold: self.threshold,
+            committee_index: self.committee_index,
+            voting_public_key: keypair.pk.clone(),
+            ids: ids.clone(),
+            public_keys: kps.iter().map(|kp| kp.pk.clone()).collect(),
+            socket_addresses: self.socket_addresses,
+            commitments: commitments.clone(),
+            node_public_keys: self.node_public_keys.clone(),
+        };
+        committee_definition.validate().map_err(Error::InvalidCommittee)?;
+
+        let deposit_data = self.deposit_info.map(|(withdrawal_credentials, amount, spec)| {
+            let mut deposit_data = DepositData {
+                pubkey: keypair.pk.clone().into(),
+                withdrawal_credentials,
+                amount,
+                signature: Signature::empty().into(),
+            };
+            deposit_data.signature = deposit_data.create_signature(&keypair.sk, spec);
+            deposit_data
+        });
+
+        kps.iter()
+            .zip(ids.iter())
+            .zip(self.node_public_keys.iter())
+            .map(|((kp, id), node_public_key)| {
+                // The password never leaves this function: it only protects the share until it
+                // is sealed.
+                let password = random_password();
+                let keystore = KeystoreBuilder::new(kp, password.as_bytes(), "".into())
+                    .and_then(|builder| match &self.kdf {
+                        Some(kdf) => builder.kdf(kdf.clone()).build(),
+                        None => builder.build(),
+                    })
+                    .map_err(Error::UnableToBuildKeystore)?;
+                let keystore_share = KeystoreShare::new(
+                    keystore,
+                    keypair.pk.clone(),
+                    self.committee_index,
+                    *id,
+                    commitments.clone(),
+                );
+                let keystore_share_envelope =
+                    KeystoreShareEnvelope::seal(&keystore_share, password.as_bytes(), node_public_key)
+                        .map_err(Error::UnableToSealShare)?;
+                Ok(ShareBundle {
+                    keystore_share_envelope,
+                    committee_definition: committee_definition.clone(),
+                    deposit_data: deposit_data.clone(),
+                })
+            })
+            .collect()
+    }
+}
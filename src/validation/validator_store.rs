@@ -341,10 +341,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         let signing_context = self.signing_context(Domain::Randao, signing_epoch);
 
         let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+            .get_signature::<_, E, BlindedPayload<E>>(
                 SignableMessage::RandaoReveal(signing_epoch),
                 signing_context,
                 &self.spec,
+                &self.slot_clock,
                 &self.task_executor,
             )
             .await?;
@@ -362,7 +363,7 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             .suggested_fee_recipient(validator_pubkey)
     }
 
-    pub async fn sign_block<Payload: ExecPayload<E>>(
+    pub async fn sign_block<Payload: ExecPayload<E> + Clone>(
         &self,
         validator_pubkey: PublicKeyBytes,
         block: BeaconBlock<E, Payload>,
@@ -400,10 +401,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
 
                 let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
                 let signature = signing_method
-                    .get_signature::<E, Payload>(
+                    .get_signature::<_, E, Payload>(
                         SignableMessage::BeaconBlock(&block),
                         signing_context,
                         &self.spec,
+                        &self.slot_clock,
                         &self.task_executor,
                     )
                     .await?;
@@ -469,10 +471,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             Ok(Safe::Valid) => {
                 let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
                 let signature = signing_method
-                    .get_signature::<E, BlindedPayload<E>>(
+                    .get_signature::<_, E, BlindedPayload<E>>(
                         SignableMessage::AttestationData(&attestation.data),
                         signing_context,
                         &self.spec,
+                        &self.slot_clock,
                         &self.task_executor,
                     )
                     .await?;
@@ -546,10 +549,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
 
         let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
         let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+            .get_signature::<_, E, BlindedPayload<E>>(
                 SignableMessage::SignedAggregateAndProof(&message),
                 signing_context,
                 &self.spec,
+                &self.slot_clock,
                 &self.task_executor,
             )
             .await?;
@@ -579,10 +583,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         let signing_method = self.doppelganger_bypassed_signing_method(validator_pubkey)?;
 
         let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+            .get_signature::<_, E, BlindedPayload<E>>(
                 SignableMessage::SelectionProof(slot),
                 signing_context,
                 &self.spec,
+                &self.slot_clock,
                 &self.task_executor,
             )
             .await
@@ -618,10 +623,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         };
 
         let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+            .get_signature::<_, E, BlindedPayload<E>>(
                 SignableMessage::SyncSelectionProof(&message),
                 signing_context,
                 &self.spec,
+                &self.slot_clock,
                 &self.task_executor,
             )
             .await
@@ -644,13 +650,14 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         let signing_method = self.doppelganger_bypassed_signing_method(*validator_pubkey)?;
 
         let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+            .get_signature::<_, E, BlindedPayload<E>>(
                 SignableMessage::SyncCommitteeSignature {
                     beacon_block_root,
                     slot,
                 },
                 signing_context,
                 &self.spec,
+                &self.slot_clock,
                 &self.task_executor,
             )
             .await
@@ -689,10 +696,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         };
 
         let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+            .get_signature::<_, E, BlindedPayload<E>>(
                 SignableMessage::SignedContributionAndProof(&message),
                 signing_context,
                 &self.spec,
+                &self.slot_clock,
                 &self.task_executor,
             )
             .await
@@ -0,0 +1,130 @@
+use dvf::crypto::{ThresholdSignature};
+use dvf::utils::error::DvfError;
+use bls::{Keypair, Signature, PublicKey};
+use types::Hash256;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+const ROUNDS: usize = 20;
+
+#[test]
+fn prop_random_subsets_aggregate_to_master_signature() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..ROUNDS {
+        let n: usize = rng.gen_range(1..=10);
+        let t: usize = rng.gen_range(1..=n);
+        let mut m_threshold = ThresholdSignature::new(t);
+        let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+        let message = Hash256::random();
+        let master_sig = kp.sk.sign(message);
+
+        // Any random subset of at least t shares, in any order
+        let size = rng.gen_range(t..=n);
+        let subset: Vec<usize> = (0..n).collect::<Vec<_>>().choose_multiple(&mut rng, size).cloned().collect();
+        let sigs: Vec<Signature> = subset.iter().map(|i| kps[*i].sk.sign(message)).collect();
+        let sigs: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = subset.iter().map(|i| &kps[*i].pk).collect();
+        let sub_ids: Vec<u64> = subset.iter().map(|i| ids[*i]).collect();
+
+        let agg_sig = m_threshold.threshold_aggregate(&sigs[..], &pks[..], &sub_ids[..], message).unwrap();
+        assert_eq!(agg_sig, master_sig, "n={} t={} subset={:?}", n, t, sub_ids);
+
+        // Interpolating exactly t unverified shares gives the same result
+        let agg_sig = m_threshold.unsafe_aggregate(&sigs[..t], &sub_ids[..t]).unwrap();
+        assert_eq!(agg_sig, master_sig);
+    }
+}
+
+#[test]
+fn prop_invalid_shares_are_skipped() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..ROUNDS {
+        let n: usize = rng.gen_range(2..=10);
+        let t: usize = rng.gen_range(1..n);
+        let mut m_threshold = ThresholdSignature::new(t);
+        let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+        let message = Hash256::random();
+
+        // Corrupt up to n - t shares
+        let bad = rng.gen_range(1..=(n - t));
+        let mut sigs: Vec<Signature> = kps.iter().map(|k| k.sk.sign(message)).collect();
+        for i in (0..n).collect::<Vec<_>>().choose_multiple(&mut rng, bad) {
+            sigs[*i] = Keypair::random().sk.sign(message);
+        }
+        let sigs: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = kps.iter().map(|k| &k.pk).collect();
+        let agg_sig = m_threshold.threshold_aggregate(&sigs[..], &pks[..], &ids[..], message).unwrap();
+        assert_eq!(agg_sig, kp.sk.sign(message));
+    }
+}
+
+#[test]
+fn test_threshold_aggregate_errors() {
+    let (t, n) = (3, 5);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (_, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let message = Hash256::random();
+    let sigs: Vec<Signature> = kps.iter().map(|k| k.sk.sign(message)).collect();
+    let sigs: Vec<&Signature> = sigs.iter().collect();
+    let pks: Vec<&PublicKey> = kps.iter().map(|k| &k.pk).collect();
+
+    // Zero and duplicated ids
+    let mut zero_ids = ids.clone();
+    zero_ids[0] = 0;
+    assert_eq!(m_threshold.threshold_aggregate(&sigs[..], &pks[..], &zero_ids[..], message), Err(DvfError::InvalidOperatorIds));
+    assert_eq!(m_threshold.unsafe_aggregate(&sigs[..], &zero_ids[..]), Err(DvfError::InvalidOperatorIds));
+    let mut dup_ids = ids.clone();
+    dup_ids[1] = dup_ids[0];
+    assert_eq!(m_threshold.threshold_aggregate(&sigs[..], &pks[..], &dup_ids[..], message), Err(DvfError::InvalidOperatorIds));
+
+    // Length mismatch
+    assert_eq!(
+        m_threshold.threshold_aggregate(&sigs[..], &pks[..n - 1], &ids[..], message),
+        Err(DvfError::DifferentLength{x: n, y: n - 1})
+    );
+
+    // Too few shares, or too few valid shares
+    assert_eq!(
+        m_threshold.threshold_aggregate(&sigs[..t - 1], &pks[..t - 1], &ids[..t - 1], message),
+        Err(DvfError::InsufficientSignatures{got: t - 1, expected: t})
+    );
+    let other = Hash256::random();
+    assert_eq!(
+        m_threshold.threshold_aggregate(&sigs[..], &pks[..], &ids[..], other),
+        Err(DvfError::InsufficientValidSignatures{got: 0, expected: t, invalid: ids.clone()})
+    );
+}
+
+#[test]
+fn prop_batch_verification_finds_bad_shares() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..ROUNDS {
+        let n: usize = rng.gen_range(1..=16);
+        let mut m_threshold = ThresholdSignature::new(1);
+        let (_, kps, ids) = m_threshold.key_gen(n).unwrap();
+        let message = Hash256::random();
+
+        let bad = rng.gen_range(0..=n);
+        let mut bad_ids: Vec<u64> = ids.choose_multiple(&mut rng, bad).cloned().collect();
+        bad_ids.sort_unstable();
+        let sigs: Vec<Signature> = kps.iter().zip(ids.iter())
+            .map(|(k, id)| if bad_ids.contains(id) { k.sk.sign(Hash256::random()) } else { k.sk.sign(message) })
+            .collect();
+        let sigs: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = kps.iter().map(|k| &k.pk).collect();
+
+        let mut found = ThresholdSignature::invalid_share_ids(&sigs[..], &pks[..], &ids[..], message);
+        found.sort_unstable();
+        assert_eq!(found, bad_ids);
+
+        // Aggregation reports the same shares, whether it succeeds or not.
+        let mut reported = match m_threshold.threshold_aggregate_with_invalid_ids(&sigs[..], &pks[..], &ids[..], message) {
+            Ok((_, invalid)) => invalid,
+            Err(DvfError::InsufficientValidSignatures { invalid, .. }) => invalid,
+            Err(e) => panic!("unexpected error {:?}", e),
+        };
+        reported.sort_unstable();
+        assert_eq!(reported, bad_ids);
+        assert_eq!(m_threshold.threshold_aggregate(&sigs[..], &pks[..], &ids[..], message).is_ok(), bad < n);
+    }
+}
@@ -0,0 +1,100 @@
+//! Checks that the blst and miracl_core threshold backends agree, byte for byte.
+//!
+//! Run with `cargo test --features miracl_threshold`.
+#![cfg(feature = "miracl_threshold")]
+
+use dvf::crypto::blst_threshold_implementations::ThresholdSignature as BlstThresholdSignature;
+use dvf::crypto::miracl_threshold_implementations::ThresholdSignature as MiraclThresholdSignature;
+use dvf::utils::error::DvfError;
+use bls::{Keypair, Signature, PublicKey};
+use types::Hash256;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+const ROUNDS: usize = 10;
+
+#[test]
+fn test_backends_aggregate_identical_signatures() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..ROUNDS {
+        let n: usize = rng.gen_range(1..=8);
+        let t: usize = rng.gen_range(1..=n);
+        let kp = Keypair::random();
+        let (kps, ids, _) = BlstThresholdSignature::new(t).key_split(&kp.sk, n).unwrap();
+        let message = Hash256::random();
+
+        let size = rng.gen_range(t..=n);
+        let subset: Vec<usize> = (0..n).collect::<Vec<_>>().choose_multiple(&mut rng, size).cloned().collect();
+        let sigs: Vec<Signature> = subset.iter().map(|i| kps[*i].sk.sign(message)).collect();
+        let sigs: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = subset.iter().map(|i| &kps[*i].pk).collect();
+        let sub_ids: Vec<u64> = subset.iter().map(|i| ids[*i]).collect();
+
+        let blst_sig = BlstThresholdSignature::new(t).threshold_aggregate(&sigs[..], &pks[..], &sub_ids[..], message).unwrap();
+        let miracl_sig = MiraclThresholdSignature::new(t).threshold_aggregate(&sigs[..], &pks[..], &sub_ids[..], message).unwrap();
+        assert_eq!(blst_sig.serialize(), miracl_sig.serialize(), "n={} t={} subset={:?}", n, t, sub_ids);
+        assert_eq!(miracl_sig, kp.sk.sign(message));
+
+        let blst_sig = BlstThresholdSignature::new(t).unsafe_aggregate(&sigs[..], &sub_ids[..]).unwrap();
+        let miracl_sig = MiraclThresholdSignature::new(t).unsafe_aggregate(&sigs[..], &sub_ids[..]).unwrap();
+        assert_eq!(blst_sig.serialize(), miracl_sig.serialize());
+    }
+}
+
+#[test]
+fn test_backends_agree_on_public_keys() {
+    let (t, n) = (3, 5);
+    let kp = Keypair::random();
+    let (kps, ids, commitments) = BlstThresholdSignature::new(t).key_split(&kp.sk, n).unwrap();
+
+    for (k, id) in kps.iter().zip(ids.iter()) {
+        let blst_pk = BlstThresholdSignature::share_public_key(*id, &commitments).unwrap();
+        let miracl_pk = MiraclThresholdSignature::share_public_key(*id, &commitments).unwrap();
+        assert_eq!(blst_pk.serialize(), miracl_pk.serialize());
+        assert_eq!(miracl_pk, k.pk);
+    }
+
+    let pks: Vec<&PublicKey> = kps.iter().map(|k| &k.pk).collect();
+    let blst_pk = BlstThresholdSignature::interpolate_public_keys(&pks[1..1 + t], &ids[1..1 + t]).unwrap();
+    let miracl_pk = MiraclThresholdSignature::interpolate_public_keys(&pks[1..1 + t], &ids[1..1 + t]).unwrap();
+    assert_eq!(blst_pk.serialize(), miracl_pk.serialize());
+    assert_eq!(miracl_pk, kp.pk);
+}
+
+#[test]
+fn test_backends_reject_the_same_shares() {
+    let (t, n) = (2, 4);
+    let mut m_threshold = MiraclThresholdSignature::new(t);
+    let (_, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let message = Hash256::random();
+
+    let mut sigs: Vec<Signature> = kps.iter().map(|k| k.sk.sign(message)).collect();
+    sigs[1] = kps[1].sk.sign(Hash256::random());
+    sigs[3] = kps[2].sk.sign(message);
+    let sigs: Vec<&Signature> = sigs.iter().collect();
+    let pks: Vec<&PublicKey> = kps.iter().map(|k| &k.pk).collect();
+
+    let blst_bad = BlstThresholdSignature::invalid_share_ids(&sigs[..], &pks[..], &ids[..], message);
+    let miracl_bad = MiraclThresholdSignature::invalid_share_ids(&sigs[..], &pks[..], &ids[..], message);
+    assert_eq!(blst_bad, vec![ids[1], ids[3]]);
+    assert_eq!(miracl_bad, blst_bad);
+
+    let blst_agg = BlstThresholdSignature::new(t).threshold_aggregate_with_invalid_ids(&sigs[..], &pks[..], &ids[..], message).unwrap();
+    let miracl_agg = m_threshold.threshold_aggregate_with_invalid_ids(&sigs[..], &pks[..], &ids[..], message).unwrap();
+    assert_eq!(blst_agg.1, vec![ids[1], ids[3]]);
+    assert_eq!(miracl_agg, blst_agg);
+
+    assert_eq!(
+        m_threshold.threshold_aggregate(&sigs[..2], &pks[..2], &ids[..2], message),
+        Err(DvfError::InsufficientValidSignatures { got: 1, expected: 2, invalid: vec![ids[1]] })
+    );
+}
+
+#[test]
+fn test_backends_agree_on_pedersen_commitments() {
+    let (value, blinding) = (Keypair::random(), Keypair::random());
+    let blst_commitment = BlstThresholdSignature::pedersen_commit(&value.sk, &blinding.sk).unwrap();
+    let miracl_commitment = MiraclThresholdSignature::pedersen_commit(&value.sk, &blinding.sk).unwrap();
+    assert_eq!(blst_commitment.serialize(), miracl_commitment.serialize());
+    assert_ne!(blst_commitment, value.pk);
+}
@@ -0,0 +1,112 @@
+use dvf::crypto::{ThresholdSignature};
+use dvf::crypto::dkg::{DkgMessage, DkgOutput, DkgParty};
+use dvf::simulator::local_dkg::{run_local_dkg, DealerBehaviour};
+use bls::{Signature, PublicKey};
+use types::Hash256;
+use std::collections::HashMap;
+use eth2_hashing::{Context, Sha256Context};
+
+fn check_outputs(outputs: &[DkgOutput], t: usize) {
+    let master_pk = &outputs[0].master_public_key;
+    for output in outputs {
+        assert_eq!(&output.master_public_key, master_pk, "Operators disagree on the master public key");
+        assert_eq!(output.commitments, outputs[0].commitments);
+        assert_eq!(output.qualified, outputs[0].qualified);
+        assert_eq!(output.reconstructed, outputs[0].reconstructed);
+        let idx = output.ids.iter().position(|id| *id == output.id).unwrap();
+        assert_eq!(output.share_public_keys[idx], output.keypair.pk);
+    }
+
+    let message = "hello world";
+    let mut context = Context::new();
+    context.update(message.as_bytes());
+    let message = Hash256::from_slice(&context.finalize());
+
+    // Any t operators can sign for the master public key.
+    let signers = &outputs[outputs.len() - t..];
+    let sigs: Vec<Signature> = signers.iter().map(|o| o.keypair.sk.sign(message)).collect();
+    let sigs: Vec<&Signature> = sigs.iter().collect();
+    let pks: Vec<&PublicKey> = signers.iter().map(|o| &o.keypair.pk).collect();
+    let ids: Vec<u64> = signers.iter().map(|o| o.id).collect();
+    let agg_sig = ThresholdSignature::new(t)
+        .threshold_aggregate(&sigs[..], &pks[..], &ids[..], message)
+        .unwrap();
+    assert!(agg_sig.verify(master_pk, message), "Aggregate signature verification failed");
+}
+
+#[tokio::test]
+async fn test_dkg_honest() {
+    let t = 3;
+    let ids: Vec<u64> = vec![1, 2, 3, 4];
+    let outputs: Vec<DkgOutput> = run_local_dkg(ids.clone(), t, HashMap::new())
+        .await
+        .into_iter()
+        .map(|o| o.unwrap())
+        .collect();
+    assert_eq!(outputs[0].qualified, ids);
+    assert!(outputs[0].reconstructed.is_empty());
+    check_outputs(&outputs, t);
+}
+
+#[tokio::test]
+async fn test_dkg_complaints() {
+    let t = 3;
+    let ids: Vec<u64> = vec![1, 2, 3, 4, 5];
+    let mut behaviours = HashMap::new();
+    behaviours.insert(2, DealerBehaviour::BadShareThenJustify(3));
+    behaviours.insert(4, DealerBehaviour::BadShareNoJustify(1));
+    behaviours.insert(5, DealerBehaviour::Silent);
+    let outputs: Vec<DkgOutput> = run_local_dkg(ids, t, behaviours)
+        .await
+        .into_iter()
+        .map(|o| o.unwrap())
+        .collect();
+    // Dealer 2 answered the complaint, dealer 4 did not, dealer 5 never dealt.
+    assert_eq!(outputs[0].qualified, vec![1, 2, 3]);
+    assert!(outputs[0].reconstructed.is_empty());
+    check_outputs(&outputs, t);
+}
+
+#[tokio::test]
+async fn test_dkg_reconstruct_public_coefficients() {
+    let t = 3;
+    let ids: Vec<u64> = vec![1, 2, 3, 4, 5];
+    let mut behaviours = HashMap::new();
+    behaviours.insert(2, DealerBehaviour::BadPublicCoefficients);
+    behaviours.insert(4, DealerBehaviour::SilentPublicCoefficients);
+    let outputs: Vec<DkgOutput> = run_local_dkg(ids.clone(), t, behaviours)
+        .await
+        .into_iter()
+        .map(|o| o.unwrap())
+        .collect();
+    // Dealers cannot drop out once qualified: their polynomial is reconstructed instead.
+    assert_eq!(outputs[0].qualified, ids);
+    assert_eq!(outputs[0].reconstructed, vec![2, 4]);
+    check_outputs(&outputs, t);
+}
+
+#[tokio::test]
+async fn test_dkg_not_enough_dealers() {
+    let mut behaviours = HashMap::new();
+    behaviours.insert(1, DealerBehaviour::Silent);
+    behaviours.insert(2, DealerBehaviour::Silent);
+    let outputs = run_local_dkg(vec![1, 2, 3, 4], 3, behaviours).await;
+    assert!(outputs.iter().all(|o| o.is_err()));
+}
+
+#[test]
+fn test_dkg_messages_encoding() {
+    let ids: Vec<u64> = vec![1, 2, 3];
+    let dealer = DkgParty::new(1, ids.clone(), 2).unwrap();
+    let mut receiver = DkgParty::new(2, ids, 2).unwrap();
+    for msg in dealer.deal().unwrap() {
+        let decoded: DkgMessage = serde_json::from_slice(&serde_json::to_vec(&msg).unwrap()).unwrap();
+        if let (DkgMessage::Share { share, .. }, DkgMessage::Share { share: decoded_share, .. }) = (&msg, &decoded) {
+            assert_eq!(share.serialize().as_bytes(), decoded_share.serialize().as_bytes());
+        }
+        receiver.handle(decoded);
+    }
+    // The decoded share matches the decoded commitments.
+    assert!(receiver.complaints().is_empty());
+    assert!(serde_json::from_str::<DkgMessage>(r#"{"Share":{"dealer":1,"receiver":2,"share":"0x00","blinding":"0x00"}}"#).is_err());
+}
@@ -0,0 +1,156 @@
+use dvf::validation::duty_proposal::{DutyKind, DutyObject, DutyProposal, DutyProposalChecker};
+use dvf::utils::error::DvfError;
+use slashing_protection::SlashingDatabase;
+use slot_clock::{ManualSlotClock, SlotClock};
+use std::time::Duration;
+use tempfile::tempdir;
+use types::{
+    AttestationData, BeaconBlock, ChainSpec, Checkpoint, Domain, Epoch, EthSpec, Hash256, Keypair,
+    MainnetEthSpec, PublicKeyBytes, SignedRoot, Slot, SyncAggregatorSelectionData,
+};
+
+type E = MainnetEthSpec;
+
+fn attestation_data(slot: u64, root: u64) -> AttestationData {
+    let epoch = Slot::new(slot).epoch(E::slots_per_epoch());
+    AttestationData {
+        slot: Slot::new(slot),
+        index: 0,
+        beacon_block_root: Hash256::from_low_u64_be(root),
+        source: Checkpoint { epoch: Epoch::new(0), root: Hash256::zero() },
+        target: Checkpoint { epoch, root: Hash256::from_low_u64_be(root) },
+    }
+}
+
+fn attestation(validator: PublicKeyBytes, slot: u64, root: u64) -> DutyProposal<E> {
+    let data = attestation_data(slot, root);
+    DutyProposal { slot: data.slot, validator, object: DutyObject::Attestation(data) }
+}
+
+fn checker(spec: &ChainSpec, validator: PublicKeyBytes, dir: &std::path::Path) -> DutyProposalChecker<ManualSlotClock, E> {
+    let slashing_protection = SlashingDatabase::create(&dir.join("slashing_protection.sqlite")).unwrap();
+    slashing_protection.register_validator(validator).unwrap();
+    DutyProposalChecker::new(spec.clone(), Hash256::zero(), slashing_protection)
+}
+
+#[test]
+fn test_duty_proposal_encoding() {
+    let spec = ChainSpec::mainnet();
+    let validator = Keypair::random().pk.compress();
+
+    let proposal = attestation(validator, 33, 1);
+    assert_eq!(proposal.kind(), DutyKind::Attestation);
+    assert!(proposal.is_consistent());
+    let decoded = DutyProposal::<E>::from_bytes(&proposal.to_bytes()).unwrap();
+    assert_eq!(decoded, proposal);
+
+    // Operators sign the same root as a regular validator client would.
+    let data = attestation_data(33, 1);
+    let domain = spec.get_domain(data.target.epoch, Domain::BeaconAttester, &spec.fork_at_epoch(data.target.epoch), Hash256::zero());
+    assert_eq!(proposal.signing_root(&spec, Hash256::zero()), data.signing_root(domain));
+
+    let block = BeaconBlock::<E>::empty(&spec);
+    let proposal = DutyProposal::<E> { slot: block.slot(), validator, object: DutyObject::BeaconBlock(block) };
+    assert_eq!(proposal.kind(), DutyKind::BeaconBlock);
+    assert_eq!(DutyProposal::<E>::from_bytes(&proposal.to_bytes()).unwrap(), proposal);
+
+    assert_eq!(DutyProposal::<E>::from_bytes(b"not a proposal"), Err(DvfError::MalformedDutyProposal));
+}
+
+#[tokio::test]
+async fn test_duty_proposal_checker() {
+    let spec = ChainSpec::mainnet();
+    let validator = Keypair::random().pk.compress();
+    let dir = tempdir().unwrap();
+    let checker = checker(&spec, validator, dir.path());
+
+    let proposal = attestation(validator, 33, 1);
+    let checked = checker.check_bytes(&proposal.to_bytes()).await.unwrap();
+    assert_eq!(checked, (validator, proposal.signing_root(&spec, Hash256::zero())));
+    // The same proposal may be committed again.
+    assert!(checker.check(&proposal).await.is_ok());
+
+    // Voting for another root in the same target epoch is slashable.
+    let double_vote = attestation(validator, 33, 2);
+    assert!(matches!(checker.check(&double_vote).await, Err(DvfError::SlashableDutyProposal { slot: 33, .. })));
+
+    // The slot of the proposal must be the one of the object.
+    let mut inconsistent = attestation(validator, 65, 3);
+    inconsistent.slot = Slot::new(66);
+    assert!(matches!(checker.check(&inconsistent).await, Err(DvfError::DutyProposalRejected { slot: 66, .. })));
+
+    // Unknown validators are not signed for.
+    let stranger = attestation(Keypair::random().pk.compress(), 97, 4);
+    assert!(matches!(checker.check(&stranger).await, Err(DvfError::SlashableDutyProposal { .. })));
+}
+
+#[tokio::test]
+async fn test_duty_proposal_from_the_future() {
+    let spec = ChainSpec::mainnet();
+    let validator = Keypair::random().pk.compress();
+    let dir = tempdir().unwrap();
+    let slot_clock = ManualSlotClock::new(Slot::new(0), Duration::from_secs(0), Duration::from_secs(12));
+    slot_clock.set_slot(40);
+    let checker = checker(&spec, validator, dir.path()).slot_clock(slot_clock);
+
+    assert!(checker.check(&attestation(validator, 40, 1)).await.is_ok());
+    assert!(matches!(
+        checker.check(&attestation(validator, 41, 1)).await,
+        Err(DvfError::DutyProposalRejected { slot: 41, .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_selection_proof_from_the_future() {
+    let spec = ChainSpec::mainnet();
+    let validator = Keypair::random().pk.compress();
+    let dir = tempdir().unwrap();
+    let slot_clock = ManualSlotClock::new(Slot::new(0), Duration::from_secs(0), Duration::from_secs(12));
+    slot_clock.set_slot(40);
+    let checker = checker(&spec, validator, dir.path()).slot_clock(slot_clock);
+
+    // Selection proofs are computed in advance for the duties of the next epochs.
+    let selection_proof = |slot: u64| DutyProposal::<E> {
+        slot: Slot::new(slot),
+        validator,
+        object: DutyObject::SelectionProof(Slot::new(slot)),
+    };
+    let sync_selection_proof = |slot: u64| DutyProposal::<E> {
+        slot: Slot::new(slot),
+        validator,
+        object: DutyObject::SyncSelectionProof(SyncAggregatorSelectionData { slot: Slot::new(slot), subcommittee_index: 0 }),
+    };
+    assert!(checker.check(&selection_proof(70)).await.is_ok());
+    assert!(checker.check(&sync_selection_proof(127)).await.is_ok());
+
+    // But not beyond the pre-computation window of the duties service.
+    assert!(matches!(
+        checker.check(&selection_proof(128)).await,
+        Err(DvfError::DutyProposalRejected { slot: 128, .. })
+    ));
+    assert!(matches!(
+        checker.check(&sync_selection_proof(128)).await,
+        Err(DvfError::DutyProposalRejected { slot: 128, .. })
+    ));
+}
+
+#[test]
+fn test_duty_proposal_deadline() {
+    let validator = Keypair::random().pk.compress();
+    let slot_clock = ManualSlotClock::new(Slot::new(0), Duration::from_secs(0), Duration::from_secs(12));
+    // A third into slot 40, when attestations are produced
+    slot_clock.set_current_time(Duration::from_secs(40 * 12 + 4));
+
+    assert_eq!(attestation(validator, 40, 1).time_to_deadline(&slot_clock), Some(Duration::from_secs(4)));
+    assert_eq!(attestation(validator, 39, 1).time_to_deadline(&slot_clock), Some(Duration::from_secs(0)));
+
+    let mut block = BeaconBlock::<E>::empty(&ChainSpec::mainnet());
+    *block.slot_mut() = Slot::new(40);
+    let proposal = DutyProposal::<E> { slot: block.slot(), validator, object: DutyObject::BeaconBlock(block) };
+    assert_eq!(proposal.time_to_deadline(&slot_clock), Some(Duration::from_secs(0)));
+
+    // A RANDAO reveal is valid until the end of its epoch
+    let epoch = Epoch::new(1);
+    let proposal = DutyProposal::<E> { slot: epoch.start_slot(E::slots_per_epoch()), validator, object: DutyObject::RandaoReveal(epoch) };
+    assert_eq!(proposal.time_to_deadline(&slot_clock), Some(Duration::from_secs(64 * 12 - 40 * 12 - 4)));
+}
@@ -0,0 +1,113 @@
+use consensus::{Block, Committee as ConsensusCommittee, Round, LAST_COMMITTED_KEY};
+use crypto::Digest;
+use mempool::Committee as MempoolCommittee;
+use node::config::{Committee, Secret};
+use node::dvfcore::{DvfCommand, DvfInfo};
+use node::node::Node;
+use std::net::SocketAddr;
+use std::time::Duration;
+use store::Store;
+use tempfile::tempdir;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+
+/// An address nobody listens to.
+fn unused_address() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+/// The round of the last block committed by the instance of `store`.
+async fn last_committed_round(store: &mut Store) -> Round {
+    let digest: Digest = match store.read(LAST_COMMITTED_KEY.to_vec()).await.unwrap() {
+        Some(bytes) => bincode::deserialize(&bytes).unwrap(),
+        None => return 0,
+    };
+    let block: Block = bincode::deserialize(&store.read(digest.to_vec()).await.unwrap().unwrap()).unwrap();
+    block.round
+}
+
+/// Wait until the instance of `store` commits a block past `round`.
+async fn wait_commit_after(store: &mut Store, round: Round) -> Round {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let committed = last_committed_round(store).await;
+            if committed > round {
+                return committed;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("The instance does not commit")
+}
+
+async fn command(commands: &Sender<DvfCommand>, make: fn(String, oneshot::Sender<bool>) -> DvfCommand, validator_id: &str) -> bool {
+    let (reply, rx_reply) = oneshot::channel();
+    commands.send(make(validator_id.to_string(), reply)).await.unwrap();
+    rx_reply.await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dvfcore_lifecycle() {
+    let secret = Secret::new();
+    let name = secret.name;
+    let (tx, mempool, consensus, dvf, signature) = (unused_address(), unused_address(), unused_address(), unused_address(), unused_address());
+    let committee = Committee {
+        consensus: ConsensusCommittee::new(vec![(name, 1, consensus)], 0),
+        mempool: MempoolCommittee::new(vec![(name, 1, tx, mempool, dvf, signature)], 0),
+    };
+    let dir = tempdir().unwrap();
+    let store_path = dir.path().join("db");
+    let mut node = Node::new(
+        &tx.to_string(),
+        &mempool.to_string(),
+        &consensus.to_string(),
+        &dvf.to_string(),
+        &signature.to_string(),
+        secret,
+        store_path.to_str().unwrap(),
+        None,
+    )
+    .await
+    .unwrap();
+    let validator_id = "validator";
+    let mut store = node.store(validator_id);
+    let handler_maps = (node.tx_handler_map.clone(), node.mempool_handler_map.clone(), node.consensus_handler_map.clone());
+    let has_handlers = || {
+        let (tx_handlers, mempool_handlers, consensus_handlers) = &handler_maps;
+        async move {
+            (
+                tx_handlers.read().await.contains_key(validator_id),
+                mempool_handlers.read().await.contains_key(validator_id),
+                consensus_handlers.read().await.contains_key(validator_id),
+            )
+        }
+    };
+    let commands = node.command_sender();
+    tokio::spawn(async move { node.run().await });
+
+    // The instance starts upon creation.
+    let dvfinfo = DvfInfo { validator_id: validator_id.to_string(), committee, epoch_history: Vec::new() };
+    commands.send(DvfCommand::Create(dvfinfo)).await.unwrap();
+    wait_commit_after(&mut store, 0).await;
+    assert_eq!(has_handlers().await, (true, true, true));
+
+    // Pausing removes the handlers and cancels the tasks: nothing is committed anymore.
+    assert!(command(&commands, DvfCommand::Pause, validator_id).await);
+    assert_eq!(has_handlers().await, (false, false, false));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let paused = last_committed_round(&mut store).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(last_committed_round(&mut store).await, paused);
+
+    // A restarted instance resumes from its store, rather than from the first round.
+    assert!(command(&commands, DvfCommand::Start, validator_id).await);
+    assert_eq!(has_handlers().await, (true, true, true));
+    wait_commit_after(&mut store, paused).await;
+
+    // A removed instance is gone for good.
+    assert!(command(&commands, DvfCommand::Remove, validator_id).await);
+    assert_eq!(has_handlers().await, (false, false, false));
+    assert!(!command(&commands, DvfCommand::Remove, validator_id).await);
+    assert!(!command(&commands, DvfCommand::Start, validator_id).await);
+}
@@ -1,5 +1,6 @@
 use dvf::crypto::{ThresholdSignature};
-use bls::{Signature, PublicKey};
+use bls::{Signature, PublicKey, Keypair};
+use dvf::utils::error::DvfError;
 use types::{Hash256}; 
 use eth2_hashing::{Context, Sha256Context};
 
@@ -8,7 +9,7 @@ fn test_generic_threshold() {
     let t = 5;
     let n = 10;
     let mut m_threshold = ThresholdSignature::new(t);
-    let (kp, kps, ids) = m_threshold.key_gen(n);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
     
     let pks: Vec<&PublicKey> = kps.iter().map(|p| &p.pk).collect();
     let message = "hello world";
@@ -33,3 +34,49 @@ fn test_generic_threshold() {
     assert!(status2, "Aggregate signature verification failed");
     assert_eq!(agg_sig, sig, "Signature not match");
 }
+
+#[test]
+fn test_verify_share() {
+    let t = 5;
+    let n = 10;
+    let mut m_threshold = ThresholdSignature::new(t);
+    let kp = Keypair::random();
+    let (kps, ids, commitments) = m_threshold.key_split(&kp.sk, n).unwrap();
+
+    assert_eq!(commitments.len(), t);
+    assert_eq!(commitments[0], kp.pk, "First commitment should be the master public key");
+    for i in 0..n {
+        assert!(ThresholdSignature::verify_share(ids[i], &kps[i].sk, &commitments[..]).is_ok());
+        assert_eq!(ThresholdSignature::share_public_key(ids[i], &commitments[..]).unwrap(), kps[i].pk);
+    }
+
+    // A share presented under the wrong id is rejected
+    assert_eq!(
+        ThresholdSignature::verify_share(ids[0], &kps[1].sk, &commitments[..]),
+        Err(DvfError::InvalidKeyShare{id: ids[0]})
+    );
+    // So is a share checked against the commitments of another split
+    let (_, _, other_commitments) = m_threshold.key_split(&kp.sk, n).unwrap();
+    assert!(ThresholdSignature::verify_share(ids[0], &kps[0].sk, &other_commitments[..]).is_err());
+}
+
+#[test]
+fn test_key_split_invalid_threshold() {
+    let kp = Keypair::random();
+    for (t, n) in [(0, 4), (5, 4), (1, 0)] {
+        let mut m_threshold = ThresholdSignature::new(t);
+        assert_eq!(
+            m_threshold.key_split(&kp.sk, n).err(),
+            Some(DvfError::InvalidThreshold{threshold: t, total: n})
+        );
+        assert_eq!(
+            m_threshold.deterministic_key_split(&kp.sk, n).err(),
+            Some(DvfError::InvalidThreshold{threshold: t, total: n})
+        );
+    }
+
+    // Every operator is needed when the threshold is the committee size
+    let mut m_threshold = ThresholdSignature::new(4);
+    let (kps, _, _) = m_threshold.key_split(&kp.sk, 4).unwrap();
+    assert_eq!(kps.len(), 4);
+}
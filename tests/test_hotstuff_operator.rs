@@ -0,0 +1,313 @@
+use dvf::validation::operator::{HotStuffOperator, LocalOperator};
+use dvf::validation::generic_operator_committee::TOperatorCommittee;
+use dvf::validation::impls::hotstuff::HotstuffOperatorCommittee;
+use dvf::validation::duty_proposal::{DutyObject, DutyProposal, DutyProposalChecker};
+use dvf::validation::committed_duties::CommittedDutySigner;
+use dvf::crypto::{ThresholdSignature};
+use dvf::utils::error::DvfError;
+use dvf::validation::operator_performance::OPERATOR_PERFORMANCES;
+use node::dvfcore::SignatureInfo;
+use network::Envelope;
+use slashing_protection::SlashingDatabase;
+use slot_clock::ManualSlotClock;
+use tempfile::tempdir;
+use bytes::Bytes;
+use futures::stream::StreamExt as _;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use types::{AttestationData, ChainSpec, Checkpoint, Epoch, Hash256, Keypair, MainnetEthSpec, PublicKey, Signature, Slot};
+use eth2_hashing::{Context, Sha256Context};
+
+fn hash(message: &str) -> Hash256 {
+    let mut context = Context::new();
+    context.update(message.as_bytes());
+    Hash256::from_slice(&context.finalize())
+}
+
+fn share(kp: &Keypair, id: u64, msg: Hash256) -> SignatureInfo {
+    SignatureInfo { from: kp.pk.clone(), signature: kp.sk.sign(msg), msg, id }
+}
+
+fn keys(kps: &[Keypair], ids: &[u64]) -> HashMap<u64, PublicKey> {
+    ids.iter().copied().zip(kps.iter().map(|kp| kp.pk.clone())).collect()
+}
+
+#[tokio::test]
+async fn test_wait_signature_stops_at_threshold() {
+    let (t, n) = (3, 5);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (tx, rx) = channel(16);
+    let mut operator = HotStuffOperator::new(kp.pk.as_hex_string(), Arc::new(kps[0].clone()), "127.0.0.1:0".parse().unwrap(), rx);
+
+    let msg = hash("duty");
+    // Share of another duty
+    tx.send(share(&kps[0], ids[0], hash("previous duty"))).await.unwrap();
+    // Duplicated share
+    tx.send(share(&kps[0], ids[0], msg)).await.unwrap();
+    tx.send(share(&kps[0], ids[0], msg)).await.unwrap();
+    // Share that does not verify against the claimed public key
+    tx.send(SignatureInfo { from: kps[1].pk.clone(), signature: kps[2].sk.sign(msg), msg, id: ids[1] }).await.unwrap();
+    for i in 1..n {
+        tx.send(share(&kps[i], ids[i], msg)).await.unwrap();
+    }
+
+    let shares = operator.wait_signature(msg, &keys(&kps, &ids), t, Duration::from_secs(1)).await.unwrap();
+    let share_ids: Vec<u64> = shares.iter().map(|s| s.id).collect();
+    assert_eq!(share_ids, ids[..t].to_vec());
+
+    let sigs: Vec<&Signature> = shares.iter().map(|s| &s.signature).collect();
+    let pks: Vec<&PublicKey> = shares.iter().map(|s| &s.from).collect();
+    let sig = m_threshold.threshold_aggregate(&sigs[..], &pks[..], &share_ids[..], msg).unwrap();
+    assert_eq!(sig, kp.sk.sign(msg));
+}
+
+#[tokio::test]
+async fn test_wait_signature_deadline() {
+    let (t, n) = (3, 4);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (tx, rx) = channel(16);
+    let mut operator = HotStuffOperator::new(kp.pk.as_hex_string(), Arc::new(kps[0].clone()), "127.0.0.1:0".parse().unwrap(), rx);
+
+    // The other operators are dead.
+    let msg = hash("duty");
+    tx.send(share(&kps[0], ids[0], msg)).await.unwrap();
+    let keys = keys(&kps, &ids);
+    let result = operator.wait_signature(msg, &keys, t, Duration::from_millis(100)).await;
+    assert_eq!(result.unwrap_err(), DvfError::SigningDeadlineExceeded { got: 1, expected: t });
+
+    // So is the node.
+    drop(tx);
+    let result = operator.wait_signature(msg, &keys, t, Duration::from_secs(1)).await;
+    assert_eq!(result.unwrap_err(), DvfError::InsufficientSignatures { got: 0, expected: t });
+}
+
+#[tokio::test]
+async fn test_shares_checked_against_committee_keys() {
+    let (t, n) = (2, 3);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (tx, rx) = channel(16);
+    let mut operator = HotStuffOperator::new(kp.pk.as_hex_string(), Arc::new(kps[0].clone()), "127.0.0.1:0".parse().unwrap(), rx);
+    let keys = keys(&kps, &ids);
+
+    let msg = hash("duty");
+    let attacker = Keypair::random();
+    // A share that verifies against the key it claims, but not against the key of the id it claims.
+    tx.send(share(&attacker, ids[1], msg)).await.unwrap();
+    // A share of an id that is not in the committee.
+    tx.send(share(&attacker, ids.iter().max().unwrap() + 1, msg)).await.unwrap();
+    tx.send(share(&kps[2], ids[2], msg)).await.unwrap();
+    let collection = operator.collect_shares(msg, &keys, t, tokio::time::Instant::now(), Duration::from_millis(100)).await;
+    assert_eq!(collection.error, Some(DvfError::SigningDeadlineExceeded { got: 1, expected: t }));
+    assert_eq!(collection.invalid, vec![ids[1]]);
+    assert_eq!(collection.shares.len(), 1);
+    assert_eq!(collection.shares[0].0.id, ids[2]);
+    assert_eq!(collection.shares[0].0.from, kps[2].pk);
+}
+
+#[tokio::test]
+async fn test_collected_shares_are_aggregated() {
+    let (t, n) = (2, 3);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (tx, rx) = channel(16);
+    let mut operator = HotStuffOperator::new(kp.pk.as_hex_string(), Arc::new(kps[0].clone()), "127.0.0.1:0".parse().unwrap(), rx);
+    let keys = keys(&kps, &ids);
+
+    let msg = hash("duty");
+    // A forged share of ids[1] arrives before the genuine one, which replaces it.
+    tx.send(share(&Keypair::random(), ids[1], msg)).await.unwrap();
+    tx.send(share(&kps[1], ids[1], msg)).await.unwrap();
+    tx.send(share(&kps[0], ids[0], msg)).await.unwrap();
+    let collection = operator.collect_shares(msg, &keys, t, tokio::time::Instant::now(), Duration::from_secs(1)).await;
+    assert_eq!(collection.error, None);
+    assert_eq!(collection.signature, Some(kp.sk.sign(msg)));
+    assert_eq!(collection.invalid, vec![ids[1]]);
+    let share_ids: Vec<u64> = collection.shares.iter().map(|(share, _)| share.id).collect();
+    assert_eq!(share_ids, vec![ids[1], ids[0]]);
+}
+
+#[tokio::test]
+async fn test_shares_of_other_duties_are_kept() {
+    let (t, n) = (2, 3);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (tx, rx) = channel(16);
+    let mut operator = HotStuffOperator::new(kp.pk.as_hex_string(), Arc::new(kps[0].clone()), "127.0.0.1:0".parse().unwrap(), rx);
+    let keys = keys(&kps, &ids);
+
+    // The shares of a duty waiting for the operator arrive while another one is collected.
+    let (first, second) = (hash("first duty"), hash("second duty"));
+    for i in 0..n {
+        tx.send(share(&kps[i], ids[i], second)).await.unwrap();
+    }
+    tx.send(share(&kps[1], ids[1], first)).await.unwrap();
+    assert!(operator.wait_commit(first, &keys, Duration::from_secs(1)).await);
+    tx.send(share(&kps[2], ids[2], first)).await.unwrap();
+    assert_eq!(operator.wait_signature(first, &keys, t, Duration::from_secs(1)).await.unwrap().len(), t);
+
+    drop(tx);
+    assert!(operator.wait_commit(second, &keys, Duration::ZERO).await);
+    let shares = operator.wait_signature(second, &keys, t, Duration::from_secs(1)).await.unwrap();
+    assert_eq!(shares.iter().map(|s| s.id).collect::<Vec<_>>(), ids[..t].to_vec());
+}
+
+fn hotstuff_committee(t: usize, kps: &[Keypair], ids: &[u64], local: usize, address: SocketAddr) -> (HotstuffOperatorCommittee, Sender<SignatureInfo>) {
+    let (tx, rx) = channel(16);
+    let mut committee = HotstuffOperatorCommittee::new(0, kps[local].pk.clone(), t);
+    for i in 0..ids.len() {
+        if i != local {
+            committee.add_operator(ids[i], Arc::new(RwLock::new(LocalOperator::new(ids[i], Arc::new(kps[i].clone())))));
+        }
+    }
+    let operator = HotStuffOperator::new("validator".to_string(), Arc::new(kps[local].clone()), address, rx);
+    committee.add_operator(ids[local], Arc::new(RwLock::new(operator)));
+    (committee, tx)
+}
+
+/// Listen on an ephemeral port, accept a single connection and return the payload of the first
+/// envelope received on it, if any within a second.
+async fn proposals() -> (SocketAddr, JoinHandle<Option<Bytes>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let (socket, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept()).await.ok()?.ok()?;
+        let mut transport = Framed::new(socket, LengthDelimitedCodec::new());
+        let frame = transport.next().await?.ok()?;
+        Some(Envelope::decode(frame.freeze()).unwrap().payload)
+    });
+    (address, handle)
+}
+
+fn proposal(slot: u64) -> Vec<u8> {
+    let data = AttestationData {
+        slot: Slot::new(slot),
+        index: 0,
+        beacon_block_root: hash("head"),
+        source: Checkpoint { epoch: Epoch::new(0), root: Hash256::zero() },
+        target: Checkpoint { epoch: Epoch::new(0), root: Hash256::zero() },
+    };
+    let validator = Keypair::random().pk.compress();
+    DutyProposal::<MainnetEthSpec> { slot: data.slot, validator, object: DutyObject::Attestation(data) }.to_bytes()
+}
+
+#[test]
+fn test_proposer_rotation() {
+    let committee = HotstuffOperatorCommittee::new(3, Keypair::random().pk, 3);
+    let members = vec![4, 2, 1, 3];
+    // Committee 3 at slot 1 starts with the first operator.
+    assert_eq!(committee.proposer_rank(1, 1, &members), Some(0));
+    assert_eq!(committee.proposer_rank(2, 1, &members), Some(1));
+    assert_eq!(committee.proposer_rank(4, 1, &members), Some(3));
+    // The proposer changes with the slot.
+    assert_eq!(committee.proposer_rank(2, 2, &members), Some(0));
+    assert_eq!(committee.proposer_rank(1, 2, &members), Some(3));
+    assert_eq!(committee.proposer_rank(5, 2, &members), None);
+    for slot in 0..16 {
+        let proposers = members.iter().filter(|id| committee.proposer_rank(**id, slot, &members) == Some(0)).count();
+        assert_eq!(proposers, 1);
+    }
+}
+
+#[tokio::test]
+async fn test_proposer_proposes() {
+    let (t, n) = (2, 3);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (_, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (address, received) = proposals().await;
+    // Operator ids[0] proposes the duties of committee 0 at slot 0.
+    let (committee, tx) = hotstuff_committee(t, &kps, &ids, 0, address);
+
+    let msg = hash("duty");
+    let proposal = proposal(0);
+    tx.send(share(&kps[1], ids[1], msg)).await.unwrap();
+    assert!(committee.consensus(&proposal, msg, Duration::from_secs(1)).await);
+    assert_eq!(received.await.unwrap(), Some(Bytes::from(proposal)));
+}
+
+#[tokio::test]
+async fn test_proposer_takeover() {
+    let (t, n) = (2, 3);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (_, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let msg = hash("duty");
+    // Operator ids[1] comes second at slot 0.
+    let (address, received) = proposals().await;
+    let (committee, _tx) = hotstuff_committee(t, &kps, &ids, 1, address);
+
+    // The proposer is dead: we propose after our turn, but nobody commits.
+    let start = std::time::Instant::now();
+    assert!(!committee.consensus(&proposal(0), msg, Duration::from_millis(600)).await);
+    assert!(start.elapsed() >= Duration::from_millis(600));
+    assert!(received.await.unwrap().is_some());
+
+    // The proposer is alive: its duty commits before our turn.
+    let (address, received) = proposals().await;
+    let (committee, tx) = hotstuff_committee(t, &kps, &ids, 1, address);
+    tx.send(share(&kps[0], ids[0], msg)).await.unwrap();
+    assert!(committee.consensus(&proposal(0), msg, Duration::from_millis(600)).await);
+    assert_eq!(received.await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_forged_shares_are_not_attributed() {
+    let (t, n) = (2, 3);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let (address, _received) = proposals().await;
+    let (committee, tx) = hotstuff_committee(t, &kps, &ids, 0, address);
+
+    let msg = hash("duty");
+    // Anyone can send a share in the name of ids[1].
+    tx.send(share(&Keypair::random(), ids[1], msg)).await.unwrap();
+    tx.send(share(&kps[2], ids[2], msg)).await.unwrap();
+    tx.send(share(&kps[0], ids[0], msg)).await.unwrap();
+    let sig = committee.sign_proposal(&proposal(0), msg, Duration::from_secs(1)).await.unwrap();
+    assert_eq!(sig, kp.sk.sign(msg));
+
+    let performances = OPERATOR_PERFORMANCES.read();
+    let operators = performances.committee(0).unwrap();
+    assert!(!operators.contains_key(&ids[1]));
+    assert_eq!(operators[&ids[0]].signed, 1);
+    assert_eq!(operators[&ids[2]].signed, 1);
+}
+
+#[tokio::test]
+async fn test_committed_duties_are_checked() {
+    let spec = ChainSpec::mainnet();
+    let mut m_threshold = ThresholdSignature::new(1);
+    let (kp, kps, ids) = m_threshold.key_gen(1).unwrap();
+    let validator = kp.pk.compress();
+    let dir = tempdir().unwrap();
+    let slashing_protection = SlashingDatabase::create(&dir.path().join("slashing_protection.sqlite")).unwrap();
+    slashing_protection.register_validator(validator).unwrap();
+    let checker = DutyProposalChecker::<ManualSlotClock, MainnetEthSpec>::new(spec.clone(), Hash256::zero(), slashing_protection);
+    let signer = CommittedDutySigner::new(ids[0], Arc::new(kps[0].clone()), validator, Arc::new(checker));
+
+    let mut duty = DutyProposal::<MainnetEthSpec>::from_bytes(&proposal(0)).unwrap();
+    // Proposals of other validators are not signed.
+    assert!(signer.sign(&duty.to_bytes()).await.is_none());
+
+    duty.validator = validator;
+    let signing_root = duty.signing_root(&spec, Hash256::zero());
+    let share = signer.sign(&duty.to_bytes()).await.unwrap();
+    assert_eq!(share.msg, signing_root);
+    assert_eq!(share.id, ids[0]);
+    assert!(share.signature.verify(&kps[0].pk, signing_root));
+
+    // Committed duties are not trusted: slashable and malformed ones are not signed.
+    if let DutyObject::Attestation(data) = &mut duty.object {
+        data.beacon_block_root = hash("another head");
+    }
+    assert!(signer.sign(&duty.to_bytes()).await.is_none());
+    assert!(signer.sign(b"not a proposal").await.is_none());
+}
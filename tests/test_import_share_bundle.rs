@@ -0,0 +1,131 @@
+use dvf::validation::account_utils::eth2_keystore::json_keystore::{Kdf, Scrypt};
+use dvf::validation::account_utils::eth2_keystore::DKLEN;
+use dvf::validation::account_utils::validator_definitions::ValidatorDefinitions;
+use dvf::validation::initialized_validators::{Error, InitializedValidators};
+use dvf::validation::validator_dir::share_bundle::{ShareBundle, ShareBundleBuilder};
+use environment::null_logger;
+use node::config::Secret;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::tempdir;
+use types::{Hash256, Keypair};
+
+/// A cheap, **insecure**, key derivation function for tests.
+fn insecure_kdf() -> Kdf {
+    Kdf::Scrypt(Scrypt {
+        dklen: DKLEN,
+        n: 2,
+        p: 1,
+        r: 8,
+        salt: vec![1; 32].into(),
+    })
+}
+
+fn socket_addresses(n: u16) -> Vec<SocketAddr> {
+    (0..n).map(|i| SocketAddr::from(([127, 0, 0, 1], 4000 + i))).collect()
+}
+
+/// The share bundles of a new validator of key `kp`, sealed to the operators of `node_secrets`.
+fn share_bundles(committee_index: u64, threshold: u64, kp: &Keypair, node_secrets: &[&Secret]) -> Vec<ShareBundle> {
+    ShareBundleBuilder::new(
+        committee_index,
+        threshold,
+        socket_addresses(node_secrets.len() as u16),
+        node_secrets.iter().map(|secret| secret.name).collect(),
+    )
+    .voting_keypair(kp.clone())
+    .kdf(insecure_kdf())
+    .build()
+    .unwrap()
+}
+
+async fn initialized_validators(dir: &Path, identity: Arc<Secret>) -> InitializedValidators {
+    InitializedValidators::from_definitions(ValidatorDefinitions::default(), dir.into(), identity, null_logger().unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_import_share_bundle_and_sign() {
+    let dir = tempdir().unwrap();
+    let identity = Arc::new(Secret::new());
+    let mut validators = initialized_validators(dir.path(), identity.clone()).await;
+
+    let kp = Keypair::random();
+    let bundle = share_bundles(1, 1, &kp, &[&*identity]).remove(0);
+    assert_eq!(validators.import_share_bundle(bundle.clone()).await.unwrap(), kp.pk);
+    assert_eq!(validators.committee_definitions(), &[bundle.committee_definition.clone()]);
+    assert_eq!(validators.committee_validators(1), vec![kp.pk.clone()]);
+    assert!(validators.committees().key_shares().read().contains_key(&1));
+
+    // The validator signs through its committee, whose only operator is this one.
+    let message = Hash256::repeat_byte(7);
+    assert!(validators.signing_method(&kp.pk.compress()).is_some());
+    let committee = validators.committees().committee(1).unwrap();
+    let signature = committee.read().await.sign(message).await.unwrap();
+    assert!(signature.verify(&kp.pk, message));
+    assert_eq!(signature, kp.sk.sign(message));
+
+    // The share is kept across restarts.
+    drop(validators);
+    let definitions = ValidatorDefinitions::open(dir.path()).unwrap();
+    let validators = InitializedValidators::from_definitions(definitions, dir.path().into(), identity, null_logger().unwrap())
+        .await
+        .unwrap();
+    assert!(validators.signing_method(&kp.pk.compress()).is_some());
+}
+
+#[tokio::test]
+async fn test_import_share_bundle_membership() {
+    let dir = tempdir().unwrap();
+    let identity = Arc::new(Secret::new());
+    let (other, another) = (Secret::new(), Secret::new());
+    let mut validators = initialized_validators(dir.path(), identity.clone()).await;
+
+    let kp = Keypair::random();
+    let bundles = share_bundles(2, 2, &kp, &[&*identity, &other, &another]);
+    let bundle = bundles[0].clone();
+
+    // The share of another operator cannot be opened, and its committee is not kept.
+    assert!(matches!(
+        validators.import_share_bundle(bundles[1].clone()).await,
+        Err(Error::UnableToOpenEnvelope(_))
+    ));
+    assert!(validators.committee_definitions().is_empty());
+
+    // An envelope alone is only imported into a known committee.
+    assert!(matches!(
+        validators
+            .import_keystore_share_envelope(bundle.keystore_share_envelope.clone(), &identity)
+            .await,
+        Err(Error::UnknownCommittee(2))
+    ));
+
+    // A known committee is not replaced by the one of the bundle: the share must belong to it.
+    let mut other_validator = bundle.committee_definition.clone();
+    other_validator.voting_public_key = Keypair::random().pk;
+    other_validator.commitments = vec![];
+    let mut other_share = bundle.committee_definition.clone();
+    other_share.public_keys[0] = Keypair::random().pk;
+    other_share.commitments = vec![];
+    let mut other_id = bundle.committee_definition.clone();
+    other_id.ids[0] = 4;
+    other_id.commitments = vec![];
+    for def in [other_validator, other_share, other_id] {
+        validators.add_committee_definition(def).unwrap();
+        assert!(matches!(
+            validators.import_share_bundle(bundle.clone()).await,
+            Err(Error::InvalidKeystoreShare(_))
+        ));
+        assert!(validators.committee_validators(2).is_empty());
+        validators.remove_committee_definition(2).unwrap();
+    }
+
+    assert_eq!(validators.import_share_bundle(bundle.clone()).await.unwrap(), kp.pk);
+    assert_eq!(validators.committee_validators(2), vec![kp.pk.clone()]);
+    assert!(matches!(
+        validators.import_share_bundle(bundle).await,
+        Err(Error::DuplicatePublicKey)
+    ));
+}
@@ -0,0 +1,110 @@
+use dvf::crypto::ThresholdSignature;
+use dvf::validation::account_utils::eth2_keystore::json_keystore::{Kdf, Scrypt};
+use dvf::validation::account_utils::eth2_keystore::{KeystoreBuilder, DKLEN};
+use dvf::validation::eth2_keystore_share::envelope::{Error, KeystoreShareEnvelope};
+use dvf::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use node::config::Secret;
+use tempfile::tempdir;
+use types::Keypair;
+
+/// A cheap, **insecure**, key derivation function for tests.
+fn insecure_kdf() -> Kdf {
+    Kdf::Scrypt(Scrypt {
+        dklen: DKLEN,
+        n: 2,
+        p: 1,
+        r: 8,
+        salt: vec![1; 32].into(),
+    })
+}
+
+/// The keystore share of the second operator of a random validator, along with its key and
+/// password.
+fn keystore_share(threshold: usize, total: usize) -> (KeystoreShare, Keypair, Vec<u8>) {
+    let master = Keypair::random();
+    let (kps, ids, commitments) = ThresholdSignature::new(threshold).key_split(&master.sk, total).unwrap();
+    let password = b"password".to_vec();
+    let keystore = KeystoreBuilder::new(&kps[1], &password, "".into())
+        .unwrap()
+        .kdf(insecure_kdf())
+        .build()
+        .unwrap();
+    let keystore_share = KeystoreShare::new(keystore, master.pk, 3, ids[1], commitments);
+    (keystore_share, kps[1].clone(), password)
+}
+
+#[test]
+fn test_seal_and_open_envelope() {
+    let (keystore_share, share_keypair, password) = keystore_share(2, 3);
+    let secret = Secret::new();
+    let envelope = KeystoreShareEnvelope::seal(&keystore_share, &password, &secret.name).unwrap();
+    assert_eq!(envelope.master_public_key, keystore_share.master_public_key);
+    assert_eq!(envelope.share_id, keystore_share.share_id);
+
+    let keypair = envelope.open(&secret).unwrap();
+    assert_eq!(keypair.pk, share_keypair.pk);
+
+    // Envelopes round trip through JSON files.
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("envelope.json");
+    envelope.to_json_file(&path).unwrap();
+    assert_eq!(KeystoreShareEnvelope::from_json_file(&path).unwrap(), envelope);
+    assert!(matches!(envelope.to_json_file(&path), Err(Error::UnableToSaveEnvelope(_))));
+
+    // Sealing again uses a fresh ephemeral key.
+    let other = KeystoreShareEnvelope::seal(&keystore_share, &password, &secret.name).unwrap();
+    assert_ne!(other.ephemeral_public_key, envelope.ephemeral_public_key);
+    assert_ne!(other.ciphertext, envelope.ciphertext);
+
+    assert!(matches!(
+        KeystoreShareEnvelope::seal(&keystore_share, b"wrong password", &secret.name),
+        Err(Error::UnableToDecryptKeystore(_))
+    ));
+}
+
+#[test]
+fn test_open_envelope_of_another_operator() {
+    let (keystore_share, _, password) = keystore_share(2, 3);
+    let secret = Secret::new();
+    let mut envelope = KeystoreShareEnvelope::seal(&keystore_share, &password, &secret.name).unwrap();
+
+    let other = Secret::new();
+    assert!(matches!(envelope.open(&other), Err(Error::WrongRecipient { .. })));
+
+    // Claiming to be the recipient does not help without its secret key.
+    envelope.recipient = other.name;
+    assert!(matches!(envelope.open(&other), Err(Error::UnableToDecryptShare)));
+}
+
+#[test]
+fn test_open_tampered_envelope() {
+    let (keystore_share, _, password) = keystore_share(2, 3);
+    let secret = Secret::new();
+    let envelope = KeystoreShareEnvelope::seal(&keystore_share, &password, &secret.name).unwrap();
+
+    let mut tampered = envelope.clone();
+    tampered.ciphertext[0] ^= 1;
+    assert!(matches!(tampered.open(&secret), Err(Error::UnableToDecryptShare)));
+
+    let mut tampered = envelope.clone();
+    tampered.ephemeral_public_key[0] ^= 1;
+    assert!(matches!(tampered.open(&secret), Err(Error::UnableToDecryptShare)));
+
+    let mut tampered = envelope.clone();
+    tampered.ephemeral_public_key.pop();
+    assert!(matches!(tampered.open(&secret), Err(Error::InvalidEphemeralKey)));
+
+    // A low order ephemeral key would make the shared secret public.
+    let mut tampered = envelope.clone();
+    tampered.ephemeral_public_key = vec![0; 32];
+    assert!(matches!(tampered.open(&secret), Err(Error::InvalidEphemeralKey)));
+
+    // The metadata of the share is authenticated as well.
+    let mut tampered = envelope.clone();
+    tampered.share_id += 1;
+    assert!(matches!(tampered.open(&secret), Err(Error::UnableToDecryptShare)));
+
+    let mut tampered = envelope;
+    tampered.master_public_key = Keypair::random().pk;
+    assert!(matches!(tampered.open(&secret), Err(Error::UnableToDecryptShare)));
+}
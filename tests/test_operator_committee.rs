@@ -1,18 +1,22 @@
 use dvf::validation::{OperatorCommittee};
-use dvf::validation::operator::{LocalOperator};
+use dvf::validation::operator::{LocalOperator, TOperator};
 use dvf::crypto::{ThresholdSignature};
+use dvf::utils::error::DvfError;
+use async_trait::async_trait;
 use std::sync::Arc;
-use types::Hash256;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use types::{Hash256, PublicKey, Signature};
 use eth2_hashing::{Context, Sha256Context};
 
 #[cfg(feature = "fake_committee")]
-#[test]
-fn test_fake_operator_committee() {
+#[tokio::test]
+async fn test_fake_operator_committee() {
     let t: usize = 5;
     let n: usize = 10;
 
     let mut m_threshold = ThresholdSignature::new(t);
-    let (kp, kps, ids) = m_threshold.key_gen(n);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
 
     let mut committee = OperatorCommittee::new(0, kp.pk.clone(), t);
     for i in 0..n {
@@ -26,7 +30,7 @@ fn test_fake_operator_committee() {
     context.update(message.as_bytes());
     let message = Hash256::from_slice(&context.finalize());
 
-    let sig1 = committee.sign(message).unwrap();
+    let sig1 = committee.sign(message).await.unwrap();
     let sig2 = kp.sk.sign(message);
 
     let status1 = sig1.verify(&kp.pk, message);
@@ -39,3 +43,57 @@ fn test_fake_operator_committee() {
     //let a = Arc<RwLock<dyn TOperator>>> = Arc::new(RwLock::new(
             //LocalOperator::from_keypair(Arc::new(kps[0].clone()))));
 }
+
+/// An operator that never answers.
+struct StalledOperator(PublicKey);
+
+#[async_trait]
+impl TOperator for StalledOperator {
+    async fn sign(&self, _msg: Hash256) -> Result<Signature, DvfError> {
+        futures::future::pending().await
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.0.clone()
+    }
+}
+
+#[cfg(feature = "fake_committee")]
+#[tokio::test]
+async fn test_fake_operator_committee_timeout() {
+    let (t, n) = (2, 4);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let message = Hash256::from_low_u64_be(1);
+    let timeout = Duration::from_millis(200);
+
+    // Stalled operators do not hold the committee back once the threshold is reached...
+    let mut committee = OperatorCommittee::new(0, kp.pk.clone(), t);
+    for i in 0..n {
+        let operator: Arc<RwLock<dyn TOperator>> = if i < t {
+            Arc::new(RwLock::new(LocalOperator::new(ids[i], Arc::new(kps[i].clone()))))
+        } else {
+            Arc::new(RwLock::new(StalledOperator(kps[i].pk.clone())))
+        };
+        committee.add_operator(ids[i], operator);
+    }
+    let start = Instant::now();
+    let sig = committee.sign_proposal(b"duty", message, Duration::from_secs(60)).await.unwrap();
+    assert_eq!(sig, kp.sk.sign(message));
+    assert!(start.elapsed() < timeout);
+
+    // ...nor past the deadline when it is not.
+    let mut committee = OperatorCommittee::new(1, kp.pk.clone(), t);
+    for i in 0..n {
+        let operator: Arc<RwLock<dyn TOperator>> = if i == 0 {
+            Arc::new(RwLock::new(LocalOperator::new(ids[i], Arc::new(kps[i].clone()))))
+        } else {
+            Arc::new(RwLock::new(StalledOperator(kps[i].pk.clone())))
+        };
+        committee.add_operator(ids[i], operator);
+    }
+    let start = Instant::now();
+    let result = committee.sign_proposal(b"duty", message, timeout).await;
+    assert_eq!(result, Err(DvfError::SigningDeadlineExceeded { got: 1, expected: t }));
+    assert!(start.elapsed() >= timeout && start.elapsed() < 2 * timeout);
+}
@@ -0,0 +1,85 @@
+use dvf::crypto::ThresholdSignature;
+use dvf::validation::operator_committee_definitions::{DefinitionError, OperatorCommitteeDefinition};
+use std::net::SocketAddr;
+use types::Keypair;
+
+fn committee_definition(threshold: usize, total: usize) -> OperatorCommitteeDefinition {
+    let kp = Keypair::random();
+    let mut m_threshold = ThresholdSignature::new(threshold);
+    let (kps, ids, commitments) = m_threshold.key_split(&kp.sk, total).unwrap();
+    OperatorCommitteeDefinition {
+        total: total as u64,
+        threshold: threshold as u64,
+        committee_index: 1,
+        voting_public_key: kp.pk,
+        ids,
+        public_keys: kps.iter().map(|kp| kp.pk.clone()).collect(),
+        socket_addresses: (0..total)
+            .map(|i| SocketAddr::from(([127, 0, 0, 1], 4000 + i as u16)))
+            .collect(),
+        commitments,
+        node_public_keys: vec![],
+    }
+}
+
+#[test]
+fn test_validate_committee_definition() {
+    let def = committee_definition(3, 4);
+    assert!(def.validate().is_ok());
+
+    // Commitments are optional.
+    let mut without_commitments = def.clone();
+    without_commitments.commitments.clear();
+    assert!(without_commitments.validate().is_ok());
+
+    let mut invalid = def.clone();
+    invalid.socket_addresses.pop();
+    assert!(matches!(
+        invalid.validate(),
+        Err(DefinitionError::LengthMismatch { field: "socket_addresses", got: 3, expected: 4 })
+    ));
+
+    for threshold in [0, 5] {
+        let mut invalid = def.clone();
+        invalid.threshold = threshold;
+        assert!(matches!(invalid.validate(), Err(DefinitionError::InvalidThreshold { total: 4, .. })));
+    }
+
+    let mut invalid = def.clone();
+    invalid.ids[0] = 0;
+    assert!(matches!(invalid.validate(), Err(DefinitionError::ZeroOperatorId)));
+
+    let mut invalid = def.clone();
+    invalid.ids[1] = invalid.ids[0];
+    assert!(matches!(invalid.validate(), Err(DefinitionError::DuplicateOperatorId(_))));
+
+    let mut invalid = def.clone();
+    invalid.public_keys[1] = invalid.public_keys[0].clone();
+    assert!(matches!(invalid.validate(), Err(DefinitionError::DuplicatePublicKey(_))));
+
+    let mut invalid = def.clone();
+    invalid.commitments.pop();
+    assert!(matches!(
+        invalid.validate(),
+        Err(DefinitionError::CommitmentsLength { got: 2, expected: 3 })
+    ));
+
+    let mut invalid = def.clone();
+    invalid.voting_public_key = Keypair::random().pk;
+    assert!(matches!(invalid.validate(), Err(DefinitionError::CommitmentsMismatch)));
+
+    // A share key that does not match the commitments, e.g., from another split.
+    let mut invalid = def.clone();
+    invalid.public_keys[2] = Keypair::random().pk;
+    let id = invalid.ids[2];
+    assert!(matches!(invalid.validate(), Err(DefinitionError::InvalidShareKey { id: i }) if i == id));
+}
+
+#[test]
+fn test_byzantine_safe_committee_definition() {
+    assert!(committee_definition(3, 4).is_byzantine_safe());
+    assert!(!committee_definition(2, 4).is_byzantine_safe());
+    assert!(!committee_definition(4, 4).is_byzantine_safe());
+    assert!(committee_definition(5, 7).is_byzantine_safe());
+    assert!(committee_definition(1, 1).is_byzantine_safe());
+}
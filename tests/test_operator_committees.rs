@@ -0,0 +1,191 @@
+use dvf::validation::operator_committee_definitions::{DefinitionError, OperatorCommitteeDefinition, OperatorCommitteeDefinitions};
+use dvf::validation::operator_committees::{Error, OperatorCommittees};
+use dvf::validation::operator_service::KeyShare;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use node::config::Secret;
+use tempfile::tempdir;
+use types::Keypair;
+
+fn committee_definition(committee_index: u64, total: u64) -> OperatorCommitteeDefinition {
+    OperatorCommitteeDefinition {
+        total,
+        threshold: total - 1,
+        committee_index,
+        voting_public_key: Keypair::random().pk,
+        ids: (1..=total).collect(),
+        public_keys: (0..total).map(|_| Keypair::random().pk).collect(),
+        socket_addresses: (0..total)
+            .map(|i| SocketAddr::from(([127, 0, 0, 1], 4000 + i as u16)))
+            .collect(),
+        commitments: vec![],
+        node_public_keys: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_operator_committees_management() {
+    let dir = tempdir().unwrap();
+    let definitions = OperatorCommitteeDefinitions::open_or_create(dir.path()).unwrap();
+    let mut committees = OperatorCommittees::from_definitions(definitions, dir.path().into(), Arc::new(Secret::new()))
+        .await
+        .unwrap();
+
+    let committee = committees.add_definition(committee_definition(1, 4)).unwrap();
+    committees.add_definition(committee_definition(2, 4)).unwrap();
+    assert!(matches!(
+        committees.add_definition(committee_definition(1, 4)),
+        Err(Error::DuplicateCommittee(1))
+    ));
+    let mut inconsistent = committee_definition(3, 4);
+    inconsistent.ids.pop();
+    assert!(matches!(
+        committees.add_definition(inconsistent),
+        Err(Error::InvalidDefinition(3, DefinitionError::LengthMismatch { field: "ids", got: 3, expected: 4 }))
+    ));
+
+    // The committee is rebuilt in place, so that its holders see the update.
+    let updated = committee_definition(1, 5);
+    let rebuilt = committees.update_definition(updated.clone()).await.unwrap();
+    assert!(Arc::ptr_eq(&committee, &rebuilt));
+    assert_eq!(committee.read().await.threshold(), 4);
+    assert_eq!(committees.definition(1), Some(&updated));
+    assert!(matches!(
+        committees.update_definition(committee_definition(3, 4)).await,
+        Err(Error::UnknownCommittee(3))
+    ));
+
+    committees.remove_definition(2).unwrap();
+    assert!(committees.committee(2).is_none());
+    assert!(matches!(committees.remove_definition(2), Err(Error::UnknownCommittee(2))));
+
+    // Every change is saved.
+    let definitions = OperatorCommitteeDefinitions::open(dir.path()).unwrap();
+    assert_eq!(definitions.as_slice(), &[updated]);
+    let committees = OperatorCommittees::from_definitions(definitions, dir.path().into(), Arc::new(Secret::new()))
+        .await
+        .unwrap();
+    assert!(committees.committee(1).is_some());
+}
+
+#[tokio::test]
+async fn test_operator_committees_reconcile() {
+    let dir = tempdir().unwrap();
+    let (first, second, third) = (committee_definition(1, 4), committee_definition(2, 4), committee_definition(3, 4));
+    let mut committees = OperatorCommittees::from_definitions(
+        vec![first.clone(), second.clone()].into(),
+        dir.path().into(),
+        Arc::new(Secret::new()),
+    )
+    .await
+    .unwrap();
+    let running = committees.committee(1).unwrap();
+    let retired = committees.committee(2).unwrap();
+
+    // A bad definition leaves the running committees as they are.
+    assert!(matches!(
+        committees.reconcile(vec![first.clone(), first.clone()].into()).await,
+        Err(Error::DuplicateCommittee(1))
+    ));
+    assert_eq!(committees.definitions(), &[first.clone(), second.clone()]);
+
+    // A duty in flight holds the committee: it is rebuilt once the duty is done.
+    let duty = running.clone().read_owned().await;
+    let updated = committee_definition(1, 5);
+    let definitions: OperatorCommitteeDefinitions = vec![updated.clone(), third.clone()].into();
+    let mut reconcile = tokio::spawn(async move {
+        let changes = committees.reconcile(definitions).await;
+        (committees, changes)
+    });
+    assert!(tokio::time::timeout(Duration::from_millis(100), &mut reconcile).await.is_err());
+    assert_eq!(duty.threshold(), 3);
+    drop(duty);
+
+    let (committees, changes) = reconcile.await.unwrap();
+    let changes = changes.unwrap();
+    assert_eq!(changes.added, vec![3]);
+    assert_eq!(changes.rebuilt, vec![1]);
+    assert_eq!(changes.retired, vec![2]);
+    assert!(Arc::ptr_eq(&running, &committees.committee(1).unwrap()));
+    assert_eq!(running.read().await.threshold(), 4);
+    assert!(committees.committee(2).is_none());
+    // Holders of a retired committee can still use it.
+    assert_eq!(retired.read().await.threshold(), 3);
+    assert_eq!(committees.definitions(), &[updated, third]);
+}
+
+#[tokio::test]
+async fn test_operator_committees_unsaved_changes() {
+    let dir = tempdir().unwrap();
+    let committees_dir = dir.path().join("committees");
+    std::fs::create_dir(&committees_dir).unwrap();
+    let first = committee_definition(1, 4);
+    let mut committees = OperatorCommittees::from_definitions(vec![first.clone()].into(), committees_dir.clone(), Arc::new(Secret::new()))
+        .await
+        .unwrap();
+
+    // Changes that cannot be saved are not applied.
+    std::fs::remove_dir_all(&committees_dir).unwrap();
+    assert!(matches!(
+        committees.add_definition(committee_definition(2, 4)),
+        Err(Error::UnableToSaveDefinitions(_))
+    ));
+    assert!(matches!(
+        committees.update_definition(committee_definition(1, 5)).await,
+        Err(Error::UnableToSaveDefinitions(_))
+    ));
+    assert!(matches!(committees.remove_definition(1), Err(Error::UnableToSaveDefinitions(_))));
+    assert_eq!(committees.definitions(), &[first]);
+    assert!(committees.committee(2).is_none());
+    assert_eq!(committees.committee(1).unwrap().read().await.threshold(), 3);
+}
+
+#[tokio::test]
+async fn test_operator_committees_prepared_reconcile() {
+    let dir = tempdir().unwrap();
+    let (first, second) = (committee_definition(1, 4), committee_definition(2, 4));
+    let mut committees = OperatorCommittees::from_definitions(
+        vec![first.clone(), second.clone()].into(),
+        dir.path().into(),
+        Arc::new(Secret::new()),
+    )
+    .await
+    .unwrap();
+    // This operator holds the first share of both committees.
+    for def in [&first, &second] {
+        let keypair = Keypair::random();
+        let mut updated = def.clone();
+        updated.public_keys[0] = keypair.pk.clone();
+        committees.update_definition(updated).await.unwrap();
+        committees.add_key_share(def.committee_index, KeyShare {
+            keypair: Arc::new(keypair),
+            validator: def.voting_public_key.compress(),
+        });
+    }
+
+    // The first committee keeps the share, the second one hands it to another operator.
+    let mut first_rebuilt = committees.definition(1).unwrap().clone();
+    first_rebuilt.socket_addresses[1] = SocketAddr::from(([127, 0, 0, 1], 5000));
+    let mut second_rebuilt = committees.definition(2).unwrap().clone();
+    second_rebuilt.public_keys[0] = Keypair::random().pk;
+    let definitions: OperatorCommitteeDefinitions = vec![first_rebuilt.clone(), second_rebuilt].into();
+
+    // Definitions changed while the reconciliation was prepared are not overwritten.
+    let mut reconciliation = committees.prepare(definitions.clone()).unwrap();
+    committees.add_definition(committee_definition(3, 4)).unwrap();
+    reconciliation.swap().await;
+    assert!(matches!(committees.apply(reconciliation), Err(Error::ConcurrentChange)));
+    assert!(committees.committee(3).is_some());
+
+    let mut reconciliation = committees.prepare(definitions).unwrap();
+    reconciliation.swap().await;
+    let changes = committees.apply(reconciliation).unwrap();
+    assert_eq!(changes.rebuilt, vec![1, 2]);
+    assert_eq!(changes.retired, vec![3]);
+    assert_eq!(changes.unserved, vec![2]);
+    assert_eq!(committees.definition(1), Some(&first_rebuilt));
+    let key_shares = committees.key_shares();
+    assert!(key_shares.read().contains_key(&1));
+    assert!(!key_shares.read().contains_key(&2));
+}
@@ -0,0 +1,100 @@
+use dvf::validation::operator_performance::{
+    DutyOutcome, OperatorPerformances, OPERATOR_HISTORY_LEN, OPERATOR_PERFORMANCES,
+};
+use dvf::validation::{OperatorCommittee};
+use dvf::validation::operator::{LocalOperator, TOperator};
+use dvf::crypto::{ThresholdSignature};
+use dvf::utils::error::DvfError;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+use tokio::sync::RwLock;
+use types::{Hash256, Keypair, PublicKey, Signature};
+
+#[test]
+fn test_operator_performance_history() {
+    let mut performances = OperatorPerformances::default();
+    performances.record(1, 1, DutyOutcome::Signed, Some(Duration::from_millis(100)));
+    performances.record(1, 1, DutyOutcome::Signed, Some(Duration::from_millis(300)));
+    performances.record(1, 1, DutyOutcome::Missed, None);
+    performances.record(1, 2, DutyOutcome::InvalidShare, Some(Duration::from_millis(50)));
+
+    let committee = performances.committee(1).unwrap();
+    let first = &committee[&1];
+    assert_eq!((first.signed, first.missed, first.invalid_shares), (2, 1, 0));
+    assert_eq!(first.mean_latency_ms(), Some(200));
+    assert_eq!(first.score(), Some(2.0 / 3.0));
+    let second = &committee[&2];
+    assert_eq!((second.signed, second.missed, second.invalid_shares), (0, 0, 1));
+    assert_eq!(second.score(), Some(0.0));
+    assert!(performances.committee(2).is_none());
+
+    // Only the most recent duties are kept, but the counters are not reset.
+    for _ in 0..OPERATOR_HISTORY_LEN {
+        performances.record(1, 1, DutyOutcome::Signed, Some(Duration::from_millis(10)));
+    }
+    let first = &performances.committee(1).unwrap()[&1];
+    assert_eq!(first.history.len(), OPERATOR_HISTORY_LEN);
+    assert_eq!(first.missed, 1);
+    assert_eq!(first.score(), Some(1.0));
+    assert_eq!(first.mean_latency_ms(), Some(10));
+}
+
+#[test]
+fn test_operator_performance_persistence() {
+    let dir = tempdir().unwrap();
+    assert_eq!(OperatorPerformances::open_or_default(dir.path()).unwrap(), OperatorPerformances::default());
+
+    let mut performances = OperatorPerformances::default();
+    performances.record(3, 7, DutyOutcome::Signed, Some(Duration::from_millis(20)));
+    performances.record(3, 8, DutyOutcome::Missed, None);
+    performances.save(dir.path()).unwrap();
+    assert_eq!(OperatorPerformances::open_or_default(dir.path()).unwrap(), performances);
+}
+
+#[cfg(feature = "fake_committee")]
+#[tokio::test]
+async fn test_fake_committee_records_invalid_shares() {
+    let (t, n) = (3, 5);
+    let committee_index = 42;
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+
+    let mut committee = OperatorCommittee::new(committee_index, kp.pk.clone(), t);
+    for i in 0..n {
+        // The first operator answers first, with a key that does not match its public key.
+        let operator: Arc<RwLock<dyn TOperator>> = if i == 0 {
+            let keypair = Keypair::from_components(kps[0].pk.clone(), kps[1].sk.clone());
+            Arc::new(RwLock::new(LocalOperator::new(ids[i], Arc::new(keypair))))
+        } else {
+            let operator = LocalOperator::new(ids[i], Arc::new(kps[i].clone()));
+            Arc::new(RwLock::new(DelayedOperator(operator, Duration::from_millis(50))))
+        };
+        committee.add_operator(ids[i], operator);
+    }
+
+    let message = Hash256::from_low_u64_be(1);
+    assert_eq!(committee.sign(message).await.unwrap(), kp.sk.sign(message));
+
+    // The committee stops at the threshold.
+    let performances = OPERATOR_PERFORMANCES.read();
+    let operators = performances.committee(committee_index).unwrap();
+    assert_eq!(operators[&ids[0]].invalid_shares, 1);
+    assert_eq!(ids[1..].iter().filter(|id| operators.get(id).map_or(false, |op| op.signed == 1)).count(), t);
+}
+
+/// An operator that answers after a delay.
+struct DelayedOperator(LocalOperator, Duration);
+
+#[async_trait]
+impl TOperator for DelayedOperator {
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
+        tokio::time::sleep(self.1).await;
+        self.0.sign(msg).await
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.0.public_key()
+    }
+}
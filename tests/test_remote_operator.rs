@@ -0,0 +1,226 @@
+use dvf::validation::{OperatorCommittee};
+use dvf::validation::duty_proposal::{DutyObject, DutyProposal, DutyProposalChecker};
+use dvf::validation::operator::{RemoteOperator, TOperator, MAX_BLOCK_SSZ_LEN, MAX_OPERATOR_FRAME_LEN};
+use dvf::validation::operator_service::{OperatorService, KeyShare, KeyShareMap, ClientMap};
+use dvf::utils::error::DvfError;
+use dvf::crypto::{ThresholdSignature};
+use node::config::Secret;
+use slashing_protection::SlashingDatabase;
+use slot_clock::ManualSlotClock;
+use std::sync::Arc;
+use std::net::SocketAddr;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use parking_lot::RwLock;
+use ssz::Encode;
+use tempfile::tempdir;
+use tokio::sync::RwLock as AsyncRwLock;
+use types::{
+    AttestationData, BeaconBlock, BeaconBlockAltair, BeaconBlockMerge, ChainSpec, Checkpoint, Epoch, EthSpec, Hash256,
+    Keypair, MainnetEthSpec, PublicKeyBytes, Slot, Transaction, VariableList,
+};
+
+type E = MainnetEthSpec;
+
+fn attestation(validator: PublicKeyBytes, slot: u64, root: u64) -> DutyProposal<E> {
+    let epoch = Slot::new(slot).epoch(E::slots_per_epoch());
+    let data = AttestationData {
+        slot: Slot::new(slot),
+        index: 0,
+        beacon_block_root: Hash256::from_low_u64_be(root),
+        source: Checkpoint { epoch: Epoch::new(0), root: Hash256::zero() },
+        target: Checkpoint { epoch, root: Hash256::from_low_u64_be(root) },
+    };
+    DutyProposal { slot: data.slot, validator, object: DutyObject::Attestation(data) }
+}
+
+/// A block with as many operations as allowed, whose transactions fill the rest of
+/// `MAX_BLOCK_SSZ_LEN`.
+fn max_size_block(spec: &ChainSpec, slot: u64) -> BeaconBlock<E> {
+    let full = BeaconBlockAltair::<E>::full(spec);
+    let mut block = BeaconBlockMerge::<E>::empty(spec);
+    block.slot = Slot::new(slot);
+    block.body.proposer_slashings = full.body.proposer_slashings;
+    block.body.attester_slashings = full.body.attester_slashings;
+    block.body.attestations = full.body.attestations;
+    block.body.deposits = full.body.deposits;
+    block.body.voluntary_exits = full.body.voluntary_exits;
+
+    // Each transaction also takes a 4-byte offset.
+    let len = MAX_BLOCK_SSZ_LEN - block.ssz_bytes_len() - 4;
+    let transaction = Transaction::<<E as EthSpec>::MaxBytesPerTransaction>::from(vec![0xff; len]);
+    block.body.execution_payload.execution_payload.transactions = VariableList::from(vec![transaction]);
+    BeaconBlock::Merge(block)
+}
+
+/// Spawn an operator service on an ephemeral port, holding `keypair` for `committee_index` and
+/// answering `client` only.
+async fn operator_service(
+    committee_index: u64,
+    keypair: Keypair,
+    validator: PublicKeyBytes,
+    client: &Secret,
+    dir: &Path,
+) -> SocketAddr {
+    let key_shares: KeyShareMap = Arc::new(RwLock::new(HashMap::new()));
+    key_shares.write().insert(committee_index, KeyShare { keypair: Arc::new(keypair), validator });
+    let clients: ClientMap = Arc::new(RwLock::new(HashMap::new()));
+    clients.write().insert(committee_index, HashSet::from([client.name]));
+
+    let slashing_protection = SlashingDatabase::create(&dir.join("slashing_protection.sqlite")).unwrap();
+    slashing_protection.register_validator(validator).unwrap();
+    let checker = DutyProposalChecker::<ManualSlotClock, E>::new(ChainSpec::mainnet(), Hash256::zero(), slashing_protection);
+
+    OperatorService::spawn("127.0.0.1:0".parse().unwrap(), key_shares, clients, Arc::new(checker))
+        .await
+        .unwrap()
+}
+
+/// An address nobody listens to.
+fn unused_address() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+#[cfg(feature = "fake_committee")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remote_operator_committee() {
+    let t: usize = 3;
+    let n: usize = 4;
+    let committee_index = 7;
+    let spec = ChainSpec::mainnet();
+    let identity = Arc::new(Secret::new());
+
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
+    let validator = kp.pk.compress();
+
+    let dirs: Vec<_> = (0..n).map(|_| tempdir().unwrap()).collect();
+    let mut committee = OperatorCommittee::new(committee_index, kp.pk.clone(), t);
+    for i in 0..n {
+        // The last operator is offline: the committee should still reach the threshold.
+        let address = if i + 1 < n {
+            operator_service(committee_index, kps[i].clone(), validator, &identity, dirs[i].path()).await
+        } else {
+            unused_address()
+        };
+        let operator = RemoteOperator::new(ids[i], committee_index, kps[i].pk.clone(), address, identity.clone());
+        committee.add_operator(ids[i], Arc::new(AsyncRwLock::new(operator)));
+    }
+
+    let proposal = attestation(validator, 33, 1);
+    let signing_root = proposal.signing_root(&spec, Hash256::zero());
+    let sig = committee.sign_proposal(&proposal.to_bytes(), signing_root, Duration::from_secs(5)).await.unwrap();
+    assert!(sig.verify(&kp.pk, signing_root), "Signature verification failed");
+    assert_eq!(sig, kp.sk.sign(signing_root), "Signature not match");
+
+    // Remote operators do not sign a bare root.
+    assert!(committee.sign(signing_root).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remote_operator_errors() {
+    let mut m_threshold = ThresholdSignature::new(1);
+    let (_, kps, _) = m_threshold.key_gen(1).unwrap();
+    let validator = kps[0].pk.compress();
+    let identity = Arc::new(Secret::new());
+    let dir = tempdir().unwrap();
+    let address = operator_service(1, kps[0].clone(), validator, &identity, dir.path()).await;
+
+    let pk = kps[0].pk.clone();
+    let proposal = attestation(validator, 33, 1).to_bytes();
+    let root = Hash256::zero();
+    // Unknown committee index
+    let unknown = RemoteOperator::new(1, 2, pk.clone(), address, identity.clone()).sign_proposal(&proposal, root).await;
+    // Nobody listening
+    let offline = RemoteOperator::new(2, 1, pk.clone(), unused_address(), identity.clone()).sign_proposal(&proposal, root).await;
+    // A bare root is not signed
+    let untyped = RemoteOperator::new(1, 1, pk, address, identity).sign(root).await;
+    assert!(matches!(unknown, Err(DvfError::OperatorRejected { id: 1, .. })));
+    assert!(matches!(offline, Err(DvfError::OperatorUnreachable { id: 2, .. })));
+    assert_eq!(untyped, Err(DvfError::UntypedDuty));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remote_operator_checks_requests() {
+    let spec = ChainSpec::mainnet();
+    let mut m_threshold = ThresholdSignature::new(1);
+    let (_, kps, _) = m_threshold.key_gen(1).unwrap();
+    let validator = kps[0].pk.compress();
+    let identity = Arc::new(Secret::new());
+    let dir = tempdir().unwrap();
+    let address = operator_service(1, kps[0].clone(), validator, &identity, dir.path()).await;
+    let operator = RemoteOperator::new(1, 1, kps[0].pk.clone(), address, identity);
+
+    let proposal = attestation(validator, 33, 1);
+    let signing_root = proposal.signing_root(&spec, Hash256::zero());
+    let sig = operator.sign_proposal(&proposal.to_bytes(), Hash256::zero()).await.unwrap();
+    // The operator signs the root of the proposal, whatever root the client expects.
+    assert!(sig.verify(&kps[0].pk, signing_root));
+
+    // Operators outside of the committee are not answered.
+    let stranger = RemoteOperator::new(1, 1, kps[0].pk.clone(), address, Arc::new(Secret::new()));
+    let result = stranger.sign_proposal(&proposal.to_bytes(), signing_root).await;
+    assert!(matches!(result, Err(DvfError::OperatorRejected { id: 1, .. })));
+
+    // Neither are slashable or malformed proposals.
+    let double_vote = attestation(validator, 33, 2).to_bytes();
+    let result = operator.sign_proposal(&double_vote, signing_root).await;
+    assert!(matches!(result, Err(DvfError::OperatorRejected { id: 1, .. })));
+    let result = operator.sign_proposal(signing_root.as_bytes(), signing_root).await;
+    assert!(matches!(result, Err(DvfError::OperatorRejected { id: 1, .. })));
+
+    // Nor proposals of another validator.
+    let other = attestation(Keypair::random().pk.compress(), 65, 3).to_bytes();
+    let result = operator.sign_proposal(&other, signing_root).await;
+    assert!(matches!(result, Err(DvfError::OperatorRejected { id: 1, .. })));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remote_operator_cancellation() {
+    let mut m_threshold = ThresholdSignature::new(1);
+    let (_, kps, _) = m_threshold.key_gen(1).unwrap();
+    // An operator that accepts connections but never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+
+    let proposal = attestation(kps[0].pk.compress(), 33, 1).to_bytes();
+    let root = Hash256::zero();
+    let mut operator = RemoteOperator::new(1, 1, kps[0].pk.clone(), address, Arc::new(Secret::new()));
+    operator.timeout = Duration::from_millis(500);
+    // Dropping the request cancels it...
+    let cancelled = tokio::time::timeout(Duration::from_millis(50), operator.sign_proposal(&proposal, root)).await;
+    assert!(cancelled.is_err());
+    // ...and the operator can still be used.
+    assert_eq!(operator.sign_proposal(&proposal, root).await, Err(DvfError::OperatorTimeout { id: 1 }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remote_operator_max_size_block() {
+    let spec = ChainSpec::mainnet();
+    let mut m_threshold = ThresholdSignature::new(1);
+    let (_, kps, _) = m_threshold.key_gen(1).unwrap();
+    let validator = kps[0].pk.compress();
+    let identity = Arc::new(Secret::new());
+    let dir = tempdir().unwrap();
+    let address = operator_service(1, kps[0].clone(), validator, &identity, dir.path()).await;
+    let mut operator = RemoteOperator::new(1, 1, kps[0].pk.clone(), address, identity);
+    // Leave time to encode and check the proposal in debug builds.
+    operator.timeout = Duration::from_secs(30);
+
+    let block = max_size_block(&spec, 1);
+    assert_eq!(block.ssz_bytes_len(), MAX_BLOCK_SSZ_LEN);
+    let proposal = DutyProposal::<E> { slot: block.slot(), validator, object: DutyObject::BeaconBlock(block) };
+    let encoded = proposal.to_bytes();
+    assert!(encoded.len() > MAX_BLOCK_SSZ_LEN && encoded.len() < MAX_OPERATOR_FRAME_LEN);
+
+    let signing_root = proposal.signing_root(&spec, Hash256::zero());
+    let sig = operator.sign_proposal(&encoded, signing_root).await.unwrap();
+    assert!(sig.verify(&kps[0].pk, signing_root));
+}
@@ -0,0 +1,127 @@
+use dvf::crypto::{ThresholdSignature};
+use dvf::crypto::reshare::{CommitteeShape, ReshareDealer, ReshareMessage, ReshareOutput, ReshareReceiver};
+use dvf::validation::operator_committee_definitions::OperatorCommitteeDefinition;
+use bls::{Keypair, Signature, PublicKey};
+use node::config::Secret;
+use types::Hash256;
+use std::net::SocketAddr;
+use eth2_hashing::{Context, Sha256Context};
+
+fn hash(message: &str) -> Hash256 {
+    let mut context = Context::new();
+    context.update(message.as_bytes());
+    Hash256::from_slice(&context.finalize())
+}
+
+fn aggregate(t: usize, kps: &[&Keypair], ids: &[u64], message: Hash256) -> Signature {
+    let sigs: Vec<Signature> = kps.iter().map(|kp| kp.sk.sign(message)).collect();
+    let sigs: Vec<&Signature> = sigs.iter().collect();
+    let pks: Vec<&PublicKey> = kps.iter().map(|kp| &kp.pk).collect();
+    ThresholdSignature::new(t).threshold_aggregate(&sigs[..], &pks[..], ids, message).unwrap()
+}
+
+/// Runs a resharing where every old operator deals and all messages are delivered.
+fn reshare(old_def: &OperatorCommitteeDefinition, old_kps: &[Keypair], new_committee: CommitteeShape) -> Vec<ReshareOutput> {
+    let dealers: Vec<ReshareDealer> = old_def.ids.iter().zip(old_kps.iter())
+        .map(|(id, kp)| ReshareDealer::new(*id, &kp.sk, new_committee.clone()))
+        .collect();
+    let mut receivers: Vec<ReshareReceiver> = new_committee.ids.iter()
+        .map(|id| ReshareReceiver::new(*id, old_def, new_committee.clone()).unwrap())
+        .collect();
+
+    // Messages go through the network encoded.
+    for msg in dealers.iter().flat_map(|d| d.deal().unwrap()) {
+        let encoded = serde_json::to_vec(&msg).unwrap();
+        receivers.iter_mut().for_each(|r| r.handle(serde_json::from_slice(&encoded).unwrap()));
+    }
+    let complaints: Vec<ReshareMessage> = receivers.iter().flat_map(|r| r.complaints()).collect();
+    assert!(complaints.is_empty());
+    receivers.iter().map(|r| r.finalize().unwrap()).collect()
+}
+
+#[test]
+fn test_reshare_to_new_committee() {
+    let (t, n) = (3, 5);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let kp = Keypair::random();
+    let (old_kps, old_ids, old_commitments) = m_threshold.key_split(&kp.sk, n).unwrap();
+    let addresses: Vec<SocketAddr> = (0..n).map(|i| format!("127.0.0.1:{}", 4000 + i).parse().unwrap()).collect();
+    let old_def = OperatorCommitteeDefinition {
+        total: n as u64,
+        threshold: t as u64,
+        committee_index: 1,
+        voting_public_key: kp.pk.clone(),
+        ids: old_ids.clone(),
+        public_keys: old_kps.iter().map(|kp| kp.pk.clone()).collect(),
+        socket_addresses: addresses,
+        commitments: old_commitments,
+        node_public_keys: vec![],
+    };
+
+    let (new_t, new_ids) = (2, vec![11, 12, 13, 14]);
+    let outputs = reshare(&old_def, &old_kps, CommitteeShape::new(new_ids.clone(), new_t).unwrap());
+
+    let new_addresses: Vec<SocketAddr> = (0..new_ids.len()).map(|i| format!("127.0.0.1:{}", 5000 + i).parse().unwrap()).collect();
+    let node_public_keys: Vec<_> = new_ids.iter().map(|_| Secret::new().name).collect();
+    assert!(outputs[0].committee_definition(1, new_addresses.clone(), node_public_keys[1..].to_vec()).is_err());
+    let new_def = outputs[0].committee_definition(1, new_addresses, node_public_keys.clone()).unwrap();
+    assert_eq!(new_def.voting_public_key, kp.pk, "Voting public key must not change");
+    assert_eq!(new_def.threshold, new_t as u64);
+    assert_eq!(new_def.node_public_keys, node_public_keys);
+    assert!(new_def.validate().is_ok());
+    for output in &outputs {
+        assert_eq!(output.commitments, outputs[0].commitments);
+        assert!(ThresholdSignature::verify_share(output.id, &output.keypair.sk, &new_def_commitments(&outputs)).is_ok());
+    }
+
+    // The new committee signs for the same validator
+    let message = hash("hello world");
+    let sig = aggregate(new_t, &[&outputs[1].keypair, &outputs[3].keypair], &[12, 14], message);
+    assert_eq!(sig, kp.sk.sign(message));
+
+    // Old shares cannot be combined with the new ones
+    let mixed = aggregate(new_t, &[&old_kps[0], &outputs[1].keypair], &[11, 12], message);
+    assert!(!mixed.verify(&kp.pk, message));
+    assert!(ThresholdSignature::verify_share(11, &old_kps[0].sk, &new_def_commitments(&outputs)).is_err());
+}
+
+#[test]
+fn test_refresh_in_place() {
+    let (t, n) = (3, 4);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let kp = Keypair::random();
+    let (old_kps, ids, old_commitments) = m_threshold.key_split(&kp.sk, n).unwrap();
+    let old_def = OperatorCommitteeDefinition {
+        total: n as u64,
+        threshold: t as u64,
+        committee_index: 2,
+        voting_public_key: kp.pk.clone(),
+        ids: ids.clone(),
+        public_keys: old_kps.iter().map(|kp| kp.pk.clone()).collect(),
+        socket_addresses: (0..n).map(|i| format!("127.0.0.1:{}", 4000 + i).parse().unwrap()).collect(),
+        commitments: old_commitments,
+        node_public_keys: vec![],
+    };
+
+    let outputs = reshare(&old_def, &old_kps, CommitteeShape::new(ids.clone(), t).unwrap());
+    let message = hash("refresh");
+    let new_kps: Vec<&Keypair> = outputs.iter().map(|o| &o.keypair).collect();
+    assert_eq!(aggregate(t, &new_kps[..t], &ids[..t], message), kp.sk.sign(message));
+
+    // Every share changed, and two old shares plus one new share are useless
+    for i in 0..n {
+        assert_ne!(old_kps[i].pk, outputs[i].keypair.pk);
+    }
+    let mixed = aggregate(t, &[&old_kps[0], &old_kps[1], new_kps[2]], &ids[..t], message);
+    assert!(!mixed.verify(&kp.pk, message));
+}
+
+fn new_def_commitments(outputs: &[ReshareOutput]) -> Vec<PublicKey> {
+    outputs[0].commitments.clone()
+}
+
+#[test]
+fn test_reshare_messages_encoding() {
+    assert!(serde_json::from_str::<ReshareMessage>(r#"{"SubShare":{"dealer":1,"receiver":2,"share":"0x00"}}"#).is_err());
+    assert!(serde_json::from_str::<ReshareMessage>(r#"{"Complaint":{"dealer":1,"accuser":2}}"#).is_ok());
+}
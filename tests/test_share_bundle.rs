@@ -0,0 +1,118 @@
+use dvf::crypto::ThresholdSignature;
+use dvf::validation::account_utils::eth2_keystore::json_keystore::{Kdf, Scrypt};
+use dvf::validation::account_utils::eth2_keystore::DKLEN;
+use dvf::validation::eth2_keystore_share::envelope::Error as EnvelopeError;
+use dvf::validation::validator_dir::share_bundle::{Error, ShareBundle, ShareBundleBuilder, SHARE_BUNDLE_FILE};
+use node::config::Secret;
+use std::fs;
+use std::net::SocketAddr;
+use tempfile::tempdir;
+use types::{ChainSpec, Hash256, Keypair};
+
+/// A cheap, **insecure**, key derivation function for tests.
+fn insecure_kdf() -> Kdf {
+    Kdf::Scrypt(Scrypt {
+        dklen: DKLEN,
+        n: 2,
+        p: 1,
+        r: 8,
+        salt: vec![1; 32].into(),
+    })
+}
+
+fn socket_addresses(n: u16) -> Vec<SocketAddr> {
+    (0..n).map(|i| SocketAddr::from(([127, 0, 0, 1], 4000 + i))).collect()
+}
+
+/// The node secrets of `n` operators.
+fn secrets(n: usize) -> Vec<Secret> {
+    (0..n).map(|_| Secret::new()).collect()
+}
+
+fn node_public_keys(secrets: &[Secret]) -> Vec<crypto::PublicKey> {
+    secrets.iter().map(|secret| secret.name).collect()
+}
+
+#[test]
+fn test_share_bundles() {
+    let spec = ChainSpec::mainnet();
+    let kp = Keypair::random();
+    let withdrawal_credentials = Hash256::repeat_byte(1);
+    let secrets = secrets(3);
+    let bundles = ShareBundleBuilder::new(7, 2, socket_addresses(3), node_public_keys(&secrets))
+        .voting_keypair(kp.clone())
+        .kdf(insecure_kdf())
+        .create_deposit_data(withdrawal_credentials, spec.max_effective_balance, &spec)
+        .build()
+        .unwrap();
+    assert_eq!(bundles.len(), 3);
+
+    // Every operator gets its own share of the same committee and deposit.
+    let first = &bundles[0];
+    assert_eq!(first.committee_definition.voting_public_key, kp.pk);
+    assert_eq!(first.committee_definition.node_public_keys, node_public_keys(&secrets));
+    assert_eq!(first.committee_definition.committee_index, 7);
+    assert!(first.committee_definition.validate().is_ok());
+    let deposit_data = first.deposit_data.clone().unwrap();
+    assert_eq!(deposit_data.withdrawal_credentials, withdrawal_credentials);
+    assert_eq!(deposit_data.signature, deposit_data.create_signature(&kp.sk, &spec));
+    assert!(first.eth1_deposit_tx_data().unwrap().is_some());
+
+    // Each share is sealed to its operator: no other operator can open it.
+    let mut keypairs = Vec::new();
+    for (i, bundle) in bundles.iter().enumerate() {
+        assert_eq!(bundle.committee_definition, first.committee_definition);
+        assert_eq!(bundle.deposit_data, first.deposit_data);
+        assert_eq!(bundle.operator_id(), bundle.committee_definition.ids[i]);
+        assert_eq!(bundle.keystore_share_envelope.recipient, secrets[i].name);
+        let keypair = bundle.keystore_share_envelope.open(&secrets[i]).unwrap();
+        assert_eq!(keypair.pk, bundle.committee_definition.public_keys[i]);
+        let other = &secrets[(i + 1) % secrets.len()];
+        assert!(matches!(
+            bundle.keystore_share_envelope.open(other),
+            Err(EnvelopeError::WrongRecipient { .. })
+        ));
+        keypairs.push(keypair);
+    }
+    let sks: Vec<_> = keypairs[1..].iter().map(|keypair| &keypair.sk).collect();
+    let ids = &first.committee_definition.ids[1..];
+    assert_eq!(ThresholdSignature::recover_secret(&sks, ids).unwrap().public_key(), kp.pk);
+
+    // Bundles are saved once, without any password.
+    let dir = tempdir().unwrap();
+    let bundle = &bundles[1];
+    let bundle_dir = bundle.save(dir.path()).unwrap();
+    assert_eq!(&ShareBundle::from_json_file(bundle_dir.join(SHARE_BUNDLE_FILE)).unwrap(), bundle);
+    assert_eq!(fs::read_dir(&bundle_dir).unwrap().count(), 1);
+    assert!(matches!(bundle.save(dir.path()), Err(Error::BundleAlreadyExists(_))));
+}
+
+#[test]
+fn test_share_bundles_invalid_committee() {
+    let keys = node_public_keys(&secrets(3));
+    assert!(matches!(
+        ShareBundleBuilder::new(1, 4, socket_addresses(3), keys.clone()).build(),
+        Err(Error::InvalidCommittee(_))
+    ));
+    assert!(matches!(
+        ShareBundleBuilder::new(1, 0, socket_addresses(3), keys.clone()).build(),
+        Err(Error::InvalidCommittee(_))
+    ));
+
+    // Shares cannot be handed out without the node key of every operator.
+    assert!(matches!(
+        ShareBundleBuilder::new(1, 2, socket_addresses(3), keys[..2].to_vec()).build(),
+        Err(Error::InvalidCommittee(_))
+    ));
+    assert!(matches!(
+        ShareBundleBuilder::new(1, 2, socket_addresses(3), vec![keys[0]; 3]).kdf(insecure_kdf()).build(),
+        Err(Error::InvalidCommittee(_))
+    ));
+
+    // A random key is split when none is given, and no deposit is made.
+    let bundles = ShareBundleBuilder::new(1, 1, socket_addresses(1), keys[..1].to_vec())
+        .kdf(insecure_kdf())
+        .build()
+        .unwrap();
+    assert!(bundles[0].deposit_data.is_none());
+}
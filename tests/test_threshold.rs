@@ -1,3 +1,10 @@
+use dvf::crypto::ThresholdSignature;
+use dvf::crypto::generic_threshold::{bigint_to_secret_key, secret_key_to_bigint};
+use dvf::crypto::repair::{RepairHelper, RepairMessage, RepairReceiver};
+use dvf::utils::error::DvfError;
+use dvf::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use bls::{Keypair, SecretKey};
+use eth2_keystore::KeystoreBuilder;
 //use miracl_core::bls12381::bls::{BLS_OK}; 
 //use dvf::crypto::bls::{Bls};
 //use dvf::crypto::threshold::{ThresholdBls};
@@ -35,18 +42,104 @@
     //assert_eq!(agg_sig, sig, "Signature not match");
 //}
 
-//#[test]
-//fn test_recover_sk() {
-    //let t = 5;
-    //let n = 10;
-    //let mut m_threshold = ThresholdBls::new(t, n);
-    //let mut sk_shares = vec![[0 as u8; MB]; n]; 
-    //let mut ids = vec![0; n];
-    //m_threshold.generate(&mut sk_shares, &mut ids);
+#[test]
+fn test_recover_sk() {
+    let t = 5;
+    let n = 10;
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps, ids) = m_threshold.key_gen(n).unwrap();
 
-    //let mut sk = [0 as u8; MB];
-    //m_threshold.recover_sk(&mut sk, &sk_shares, &ids);
+    // Any t shares recover the key
+    let sks: Vec<&SecretKey> = kps.iter().map(|k| &k.sk).collect();
+    let sk = ThresholdSignature::recover_secret(&sks[3..3 + t], &ids[3..3 + t]).unwrap();
+    assert_eq!(sk.serialize().as_bytes(), kp.sk.serialize().as_bytes(), "Failed to recover key");
 
-    //assert_eq!(sk, m_threshold.master_bls.sk, "Faile to recover key");
-//}
+    // Fewer do not
+    let sk = ThresholdSignature::recover_secret(&sks[..t - 1], &ids[..t - 1]).unwrap();
+    assert_ne!(sk.public_key(), kp.pk);
+
+    assert_eq!(
+        ThresholdSignature::recover_secret(&sks[..2], &[1, 1]).map(|_| ()),
+        Err(DvfError::InvalidOperatorIds)
+    );
+
+    // Shares 1 and 2 interpolate to zero at zero if the second is twice the first
+    let share = SecretKey::random();
+    let double = bigint_to_secret_key(&(secret_key_to_bigint(&share) * 2)).unwrap();
+    assert_eq!(
+        ThresholdSignature::recover_secret(&[&share, &double], &[1, 2]).map(|_| ()),
+        Err(DvfError::ZeroSecretKey)
+    );
+}
+
+#[test]
+fn test_recover_master_keypair_from_keystore_shares() {
+    let (t, n) = (3, 4);
+    let password = b"password";
+    let mut m_threshold = ThresholdSignature::new(t);
+    let kp = Keypair::random();
+    let (kps, ids, commitments) = m_threshold.key_split(&kp.sk, n).unwrap();
+    let shares: Vec<KeystoreShare> = kps.iter().zip(ids.iter())
+        .map(|(k, id)| {
+            let keystore = KeystoreBuilder::new(k, password, "".into()).unwrap().build().unwrap();
+            KeystoreShare::new(keystore, kp.pk.clone(), 1, *id, commitments.clone())
+        })
+        .collect();
+    let decrypted: Vec<Keypair> = shares.iter().map(|s| s.keystore.decrypt_keypair(password).unwrap()).collect();
+
+    let inputs: Vec<(&KeystoreShare, &Keypair)> = shares.iter().zip(decrypted.iter()).skip(1).collect();
+    let recovered = KeystoreShare::recover_master_keypair(&inputs[..]).unwrap();
+    assert_eq!(recovered.pk, kp.pk);
+
+    let inputs: Vec<(&KeystoreShare, &Keypair)> = shares.iter().zip(decrypted.iter()).take(t - 1).collect();
+    assert_eq!(KeystoreShare::recover_master_keypair(&inputs[..]).map(|k| k.pk), Err(DvfError::KeyRecoveryFailed));
+}
+
+#[test]
+fn test_repair_share() {
+    let (t, n) = (3, 5);
+    let mut m_threshold = ThresholdSignature::new(t);
+    let kp = Keypair::random();
+    let (kps, ids, commitments) = m_threshold.key_split(&kp.sk, n).unwrap();
+
+    // Operator 2 lost its share; operators 1, 4 and 5 regenerate it.
+    let target = ids[1];
+    let helper_ids = vec![ids[0], ids[3], ids[4]];
+    let mut helpers: Vec<RepairHelper> = [0, 3, 4].iter()
+        .map(|i| RepairHelper::new(ids[*i], &kps[*i].sk, helper_ids.clone(), target).unwrap())
+        .collect();
+    let mut receiver = RepairReceiver::new(target, helper_ids.clone(), commitments.clone()).unwrap();
+
+    let parts: Vec<RepairMessage> = helpers.iter().flat_map(|h| h.deal().unwrap()).collect();
+    for part in parts {
+        helpers.iter_mut().for_each(|h| h.handle(part.clone()));
+    }
+    for helper in &helpers {
+        receiver.handle(helper.finalize().unwrap());
+    }
+    let output = receiver.finalize().unwrap();
+    assert_eq!(output.keypair.pk, kps[1].pk);
+    assert_eq!(output.master_public_key, kp.pk);
+
+    // A missing or tampered contribution is detected
+    let mut receiver = RepairReceiver::new(target, helper_ids.clone(), commitments.clone()).unwrap();
+    receiver.handle(helpers[0].finalize().unwrap());
+    receiver.handle(helpers[1].finalize().unwrap());
+    assert_eq!(receiver.finalize().map(|o| o.id), Err(DvfError::InvalidKeyShare { id: helper_ids[2] }));
+    receiver.handle(RepairMessage::Sum { helper: helper_ids[2], sum: SecretKey::random() });
+    assert_eq!(receiver.finalize().map(|o| o.id), Err(DvfError::InvalidKeyShare { id: target }));
 
+    // Including a sum that cancels the others out
+    let mut receiver = RepairReceiver::new(target, helper_ids.clone(), commitments).unwrap();
+    let sums: Vec<SecretKey> = helpers[..2].iter()
+        .map(|h| match h.finalize().unwrap() {
+            RepairMessage::Sum { sum, .. } => sum,
+            _ => unreachable!(),
+        })
+        .collect();
+    let cancelling = -(secret_key_to_bigint(&sums[0]) + secret_key_to_bigint(&sums[1]));
+    receiver.handle(RepairMessage::Sum { helper: helper_ids[0], sum: sums[0].clone() });
+    receiver.handle(RepairMessage::Sum { helper: helper_ids[1], sum: sums[1].clone() });
+    receiver.handle(RepairMessage::Sum { helper: helper_ids[2], sum: bigint_to_secret_key(&cancelling).unwrap() });
+    assert_eq!(receiver.finalize().map(|o| o.id), Err(DvfError::ZeroSecretKey));
+}
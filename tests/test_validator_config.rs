@@ -0,0 +1,32 @@
+use dvf::validation::Config;
+use environment::null_logger;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+fn config_from(args: &[&str]) -> Result<Config, String> {
+    let dir = tempdir().unwrap();
+    let validators_dir = dir.path().join("validators");
+    let secrets_dir = dir.path().join("secrets");
+    let matches = validator_client::cli_app()
+        .get_matches_from_safe(
+            ["validator_client", "--validators-dir", validators_dir.to_str().unwrap(), "--secrets-dir", secrets_dir.to_str().unwrap()]
+                .iter()
+                .chain(args),
+        )
+        .map_err(|e| e.to_string())?;
+    Config::from_cli(&matches, &null_logger().unwrap())
+}
+
+#[test]
+fn test_operator_flags() {
+    let config = config_from(&[]).unwrap();
+    assert_eq!(config.node_key_path, None);
+    assert_eq!(config.operator_service_address, None);
+
+    let config = config_from(&["--node-key", "/tmp/node_key.json", "--operator-address", "0.0.0.0:25000"]).unwrap();
+    assert_eq!(config.node_key_path, Some(PathBuf::from("/tmp/node_key.json")));
+    assert_eq!(config.operator_service_address, Some(SocketAddr::from(([0, 0, 0, 0], 25000))));
+
+    assert!(config_from(&["--operator-address", "not an address"]).is_err());
+}